@@ -13,11 +13,11 @@ use std::{
 };
 
 use amadeus_core::{
-	into_par_stream::IntoDistributedStream, par_stream::DistributedStream, util::{DistParStream, ResultExpandIter}, Source
+	into_par_stream::IntoDistributedStream, par_stream::DistributedStream, util::{DistParStream, ResultExpandIter, RetryPolicy}, Source
 };
 use amadeus_types::{Data, DateTime, IpAddr, Url};
 
-use super::{list, retry, AwsCredentials, AwsError, AwsRegion, Ref, RUSOTO_DISPATCHER};
+use super::{body, list, retry, AwsCredentials, AwsError, AwsRegion, Ref, RUSOTO_DISPATCHER};
 
 #[derive(Clone, Debug)]
 pub struct Cloudfront {
@@ -25,6 +25,7 @@ pub struct Cloudfront {
 	bucket: String,
 	objects: Vec<String>,
 	credentials: AwsCredentials,
+	retry: RetryPolicy,
 }
 impl Cloudfront {
 	pub async fn new(region: AwsRegion, bucket: &str, prefix: &str) -> Result<Self, AwsError> {
@@ -32,6 +33,14 @@ impl Cloudfront {
 	}
 	pub async fn new_with(
 		region: AwsRegion, bucket: &str, prefix: &str, credentials: AwsCredentials,
+	) -> Result<Self, AwsError> {
+		Self::new_with_retry(region, bucket, prefix, credentials, RetryPolicy::default()).await
+	}
+	/// Like [`new_with`](Self::new_with), retrying failed requests, both to list
+	/// the logs and to read them, according to `retry`.
+	pub async fn new_with_retry(
+		region: AwsRegion, bucket: &str, prefix: &str, credentials: AwsCredentials,
+		retry: RetryPolicy,
 	) -> Result<Self, AwsError> {
 		let (bucket, prefix) = (bucket.to_owned(), prefix.to_owned());
 		let client = S3Client::new_with(
@@ -40,7 +49,7 @@ impl Cloudfront {
 			region.clone(),
 		);
 
-		let objects = list(&client, &bucket, &prefix)
+		let objects = list(&client, &retry, &bucket, &prefix)
 			.await?
 			.map(|object: Object| object.key.unwrap());
 
@@ -49,6 +58,7 @@ impl Cloudfront {
 			bucket,
 			objects,
 			credentials,
+			retry,
 		})
 	}
 }
@@ -59,9 +69,9 @@ type Output = std::pin::Pin<Box<dyn Stream<Item = Result<CloudfrontRow, AwsError
 type Output = impl Stream<Item = Result<CloudfrontRow, AwsError>> + Send;
 
 FnMutNamed! {
-	pub type Closure<> = |self, credentials: AwsCredentials, region: AwsRegion, bucket: String|key=> String| -> Output where {
-		let (credentials, region, bucket) =
-			(self.credentials.clone(), self.region.clone(), self.bucket.clone());
+	pub type Closure<> = |self, credentials: AwsCredentials, region: AwsRegion, bucket: String, policy: RetryPolicy|key=> String| -> Output where {
+		let (credentials, region, bucket, policy) =
+			(self.credentials.clone(), self.region.clone(), self.bucket.clone(), self.policy);
 		#[allow(clippy::let_and_return)]
 		let ret = async move {
 			let client = S3Client::new_with(
@@ -69,7 +79,7 @@ FnMutNamed! {
 				credentials,
 				region,
 			);
-			let rows = retry(|| {
+			let rows = retry(&policy, || {
 				client.get_object(GetObjectRequest {
 					bucket: bucket.clone(),
 					key: key.clone(),
@@ -78,8 +88,9 @@ FnMutNamed! {
 			})
 			.await
 			.map_err(AwsError::from)
-			.map(|res| {
-				let body = BufReader::new(TryStreamExt::into_async_read(res.body.unwrap()));
+			.and_then(|res| body(res.body, &policy))
+			.map(|body| {
+				let body = BufReader::new(TryStreamExt::into_async_read(body));
 				let mut body = GzipDecoder::new(body); // Content-Encoding isn't set, so decode manually
 				body.multiple_members(true);
 				BufReader::new(body)
@@ -133,10 +144,11 @@ impl Source for Cloudfront {
 			region,
 			objects,
 			credentials,
+			retry,
 		} = self;
 		objects
 			.into_dist_stream()
-			.flat_map(Closure::new(credentials, region, bucket))
+			.flat_map(Closure::new(credentials, region, bucket, retry))
 	}
 }
 
@@ -1,18 +1,25 @@
 use async_trait::async_trait;
-use futures::{future, future::LocalBoxFuture, FutureExt};
-use rusoto_core::RusotoError;
-use rusoto_s3::{GetObjectRequest, HeadObjectRequest, S3Client, S3};
+use futures::{future, future::LocalBoxFuture, lock::Mutex, FutureExt};
+use rusoto_s3::{
+	AbortMultipartUploadRequest, CompleteMultipartUploadRequest, CompletedMultipartUpload, CompletedPart, CreateMultipartUploadRequest, DeleteObjectRequest, GetObjectRequest, HeadObjectRequest, Object, PutObjectRequest, S3Client, StreamingBody, UploadPartRequest, S3
+};
 use serde::{Deserialize, Serialize};
 use std::{
-	convert::{TryFrom, TryInto}, sync::Arc
+	convert::{TryFrom, TryInto}, future::Future, io, mem, sync::Arc
+};
+use tokio::{
+	io::AsyncReadExt, runtime::{Builder, Handle}
 };
-use tokio::io::AsyncReadExt;
 
 use amadeus_core::{
-	file::{Directory, File, Page, Partition, PathBuf}, util::IoError
+	file::{Create, Directory, File, Page, Partition, PathBuf, WriteMode}, util::{IoError, RetryPolicy}
 };
 
-use super::{retry, AwsCredentials, AwsError, AwsRegion, Ref, RUSOTO_DISPATCHER};
+use super::{custom_endpoint, retry, AwsCredentials, AwsError, AwsRegion, Ref, RUSOTO_DISPATCHER};
+
+/// Writes are buffered into parts of at least this size, as S3 requires all but
+/// the last part of a multipart upload to be at least 5 MiB.
+const PART_SIZE: usize = 8 * 1024 * 1024;
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct S3Directory {
@@ -20,6 +27,7 @@ pub struct S3Directory {
 	bucket: String,
 	prefix: String,
 	credentials: AwsCredentials,
+	retry: RetryPolicy,
 }
 impl S3Directory {
 	pub fn new(region: AwsRegion, bucket: &str, prefix: &str) -> Self {
@@ -34,14 +42,40 @@ impl S3Directory {
 			bucket,
 			prefix,
 			credentials,
+			retry: RetryPolicy::default(),
 		}
 	}
+	/// Send requests to `endpoint`, e.g. `http://localhost:9000`, rather than
+	/// to AWS, signing them for this region. Buckets are addressed path-style,
+	/// as `endpoint/bucket/key`, as most S3-compatible services expect.
+	#[must_use]
+	pub fn endpoint(mut self, endpoint: &str) -> Self {
+		self.region = custom_endpoint(&self.region, endpoint);
+		self
+	}
+	/// How failed requests, and reads that stall for longer than its timeout, are
+	/// retried. Exhausted retries are returned as errors.
+	#[must_use]
+	pub fn retry(mut self, retry: RetryPolicy) -> Self {
+		self.retry = retry;
+		self
+	}
+	/// The file `name` under this directory's prefix.
+	pub fn file(&self, name: &str) -> S3File {
+		S3File::new_with(
+			self.region.clone(),
+			&self.bucket,
+			&format!("{}{}", self.prefix, name),
+			self.credentials.clone(),
+		)
+		.retry(self.retry)
+	}
 }
 #[async_trait(?Send)]
 impl Directory for S3Directory {
 	async fn partitions_filter<F>(
 		self, mut f: F,
-	) -> Result<Vec<<Self as File>::Partition>, <Self as File>::Error>
+	) -> Result<Vec<(PathBuf, <Self as File>::Partition)>, <Self as File>::Error>
 	where
 		F: FnMut(&PathBuf) -> bool,
 	{
@@ -50,64 +84,59 @@ impl Directory for S3Directory {
 			bucket,
 			prefix,
 			credentials,
+			retry: policy,
 		} = self;
 		let client = S3Client::new_with(
 			Ref(&*RUSOTO_DISPATCHER),
 			credentials.clone(),
 			region.clone(),
 		);
-		let objects = super::list(&client, &bucket, &prefix).await?;
-
-		let mut current_path = PathBuf::new();
-		let mut skip = false;
-		let mut last_key: Option<String> = None;
-		objects
-			.into_iter()
-			.filter(|object| {
-				let key = object.key.as_ref().unwrap();
-				assert!(key.starts_with(&prefix));
-				let key = &key[prefix.len()..];
-				assert!(last_key.is_none() || **last_key.as_ref().unwrap() < *key, "S3 API not returning objects in \"UTF-8 character encoding in lexicographical order\" as their docs specify");
-				last_key = Some(key.to_owned());
-				let mut path = key.split('/').collect::<Vec<&str>>();
-				let file_name = path.pop().unwrap();
-				skip = skip
-					&& path.len() >= current_path.depth()
-					&& current_path.iter().eq(path
-						.iter()
-						.take(current_path.depth())
-						.copied());
-				if skip {
-					return false;
-				}
-				while current_path.depth() > path.len()
-					|| (current_path.depth() > 0
-						&& current_path.last().unwrap() != path[current_path.depth() - 1])
-				{
-					let _ = current_path.pop().unwrap();
+		// Descend a "directory" at a time, so that those `f` rejects aren't listed
+		enum Entry {
+			Directory(PathBuf, String),
+			File(PathBuf, Object),
+		}
+		let mut partitions = Vec::new();
+		let mut stack = vec![Entry::Directory(PathBuf::new(), prefix)];
+		while let Some(entry) = stack.pop() {
+			match entry {
+				Entry::Directory(path, prefix) => {
+					if path.depth() != 0 && !f(&path) {
+						continue;
+					}
+					let (objects, prefixes) =
+						super::list_dir(&client, &policy, &bucket, &prefix).await?;
+					let objects = objects.into_iter().map(|object| {
+						let key = object.key.clone().unwrap();
+						let mut path = path.clone();
+						path.set_file_name(Some(&key[prefix.len()..]));
+						(key, Entry::File(path, object))
+					});
+					let directories = prefixes.into_iter().map(|directory| {
+						let mut path = path.clone();
+						path.push(&directory[prefix.len()..directory.len() - 1]);
+						(directory.clone(), Entry::Directory(path, directory))
+					});
+					let mut entries = objects.chain(directories).collect::<Vec<_>>();
+					// Reversed, so they're popped in lexicographical order
+					entries.sort_by(|(a, _), (b, _)| b.cmp(a));
+					stack.extend(entries.into_iter().map(|(_, entry)| entry));
 				}
-				while path.len() > current_path.depth() {
-					current_path.push(path[current_path.depth()]);
-					if !f(&current_path) {
-						skip = true;
-						return false;
+				Entry::File(path, object) => {
+					if f(&path) {
+						partitions.push((path, S3Partition {
+							region: region.clone(),
+							bucket: bucket.clone(),
+							key: object.key.unwrap(),
+							len: object.size.unwrap().try_into().unwrap(),
+							credentials: credentials.clone(),
+							retry: policy,
+						}));
 					}
 				}
-				current_path.set_file_name(Some(file_name));
-				let ret = f(&current_path);
-				current_path.set_file_name::<Vec<u8>>(None);
-				ret
-			})
-			.map(|object| {
-				Ok(S3Partition {
-					region: region.clone(),
-					bucket: bucket.clone(),
-					key: object.key.unwrap(),
-					len: object.size.unwrap().try_into().unwrap(),
-					credentials: credentials.clone()
-				})
-			})
-			.collect()
+			}
+		}
+		Ok(partitions)
 	}
 }
 
@@ -117,7 +146,77 @@ impl File for S3Directory {
 	type Error = AwsError;
 
 	async fn partitions(self) -> Result<Vec<Self::Partition>, Self::Error> {
-		self.partitions_filter(|_| true).await
+		Ok(self
+			.partitions_filter(|_| true)
+			.await?
+			.into_iter()
+			.map(|(_, partition)| partition)
+			.collect())
+	}
+}
+
+/// New files are created under the prefix, which should end in `/`, either
+/// directly or under partition prefixes like `year=2020/`, where the write mode
+/// applies too. Their contents are uploaded with a multipart upload, that's
+/// completed once the last write has finished, or aborted if any part fails.
+#[async_trait(?Send)]
+impl Create for S3Directory {
+	type Page = S3Page;
+	type Error = IoError;
+
+	async fn prepare(&self, mode: WriteMode) -> Result<(), Self::Error> {
+		if mode == WriteMode::Append {
+			return Ok(());
+		}
+		let client = S3Client::new_with(
+			Ref(&*RUSOTO_DISPATCHER),
+			self.credentials.clone(),
+			self.region.clone(),
+		);
+		let objects = super::list(&client, &self.retry, &self.bucket, &self.prefix)
+			.await
+			.map_err(io_error)?;
+		for object in objects {
+			let key = object.key.unwrap();
+			// Only files directly under the prefix, or under partition prefixes like
+			// `year=2020/`, as written by a partitioned destination
+			let name = &key[self.prefix.len()..];
+			let mut prefixes = name.split('/').rev().skip(1);
+			if !prefixes.all(|prefix| prefix.find('=').map_or(false, |offset| offset > 0)) {
+				continue;
+			}
+			match mode {
+				WriteMode::Create => {
+					let _ = retry(&self.retry, || {
+						client.delete_object(DeleteObjectRequest {
+							bucket: self.bucket.clone(),
+							key: key.clone(),
+							..DeleteObjectRequest::default()
+						})
+					})
+					.await
+					.map_err(io_error)?;
+				}
+				WriteMode::FailIfExists => {
+					return Err(io::Error::new(
+						io::ErrorKind::AlreadyExists,
+						format!("s3://{}/{} already exists", self.bucket, key),
+					)
+					.into())
+				}
+				WriteMode::Append => unreachable!(),
+			}
+		}
+		Ok(())
+	}
+	async fn create(&self, name: &str) -> Result<Self::Page, Self::Error> {
+		let client = S3Client::new_with(
+			Ref(&*RUSOTO_DISPATCHER),
+			self.credentials.clone(),
+			self.region.clone(),
+		);
+		let (bucket, key) = (self.bucket.clone(), format!("{}{}", self.prefix, name));
+		Ok(S3Page::from_inner(client, self.retry, bucket, key, 0))
 	}
 }
 
@@ -127,6 +226,7 @@ pub struct S3File {
 	bucket: String,
 	key: String,
 	credentials: AwsCredentials,
+	retry: RetryPolicy,
 }
 impl S3File {
 	pub fn new(region: AwsRegion, bucket: &str, key: &str) -> Self {
@@ -141,8 +241,24 @@ impl S3File {
 			bucket,
 			key,
 			credentials,
+			retry: RetryPolicy::default(),
 		}
 	}
+	/// Send requests to `endpoint`, e.g. `http://localhost:9000`, rather than
+	/// to AWS, signing them for this region. Buckets are addressed path-style,
+	/// as `endpoint/bucket/key`, as most S3-compatible services expect.
+	#[must_use]
+	pub fn endpoint(mut self, endpoint: &str) -> Self {
+		self.region = custom_endpoint(&self.region, endpoint);
+		self
+	}
+	/// How failed requests, and reads that stall for longer than its timeout, are
+	/// retried. Exhausted retries are returned as errors.
+	#[must_use]
+	pub fn retry(mut self, retry: RetryPolicy) -> Self {
+		self.retry = retry;
+		self
+	}
 }
 #[async_trait(?Send)]
 impl File for S3File {
@@ -160,7 +276,14 @@ impl Partition for S3File {
 
 	async fn pages(self) -> Result<Vec<Self::Page>, Self::Error> {
 		Ok(vec![
-			S3Page::new(self.region, self.bucket, self.key, self.credentials).await,
+			S3Page::new(
+				self.region,
+				self.bucket,
+				self.key,
+				self.credentials,
+				self.retry,
+			)
+			.await?,
 		])
 	}
 }
@@ -172,6 +295,7 @@ pub struct S3Partition {
 	key: String,
 	len: u64,
 	credentials: AwsCredentials,
+	retry: RetryPolicy,
 }
 #[async_trait(?Send)]
 impl Partition for S3Partition {
@@ -181,31 +305,162 @@ impl Partition for S3Partition {
 	async fn pages(self) -> Result<Vec<Self::Page>, Self::Error> {
 		let client = S3Client::new_with(Ref(&*RUSOTO_DISPATCHER), self.credentials, self.region);
 		let (bucket, key, len) = (self.bucket, self.key, self.len);
-		let inner = Arc::new(S3PageInner {
-			client,
-			bucket,
-			key,
-			len,
-		});
-		Ok(vec![S3Page { inner }])
+		Ok(vec![S3Page::from_inner(
+			client, self.retry, bucket, key, len,
+		)])
 	}
 }
 
 struct S3PageInner {
 	client: S3Client,
+	retry: RetryPolicy,
 	bucket: String,
 	key: String,
 	len: u64,
+	upload: Mutex<Upload>,
+}
+
+/// The state of an in-progress write. Nothing is uploaded until `PART_SIZE` bytes
+/// have been buffered, so small files are written with a single `PutObject`.
+#[derive(Default)]
+struct Upload {
+	id: Option<String>,
+	parts: Vec<CompletedPart>,
+	buf: Vec<u8>,
+	written: u64,
 }
+
+impl S3PageInner {
+	async fn upload_part(&self, upload: &mut Upload) -> Result<(), AwsError> {
+		if upload.id.is_none() {
+			let res = retry(&self.retry, || {
+				self.client
+					.create_multipart_upload(CreateMultipartUploadRequest {
+						bucket: self.bucket.clone(),
+						key: self.key.clone(),
+						..CreateMultipartUploadRequest::default()
+					})
+			})
+			.await?;
+			upload.id = Some(res.upload_id.unwrap());
+		}
+		let upload_id = upload.id.clone().unwrap();
+		let part_number = i64::try_from(upload.parts.len() + 1).unwrap();
+		let buf = mem::take(&mut upload.buf);
+		let res = retry(&self.retry, || {
+			self.client.upload_part(UploadPartRequest {
+				bucket: self.bucket.clone(),
+				key: self.key.clone(),
+				upload_id: upload_id.clone(),
+				part_number,
+				content_length: Some(i64::try_from(buf.len()).unwrap()),
+				body: Some(buf.clone().into()),
+				..UploadPartRequest::default()
+			})
+		})
+		.await?;
+		upload.parts.push(CompletedPart {
+			e_tag: res.e_tag,
+			part_number: Some(part_number),
+		});
+		Ok(())
+	}
+	async fn complete(&self, upload: &mut Upload) -> Result<(), AwsError> {
+		if upload.id.is_none() {
+			let buf = mem::take(&mut upload.buf);
+			let _ = retry(&self.retry, || {
+				self.client.put_object(PutObjectRequest {
+					bucket: self.bucket.clone(),
+					key: self.key.clone(),
+					content_length: Some(i64::try_from(buf.len()).unwrap()),
+					body: Some(buf.clone().into()),
+					..PutObjectRequest::default()
+				})
+			})
+			.await?;
+			return Ok(());
+		}
+		if !upload.buf.is_empty() {
+			self.upload_part(upload).await?;
+		}
+		let upload_id = upload.id.clone().unwrap();
+		let parts = mem::take(&mut upload.parts);
+		let _ = retry(&self.retry, || {
+			self.client
+				.complete_multipart_upload(CompleteMultipartUploadRequest {
+					bucket: self.bucket.clone(),
+					key: self.key.clone(),
+					upload_id: upload_id.clone(),
+					multipart_upload: Some(CompletedMultipartUpload {
+						parts: Some(parts.clone()),
+					}),
+					..CompleteMultipartUploadRequest::default()
+				})
+		})
+		.await?;
+		upload.id = None;
+		Ok(())
+	}
+	fn abort(&self, upload: Upload) -> impl Future<Output = ()> + Send + 'static {
+		let (client, policy) = (self.client.clone(), self.retry);
+		let (bucket, key) = (self.bucket.clone(), self.key.clone());
+		async move {
+			if let Some(upload_id) = upload.id {
+				// Best effort: a lifecycle rule is the backstop for uploads this fails to abort
+				let _ = retry(&policy, || {
+					client.abort_multipart_upload(AbortMultipartUploadRequest {
+						bucket: bucket.clone(),
+						key: key.clone(),
+						upload_id: upload_id.clone(),
+						..AbortMultipartUploadRequest::default()
+					})
+				})
+				.await;
+			}
+		}
+	}
+}
+impl Drop for S3PageInner {
+	fn drop(&mut self) {
+		// Abort uploads that were never closed, e.g. because the write errored
+		let upload = mem::take(self.upload.get_mut());
+		if upload.id.is_some() {
+			let abort = self.abort(upload);
+			if let Ok(handle) = Handle::try_current() {
+				drop(handle.spawn(abort));
+			} else if let Ok(mut runtime) = Builder::new().basic_scheduler().enable_all().build() {
+				// There's no runtime to spawn on, so block on a temporary one rather than
+				// leaking the upload
+				runtime.block_on(abort);
+			}
+		}
+	}
+}
+
 pub struct S3Page {
 	inner: Arc<S3PageInner>,
 }
 impl S3Page {
+	fn from_inner(
+		client: S3Client, retry: RetryPolicy, bucket: String, key: String, len: u64,
+	) -> Self {
+		let inner = Arc::new(S3PageInner {
+			client,
+			retry,
+			bucket,
+			key,
+			len,
+			upload: Mutex::new(Upload::default()),
+		});
+		Self { inner }
+	}
+
 	async fn new(
 		region: AwsRegion, bucket: String, key: String, credentials: AwsCredentials,
-	) -> Self {
+		policy: RetryPolicy,
+	) -> Result<Self, IoError> {
 		let client = S3Client::new_with(Ref(&*RUSOTO_DISPATCHER), credentials, region);
-		let object = retry(|| {
+		let object = retry(&policy, || {
 			client.head_object(HeadObjectRequest {
 				bucket: bucket.clone(),
 				key: key.clone(),
@@ -213,15 +468,9 @@ impl S3Page {
 			})
 		})
 		.await
-		.unwrap();
+		.map_err(io_error)?;
 		let len = object.content_length.unwrap().try_into().unwrap();
-		let inner = Arc::new(S3PageInner {
-			client,
-			bucket,
-			key,
-			len,
-		});
-		Self { inner }
+		Ok(Self::from_inner(client, policy, bucket, key, len))
 	}
 }
 impl Page for S3Page {
@@ -242,46 +491,89 @@ impl Page for S3Page {
 			let mut buf = &mut *buf_;
 			let len: u64 = len.try_into().unwrap();
 			let mut pos = 0_u64;
-			let mut errors: usize = 0;
+			let mut attempt = 0;
 			let end = offset + len - 1;
+			let policy = &self_.inner.retry;
 			while !buf.is_empty() {
 				let start = offset + pos;
 				assert_eq!(start, end + 1 - u64::try_from(buf.len()).unwrap()); // TODO
-				let res = self_.inner.client.get_object(GetObjectRequest {
-					bucket: self_.inner.bucket.clone(),
-					key: self_.inner.key.clone(),
-					range: Some(format!("bytes={}-{}", start, end)),
-					..GetObjectRequest::default()
-				});
-				let res = res.await;
-				match res {
-					Err(RusotoError::HttpDispatch(_)) if errors < 10 => {
-						errors += 1;
-						continue;
-					}
-					Err(RusotoError::Unknown(response))
-						if response.status.is_server_error() && errors < 10 =>
-					{
-						errors += 1;
-						continue;
+				let res = retry(policy, || {
+					self_.inner.client.get_object(GetObjectRequest {
+						bucket: self_.inner.bucket.clone(),
+						key: self_.inner.key.clone(),
+						range: Some(format!("bytes={}-{}", start, end)),
+						..GetObjectRequest::default()
+					})
+				})
+				.await
+				.map_err(io_error)?;
+				let body = super::body(res.body, policy).map_err(io_error)?;
+				let mut read = StreamingBody::new(body).into_async_read();
+				let mut error = None;
+				while !buf.is_empty() {
+					match read.read_buf(&mut buf).await {
+						Ok(0) => break,
+						Ok(n) => {
+							pos += u64::try_from(n).unwrap();
+						}
+						Err(err) => {
+							error = Some(err);
+							break;
+						}
 					}
-					_ => (),
 				}
-				let mut read = res.unwrap().body.unwrap().into_async_read();
-				while !buf.is_empty() {
-					let res = read.read_buf(&mut buf).await;
-					match res {
-						Ok(0) | Err(_) => break,
-						Ok(n) => pos += u64::try_from(n).unwrap(),
+				if !buf.is_empty() {
+					// The body was cut short, so request the rest after backing off
+					attempt += 1;
+					if attempt >= policy.get_max_attempts() {
+						let error = error.unwrap_or_else(|| io::ErrorKind::UnexpectedEof.into());
+						return Err(error.into());
 					}
+					policy.sleep(attempt - 1).await;
 				}
 			}
 			Ok(buf_)
 		})
 	}
+	/// Writes must be sequential, starting at offset 0. They're buffered and uploaded
+	/// as the parts of a multipart upload, that's completed by [`close`](Page::close).
 	fn write(
-		&self, _offset: u64, _buf: Box<[u8]>,
+		&self, offset: u64, buf: Box<[u8]>,
 	) -> LocalBoxFuture<'static, Result<(), Self::Error>> {
-		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/61")
+		let inner = self.inner.clone();
+		Box::pin(async move {
+			let mut upload = inner.upload.lock().await;
+			if offset != upload.written {
+				return Err(io::Error::new(
+					io::ErrorKind::InvalidInput,
+					"S3 objects can only be written sequentially",
+				)
+				.into());
+			}
+			upload.written += u64::try_from(buf.len()).unwrap();
+			upload.buf.extend_from_slice(&buf);
+			if upload.buf.len() >= PART_SIZE {
+				if let Err(err) = inner.upload_part(&mut upload).await {
+					inner.abort(mem::take(&mut *upload)).await;
+					return Err(io_error(err));
+				}
+			}
+			Ok(())
+		})
 	}
+	fn close(&self) -> LocalBoxFuture<'static, Result<(), Self::Error>> {
+		let inner = self.inner.clone();
+		Box::pin(async move {
+			let mut upload = inner.upload.lock().await;
+			if let Err(err) = inner.complete(&mut upload).await {
+				inner.abort(mem::take(&mut *upload)).await;
+				return Err(io_error(err));
+			}
+			Ok(())
+		})
+	}
+}
+
+fn io_error<E: Into<AwsError>>(err: E) -> IoError {
+	io::Error::new(io::ErrorKind::Other, err.into()).into()
 }
@@ -35,19 +35,25 @@ mod cloudfront;
 mod file;
 
 use async_trait::async_trait;
-use futures::{stream, StreamExt, TryFutureExt, TryStreamExt};
+use futures::{stream, Stream, StreamExt, TryStreamExt};
+use http::StatusCode;
 use once_cell::sync::Lazy;
 use rusoto_core::{
-	credential::StaticProvider, request::{DispatchSignedRequest, DispatchSignedRequestFuture, HttpClient}, signature::SignedRequest, RusotoError
+	credential::StaticProvider, request::{DispatchSignedRequest, DispatchSignedRequestFuture, HttpClient, HttpDispatchError}, signature::SignedRequest, RusotoError
 };
-use rusoto_credential::{CredentialsError, DefaultCredentialsProvider, ProvideAwsCredentials};
-use rusoto_s3::{GetObjectError, ListObjectsV2Error, ListObjectsV2Request, Object, S3Client, S3};
+use rusoto_credential::{
+	AutoRefreshingProvider, CredentialsError, DefaultCredentialsProvider, ProfileProvider, ProvideAwsCredentials
+};
+use rusoto_s3::{
+	AbortMultipartUploadError, CompleteMultipartUploadError, CreateMultipartUploadError, DeleteObjectError, GetObjectError, HeadObjectError, ListObjectsV2Error, ListObjectsV2Request, Object, PutObjectError, S3Client, UploadPartError, S3
+};
+use rusoto_sts::{StsAssumeRoleSessionCredentialsProvider, StsClient};
 use serde::{Deserialize, Serialize};
 use std::{
-	error, fmt::{self, Display}, future::Future, io, ops::FnMut, time::Duration
+	collections::{hash_map::Entry, HashMap}, error, fmt::{self, Display}, future::Future, io, ops::FnMut, pin::Pin, sync::{Arc, Mutex}, time::Duration
 };
 
-use amadeus_core::util::{IoError, ResultExpand};
+use amadeus_core::util::{IoError, ResultExpand, RetryPolicy};
 
 #[doc(inline)]
 pub use cloudfront::{Cloudfront, CloudfrontRow};
@@ -62,27 +68,88 @@ static RUSOTO_DISPATCHER: Lazy<HttpClient> =
 	Lazy::new(|| HttpClient::new().expect("failed to create request dispatcher"));
 static RUSOTO_CREDENTIALS_PROVIDER: Lazy<DefaultCredentialsProvider> =
 	Lazy::new(|| DefaultCredentialsProvider::new().expect("failed to create credentials provider"));
+static RUSOTO_ASSUMED_ROLES: Lazy<Mutex<HashMap<AssumedRoleKey, Arc<AssumedRoleProvider>>>> =
+	Lazy::new(|| Mutex::new(HashMap::new()));
+type AssumedRoleProvider = AutoRefreshingProvider<StsAssumeRoleSessionCredentialsProvider>;
+#[derive(PartialEq, Eq, Hash)]
+struct AssumedRoleKey {
+	role_arn: String,
+	session_name: String,
+	external_id: Option<String>,
+	region: AwsRegion,
+	source: String,
+}
 
-fn retry<F, FU, T, S>(f: F) -> impl Future<Output = Result<T, RusotoError<S>>>
+async fn retry<F, FU, T, S>(policy: &RetryPolicy, f: F) -> Result<T, RusotoError<S>>
 where
-	F: FnMut() -> FU + Unpin,
+	F: FnMut() -> FU,
 	FU: Future<Output = Result<T, RusotoError<S>>>,
 {
-	futures_retry::FutureRetry::new(f, |err| match err {
-		RusotoError::HttpDispatch(_) => {
-			futures_retry::RetryPolicy::WaitRetry(std::time::Duration::from_millis(10))
-		}
-		RusotoError::Unknown(response) if response.status.is_server_error() => {
-			futures_retry::RetryPolicy::WaitRetry(std::time::Duration::from_millis(10))
+	policy
+		.retry(
+			f,
+			|err| match err {
+				RusotoError::HttpDispatch(_) => true,
+				// Includes throttling, i.e. 503 Slow Down
+				RusotoError::Unknown(response) => {
+					response.status.is_server_error()
+						|| response.status == StatusCode::TOO_MANY_REQUESTS
+				}
+				_ => false,
+			},
+			|timeout| {
+				RusotoError::HttpDispatch(HttpDispatchError::new(format!(
+					"request timed out after {:?}",
+					timeout
+				)))
+			},
+		)
+		.await
+}
+
+type Body<T> = Pin<Box<dyn Stream<Item = Result<T, io::Error>> + Send + Sync>>;
+
+/// The body of a response, that fails with [`io::ErrorKind::TimedOut`] and ends if
+/// it stalls for longer than `policy`'s timeout.
+fn body<S, T>(
+	body: Option<S>, policy: &RetryPolicy,
+) -> Result<Body<T>, AwsError>
+where
+	S: Stream<Item = Result<T, io::Error>> + Send + Sync + Unpin + 'static,
+	T: Send + 'static,
+{
+	let body = body.ok_or_else(|| AwsError::ParseError(String::from("response has no body")))?;
+	let timeout = policy.get_timeout();
+	Ok(Box::pin(stream::unfold(Some(body), move |body| async move {
+		let mut body = body?;
+		let next = match timeout {
+			Some(timeout) => tokio::time::timeout(timeout, body.next())
+				.await
+				.map_err(|_| timeout),
+			None => Ok(body.next().await),
+		};
+		match next {
+			Ok(next) => next.map(|next| (next, Some(body))),
+			Err(timeout) => Some((
+				Err(io::Error::new(
+					io::ErrorKind::TimedOut,
+					format!("body stalled for {:?}", timeout),
+				)),
+				None,
+			)),
 		}
-		e => futures_retry::RetryPolicy::ForwardError(e),
-	})
-	.map_ok(|(x, _)| x)
-	.map_err(|(x, _)| x)
+	})))
+}
+
+fn custom_endpoint(region: &AwsRegion, endpoint: &str) -> AwsRegion {
+	AwsRegion::Custom {
+		name: region.name().to_owned(),
+		endpoint: endpoint.to_owned(),
+	}
 }
 
 async fn list(
-	client: &S3Client, bucket: &str, prefix: &str,
+	client: &S3Client, policy: &RetryPolicy, bucket: &str, prefix: &str,
 ) -> Result<Vec<Object>, RusotoError<ListObjectsV2Error>> {
 	let (first, continuation_token) = (true, None);
 	let objects: Result<Vec<Object>, _> = stream::unfold(
@@ -94,7 +161,7 @@ async fn list(
 			first = false;
 			Some((
 				stream::iter(ResultExpand(
-					retry(|| {
+					retry(policy, || {
 						client.list_objects_v2(ListObjectsV2Request {
 							bucket: bucket.to_owned(),
 							prefix: Some(prefix.to_owned()),
@@ -118,6 +185,38 @@ async fn list(
 	objects
 }
 
+/// The objects directly under `prefix`, along with its common prefixes, i.e. the
+/// "directories" under it, each ending in `/`.
+async fn list_dir(
+	client: &S3Client, policy: &RetryPolicy, bucket: &str, prefix: &str,
+) -> Result<(Vec<Object>, Vec<String>), RusotoError<ListObjectsV2Error>> {
+	let (mut objects, mut prefixes) = (Vec::new(), Vec::new());
+	let mut continuation_token = None;
+	loop {
+		let res = retry(policy, || {
+			client.list_objects_v2(ListObjectsV2Request {
+				bucket: bucket.to_owned(),
+				prefix: Some(prefix.to_owned()),
+				delimiter: Some(String::from("/")),
+				continuation_token: continuation_token.clone(),
+				..ListObjectsV2Request::default()
+			})
+		})
+		.await?;
+		objects.extend(res.contents.unwrap_or_default());
+		prefixes.extend(
+			res.common_prefixes
+				.unwrap_or_default()
+				.into_iter()
+				.filter_map(|common_prefix| common_prefix.prefix),
+		);
+		continuation_token = res.next_continuation_token;
+		if continuation_token.is_none() {
+			break Ok((objects, prefixes));
+		}
+	}
+}
+
 struct Ref<T: 'static>(&'static T);
 impl<T: 'static> Copy for Ref<T> {}
 impl<T: 'static> Clone for Ref<T> {
@@ -136,11 +235,55 @@ where
 	}
 }
 
-#[derive(Clone, Eq, PartialEq, Serialize, Deserialize, Debug)]
+/// The credentials to sign requests with. Being plain data, they are serialized
+/// along with the files they are used for and resolved in each process.
+#[derive(Clone, Eq, PartialEq, Hash, Serialize, Deserialize, Debug)]
 pub enum AwsCredentials {
 	Anonymous,
-	AccessKey { id: String, secret: String },
+	AccessKey {
+		id: String,
+		secret: String,
+	},
+	/// Temporary credentials, as issued by STS.
+	SessionToken {
+		id: String,
+		secret: String,
+		token: String,
+	},
+	/// The credentials, chain of default providers included, of the process.
 	Environment,
+	/// A named profile from the shared credentials file, `~/.aws/credentials`
+	/// by default or `AWS_SHARED_CREDENTIALS_FILE` if set.
+	Profile {
+		name: String,
+	},
+	/// Credentials for `role_arn`, obtained from STS using the `source`
+	/// credentials and refreshed before they expire. `region` is that of the STS
+	/// endpoint called, which can be an [`AwsRegion::Custom`] endpoint.
+	AssumeRole {
+		role_arn: String,
+		session_name: String,
+		external_id: Option<String>,
+		source: Box<AwsCredentials>,
+		region: AwsRegion,
+	},
+}
+impl AwsCredentials {
+	/// Who these credentials are for, leaving out any secrets.
+	fn identity(&self) -> String {
+		match self {
+			Self::Anonymous => String::from("anonymous"),
+			Self::AccessKey { id, .. } | Self::SessionToken { id, .. } => format!("key:{}", id),
+			Self::Environment => String::from("environment"),
+			Self::Profile { name } => format!("profile:{}", name),
+			Self::AssumeRole {
+				role_arn,
+				session_name,
+				source,
+				..
+			} => format!("role:{}:{}:{}", role_arn, session_name, source.identity()),
+		}
+	}
 }
 impl Default for AwsCredentials {
 	fn default() -> Self {
@@ -163,7 +306,60 @@ impl ProvideAwsCredentials for AwsCredentials {
 					.await
 			}
 
+			AwsCredentials::SessionToken { id, secret, token } => {
+				StaticProvider::new(id.clone(), secret.clone(), Some(token.clone()), None)
+					.credentials()
+					.await
+			}
+
 			AwsCredentials::Environment => RUSOTO_CREDENTIALS_PROVIDER.credentials().await,
+
+			AwsCredentials::Profile { name } => {
+				ProfileProvider::with_default_credentials(name)?
+					.credentials()
+					.await
+			}
+
+			AwsCredentials::AssumeRole {
+				role_arn,
+				session_name,
+				external_id,
+				source,
+				region,
+			} => {
+				// Cached, keyed by the role, STS region and who assumes it, so that STS is only
+				// called again once the credentials near expiry
+				let key = AssumedRoleKey {
+					role_arn: role_arn.clone(),
+					session_name: session_name.clone(),
+					external_id: external_id.clone(),
+					region: region.clone(),
+					source: source.identity(),
+				};
+				let provider = match RUSOTO_ASSUMED_ROLES.lock().unwrap().entry(key) {
+					Entry::Occupied(entry) => entry.get().clone(),
+					Entry::Vacant(entry) => {
+						let client = StsClient::new_with(
+							Ref(&*RUSOTO_DISPATCHER),
+							(**source).clone(),
+							region.clone(),
+						);
+						let provider = StsAssumeRoleSessionCredentialsProvider::new(
+							client,
+							role_arn.clone(),
+							session_name.clone(),
+							external_id.clone(),
+							None,
+							None,
+							None,
+						);
+						entry
+							.insert(Arc::new(AutoRefreshingProvider::new(provider)?))
+							.clone()
+					}
+				};
+				provider.credentials().await
+			}
 		}
 	}
 }
@@ -271,3 +467,42 @@ impl From<GetObjectError> for AwsError {
 		}
 	}
 }
+impl From<HeadObjectError> for AwsError {
+	fn from(err: HeadObjectError) -> Self {
+		match err {
+			HeadObjectError::NoSuchKey(err) => Self::NoSuchKey(err),
+		}
+	}
+}
+impl From<CreateMultipartUploadError> for AwsError {
+	fn from(err: CreateMultipartUploadError) -> Self {
+		match err {}
+	}
+}
+impl From<UploadPartError> for AwsError {
+	fn from(err: UploadPartError) -> Self {
+		match err {}
+	}
+}
+impl From<CompleteMultipartUploadError> for AwsError {
+	fn from(err: CompleteMultipartUploadError) -> Self {
+		match err {}
+	}
+}
+impl From<AbortMultipartUploadError> for AwsError {
+	fn from(err: AbortMultipartUploadError) -> Self {
+		match err {
+			AbortMultipartUploadError::NoSuchUpload(err) => Self::Validation(err),
+		}
+	}
+}
+impl From<PutObjectError> for AwsError {
+	fn from(err: PutObjectError) -> Self {
+		match err {}
+	}
+}
+impl From<DeleteObjectError> for AwsError {
+	fn from(err: DeleteObjectError) -> Self {
+		match err {}
+	}
+}
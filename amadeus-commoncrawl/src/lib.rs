@@ -32,13 +32,16 @@ mod commoncrawl;
 mod parser;
 
 use async_compression::futures::bufread::GzipDecoder; // TODO: use stream or https://github.com/alexcrichton/flate2-rs/pull/214
-use futures::{io::BufReader, AsyncBufReadExt, FutureExt, Stream, StreamExt, TryStreamExt};
-use reqwest_resume::ClientExt;
+use bytes::Bytes;
+use futures::{
+	io::BufReader, stream::{self, BoxStream}, AsyncBufReadExt, FutureExt, Stream, StreamExt, TryFutureExt, TryStreamExt
+};
+use reqwest::{header::RANGE, StatusCode, Url};
 use serde_closure::FnMutNamed;
-use std::{io, time};
+use std::{convert::TryFrom, io};
 
 use amadeus_core::{
-	into_par_stream::IntoDistributedStream, par_stream::DistributedStream, util::DistParStream, Source
+	into_par_stream::IntoDistributedStream, par_stream::DistributedStream, util::{DistParStream, RetryPolicy}, Source
 };
 use amadeus_types::Webpage;
 
@@ -48,38 +51,134 @@ use commoncrawl::WarcParser;
 #[derive(Clone, Debug)]
 pub struct CommonCrawl {
 	urls: Vec<String>,
+	retry: RetryPolicy,
 }
 impl CommonCrawl {
 	/// CC-MAIN-2020-24
-	pub async fn new(id: &str) -> Result<Self, reqwest::Error> {
+	pub async fn new(id: &str) -> Result<Self, io::Error> {
+		Self::new_with_retry(id, RetryPolicy::default()).await
+	}
+	/// Like [`new`](Self::new), retrying failed requests, both for the index and
+	/// for the WARC files, according to `retry`.
+	pub async fn new_with_retry(id: &str, retry: RetryPolicy) -> Result<Self, io::Error> {
 		let url = format!(
 			"https://commoncrawl.s3.amazonaws.com/crawl-data/{}/warc.paths.gz",
 			id
 		);
-		let body = reqwest::ClientBuilder::new()
-			.timeout(time::Duration::new(120, 0))
-			.build()
-			.unwrap()
-			.resumable()
-			.get(url.parse().unwrap())
-			.send();
-		let body = body
-			.await?
-			.bytes_stream()
-			.map_err(|e| io::Error::new(io::ErrorKind::Other, e));
+		let body = get(url.parse().unwrap(), retry);
 		let body = BufReader::new(body.into_async_read());
 		let mut body = GzipDecoder::new(body); // Content-Encoding isn't set, so decode manually
 		body.multiple_members(true);
 
 		let urls = BufReader::new(body)
 			.lines()
-			.map(|url: Result<String, io::Error>| -> String {
-				format!("http://commoncrawl.s3.amazonaws.com/{}", url.unwrap())
+			.map_ok(|url: String| -> String {
+				format!("http://commoncrawl.s3.amazonaws.com/{}", url)
 			})
-			.collect()
-			.await;
-		Ok(Self { urls })
+			.try_collect()
+			.await?;
+		Ok(Self { urls, retry })
+	}
+}
+
+/// GET `url`, retrying failed requests and resuming the body from where it got
+/// to if the connection drops or stalls for longer than the policy's timeout,
+/// until `policy` is exhausted. Attempts are counted across the whole body rather
+/// than reset by each chunk received.
+fn get(url: Url, policy: RetryPolicy) -> impl Stream<Item = Result<Bytes, io::Error>> + Send {
+	let client = reqwest::Client::new();
+	let state: (Option<BoxStream<'static, reqwest::Result<Bytes>>>, u64, u32) = (None, 0, 0);
+	stream::try_unfold(state, move |(mut body, mut pos, mut attempt)| {
+		let (client, url) = (client.clone(), url.clone());
+		async move {
+			loop {
+				if body.is_none() {
+					body = Some(
+						request(&client, &url, pos, &policy)
+							.await?
+							.bytes_stream()
+							.boxed(),
+					);
+				}
+				let next = body.as_mut().unwrap().next().map(|next| {
+					next.transpose()
+						.map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+				});
+				let next = match policy.get_timeout() {
+					Some(timeout) => {
+						tokio::time::timeout(timeout, next)
+							.await
+							.unwrap_or_else(|_| {
+								Err(io::Error::new(
+									io::ErrorKind::TimedOut,
+									format!("body stalled for {:?}", timeout),
+								))
+							})
+					}
+					None => next.await,
+				};
+				match next {
+					Ok(Some(bytes)) => {
+						pos += u64::try_from(bytes.len()).unwrap();
+						break Ok(Some((bytes, (body, pos, attempt))));
+					}
+					Ok(None) => break Ok(None),
+					Err(err) => {
+						attempt += 1;
+						if attempt >= policy.get_max_attempts() {
+							break Err(err);
+						}
+						body = None;
+						policy.sleep(attempt - 1).await;
+					}
+				}
+			}
+		}
+	})
+}
+
+async fn request(
+	client: &reqwest::Client, url: &Url, pos: u64, policy: &RetryPolicy,
+) -> Result<reqwest::Response, io::Error> {
+	let response = policy
+		.retry(
+			|| {
+				let mut request = client.get(url.clone());
+				if pos != 0 {
+					request = request.header(RANGE, format!("bytes={}-", pos));
+				}
+				request
+					.send()
+					.and_then(|response| async { response.error_for_status() })
+					.map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+			},
+			|err: &io::Error| {
+				let err = err
+					.get_ref()
+					.unwrap()
+					.downcast_ref::<reqwest::Error>()
+					.unwrap();
+				// Includes throttling, i.e. 503 Slow Down
+				err.status()
+					.map_or(!err.is_builder() && !err.is_redirect(), |status| {
+						status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+					})
+			},
+			|timeout| {
+				io::Error::new(
+					io::ErrorKind::TimedOut,
+					format!("request timed out after {:?}", timeout),
+				)
+			},
+		)
+		.await?;
+	if pos != 0 && response.status() != StatusCode::PARTIAL_CONTENT {
+		return Err(io::Error::new(
+			io::ErrorKind::Other,
+			format!("{} doesn't support resuming from an offset", url),
+		));
 	}
+	Ok(response)
 }
 
 #[cfg(not(nightly))]
@@ -88,13 +187,11 @@ type Output = std::pin::Pin<Box<dyn Stream<Item = Result<Webpage<'static>, io::E
 type Output = impl Stream<Item = Result<Webpage<'static>, io::Error>> + Send;
 
 FnMutNamed! {
-	pub type Closure<> = |self|url=> String| -> Output where {
+	pub type Closure<> = |self, policy: RetryPolicy|url=> String| -> Output where {
+		let policy = self.policy;
 		#[allow(clippy::let_and_return)]
 		let ret = async move {
-				let body = reqwest_resume::get(url.parse().unwrap()).await.unwrap();
-				let body = body
-					.bytes_stream()
-					.map_err(|e| io::Error::new(io::ErrorKind::Other, e));
+				let body = get(url.parse().unwrap(), policy);
 				let body = BufReader::new(body.into_async_read());
 				let mut body = GzipDecoder::new(body); // Content-Encoding isn't set, so decode manually
 				body.multiple_members(true);
@@ -126,6 +223,8 @@ impl Source for CommonCrawl {
 	}
 	#[allow(clippy::let_and_return)]
 	fn dist_stream(self) -> Self::DistStream {
-		self.urls.into_dist_stream().flat_map(Closure::new())
+		self.urls
+			.into_dist_stream()
+			.flat_map(Closure::new(self.retry))
 	}
 }
@@ -2,11 +2,13 @@
 
 #![allow(clippy::type_complexity)]
 
+mod hive;
 mod local;
 
 use async_trait::async_trait;
-use futures::{future::LocalBoxFuture, ready};
+use futures::{future, future::LocalBoxFuture, ready, FutureExt};
 use pin_project::pin_project;
+use serde::{Deserialize, Serialize};
 use std::{
 	convert::TryFrom, error::Error, ffi, fmt, future::Future, io, pin::Pin, sync::Arc, task::{Context, Poll}
 };
@@ -14,6 +16,7 @@ use widestring::U16String;
 
 use crate::pool::ProcessSend;
 
+pub use hive::{HiveDirectory, HivePartition, PartitionValues};
 pub use local::LocalFile;
 
 const PAGE_SIZE: usize = 10 * 1024 * 1024; // `Reader` reads this many bytes at a time
@@ -185,9 +188,11 @@ impl fmt::Debug for PathBuf {
 
 #[async_trait(?Send)]
 pub trait Directory: File {
+	/// The partitions of the files under this directory, along with their paths relative
+	/// to it. `f` is called on each directory and file, and those it rejects are skipped.
 	async fn partitions_filter<F>(
 		self, f: F,
-	) -> Result<Vec<<Self as File>::Partition>, <Self as File>::Error>
+	) -> Result<Vec<(PathBuf, <Self as File>::Partition)>, <Self as File>::Error>
 	where
 		F: FnMut(&PathBuf) -> bool;
 }
@@ -205,7 +210,44 @@ pub trait Partition: Clone + fmt::Debug + ProcessSend + 'static {
 	type Error: Error + Clone + PartialEq + ProcessSend + 'static;
 
 	async fn pages(self) -> Result<Vec<Self::Page>, Self::Error>;
+	/// The values of the Hive-style partition columns of this partition, if it came
+	/// from a partitioned directory. See [`HiveDirectory`].
+	fn partition_values(&self) -> Option<&PartitionValues> {
+		None
+	}
+}
+/// A location, such as a local directory or an S3 prefix, that new files can be
+/// created under. Used by destinations to write their output.
+#[async_trait(?Send)]
+pub trait Create: Clone + fmt::Debug + ProcessSend + 'static {
+	type Page: Page + 'static;
+	type Error: Error + Clone + PartialEq + ProcessSend + 'static;
+
+	/// Apply `mode` to the files already under this location, including those in its
+	/// Hive-style `key=value` partition directories. Destinations call this once, when
+	/// they're built, before any worker starts writing.
+	async fn prepare(&self, mode: WriteMode) -> Result<(), Self::Error>;
+	/// Create the file `name` under this location, truncating it if it already exists.
+	/// `name` may have `/`-separated directories, which are created as needed.
+	async fn create(&self, name: &str) -> Result<Self::Page, Self::Error>;
+}
+
+/// What a destination does with files that already exist where it's writing.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub enum WriteMode {
+	/// Remove existing files, replacing them with the written ones.
+	Create,
+	/// Leave existing files in place, adding the written ones alongside them.
+	Append,
+	/// Fail if there are any existing files.
+	FailIfExists,
 }
+impl Default for WriteMode {
+	fn default() -> Self {
+		Self::FailIfExists
+	}
+}
+
 #[allow(clippy::len_without_is_empty)]
 pub trait Page {
 	type Error: Error + Clone + PartialEq + Into<io::Error> + ProcessSend + 'static;
@@ -217,6 +259,11 @@ pub trait Page {
 	fn write(
 		&self, offset: u64, buf: Box<[u8]>,
 	) -> LocalBoxFuture<'static, Result<(), Self::Error>>;
+	/// Called once the last write has completed, for pages that need to finalise
+	/// what's been written, such as completing an S3 multipart upload.
+	fn close(&self) -> LocalBoxFuture<'static, Result<(), Self::Error>> {
+		future::ready(Ok(())).boxed_local()
+	}
 
 	fn reader(self) -> Reader<Self>
 	where
@@ -245,6 +292,9 @@ where
 	) -> LocalBoxFuture<'static, Result<(), Self::Error>> {
 		(**self).write(offset, buf)
 	}
+	fn close(&self) -> LocalBoxFuture<'static, Result<(), Self::Error>> {
+		(**self).close()
+	}
 }
 impl<T: ?Sized> Page for Arc<T>
 where
@@ -265,6 +315,9 @@ where
 	) -> LocalBoxFuture<'static, Result<(), Self::Error>> {
 		(**self).write(offset, buf)
 	}
+	fn close(&self) -> LocalBoxFuture<'static, Result<(), Self::Error>> {
+		(**self).close()
+	}
 }
 
 #[pin_project]
@@ -0,0 +1,247 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::{
+	fmt::{self, Write}, rc::Rc
+};
+
+use super::{Directory, File, Partition, PathBuf};
+
+/// The name Hive gives the directory of rows whose partition column is null.
+const DEFAULT_PARTITION: &str = "__HIVE_DEFAULT_PARTITION__";
+
+/// The values of the partition columns of a Hive-style partitioned dataset, parsed from
+/// the `key=value` directories of a path such as `year=2018/month=11/part-0.parquet`.
+#[derive(Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize, Debug)]
+pub struct PartitionValues(Vec<(String, Option<String>)>);
+impl PartitionValues {
+	/// The values of these partition columns, with `None` for Hive's default
+	/// partition, i.e. null.
+	pub fn new(values: Vec<(String, Option<String>)>) -> Self {
+		Self(values)
+	}
+	/// Returns the value of the partition column `key`, or `None` if the path doesn't
+	/// have it. The value is `Some(None)` for Hive's default partition, i.e. null.
+	pub fn get(&self, key: &str) -> Option<Option<&str>> {
+		self.0
+			.iter()
+			.find(|(key_, _)| key_ == key)
+			.map(|(_, value)| value.as_deref())
+	}
+	pub fn iter(&self) -> impl Iterator<Item = (&str, Option<&str>)> + '_ {
+		self.0.iter().map(|(key, value)| (&**key, value.as_deref()))
+	}
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+	/// The `key=value` directories these values are parsed from, like
+	/// `year=2018/month=11`, escaped as Hive does.
+	pub fn path(&self) -> String {
+		let mut path = String::new();
+		for (key, value) in &self.0 {
+			if !path.is_empty() {
+				path.push('/');
+			}
+			escape(key, &mut path);
+			path.push('=');
+			match value {
+				Some(value) => escape(value, &mut path),
+				None => path.push_str(DEFAULT_PARTITION),
+			}
+		}
+		path
+	}
+}
+
+impl PathBuf {
+	/// Parses the Hive-style partition columns out of the directories of this path.
+	/// Directories that aren't of the form `key=value` are ignored.
+	pub fn partition_values(&self) -> PartitionValues {
+		PartitionValues(
+			self.iter()
+				.filter_map(|component| {
+					let component = component.to_string_lossy();
+					let offset = component.find('=').filter(|&offset| offset > 0)?;
+					let (key, value) = (&component[..offset], &component[offset + 1..]);
+					let value = if value == DEFAULT_PARTITION {
+						None
+					} else {
+						Some(unescape(value))
+					};
+					Some((unescape(key), value))
+				})
+				.collect(),
+		)
+	}
+}
+
+/// Hive percent-encodes characters like `/` and `=` in partition directory names.
+fn escape(s: &str, to: &mut String) {
+	for char in s.chars() {
+		if char.is_ascii_control() || "\"#%'*/:=?\\[]^{".contains(char) {
+			write!(to, "%{:02X}", u32::from(char)).unwrap();
+		} else {
+			to.push(char);
+		}
+	}
+}
+fn unescape(s: &str) -> String {
+	let mut bytes = Vec::with_capacity(s.len());
+	let mut rest = s.as_bytes();
+	while let Some((&byte, rest_)) = rest.split_first() {
+		rest = rest_;
+		let escaped = if byte == b'%' && rest.len() >= 2 {
+			std::str::from_utf8(&rest[..2])
+				.ok()
+				.and_then(|hex| u8::from_str_radix(hex, 16).ok())
+		} else {
+			None
+		};
+		if let Some(escaped) = escaped {
+			bytes.push(escaped);
+			rest = &rest[2..];
+		} else {
+			bytes.push(byte);
+		}
+	}
+	String::from_utf8(bytes).unwrap_or_else(|_| s.to_owned())
+}
+
+/// A partition of a Hive-style partitioned directory, along with the values of its
+/// partition columns.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct HivePartition<P> {
+	partition: P,
+	values: PartitionValues,
+}
+impl<P> HivePartition<P> {
+	pub fn new(partition: P, values: PartitionValues) -> Self {
+		Self { partition, values }
+	}
+	pub fn into_inner(self) -> P {
+		self.partition
+	}
+}
+#[async_trait(?Send)]
+impl<P> Partition for HivePartition<P>
+where
+	P: Partition,
+{
+	type Page = P::Page;
+	type Error = P::Error;
+
+	async fn pages(self) -> Result<Vec<Self::Page>, Self::Error> {
+		self.partition.pages().await
+	}
+	fn partition_values(&self) -> Option<&PartitionValues> {
+		Some(&self.values)
+	}
+}
+
+/// A Hive-style partitioned directory, like `table/year=2018/month=11/part-0.csv`.
+///
+/// Its partitions carry the values of the partition columns parsed from their paths,
+/// which sources use to fill the fields of the row type marked `#[amadeus(partition)]`.
+/// Hidden files and directories, whose names start with `.` or `_`, are skipped.
+#[derive(Serialize, Deserialize)]
+pub struct HiveDirectory<D> {
+	directory: D,
+	#[serde(skip)]
+	filter: Option<Rc<dyn Fn(&PartitionValues) -> bool>>,
+}
+impl<D> HiveDirectory<D> {
+	pub fn new(directory: D) -> Self {
+		Self {
+			directory,
+			filter: None,
+		}
+	}
+	/// Only reads the partitions whose partition column values satisfy `filter`.
+	///
+	/// Directories are pruned as they're reached, without listing their contents, so
+	/// `filter` is also called with the values of directories only part of the way
+	/// down, and should accept values that are missing.
+	///
+	/// ```
+	/// # use amadeus_core::file::HiveDirectory;
+	/// let directory = HiveDirectory::new(std::path::PathBuf::from("logs"))
+	///     .filter(|values| values.get("year").map_or(true, |year| year == Some("2018")));
+	/// ```
+	#[must_use]
+	pub fn filter<F>(self, filter: F) -> Self
+	where
+		F: Fn(&PartitionValues) -> bool + 'static,
+	{
+		Self {
+			filter: Some(Rc::new(filter)),
+			..self
+		}
+	}
+}
+impl<D> fmt::Debug for HiveDirectory<D>
+where
+	D: fmt::Debug,
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("HiveDirectory")
+			.field("directory", &self.directory)
+			.finish_non_exhaustive()
+	}
+}
+#[async_trait(?Send)]
+impl<D> File for HiveDirectory<D>
+where
+	D: Directory,
+{
+	type Partition = HivePartition<D::Partition>;
+	type Error = D::Error;
+
+	async fn partitions(self) -> Result<Vec<Self::Partition>, Self::Error> {
+		Ok(self
+			.partitions_filter(|_| true)
+			.await?
+			.into_iter()
+			.map(|(_, partition)| partition)
+			.collect())
+	}
+}
+#[async_trait(?Send)]
+impl<D> Directory for HiveDirectory<D>
+where
+	D: Directory,
+{
+	async fn partitions_filter<F>(
+		self, mut f: F,
+	) -> Result<Vec<(PathBuf, <Self as File>::Partition)>, <Self as File>::Error>
+	where
+		F: FnMut(&PathBuf) -> bool,
+	{
+		let filter = self.filter;
+		Ok(self
+			.directory
+			.partitions_filter(|path| {
+				let name = if path.is_file() {
+					path.file_name()
+				} else {
+					path.last()
+				}
+				.unwrap()
+				.to_string_lossy();
+				let skip = name.starts_with('.')
+					|| (name.starts_with('_') && (path.is_file() || !name.contains('=')))
+					|| name.ends_with("_$folder$"); // This is created by Apache tools on S3
+				!skip
+					&& (path.is_file()
+						|| filter
+							.as_ref()
+							.map_or(true, |filter| filter(&path.partition_values())))
+					&& f(path)
+			})
+			.await?
+			.into_iter()
+			.map(|(path, partition)| {
+				let values = path.partition_values();
+				(path, HivePartition::new(partition, values))
+			})
+			.collect())
+	}
+}
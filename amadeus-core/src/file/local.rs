@@ -20,7 +20,7 @@ use {
 	std::io::{Seek, SeekFrom}, tokio::task::spawn_blocking
 };
 
-use super::{Directory, File, Page, Partition};
+use super::{Create, Directory, File, Page, Partition, WriteMode};
 #[cfg(target_arch = "wasm32")]
 use crate::util::{f64_to_u64, u64_to_f64};
 use crate::util::{IoError, ResultExpand};
@@ -72,7 +72,7 @@ impl File for PathBuf {
 impl Directory for PathBuf {
 	async fn partitions_filter<F>(
 		self, f: F,
-	) -> Result<Vec<<Self as File>::Partition>, <Self as File>::Error>
+	) -> Result<Vec<(super::PathBuf, <Self as File>::Partition)>, <Self as File>::Error>
 	where
 		F: FnMut(&super::PathBuf) -> bool,
 	{
@@ -89,39 +89,99 @@ impl Partition for PathBuf {
 	}
 }
 #[async_trait(?Send)]
+impl Create for PathBuf {
+	type Page = LocalFile;
+	type Error = IoError;
+
+	async fn prepare(&self, mode: WriteMode) -> Result<(), Self::Error> {
+		if mode == WriteMode::Append {
+			return Ok(());
+		}
+		Ok(prepare(self, mode)?)
+	}
+	async fn create(&self, name: &str) -> Result<Self::Page, Self::Error> {
+		let path = self.join(name);
+		fs::create_dir_all(path.parent().unwrap())?;
+		Ok(LocalFile::create(path)?)
+	}
+}
+/// Apply `mode` to the files in `dir`, and in its partition directories.
+fn prepare(dir: &Path, mode: WriteMode) -> io::Result<()> {
+	let entries = match fs::read_dir(dir) {
+		Ok(entries) => entries,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+		Err(e) => return Err(e),
+	};
+	for entry in entries {
+		let entry = entry?;
+		if entry.file_type()?.is_dir() {
+			let name = entry.file_name();
+			let partition = name
+				.to_str()
+				.and_then(|name| name.find('='))
+				.map_or(false, |offset| offset > 0);
+			if partition {
+				prepare(&entry.path(), mode)?;
+			}
+			continue;
+		}
+		match mode {
+			WriteMode::Create => fs::remove_file(entry.path()).or_else(|e| {
+				// Another write may have removed it first
+				if e.kind() == io::ErrorKind::NotFound {
+					Ok(())
+				} else {
+					Err(e)
+				}
+			})?,
+			WriteMode::FailIfExists => {
+				return Err(io::Error::new(
+					io::ErrorKind::AlreadyExists,
+					format!("{} already exists", entry.path().display()),
+				))
+			}
+			WriteMode::Append => unreachable!(),
+		}
+	}
+	Ok(())
+}
+#[async_trait(?Send)]
 impl Directory for &Path {
 	async fn partitions_filter<F>(
 		self, mut f: F,
-	) -> Result<Vec<<Self as File>::Partition>, <Self as File>::Error>
+	) -> Result<Vec<(super::PathBuf, <Self as File>::Partition)>, <Self as File>::Error>
 	where
 		F: FnMut(&super::PathBuf) -> bool,
 	{
+		let relative = |path: &Path, is_dir: bool| {
+			let mut path = path.strip_prefix(self).unwrap();
+			let mut path_buf = super::PathBuf::new();
+			let mut file_name = None;
+			if !is_dir {
+				// `self` is itself the file if it's relative to itself
+				file_name = path.file_name().or_else(|| self.file_name());
+				path = path.parent().unwrap_or(path);
+			}
+			for component in path {
+				path_buf.push(component);
+			}
+			path_buf.set_file_name(file_name);
+			path_buf
+		};
 		WalkDir::new(self)
 			.follow_links(true)
 			.sort_by(|a, b| a.file_name().cmp(b.file_name()))
 			.into_iter()
 			.filter_entry(|e| {
-				let is_dir = e.file_type().is_dir();
 				let path = e.path();
 				if path == self {
 					return true;
 				}
-				let mut path = path.strip_prefix(self).unwrap();
-				let mut path_buf = super::PathBuf::new();
-				let mut file_name = None;
-				if !is_dir {
-					file_name = Some(path.file_name().unwrap());
-					path = path.parent().unwrap();
-				}
-				for component in path {
-					path_buf.push(component);
-				}
-				path_buf.set_file_name(file_name);
-				f(&path_buf)
+				f(&relative(path, e.file_type().is_dir()))
 			})
 			.filter_map(|e| match e {
 				Ok(ref e) if e.file_type().is_dir() => None,
-				Ok(e) => Some(Ok(e.into_path())),
+				Ok(e) => Some(Ok((relative(e.path(), false), e.into_path()))),
 				Err(e) => Some(Err(if e.io_error().is_some() {
 					e.into_io_error().unwrap()
 				} else {
@@ -251,6 +311,27 @@ impl LocalFile {
 		}
 	}
 
+	/// [Creates](https://doc.rust-lang.org/std/fs/struct.File.html#method.create)
+	/// a file for random access, truncating it if it already exists.
+	pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+		#[cfg(not(target_arch = "wasm32"))]
+		{
+			Self::from_file(
+				fs::OpenOptions::new()
+					.read(true)
+					.write(true)
+					.create(true)
+					.truncate(true)
+					.open(path)?,
+			)
+		}
+		#[cfg(target_arch = "wasm32")]
+		{
+			let _path = path;
+			unimplemented!()
+		}
+	}
+
 	fn clone(&self) -> Self {
 		Self {
 			inner: self.inner.clone(),
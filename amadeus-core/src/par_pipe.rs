@@ -63,6 +63,24 @@ macro_rules! pipe {
 				$assert_pipe(FlatMap::new(self, f))
 			}
 
+			/// Pair each item with the windows of event time it falls in, by the time
+			/// `f` returns. Aggregate per window with
+			/// [`group_by_window`](Self::group_by_window).
+			///
+			/// Panics if the lengths of `windowing` aren't positive.
+			#[inline]
+			fn window<F, T, W>(self, f: F, windowing: W) -> FlatMapSync<Self, AssignWindows<F, W>>
+			where
+				F: $fns::FnMut(&Self::Output) -> T + Clone + $send + 'static,
+				T: EventTime,
+				W: Windowing<T> + Clone + $send + 'static,
+				Self::Output: Clone,
+				Self: Sized,
+			{
+				windowing.assert_positive();
+				$assert_pipe(FlatMapSync::new(self, AssignWindows::new(f, windowing)))
+			}
+
 			#[inline]
 			fn filter<F>(self, f: F) -> Filter<Self, F>
 			where
@@ -72,6 +90,19 @@ macro_rules! pipe {
 				$assert_pipe(Filter::new(self, f))
 			}
 
+			/// Keep only the items whose key, as returned by `f`, might be in `filter`,
+			/// such as a [`BloomFilter`](::amadeus_streaming::BloomFilter) of another
+			/// stream's keys. Some items whose key isn't in it may be kept.
+			#[inline]
+			fn filter_in<S, F, K>(self, filter: S, f: F) -> Filter<Self, FilterIn<S, F>>
+			where
+				S: ::amadeus_streaming::Contains<K> + Clone + $send + 'static,
+				F: $fns::FnMut(&Self::Output) -> K + Clone + $send + 'static,
+				Self: Sized,
+			{
+				$assert_pipe(Filter::new(self, FilterIn::new(filter, f)))
+			}
+
 			#[inline]
 			fn cloned<'a, T>(self) -> Cloned<Self, T, Input>
 			where
@@ -168,6 +199,35 @@ macro_rules! pipe {
 				$assert_sink(GroupBy::new(self, sink))
 			}
 
+			/// Group items by window, merging session windows that overlap, and
+			/// reduce each group with `sink`. The groups are returned in order of
+			/// window.
+			#[inline]
+			fn group_by_window<S, T, B>(self, sink: S) -> GroupByWindow<Self, S>
+			where
+				T: EventTime + Hash + $send + 'static,
+				S: $sink<B>,
+				<S::Pipe as $pipe<B>>::Task: Clone + $send + 'static,
+				S::ReduceA: 'static,
+				S::ReduceC: Clone,
+				S::Done: $send + 'static,
+				Self: $pipe<Input, Output = (Window<T>, B)> + Sized,
+			{
+				$assert_sink(GroupByWindow::new(self, sink))
+			}
+
+			#[inline]
+			fn repartition_by_key<F, K>(self, n: usize, f: F) -> RepartitionByKey<Self, F>
+			where
+				F: $fns::FnMut(&Self::Output) -> K + Clone + $send + 'static,
+				K: Hash + 'static,
+				Self::Output: ProcessSend + 'static,
+				Self: Sized,
+			{
+				assert_ne!(n, 0, "can't repartition into 0 partitions");
+				$assert_sink(RepartitionByKey::new(self, n, f))
+			}
+
 			#[inline]
 			fn histogram(self) -> Histogram<Self>
 			where
@@ -212,6 +272,14 @@ macro_rules! pipe {
 				$assert_sink(Mean::new(self))
 			}
 
+			#[inline]
+			fn quantiles(self, quantiles: &[f64]) -> Quantiles<Self>
+			where
+				Self: $pipe<Input, Output = f64> + Sized,
+			{
+				$assert_sink(Quantiles::new(self, quantiles.to_vec()))
+			}
+
 			#[inline]
 			fn stddev(self) -> StdDev<Self>
 			where
@@ -220,6 +288,55 @@ macro_rules! pipe {
 				$assert_sink(StdDev::new(self))
 			}
 
+			#[inline]
+			fn median(self) -> Median<Self>
+			where
+				Self: $pipe<Input, Output = f64> + Sized,
+			{
+				$assert_sink(Median::new(self))
+			}
+
+			#[inline]
+			fn quantile(self, q: f64) -> Quantile<Self>
+			where
+				Self: $pipe<Input, Output = f64> + Sized,
+			{
+				assert!((0.0..=1.0).contains(&q), "q must be between 0 and 1");
+				$assert_sink(Quantile::new(self, q))
+			}
+
+			#[inline]
+			fn min_max(self) -> MinMax<Self>
+			where
+				Self: $pipe<Input, Output = f64> + Sized,
+			{
+				$assert_sink(MinMax::new(self))
+			}
+
+			#[inline]
+			fn describe(self) -> Describe<Self>
+			where
+				Self: $pipe<Input, Output = f64> + Sized,
+			{
+				$assert_sink(Describe::new(self))
+			}
+
+			#[inline]
+			fn covariance(self) -> Covariance<Self>
+			where
+				Self: $pipe<Input, Output = (f64, f64)> + Sized,
+			{
+				$assert_sink(Covariance::new(self))
+			}
+
+			#[inline]
+			fn correlation(self) -> Correlation<Self>
+			where
+				Self: $pipe<Input, Output = (f64, f64)> + Sized,
+			{
+				$assert_sink(Correlation::new(self))
+			}
+
 			#[inline]
 			fn combine<F>(self, f: F) -> Combine<Self, F>
 			where
@@ -317,6 +434,53 @@ macro_rules! pipe {
 				))
 			}
 
+			#[inline]
+			fn distinct(self) -> Distinct<Self>
+			where
+				Self::Output: Eq + Hash + $send + 'static,
+				Self: Sized,
+			{
+				$assert_sink(Distinct::new(self))
+			}
+
+			#[inline]
+			fn distinct_by_key<F, K>(self, f: F) -> DistinctByKey<Self, F>
+			where
+				F: $fns::FnMut(&Self::Output) -> K + Clone + $send + 'static,
+				K: Eq + Hash + $send + 'static,
+				Self::Output: $send + 'static,
+				Self: Sized,
+			{
+				$assert_sink(DistinctByKey::new(self, f))
+			}
+
+			#[inline]
+			fn count_distinct(self, error_rate: f64) -> CountDistinct<Self>
+			where
+				Self::Output: Hash + 'static,
+				Self: Sized,
+			{
+				$assert_sink(CountDistinct::new(self, error_rate))
+			}
+
+			#[inline]
+			fn bloom_filter(self, n: usize, fp_rate: f64) -> BloomFilterSink<Self>
+			where
+				Self::Output: Hash + 'static,
+				Self: Sized,
+			{
+				$assert_sink(BloomFilterSink::new(self, n, fp_rate))
+			}
+
+			#[inline]
+			fn cuckoo_filter(self, n: usize, fp_rate: f64) -> CuckooFilterSink<Self>
+			where
+				Self::Output: Hash + 'static,
+				Self: Sized,
+			{
+				$assert_sink(CuckooFilterSink::new(self, n, fp_rate))
+			}
+
 			#[inline]
 			fn sample_unstable(self, samples: usize) -> SampleUnstable<Self>
 			where
@@ -4,6 +4,9 @@ mod collect;
 mod combine;
 mod combiner;
 mod count;
+mod describe;
+mod distinct;
+mod exchange;
 mod fold;
 mod folder;
 mod for_each;
@@ -12,17 +15,22 @@ mod group_by;
 mod histogram;
 mod max;
 mod mean;
+mod median;
 mod pipe;
+mod quantiles;
+mod repartition;
 mod sample;
+mod spill;
 mod stddev;
 mod sum;
 mod tuple;
+mod write;
 
 use super::par_pipe::*;
 use crate::{pipe::Sink, pool::ProcessSend};
 
 pub use self::{
-	all::*, any::*, collect::*, combine::*, combiner::*, count::*, fold::*, folder::*, for_each::*, fork::*, group_by::*, histogram::*, max::*, mean::*, pipe::*, sample::*, stddev::*, sum::*, tuple::*
+	all::*, any::*, collect::*, combine::*, combiner::*, count::*, describe::*, distinct::*, exchange::set_exchange_host, fold::*, folder::*, for_each::*, fork::*, group_by::*, histogram::*, max::*, mean::*, median::*, pipe::*, quantiles::*, repartition::*, sample::*, spill::*, stddev::*, sum::*, tuple::*, write::*
 };
 
 #[must_use]
@@ -8,9 +8,9 @@ use std::{
 };
 
 use super::{
-	DistributedPipe, DistributedSink, ParallelPipe, ParallelSink, Reducer, ReducerProcessSend, ReducerSend
+	folder_par_sink, spill::try_update, DistributedPipe, DistributedSink, FolderSync, FolderSyncReducer, ParallelPipe, ParallelSink, Reducer, ReducerProcessSend, ReducerSend, SpillVec
 };
-use crate::{pipe::Sink, pool::ProcessSend};
+use crate::{pipe::Sink, pool::ProcessSend, util::IoError};
 
 #[derive(new)]
 #[must_use]
@@ -47,6 +47,87 @@ impl<P: DistributedPipe<Item>, Item, T: FromDistributedStream<P::Output>> Distri
 	}
 }
 
+impl<P, T> Collect<P, Vec<T>> {
+	/// Hold at most `max_items_in_memory` items in memory in each task and in
+	/// the final result, spilling the rest to local temporary files.
+	///
+	/// The limit is a count of items, not a number of bytes: to keep within a
+	/// memory budget, divide it by the size of an item.
+	///
+	/// The result is a [`SpillVec`] that streams the items back from disk in the
+	/// order they'd have been collected into a [`Vec`], or the error
+	/// encountered writing or reading the temporary files.
+	pub fn spill(self, max_items_in_memory: usize) -> CollectSpill<P> {
+		assert_ne!(
+			max_items_in_memory, 0,
+			"max_items_in_memory must be at least 1"
+		);
+		CollectSpill::new(self.pipe, max_items_in_memory)
+	}
+}
+
+#[derive(new)]
+#[must_use]
+pub struct CollectSpill<P> {
+	pipe: P,
+	max_items_in_memory: usize,
+}
+
+impl_par_dist! {
+	impl<P: ParallelPipe<Item>, Item> ParallelSink<Item> for CollectSpill<P>
+	where
+		P::Output: ProcessSend + 'static,
+	{
+		folder_par_sink!(CollectSpillFolder<P::Output, StepA>, CollectSpillFolder<P::Output, StepB>, self, CollectSpillFolder::new(self.max_items_in_memory), CollectSpillFolder::new(self.max_items_in_memory));
+	}
+}
+
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone)]
+#[serde(bound = "")]
+pub struct CollectSpillFolder<B, Step> {
+	max_items_in_memory: usize,
+	marker: PhantomData<fn() -> (B, Step)>,
+}
+
+pub struct StepA;
+pub struct StepB;
+
+impl<Item> FolderSync<Item> for CollectSpillFolder<Item, StepA>
+where
+	Item: ProcessSend,
+{
+	type State = Result<SpillVec<Item>, IoError>;
+	type Done = Self::State;
+
+	fn zero(&mut self) -> Self::State {
+		Ok(SpillVec::new(self.max_items_in_memory))
+	}
+	fn push(&mut self, state: &mut Self::State, item: Item) {
+		try_update(state, |state| state.push(item));
+	}
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state
+	}
+}
+impl<B> FolderSync<Result<SpillVec<B>, IoError>> for CollectSpillFolder<B, StepB>
+where
+	B: ProcessSend,
+{
+	type State = Result<SpillVec<B>, IoError>;
+	type Done = Self::State;
+
+	fn zero(&mut self) -> Self::State {
+		Ok(SpillVec::new(self.max_items_in_memory))
+	}
+	fn push(&mut self, state: &mut Self::State, b: Result<SpillVec<B>, IoError>) {
+		try_update(state, |state| state.append(b?));
+	}
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state
+	}
+}
+
 pub trait FromParallelStream<T>: Sized {
 	type ReduceA: ReducerSend<T> + Clone + Send;
 	type ReduceC: Reducer<<Self::ReduceA as ReducerSend<T>>::Done, Done = Self>;
@@ -0,0 +1,355 @@
+use derive_new::new;
+use serde::{Deserialize, Serialize};
+
+use super::{folder_par_sink, FolderSync, FolderSyncReducer, ParallelPipe, ParallelSink};
+use crate::util::u64_to_f64;
+
+#[derive(new)]
+#[must_use]
+pub struct Describe<P> {
+	pipe: P,
+}
+
+impl_par_dist! {
+	impl<P: ParallelPipe<Item, Output = f64>, Item> ParallelSink<Item> for Describe<P> {
+		folder_par_sink!(
+			DescribeFolder,
+			DescribeFolder,
+			self,
+			DescribeFolder,
+			DescribeFolder
+		);
+	}
+}
+
+#[derive(new)]
+#[must_use]
+pub struct Covariance<P> {
+	pipe: P,
+}
+
+impl_par_dist! {
+	impl<P: ParallelPipe<Item, Output = (f64, f64)>, Item> ParallelSink<Item> for Covariance<P> {
+		folder_par_sink!(
+			CovarianceFolder,
+			CovarianceFolder,
+			self,
+			CovarianceFolder,
+			CovarianceFolder
+		);
+	}
+}
+
+#[derive(new)]
+#[must_use]
+pub struct Correlation<P> {
+	pipe: P,
+}
+
+impl_par_dist! {
+	impl<P: ParallelPipe<Item, Output = (f64, f64)>, Item> ParallelSink<Item> for Correlation<P> {
+		folder_par_sink!(
+			CorrelationFolder,
+			CorrelationFolder,
+			self,
+			CorrelationFolder,
+			CorrelationFolder
+		);
+	}
+}
+
+/// Summary statistics of a stream of `f64`, as returned by
+/// [`describe`](crate::par_stream::ParallelStream::describe).
+///
+/// The variance, skewness and kurtosis are those of the population, and the
+/// kurtosis is the excess kurtosis, i.e. 0 for a normal distribution. All but
+/// `count` are NaN for an empty stream.
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub struct Description {
+	pub count: u64,
+	pub mean: f64,
+	pub variance: f64,
+	pub stddev: f64,
+	pub min: f64,
+	pub max: f64,
+	pub skewness: f64,
+	pub kurtosis: f64,
+}
+
+/// The count, mean, min, max and the sums of the 2nd to 4th powers of the
+/// differences from the mean, that can be combined exactly.
+///
+/// Taken from <https://www.johndcook.com/blog/skewness_kurtosis/> and
+/// <https://www.osti.gov/biblio/1028931>.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug)]
+pub struct Moments {
+	count: u64,
+	mean: f64,
+	m2: f64,
+	m3: f64,
+	m4: f64,
+	min: f64,
+	max: f64,
+}
+impl Moments {
+	fn new() -> Self {
+		Self {
+			count: 0,
+			mean: 0.0,
+			m2: 0.0,
+			m3: 0.0,
+			m4: 0.0,
+			min: f64::INFINITY,
+			max: f64::NEG_INFINITY,
+		}
+	}
+	fn push(&mut self, item: f64) {
+		let n1 = u64_to_f64(self.count);
+		self.count += 1;
+		let n = u64_to_f64(self.count);
+		let delta = item - self.mean;
+		let delta_n = delta / n;
+		let delta_n2 = delta_n * delta_n;
+		let term1 = delta * delta_n * n1;
+		self.mean += delta_n;
+		self.m4 += term1 * delta_n2 * (n * n - 3.0 * n + 3.0) + 6.0 * delta_n2 * self.m2
+			- 4.0 * delta_n * self.m3;
+		self.m3 += term1 * delta_n * (n - 2.0) - 3.0 * delta_n * self.m2;
+		self.m2 += term1;
+		self.min = self.min.min(item);
+		self.max = self.max.max(item);
+	}
+	fn merge(&mut self, other: &Self) {
+		if other.count == 0 {
+			return;
+		}
+		if self.count == 0 {
+			*self = *other;
+			return;
+		}
+		let (na, nb) = (u64_to_f64(self.count), u64_to_f64(other.count));
+		let n = na + nb;
+		let delta = other.mean - self.mean;
+		let (delta2, delta3, delta4) = (delta * delta, delta.powi(3), delta.powi(4));
+		let m2 = self.m2 + other.m2 + delta2 * na * nb / n;
+		let m3 = self.m3
+			+ other.m3
+			+ delta3 * na * nb * (na - nb) / (n * n)
+			+ 3.0 * delta * (na * other.m2 - nb * self.m2) / n;
+		let m4 = self.m4
+			+ other.m4
+			+ delta4 * na * nb * (na * na - na * nb + nb * nb) / (n * n * n)
+			+ 6.0 * delta2 * (na * na * other.m2 + nb * nb * self.m2) / (n * n)
+			+ 4.0 * delta * (na * other.m3 - nb * self.m3) / n;
+		self.count += other.count;
+		self.mean += delta * nb / n;
+		self.m2 = m2;
+		self.m3 = m3;
+		self.m4 = m4;
+		self.min = self.min.min(other.min);
+		self.max = self.max.max(other.max);
+	}
+	fn describe(&self) -> Description {
+		if self.count == 0 {
+			return Description {
+				count: 0,
+				mean: f64::NAN,
+				variance: f64::NAN,
+				stddev: f64::NAN,
+				min: f64::NAN,
+				max: f64::NAN,
+				skewness: f64::NAN,
+				kurtosis: f64::NAN,
+			};
+		}
+		let n = u64_to_f64(self.count);
+		let variance = self.m2 / n;
+		Description {
+			count: self.count,
+			mean: self.mean,
+			variance,
+			stddev: variance.sqrt(),
+			min: self.min,
+			max: self.max,
+			skewness: n.sqrt() * self.m3 / self.m2.powf(1.5),
+			kurtosis: n * self.m4 / (self.m2 * self.m2) - 3.0,
+		}
+	}
+}
+
+/// The count, means, and the sums of the squared differences from the means
+/// and of their products, that can be combined exactly.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug)]
+pub struct CoMoments {
+	count: u64,
+	mean_x: f64,
+	mean_y: f64,
+	m2_x: f64,
+	m2_y: f64,
+	c: f64,
+}
+impl CoMoments {
+	fn new() -> Self {
+		Self {
+			count: 0,
+			mean_x: 0.0,
+			mean_y: 0.0,
+			m2_x: 0.0,
+			m2_y: 0.0,
+			c: 0.0,
+		}
+	}
+	fn push(&mut self, (x, y): (f64, f64)) {
+		self.count += 1;
+		let n = u64_to_f64(self.count);
+		let delta_x = x - self.mean_x;
+		let delta_y = y - self.mean_y;
+		self.mean_x += delta_x / n;
+		self.mean_y += delta_y / n;
+		self.m2_x += delta_x * (x - self.mean_x);
+		self.m2_y += delta_y * (y - self.mean_y);
+		self.c += delta_x * (y - self.mean_y);
+	}
+	fn merge(&mut self, other: &Self) {
+		if other.count == 0 {
+			return;
+		}
+		if self.count == 0 {
+			*self = *other;
+			return;
+		}
+		let (na, nb) = (u64_to_f64(self.count), u64_to_f64(other.count));
+		let n = na + nb;
+		let delta_x = other.mean_x - self.mean_x;
+		let delta_y = other.mean_y - self.mean_y;
+		self.count += other.count;
+		self.mean_x += delta_x * nb / n;
+		self.mean_y += delta_y * nb / n;
+		self.m2_x += other.m2_x + delta_x * delta_x * na * nb / n;
+		self.m2_y += other.m2_y + delta_y * delta_y * na * nb / n;
+		self.c += other.c + delta_x * delta_y * na * nb / n;
+	}
+	fn covariance(&self) -> f64 {
+		self.c / u64_to_f64(self.count)
+	}
+	fn correlation(&self) -> f64 {
+		self.c / (self.m2_x * self.m2_y).sqrt()
+	}
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DescribeFolder;
+
+impl FolderSync<f64> for DescribeFolder {
+	type State = Moments;
+	type Done = Description;
+
+	#[inline(always)]
+	fn zero(&mut self) -> Self::State {
+		Moments::new()
+	}
+	#[inline(always)]
+	fn push(&mut self, state: &mut Self::State, item: f64) {
+		state.push(item);
+	}
+	#[inline(always)]
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state.describe()
+	}
+}
+
+impl FolderSync<Moments> for DescribeFolder {
+	type State = Moments;
+	type Done = Description;
+
+	#[inline(always)]
+	fn zero(&mut self) -> Self::State {
+		Moments::new()
+	}
+	#[inline(always)]
+	fn push(&mut self, state: &mut Self::State, item: Moments) {
+		state.merge(&item);
+	}
+	#[inline(always)]
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state.describe()
+	}
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CovarianceFolder;
+
+impl FolderSync<(f64, f64)> for CovarianceFolder {
+	type State = CoMoments;
+	type Done = f64;
+
+	#[inline(always)]
+	fn zero(&mut self) -> Self::State {
+		CoMoments::new()
+	}
+	#[inline(always)]
+	fn push(&mut self, state: &mut Self::State, item: (f64, f64)) {
+		state.push(item);
+	}
+	#[inline(always)]
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state.covariance()
+	}
+}
+
+impl FolderSync<CoMoments> for CovarianceFolder {
+	type State = CoMoments;
+	type Done = f64;
+
+	#[inline(always)]
+	fn zero(&mut self) -> Self::State {
+		CoMoments::new()
+	}
+	#[inline(always)]
+	fn push(&mut self, state: &mut Self::State, item: CoMoments) {
+		state.merge(&item);
+	}
+	#[inline(always)]
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state.covariance()
+	}
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CorrelationFolder;
+
+impl FolderSync<(f64, f64)> for CorrelationFolder {
+	type State = CoMoments;
+	type Done = f64;
+
+	#[inline(always)]
+	fn zero(&mut self) -> Self::State {
+		CoMoments::new()
+	}
+	#[inline(always)]
+	fn push(&mut self, state: &mut Self::State, item: (f64, f64)) {
+		state.push(item);
+	}
+	#[inline(always)]
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state.correlation()
+	}
+}
+
+impl FolderSync<CoMoments> for CorrelationFolder {
+	type State = CoMoments;
+	type Done = f64;
+
+	#[inline(always)]
+	fn zero(&mut self) -> Self::State {
+		CoMoments::new()
+	}
+	#[inline(always)]
+	fn push(&mut self, state: &mut Self::State, item: CoMoments) {
+		state.merge(&item);
+	}
+	#[inline(always)]
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state.correlation()
+	}
+}
@@ -0,0 +1,318 @@
+#![allow(clippy::type_complexity)]
+
+use amadeus_streaming::{BloomFilter, CuckooFilter, HyperLogLog};
+use derive_new::new;
+use educe::Educe;
+use serde::{Deserialize, Serialize};
+use serde_closure::traits::FnMut;
+use std::{
+	collections::{HashMap, HashSet}, hash::{BuildHasher, Hash}, marker::PhantomData, mem
+};
+
+use super::{folder_par_sink, FolderSync, FolderSyncReducer, ParallelPipe, ParallelSink};
+
+#[derive(new)]
+#[must_use]
+pub struct Distinct<P> {
+	pipe: P,
+}
+
+impl_par_dist! {
+	impl<P: ParallelPipe<Item>, Item> ParallelSink<Item> for Distinct<P>
+	where
+		P::Output: Eq + Hash + Send + 'static,
+	{
+		folder_par_sink!(DistinctFolder<P::Output>, UnionFolder<HashSet<P::Output>>, self, DistinctFolder::new(), UnionFolder::new(()));
+	}
+}
+
+#[derive(new)]
+#[must_use]
+pub struct DistinctByKey<P, F> {
+	pipe: P,
+	f: F,
+}
+
+impl_par_dist! {
+	impl<P: ParallelPipe<Item>, Item, F, K> ParallelSink<Item> for DistinctByKey<P, F>
+	where
+		F: for<'a> FnMut<(&'a P::Output,), Output = K> + Clone + Send + 'static,
+		K: Eq + Hash + Send + 'static,
+		P::Output: Send + 'static,
+	{
+		folder_par_sink!(DistinctByKeyFolder<P::Output, F, K>, UnionFolder<HashMap<K, P::Output>>, self, DistinctByKeyFolder::new(self.f), UnionFolder::new(()));
+	}
+}
+
+#[derive(new)]
+#[must_use]
+pub struct CountDistinct<P> {
+	pipe: P,
+	error_rate: f64,
+}
+
+impl_par_dist! {
+	impl<P: ParallelPipe<Item>, Item> ParallelSink<Item> for CountDistinct<P>
+	where
+		P::Output: Hash + 'static,
+	{
+		folder_par_sink!(CountDistinctFolder, UnionFolder<HyperLogLog<P::Output>>, self, CountDistinctFolder::new(self.error_rate), UnionFolder::new(self.error_rate));
+	}
+}
+
+#[derive(new)]
+#[must_use]
+pub struct BloomFilterSink<P> {
+	pipe: P,
+	n: usize,
+	fp_rate: f64,
+}
+
+impl_par_dist! {
+	impl<P: ParallelPipe<Item>, Item> ParallelSink<Item> for BloomFilterSink<P>
+	where
+		P::Output: Hash + 'static,
+	{
+		folder_par_sink!(BloomFilterFolder, UnionFolder<BloomFilter<P::Output>>, self, BloomFilterFolder::new(self.n, self.fp_rate), UnionFolder::new((self.n, self.fp_rate)));
+	}
+}
+
+#[derive(new)]
+#[must_use]
+pub struct CuckooFilterSink<P> {
+	pipe: P,
+	n: usize,
+	fp_rate: f64,
+}
+
+impl_par_dist! {
+	impl<P: ParallelPipe<Item>, Item> ParallelSink<Item> for CuckooFilterSink<P>
+	where
+		P::Output: Hash + 'static,
+	{
+		folder_par_sink!(CuckooFilterFolder, UnionFolder<CuckooFilter<P::Output>>, self, CuckooFilterFolder::new(self.n, self.fp_rate), UnionFolder::new((self.n, self.fp_rate)));
+	}
+}
+
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone)]
+#[serde(bound = "")]
+pub struct DistinctFolder<Item> {
+	marker: PhantomData<fn() -> Item>,
+}
+
+impl<Item> FolderSync<Item> for DistinctFolder<Item>
+where
+	Item: Eq + Hash,
+{
+	type State = HashSet<Item>;
+	type Done = Self::State;
+
+	fn zero(&mut self) -> Self::State {
+		HashSet::new()
+	}
+	fn push(&mut self, state: &mut Self::State, item: Item) {
+		let _ = state.insert(item);
+	}
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state
+	}
+}
+
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone(bound = "F: Clone"))]
+#[serde(
+	bound(serialize = "F: Serialize"),
+	bound(deserialize = "F: Deserialize<'de>")
+)]
+pub struct DistinctByKeyFolder<Item, F, K> {
+	f: F,
+	marker: PhantomData<fn() -> (Item, K)>,
+}
+
+impl<Item, F, K> FolderSync<Item> for DistinctByKeyFolder<Item, F, K>
+where
+	F: for<'a> FnMut<(&'a Item,), Output = K>,
+	K: Eq + Hash,
+{
+	type State = HashMap<K, Item>;
+	type Done = Self::State;
+
+	fn zero(&mut self) -> Self::State {
+		HashMap::new()
+	}
+	fn push(&mut self, state: &mut Self::State, item: Item) {
+		let key = self.f.call_mut((&item,));
+		let _ = state.entry(key).or_insert(item);
+	}
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state
+	}
+}
+
+#[derive(Clone, Serialize, Deserialize, new)]
+pub struct CountDistinctFolder {
+	error_rate: f64,
+}
+
+impl<Item> FolderSync<Item> for CountDistinctFolder
+where
+	Item: Hash,
+{
+	type State = HyperLogLog<Item>;
+	type Done = Self::State;
+
+	fn zero(&mut self) -> Self::State {
+		HyperLogLog::new(self.error_rate)
+	}
+	fn push(&mut self, state: &mut Self::State, item: Item) {
+		state.push(&item);
+	}
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state
+	}
+}
+
+#[derive(Clone, Serialize, Deserialize, new)]
+pub struct BloomFilterFolder {
+	n: usize,
+	fp_rate: f64,
+}
+
+impl<Item> FolderSync<Item> for BloomFilterFolder
+where
+	Item: Hash,
+{
+	type State = BloomFilter<Item>;
+	type Done = Self::State;
+
+	fn zero(&mut self) -> Self::State {
+		BloomFilter::new(self.n, self.fp_rate)
+	}
+	fn push(&mut self, state: &mut Self::State, item: Item) {
+		let _ = state.push(&item);
+	}
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state
+	}
+}
+
+#[derive(Clone, Serialize, Deserialize, new)]
+pub struct CuckooFilterFolder {
+	n: usize,
+	fp_rate: f64,
+}
+
+impl<Item> FolderSync<Item> for CuckooFilterFolder
+where
+	Item: Hash,
+{
+	type State = CuckooFilter<Item>;
+	type Done = Self::State;
+
+	fn zero(&mut self) -> Self::State {
+		CuckooFilter::new(self.n, self.fp_rate)
+	}
+	fn push(&mut self, state: &mut Self::State, item: Item) {
+		let _ = state.push(&item);
+	}
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state
+	}
+}
+
+/// Partial results of the distinct sinks, that can be combined into one.
+pub trait Union {
+	/// What's needed to create an empty one.
+	type Config: Clone;
+
+	fn zero(config: &Self::Config) -> Self;
+	fn union(&mut self, other: Self);
+}
+impl<T: Eq + Hash, H: BuildHasher + Default> Union for HashSet<T, H> {
+	type Config = ();
+
+	fn zero(_config: &Self::Config) -> Self {
+		Self::default()
+	}
+	fn union(&mut self, mut other: Self) {
+		if self.len() < other.len() {
+			mem::swap(self, &mut other);
+		}
+		self.extend(other);
+	}
+}
+impl<K: Eq + Hash, V, H: BuildHasher + Default> Union for HashMap<K, V, H> {
+	type Config = ();
+
+	fn zero(_config: &Self::Config) -> Self {
+		Self::default()
+	}
+	fn union(&mut self, mut other: Self) {
+		if self.len() < other.len() {
+			mem::swap(self, &mut other);
+		}
+		for (key, value) in other {
+			let _ = self.entry(key).or_insert(value);
+		}
+	}
+}
+impl<V: Hash + ?Sized> Union for HyperLogLog<V> {
+	type Config = f64;
+
+	fn zero(error_rate: &Self::Config) -> Self {
+		Self::new(*error_rate)
+	}
+	fn union(&mut self, other: Self) {
+		Self::union(self, &other);
+	}
+}
+impl<V: Hash + ?Sized> Union for BloomFilter<V> {
+	type Config = (usize, f64);
+
+	fn zero((n, fp_rate): &Self::Config) -> Self {
+		Self::new(*n, *fp_rate)
+	}
+	fn union(&mut self, other: Self) {
+		Self::union(self, &other);
+	}
+}
+
+impl<V: Hash + ?Sized> Union for CuckooFilter<V> {
+	type Config = (usize, f64);
+
+	fn zero((n, fp_rate): &Self::Config) -> Self {
+		Self::new(*n, *fp_rate)
+	}
+	fn union(&mut self, other: Self) {
+		Self::union(self, &other);
+	}
+}
+
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone)]
+#[serde(
+	bound(serialize = "S::Config: Serialize"),
+	bound(deserialize = "S::Config: Deserialize<'de>")
+)]
+pub struct UnionFolder<S: Union> {
+	config: S::Config,
+}
+
+impl<S> FolderSync<S> for UnionFolder<S>
+where
+	S: Union,
+{
+	type State = S;
+	type Done = Self::State;
+
+	fn zero(&mut self) -> Self::State {
+		S::zero(&self.config)
+	}
+	fn push(&mut self, state: &mut Self::State, item: S) {
+		state.union(item);
+	}
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state
+	}
+}
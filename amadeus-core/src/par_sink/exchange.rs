@@ -0,0 +1,171 @@
+//! Serves the runs written by this process to the processes that read them.
+//!
+//! A run sent to another process is left where it was written, and only its
+//! address is sent: the process reading it fetches it from this one over TCP, or
+//! reads the file directly if it's in this process. So exchanging runs between the
+//! processes of a pool doesn't pass them through the process that coordinates it.
+
+use once_cell::sync::Lazy;
+use std::{
+	collections::HashMap, fs::{self, File}, io::{self, Read, Write}, net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream}, path::{Path, PathBuf}, sync::Mutex, thread
+};
+
+use super::spill::temp_path;
+use crate::util::IoError;
+
+const FETCH: u8 = 0;
+const RELEASE: u8 = 1;
+
+const FOUND: u8 = 0;
+const NOT_FOUND: u8 = 1;
+
+static EXCHANGE: Lazy<Mutex<Exchange>> = Lazy::new(|| {
+	Mutex::new(Exchange {
+		host: Ipv4Addr::LOCALHOST.into(),
+		addr: None,
+		files: HashMap::new(),
+	})
+});
+
+struct Exchange {
+	host: IpAddr,
+	/// The address runs are served on, once the first is sent.
+	addr: Option<SocketAddr>,
+	/// The files being served, by the random id they're fetched by.
+	files: HashMap<u128, PathBuf>,
+}
+
+/// Set the address that other processes fetch the runs written by this one from,
+/// which is the loopback address by default, so that runs can only be read by
+/// processes on the same machine.
+///
+/// A pool of processes across machines sets it to the address of each machine.
+/// It can't be changed once a run has been sent to another process.
+pub fn set_exchange_host(host: IpAddr) {
+	let mut exchange = EXCHANGE.lock().unwrap();
+	assert!(
+		exchange.addr.map_or(true, |addr| addr.ip() == host),
+		"runs are already served on {}",
+		exchange.addr.unwrap()
+	);
+	exchange.host = host;
+}
+
+/// Serve a copy of the file at `path` until it's released, returning the address
+/// and id to fetch it with.
+pub(crate) fn export(path: &Path) -> Result<(SocketAddr, u128), IoError> {
+	let mut exchange = EXCHANGE.lock().unwrap();
+	if exchange.addr.is_none() {
+		let listener = TcpListener::bind((exchange.host, 0))?;
+		let port = listener.local_addr()?.port();
+		let _ = thread::spawn(move || {
+			for stream in listener.incoming().flatten() {
+				let _ = thread::spawn(move || serve(stream));
+			}
+		});
+		exchange.addr = Some(SocketAddr::new(exchange.host, port));
+	}
+	let addr = exchange.addr.unwrap();
+	// Linked rather than copied where possible, as the run deletes its own file on drop
+	let exported = temp_path();
+	if fs::hard_link(path, &exported).is_err() {
+		if let Err(err) = fs::copy(path, &exported) {
+			let _ = fs::remove_file(&exported);
+			return Err(err.into());
+		}
+	}
+	let id = rand::random();
+	let _ = exchange.files.insert(id, exported);
+	Ok((addr, id))
+}
+
+/// Open the file served at `addr` with `id`.
+pub(crate) fn open(addr: SocketAddr, id: u128) -> Result<RunReader, IoError> {
+	if let Some(path) = local(addr, id) {
+		return Ok(RunReader::File(File::open(path)?));
+	}
+	let mut stream = TcpStream::connect(addr)?;
+	stream.write_all(&request(FETCH, id))?;
+	let mut status = [0];
+	stream.read_exact(&mut status)?;
+	if status[0] != FOUND {
+		return Err(io::Error::new(
+			io::ErrorKind::NotFound,
+			format!("run not found at {}, it may have been read already", addr),
+		)
+		.into());
+	}
+	Ok(RunReader::Tcp(stream))
+}
+
+/// Stop serving the file at `addr` with `id`, and delete it. Errors are ignored,
+/// as the process serving it may have exited, which deletes it anyway.
+pub(crate) fn release(addr: SocketAddr, id: u128) {
+	if local(addr, id).is_some() {
+		remove(id);
+		return;
+	}
+	let _ = thread::spawn(move || {
+		let mut stream = TcpStream::connect(addr)?;
+		stream.write_all(&request(RELEASE, id))
+	});
+}
+
+fn local(addr: SocketAddr, id: u128) -> Option<PathBuf> {
+	let exchange = EXCHANGE.lock().unwrap();
+	if exchange.addr != Some(addr) {
+		return None;
+	}
+	exchange.files.get(&id).cloned()
+}
+
+fn remove(id: u128) {
+	let path = EXCHANGE.lock().unwrap().files.remove(&id);
+	if let Some(path) = path {
+		let _ = fs::remove_file(path);
+	}
+}
+
+fn request(op: u8, id: u128) -> [u8; 17] {
+	let mut request = [op; 17];
+	request[1..].copy_from_slice(&id.to_le_bytes());
+	request
+}
+
+fn serve(mut stream: TcpStream) -> Result<(), io::Error> {
+	let mut request = [0; 17];
+	stream.read_exact(&mut request)?;
+	let mut id = [0; 16];
+	id.copy_from_slice(&request[1..]);
+	let id = u128::from_le_bytes(id);
+	match request[0] {
+		FETCH => {
+			let path = EXCHANGE.lock().unwrap().files.get(&id).cloned();
+			match path.map(File::open) {
+				Some(Ok(mut file)) => {
+					stream.write_all(&[FOUND])?;
+					let _ = io::copy(&mut file, &mut stream)?;
+				}
+				_ => stream.write_all(&[NOT_FOUND])?,
+			}
+		}
+		RELEASE => remove(id),
+		_ => (),
+	}
+	Ok(())
+}
+
+/// The contents of a run, read from its file or fetched from the process serving
+/// it.
+pub(crate) enum RunReader {
+	File(File),
+	Tcp(TcpStream),
+}
+impl Read for RunReader {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		match self {
+			Self::File(file) => file.read(buf),
+			Self::Tcp(stream) => stream.read(buf),
+		}
+	}
+}
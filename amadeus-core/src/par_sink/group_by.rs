@@ -7,15 +7,15 @@ use indexmap::IndexMap;
 use pin_project::pin_project;
 use serde::{Deserialize, Serialize};
 use std::{
-	hash::Hash, marker::PhantomData, mem, pin::Pin, task::{Context, Poll}
+	collections::BTreeSet, hash::Hash, marker::PhantomData, mem, pin::Pin, task::{Context, Poll}, vec
 };
 use sum::Sum2;
 
 use super::{
-	DistributedPipe, DistributedSink, ParallelPipe, ParallelSink, PipeTask, Reducer, ReducerProcessSend, ReducerSend
+	spill::Groups, DistributedPipe, DistributedSink, ParallelPipe, ParallelSink, PipeTask, Reducer, ReducerProcessSend, ReducerSend, SpillMap
 };
 use crate::{
-	pipe::{Pipe, Sink, StreamExt as _}, pool::ProcessSend
+	par_stream::{EventTime, Window}, pipe::{Pipe, Sink, StreamExt as _}, pool::ProcessSend, util::IoError
 };
 
 #[derive(new)]
@@ -25,6 +25,26 @@ pub struct GroupBy<A, B> {
 	b: B,
 }
 
+impl<A, B> GroupBy<A, B> {
+	/// Hold at most `max_keys_in_memory` groups in memory, spilling the rest to
+	/// local temporary files that are merged when the stream is exhausted.
+	///
+	/// The limit is a count of keys, not a number of bytes: to keep within a
+	/// memory budget, divide it by the size of a key and its partial result.
+	///
+	/// Once a task is reducing more than `max_keys_in_memory` groups, their
+	/// partial results are spilled and the reducers restarted. The result is a
+	/// [`SpillMap`] that streams the groups back from disk, or the error
+	/// encountered writing or reading the temporary files.
+	pub fn spill(self, max_keys_in_memory: usize) -> GroupBySpill<A, B> {
+		assert_ne!(
+			max_keys_in_memory, 0,
+			"max_keys_in_memory must be at least 1"
+		);
+		GroupBySpill::new(self.a, self.b, max_keys_in_memory)
+	}
+}
+
 impl<A: ParallelPipe<Item, Output = (T, U)>, B: ParallelSink<U>, Item, T, U> ParallelSink<Item>
 	for GroupBy<A, B>
 where
@@ -90,41 +110,103 @@ where
 	}
 }
 
+/// Picks the group that each item of a task is reduced into, by its key.
+pub trait GroupKeys<T>: Default {
+	/// The key of the group to reduce an item with key `key` into, which may
+	/// re-key one of the `groups` already being reduced.
+	fn group<G>(&mut self, key: T, groups: &mut IndexMap<T, G>) -> T;
+}
+
+/// Reduces the items with equal keys together.
+#[derive(Default)]
+pub struct EqualKeys;
+impl<T> GroupKeys<T> for EqualKeys {
+	fn group<G>(&mut self, key: T, _groups: &mut IndexMap<T, G>) -> T {
+		key
+	}
+}
+
+/// Reduces the items of a session window together with those of a session it
+/// overlaps, extending that session to cover it, so that a task reduces about
+/// one group per session rather than one per item.
+///
+/// Only the latest starting session before the end of the window is looked at,
+/// so sessions may still overlap; those are merged along with the sessions of
+/// other tasks once reduced.
+pub struct MergeSessions<T> {
+	sessions: BTreeSet<Window<T>>,
+}
+impl<T> Default for MergeSessions<T> {
+	fn default() -> Self {
+		Self {
+			sessions: BTreeSet::new(),
+		}
+	}
+}
+impl<T: EventTime + Hash> GroupKeys<Window<T>> for MergeSessions<T> {
+	fn group<G>(&mut self, window: Window<T>, groups: &mut IndexMap<Window<T>, G>) -> Window<T> {
+		if !window.is_session() {
+			return window;
+		}
+		let end = Window::new(window.end().clone(), window.end().clone(), true);
+		let session = match self.sessions.range(..end).next_back() {
+			Some(session) if session.end() > window.start() => session.clone(),
+			_ => {
+				let _ = self.sessions.insert(window.clone());
+				return window;
+			}
+		};
+		let mut merged = session.clone();
+		let _ = merged.merge(&window);
+		if merged != session && !groups.contains_key(&merged) {
+			if let Some(group) = groups.swap_remove(&session) {
+				let _ = groups.insert(merged.clone(), group);
+			}
+			let _ = self.sessions.remove(&session);
+			let _ = self.sessions.insert(merged.clone());
+		}
+		merged
+	}
+}
+
 #[derive(Educe, Serialize, Deserialize, new)]
 #[educe(Clone(bound = "P: Clone, R: Clone"))]
 #[serde(
 	bound(serialize = "P: Serialize, R: Serialize"),
 	bound(deserialize = "P: Deserialize<'de>, R: Deserialize<'de>")
 )]
-pub struct GroupByReducerA<P, R, T, U>(P, R, PhantomData<fn() -> (T, U)>);
+pub struct GroupByReducerA<P, R, T, U, K = EqualKeys>(P, R, PhantomData<fn() -> (T, U, K)>);
 
-impl<P, R, T, U> Reducer<(T, U)> for GroupByReducerA<P, R, T, U>
+impl<P, R, T, U, K> Reducer<(T, U)> for GroupByReducerA<P, R, T, U, K>
 where
 	P: PipeTask<U>,
 	R: Reducer<P::Output> + Clone,
 	T: Eq + Hash,
+	K: GroupKeys<T>,
 {
 	type Done = IndexMap<T, R::Done>;
-	type Async = GroupByReducerAAsync<P::Async, R, T, U>;
+	type Async = GroupByReducerAAsync<P::Async, R, T, U, K>;
 
 	fn into_async(self) -> Self::Async {
-		GroupByReducerAAsync::new(self.0.into_async(), self.1)
+		GroupByReducerAAsync::new(self.0.into_async(), self.1, K::default())
 	}
 }
-impl<P, R, T, U> ReducerProcessSend<(T, U)> for GroupByReducerA<P, R, T, U>
+impl<P, R, T, U, K> ReducerProcessSend<(T, U)> for GroupByReducerA<P, R, T, U, K>
 where
 	P: PipeTask<U>,
 	R: Reducer<P::Output> + Clone,
 	T: Eq + Hash + ProcessSend + 'static,
+	K: GroupKeys<T>,
 	R::Done: ProcessSend + 'static,
 {
 	type Done = IndexMap<T, R::Done>;
 }
-impl<P, R, T, U> ReducerSend<(T, U)> for GroupByReducerA<P, R, T, U>
+impl<P, R, T, U, K> ReducerSend<(T, U)> for GroupByReducerA<P, R, T, U, K>
 where
 	P: PipeTask<U>,
 	R: Reducer<P::Output> + Clone,
 	T: Eq + Hash + Send + 'static,
+	K: GroupKeys<T>,
 	R::Done: Send + 'static,
 {
 	type Done = IndexMap<T, R::Done>;
@@ -132,7 +214,7 @@ where
 
 #[pin_project]
 #[derive(new)]
-pub struct GroupByReducerAAsync<P, R, T, U>
+pub struct GroupByReducerAAsync<P, R, T, U, K>
 where
 	P: Pipe<U>,
 	R: Reducer<P::Output>,
@@ -140,17 +222,19 @@ where
 	#[pin]
 	pipe: P,
 	factory: R,
+	keys: K,
 	#[new(default)]
 	pending: Option<Sum2<(T, Option<U>, Option<Pin<Box<R::Async>>>), Vec<Option<R::Done>>>>,
 	#[new(default)]
 	map: IndexMap<T, Pin<Box<R::Async>>>,
 }
 
-impl<P, R, T, U> Sink<(T, U)> for GroupByReducerAAsync<P, R, T, U>
+impl<P, R, T, U, K> Sink<(T, U)> for GroupByReducerAAsync<P, R, T, U, K>
 where
 	P: Pipe<U>,
 	R: Reducer<P::Output> + Clone,
 	T: Eq + Hash,
+	K: GroupKeys<T>,
 {
 	type Done = IndexMap<T, R::Done>;
 
@@ -164,6 +248,7 @@ where
 				*self_.pending = Some(
 					ready!(stream.as_mut().poll_next(cx))
 						.map(|(k, u)| {
+							let k = self_.keys.group(k, self_.map);
 							let r = if !self_.map.contains_key(&k) {
 								Some(Box::pin(self_.factory.clone().into_async()))
 							} else {
@@ -390,3 +475,581 @@ where
 		}
 	}
 }
+
+#[derive(new)]
+#[must_use]
+pub struct GroupBySpill<A, B> {
+	a: A,
+	b: B,
+	max_keys_in_memory: usize,
+}
+
+impl<A: ParallelPipe<Item, Output = (T, U)>, B: ParallelSink<U>, Item, T, U> ParallelSink<Item>
+	for GroupBySpill<A, B>
+where
+	T: Eq + Hash + ProcessSend + 'static,
+	<B::Pipe as ParallelPipe<U>>::Task: Clone + Send + 'static,
+	B::ReduceA: Clone + Send + 'static,
+	<B::ReduceA as ReducerSend<<B::Pipe as ParallelPipe<U>>::Output>>::Done: ProcessSend,
+	B::ReduceC: Clone,
+	B::Done: ProcessSend + 'static,
+{
+	type Done = Result<SpillMap<T, B::Done>, IoError>;
+	type Pipe = A;
+	type ReduceA = GroupBySpillReducerA<<B::Pipe as ParallelPipe<U>>::Task, B::ReduceA, T, U>;
+	type ReduceC = GroupBySpillReducerB<
+		B::ReduceC,
+		T,
+		<B::ReduceA as ReducerSend<<B::Pipe as ParallelPipe<U>>::Output>>::Done,
+	>;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceC) {
+		let (a, b, c) = self.b.reducers();
+		(
+			self.a,
+			GroupBySpillReducerA::new(a.task(), b, self.max_keys_in_memory),
+			GroupBySpillReducerB::new(c, self.max_keys_in_memory),
+		)
+	}
+}
+
+impl<A: DistributedPipe<Item, Output = (T, U)>, B: DistributedSink<U>, Item, T, U>
+	DistributedSink<Item> for GroupBySpill<A, B>
+where
+	T: Eq + Hash + ProcessSend + 'static,
+	<B::Pipe as DistributedPipe<U>>::Task: Clone + ProcessSend + 'static,
+	B::ReduceA: Clone + ProcessSend + 'static,
+	<B::ReduceA as ReducerSend<<B::Pipe as DistributedPipe<U>>::Output>>::Done: ProcessSend,
+	B::ReduceB: Clone,
+	B::ReduceC: Clone,
+	B::Done: ProcessSend + 'static,
+{
+	type Done = Result<SpillMap<T, B::Done>, IoError>;
+	type Pipe = A;
+	type ReduceA = GroupBySpillReducerA<<B::Pipe as DistributedPipe<U>>::Task, B::ReduceA, T, U>;
+	type ReduceB = GroupBySpillReducerB<
+		B::ReduceB,
+		T,
+		<B::ReduceA as ReducerSend<<B::Pipe as DistributedPipe<U>>::Output>>::Done,
+	>;
+	type ReduceC = GroupBySpillReducerB<
+		B::ReduceC,
+		T,
+		<B::ReduceB as ReducerProcessSend<
+			<B::ReduceA as Reducer<<B::Pipe as DistributedPipe<U>>::Output>>::Done,
+		>>::Done,
+	>;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceB, Self::ReduceC) {
+		let (a, b, c, d) = self.b.reducers();
+		(
+			self.a,
+			GroupBySpillReducerA::new(a.task(), b, self.max_keys_in_memory),
+			GroupBySpillReducerB::new(c, self.max_keys_in_memory),
+			GroupBySpillReducerB::new(d, self.max_keys_in_memory),
+		)
+	}
+}
+
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone(bound = "P: Clone, R: Clone"))]
+#[serde(
+	bound(serialize = "P: Serialize, R: Serialize"),
+	bound(deserialize = "P: Deserialize<'de>, R: Deserialize<'de>")
+)]
+pub struct GroupBySpillReducerA<P, R, T, U>(P, R, usize, PhantomData<fn() -> (T, U)>);
+
+impl<P, R, T, U> Reducer<(T, U)> for GroupBySpillReducerA<P, R, T, U>
+where
+	P: PipeTask<U>,
+	R: Reducer<P::Output> + Clone,
+	T: Eq + Hash + ProcessSend + 'static,
+	R::Done: ProcessSend + 'static,
+{
+	type Done = Result<SpillMap<T, R::Done>, IoError>;
+	type Async = GroupBySpillReducerAAsync<P::Async, R, T, U>;
+
+	fn into_async(self) -> Self::Async {
+		GroupBySpillReducerAAsync::new(self.0.into_async(), self.1, self.2)
+	}
+}
+impl<P, R, T, U> ReducerProcessSend<(T, U)> for GroupBySpillReducerA<P, R, T, U>
+where
+	P: PipeTask<U>,
+	R: Reducer<P::Output> + Clone,
+	T: Eq + Hash + ProcessSend + 'static,
+	R::Done: ProcessSend + 'static,
+{
+	type Done = Result<SpillMap<T, R::Done>, IoError>;
+}
+impl<P, R, T, U> ReducerSend<(T, U)> for GroupBySpillReducerA<P, R, T, U>
+where
+	P: PipeTask<U>,
+	R: Reducer<P::Output> + Clone,
+	T: Eq + Hash + ProcessSend + 'static,
+	R::Done: ProcessSend + 'static,
+{
+	type Done = Result<SpillMap<T, R::Done>, IoError>;
+}
+
+#[pin_project]
+pub struct GroupBySpillReducerAAsync<P, R, T, U>
+where
+	P: Pipe<U>,
+	R: Reducer<P::Output>,
+{
+	#[pin]
+	pipe: P,
+	factory: R,
+	max_keys_in_memory: usize,
+	pending: Option<(T, Option<U>, Option<Pin<Box<R::Async>>>)>,
+	flushing: Option<(Vec<Option<R::Done>>, bool)>,
+	map: IndexMap<T, Pin<Box<R::Async>>>,
+	spilled: Option<SpillMap<T, R::Done>>,
+}
+impl<P, R, T, U> GroupBySpillReducerAAsync<P, R, T, U>
+where
+	P: Pipe<U>,
+	R: Reducer<P::Output>,
+	T: Eq + Hash + ProcessSend,
+	R::Done: ProcessSend,
+{
+	fn new(pipe: P, factory: R, max_keys_in_memory: usize) -> Self {
+		Self {
+			pipe,
+			factory,
+			max_keys_in_memory,
+			pending: None,
+			flushing: None,
+			map: IndexMap::new(),
+			spilled: Some(SpillMap::new(max_keys_in_memory)),
+		}
+	}
+}
+
+impl<P, R, T, U> Sink<(T, U)> for GroupBySpillReducerAAsync<P, R, T, U>
+where
+	P: Pipe<U>,
+	R: Reducer<P::Output> + Clone,
+	T: Eq + Hash + ProcessSend,
+	R::Done: ProcessSend,
+{
+	type Done = Result<SpillMap<T, R::Done>, IoError>;
+
+	#[inline(always)]
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context, mut stream: Pin<&mut impl Stream<Item = (T, U)>>,
+	) -> Poll<Self::Done> {
+		let mut self_ = self.project();
+		loop {
+			// Finish the reducers of every group held, either because there are
+			// too many or because the stream is exhausted, and spill their results.
+			if let Some((done, last)) = self_.flushing {
+				let mut done_ = true;
+				self_
+					.map
+					.values_mut()
+					.zip(done.iter_mut())
+					.for_each(|(r, done)| {
+						if done.is_none() {
+							let stream = stream::empty();
+							pin_mut!(stream);
+							if let Poll::Ready(done_) = r.as_mut().poll_forward(cx, stream) {
+								*done = Some(done_);
+							} else {
+								done_ = false;
+							}
+						}
+					});
+				if !done_ {
+					return Poll::Pending;
+				}
+				let spilled = self_.spilled.as_mut().unwrap();
+				for ((k, _), v) in mem::take(self_.map).into_iter().zip(done.iter_mut()) {
+					if let Err(err) = spilled.push(k, v.take().unwrap()) {
+						return Poll::Ready(Err(err));
+					}
+				}
+				if *last {
+					return Poll::Ready(Ok(self_.spilled.take().unwrap()));
+				}
+				*self_.flushing = None;
+				continue;
+			}
+			if self_.pending.is_none() {
+				if let Some((k, u)) = ready!(stream.as_mut().poll_next(cx)) {
+					let r = if !self_.map.contains_key(&k) {
+						Some(Box::pin(self_.factory.clone().into_async()))
+					} else {
+						None
+					};
+					*self_.pending = Some((k, Some(u), r));
+				} else {
+					*self_.flushing = Some(((0..self_.map.len()).map(|_| None).collect(), true));
+					continue;
+				}
+			}
+			let (k, u, r) = self_.pending.as_mut().unwrap();
+			let waker = cx.waker();
+			let stream = stream::poll_fn(|cx| {
+				u.take().map_or_else(
+					|| {
+						let waker_ = cx.waker();
+						if !waker.will_wake(waker_) {
+							waker_.wake_by_ref();
+						}
+						Poll::Pending
+					},
+					|u| Poll::Ready(Some(u)),
+				)
+			})
+			.fuse()
+			.pipe(self_.pipe.as_mut());
+			pin_mut!(stream);
+			let map = &mut *self_.map;
+			let r_ = r.as_mut().unwrap_or_else(|| map.get_mut(k).unwrap());
+			if r_.as_mut().poll_forward(cx, stream).is_ready() {
+				let _ = u.take();
+			}
+			if u.is_some() {
+				return Poll::Pending;
+			}
+			let (k, _u, r) = self_.pending.take().unwrap();
+			if let Some(r) = r {
+				let _ = self_.map.insert(k, r);
+				if self_.map.len() > *self_.max_keys_in_memory {
+					*self_.flushing = Some(((0..self_.map.len()).map(|_| None).collect(), false));
+				}
+			}
+		}
+	}
+}
+
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone(bound = "R: Clone"))]
+#[serde(
+	bound(serialize = "R: Serialize"),
+	bound(deserialize = "R: Deserialize<'de>")
+)]
+pub struct GroupBySpillReducerB<R, T, U>(R, usize, PhantomData<fn() -> (T, U)>);
+
+impl<R, T, U> Reducer<Result<SpillMap<T, U>, IoError>> for GroupBySpillReducerB<R, T, U>
+where
+	R: Reducer<U> + Clone,
+	T: Eq + Hash + ProcessSend + 'static,
+	U: ProcessSend + 'static,
+	R::Done: ProcessSend + 'static,
+{
+	type Done = Result<SpillMap<T, R::Done>, IoError>;
+	type Async = GroupBySpillReducerBAsync<R, T, U>;
+
+	fn into_async(self) -> Self::Async {
+		GroupBySpillReducerBAsync::new(self.0, self.1)
+	}
+}
+impl<R, T, U> ReducerProcessSend<Result<SpillMap<T, U>, IoError>> for GroupBySpillReducerB<R, T, U>
+where
+	R: Reducer<U> + Clone,
+	T: Eq + Hash + ProcessSend + 'static,
+	U: ProcessSend + 'static,
+	R::Done: ProcessSend + 'static,
+{
+	type Done = Result<SpillMap<T, R::Done>, IoError>;
+}
+impl<R, T, U> ReducerSend<Result<SpillMap<T, U>, IoError>> for GroupBySpillReducerB<R, T, U>
+where
+	R: Reducer<U> + Clone,
+	T: Eq + Hash + ProcessSend + 'static,
+	U: ProcessSend + 'static,
+	R::Done: ProcessSend + 'static,
+{
+	type Done = Result<SpillMap<T, R::Done>, IoError>;
+}
+
+#[pin_project]
+pub struct GroupBySpillReducerBAsync<R, T, U>
+where
+	R: Reducer<U>,
+{
+	f: R,
+	map: Option<SpillMap<T, U>>,
+	groups: Option<Groups<T, U>>,
+	current: Option<(T, vec::IntoIter<U>, Pin<Box<R::Async>>)>,
+	done: Option<SpillMap<T, R::Done>>,
+}
+impl<R, T, U> GroupBySpillReducerBAsync<R, T, U>
+where
+	R: Reducer<U>,
+	T: Eq + Hash + ProcessSend,
+	U: ProcessSend,
+	R::Done: ProcessSend,
+{
+	fn new(f: R, max_keys_in_memory: usize) -> Self {
+		Self {
+			f,
+			map: Some(SpillMap::new(max_keys_in_memory)),
+			groups: None,
+			current: None,
+			done: Some(SpillMap::new(max_keys_in_memory)),
+		}
+	}
+}
+
+impl<R, T, U> Sink<Result<SpillMap<T, U>, IoError>> for GroupBySpillReducerBAsync<R, T, U>
+where
+	R: Reducer<U> + Clone,
+	T: Eq + Hash + ProcessSend,
+	U: ProcessSend,
+	R::Done: ProcessSend,
+{
+	type Done = Result<SpillMap<T, R::Done>, IoError>;
+
+	#[inline(always)]
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context,
+		mut stream: Pin<&mut impl Stream<Item = Result<SpillMap<T, U>, IoError>>>,
+	) -> Poll<Self::Done> {
+		let self_ = self.project();
+		loop {
+			// Reduce each group in turn once every partial result has been received.
+			if let Some((_, values, r)) = self_.current {
+				let stream = stream::iter(values);
+				pin_mut!(stream);
+				let done = ready!(r.as_mut().poll_forward(cx, stream));
+				let (k, _, _) = self_.current.take().unwrap();
+				if let Err(err) = self_.done.as_mut().unwrap().push(k, done) {
+					return Poll::Ready(Err(err));
+				}
+				continue;
+			}
+			if let Some(groups) = self_.groups {
+				match groups.next() {
+					Some(Ok((k, values))) => {
+						*self_.current = Some((
+							k,
+							values.into_iter(),
+							Box::pin(self_.f.clone().into_async()),
+						));
+						continue;
+					}
+					Some(Err(err)) => return Poll::Ready(Err(err)),
+					None => return Poll::Ready(Ok(self_.done.take().unwrap())),
+				}
+			}
+			let res = if let Some(map) = ready!(stream.as_mut().poll_next(cx)) {
+				map.and_then(|map| self_.map.as_mut().unwrap().append(map))
+			} else {
+				self_
+					.map
+					.take()
+					.unwrap()
+					.into_groups()
+					.map(|groups| *self_.groups = Some(groups))
+			};
+			if let Err(err) = res {
+				return Poll::Ready(Err(err));
+			}
+		}
+	}
+}
+
+#[derive(new)]
+#[must_use]
+pub struct GroupByWindow<A, B> {
+	a: A,
+	b: B,
+}
+
+impl<A: ParallelPipe<Item, Output = (Window<T>, U)>, B: ParallelSink<U>, Item, T, U>
+	ParallelSink<Item> for GroupByWindow<A, B>
+where
+	T: EventTime + Hash + Send + 'static,
+	<B::Pipe as ParallelPipe<U>>::Task: Clone + Send + 'static,
+	B::ReduceA: Clone + Send + 'static,
+	B::ReduceC: Clone,
+	B::Done: Send + 'static,
+{
+	type Done = IndexMap<Window<T>, B::Done>;
+	type Pipe = A;
+	type ReduceA = GroupByReducerA<
+		<B::Pipe as ParallelPipe<U>>::Task,
+		B::ReduceA,
+		Window<T>,
+		U,
+		MergeSessions<T>,
+	>;
+	type ReduceC = GroupByWindowReducer<
+		B::ReduceC,
+		T,
+		<B::ReduceA as ReducerSend<<B::Pipe as ParallelPipe<U>>::Output>>::Done,
+	>;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceC) {
+		let (a, b, c) = self.b.reducers();
+		(
+			self.a,
+			GroupByReducerA::new(a.task(), b),
+			GroupByWindowReducer::new(c),
+		)
+	}
+}
+
+impl<A: DistributedPipe<Item, Output = (Window<T>, U)>, B: DistributedSink<U>, Item, T, U>
+	DistributedSink<Item> for GroupByWindow<A, B>
+where
+	T: EventTime + Hash + ProcessSend + 'static,
+	<B::Pipe as DistributedPipe<U>>::Task: Clone + ProcessSend + 'static,
+	B::ReduceA: Clone + ProcessSend + 'static,
+	B::ReduceB: Clone,
+	B::ReduceC: Clone,
+	B::Done: ProcessSend + 'static,
+{
+	type Done = IndexMap<Window<T>, B::Done>;
+	type Pipe = A;
+	type ReduceA = GroupByReducerA<
+		<B::Pipe as DistributedPipe<U>>::Task,
+		B::ReduceA,
+		Window<T>,
+		U,
+		MergeSessions<T>,
+	>;
+	type ReduceB = GroupByWindowReducer<
+		B::ReduceB,
+		T,
+		<B::ReduceA as ReducerSend<<B::Pipe as DistributedPipe<U>>::Output>>::Done,
+	>;
+	type ReduceC = GroupByWindowReducer<
+		B::ReduceC,
+		T,
+		<B::ReduceB as ReducerProcessSend<
+			<B::ReduceA as Reducer<<B::Pipe as DistributedPipe<U>>::Output>>::Done,
+		>>::Done,
+	>;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceB, Self::ReduceC) {
+		let (a, b, c, d) = self.b.reducers();
+		(
+			self.a,
+			GroupByReducerA::new(a.task(), b),
+			GroupByWindowReducer::new(c),
+			GroupByWindowReducer::new(d),
+		)
+	}
+}
+
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone(bound = "R: Clone"))]
+#[serde(
+	bound(serialize = "R: Serialize"),
+	bound(deserialize = "R: Deserialize<'de>")
+)]
+pub struct GroupByWindowReducer<R, T, U>(R, PhantomData<fn() -> (T, U)>);
+
+impl<R, T, U> Reducer<IndexMap<Window<T>, U>> for GroupByWindowReducer<R, T, U>
+where
+	R: Reducer<U> + Clone,
+	T: EventTime + Hash,
+{
+	type Done = IndexMap<Window<T>, R::Done>;
+	type Async = GroupByWindowReducerAsync<R, T, U>;
+
+	fn into_async(self) -> Self::Async {
+		GroupByWindowReducerAsync::new(self.0)
+	}
+}
+impl<R, T, U> ReducerProcessSend<IndexMap<Window<T>, U>> for GroupByWindowReducer<R, T, U>
+where
+	R: Reducer<U> + Clone,
+	T: EventTime + Hash + ProcessSend + 'static,
+	R::Done: ProcessSend + 'static,
+{
+	type Done = IndexMap<Window<T>, R::Done>;
+}
+impl<R, T, U> ReducerSend<IndexMap<Window<T>, U>> for GroupByWindowReducer<R, T, U>
+where
+	R: Reducer<U> + Clone,
+	T: EventTime + Hash + Send + 'static,
+	R::Done: Send + 'static,
+{
+	type Done = IndexMap<Window<T>, R::Done>;
+}
+
+#[pin_project]
+#[derive(new)]
+pub struct GroupByWindowReducerAsync<R, T, U>
+where
+	R: Reducer<U>,
+{
+	f: R,
+	#[new(default)]
+	partials: Vec<(Window<T>, U)>,
+	#[new(default)]
+	groups: Option<vec::IntoIter<(Window<T>, Vec<U>)>>,
+	#[new(default)]
+	current: Option<(Window<T>, vec::IntoIter<U>, Pin<Box<R::Async>>)>,
+	#[new(default)]
+	done: IndexMap<Window<T>, R::Done>,
+}
+
+impl<R, T, U> Sink<IndexMap<Window<T>, U>> for GroupByWindowReducerAsync<R, T, U>
+where
+	R: Reducer<U> + Clone,
+	T: EventTime + Hash,
+{
+	type Done = IndexMap<Window<T>, R::Done>;
+
+	#[inline(always)]
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context,
+		mut stream: Pin<&mut impl Stream<Item = IndexMap<Window<T>, U>>>,
+	) -> Poll<Self::Done> {
+		let self_ = self.project();
+		loop {
+			// Reduce each window in turn once every partial result has been received.
+			if let Some((_, values, r)) = self_.current {
+				let stream = stream::iter(values);
+				pin_mut!(stream);
+				let done = ready!(r.as_mut().poll_forward(cx, stream));
+				let (window, _, _) = self_.current.take().unwrap();
+				let _ = self_.done.insert(window, done);
+				continue;
+			}
+			if let Some(groups) = self_.groups {
+				if let Some((window, values)) = groups.next() {
+					*self_.current = Some((
+						window,
+						values.into_iter(),
+						Box::pin(self_.f.clone().into_async()),
+					));
+					continue;
+				}
+				return Poll::Ready(mem::take(self_.done));
+			}
+			if let Some(partials) = ready!(stream.as_mut().poll_next(cx)) {
+				self_.partials.extend(partials);
+			} else {
+				*self_.groups = Some(merge_windows(mem::take(self_.partials)).into_iter());
+			}
+		}
+	}
+}
+
+/// Sorts partial results by window, and groups those of equal windows and of
+/// overlapping session windows.
+fn merge_windows<T, U>(mut partials: Vec<(Window<T>, U)>) -> Vec<(Window<T>, Vec<U>)>
+where
+	T: EventTime,
+{
+	partials.sort_by(|a, b| a.0.cmp(&b.0));
+	let mut groups: Vec<(Window<T>, Vec<U>)> = Vec::new();
+	for (window, value) in partials {
+		if let Some((last, values)) = groups.last_mut() {
+			if last.merge(&window) {
+				values.push(value);
+				continue;
+			}
+		}
+		groups.push((window, vec![value]));
+	}
+	groups
+}
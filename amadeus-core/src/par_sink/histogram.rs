@@ -7,7 +7,10 @@ use replace_with::replace_with_or_default;
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, hash::Hash, marker::PhantomData};
 
-use super::{folder_par_sink, FolderSync, FolderSyncReducer, ParallelPipe, ParallelSink};
+use super::{
+	folder_par_sink, spill::try_update, FolderSync, FolderSyncReducer, ParallelPipe, ParallelSink, SpillMap
+};
+use crate::{pool::ProcessSend, util::IoError};
 
 #[derive(new)]
 #[must_use]
@@ -15,6 +18,33 @@ pub struct Histogram<P> {
 	pipe: P,
 }
 
+impl<P> Histogram<P> {
+	/// Hold at most `max_keys_in_memory` distinct items in memory, spilling the
+	/// rest to local temporary files that are merged when the stream is
+	/// exhausted.
+	///
+	/// The limit is a count of distinct items, not a number of bytes: to keep
+	/// within a memory budget, divide it by the size of an item and its count.
+	///
+	/// The result is a [`SpillMap`] of the count of each item, that streams them
+	/// back from disk in an unspecified order, or the error encountered writing
+	/// or reading the temporary files.
+	pub fn spill(self, max_keys_in_memory: usize) -> HistogramSpill<P> {
+		assert_ne!(
+			max_keys_in_memory, 0,
+			"max_keys_in_memory must be at least 1"
+		);
+		HistogramSpill::new(self.pipe, max_keys_in_memory)
+	}
+}
+
+#[derive(new)]
+#[must_use]
+pub struct HistogramSpill<P> {
+	pipe: P,
+	max_keys_in_memory: usize,
+}
+
 impl_par_dist! {
 	impl<P: ParallelPipe<Item>, Item> ParallelSink<Item> for Histogram<P>
 	where
@@ -24,6 +54,15 @@ impl_par_dist! {
 	}
 }
 
+impl_par_dist! {
+	impl<P: ParallelPipe<Item>, Item> ParallelSink<Item> for HistogramSpill<P>
+	where
+		P::Output: Eq + Hash + ProcessSend + 'static,
+	{
+		folder_par_sink!(HistogramSpillFolder<P::Output, StepA>, HistogramSpillFolder<P::Output, StepB>, self, HistogramSpillFolder::new(self.max_keys_in_memory), HistogramSpillFolder::new(self.max_keys_in_memory));
+	}
+}
+
 #[derive(Educe, Serialize, Deserialize, new)]
 #[educe(Clone)]
 #[serde(bound = "")]
@@ -112,3 +151,48 @@ where
 		state
 	}
 }
+
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone)]
+#[serde(bound = "")]
+pub struct HistogramSpillFolder<B, Step> {
+	max_keys_in_memory: usize,
+	marker: PhantomData<fn() -> (B, Step)>,
+}
+
+impl<Item> FolderSync<Item> for HistogramSpillFolder<Item, StepA>
+where
+	Item: Eq + Hash + ProcessSend,
+{
+	type State = Result<SpillMap<Item, usize>, IoError>;
+	type Done = Self::State;
+
+	fn zero(&mut self) -> Self::State {
+		Ok(SpillMap::new(self.max_keys_in_memory))
+	}
+	fn push(&mut self, state: &mut Self::State, item: Item) {
+		try_update(state, |state| {
+			state.insert_with(item, 1, |count, one| *count += one)
+		});
+	}
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state
+	}
+}
+impl<B> FolderSync<Result<SpillMap<B, usize>, IoError>> for HistogramSpillFolder<B, StepB>
+where
+	B: Eq + Hash + ProcessSend,
+{
+	type State = Result<SpillMap<B, usize>, IoError>;
+	type Done = Self::State;
+
+	fn zero(&mut self) -> Self::State {
+		Ok(SpillMap::new(self.max_keys_in_memory))
+	}
+	fn push(&mut self, state: &mut Self::State, b: Result<SpillMap<B, usize>, IoError>) {
+		try_update(state, |state| state.append(b?));
+	}
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state.and_then(|state| state.merge(|a, b| a + b))
+	}
+}
@@ -0,0 +1,162 @@
+use derive_new::new;
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, convert::TryFrom, mem};
+
+use super::{folder_par_sink, FolderSync, FolderSyncReducer, ParallelPipe, ParallelSink};
+use crate::util::{f64_to_u64, u64_to_f64};
+
+#[derive(new)]
+#[must_use]
+pub struct Median<P> {
+	pipe: P,
+}
+
+impl_par_dist! {
+	impl<P: ParallelPipe<Item, Output = f64>, Item> ParallelSink<Item> for Median<P> {
+		folder_par_sink!(
+			QuantileFolder,
+			QuantileFolder,
+			self,
+			QuantileFolder::new(0.5),
+			QuantileFolder::new(0.5)
+		);
+	}
+}
+
+#[derive(new)]
+#[must_use]
+pub struct Quantile<P> {
+	pipe: P,
+	q: f64,
+}
+
+impl_par_dist! {
+	impl<P: ParallelPipe<Item, Output = f64>, Item> ParallelSink<Item> for Quantile<P> {
+		folder_par_sink!(
+			QuantileFolder,
+			QuantileFolder,
+			self,
+			QuantileFolder::new(self.q),
+			QuantileFolder::new(self.q)
+		);
+	}
+}
+
+#[derive(new)]
+#[must_use]
+pub struct MinMax<P> {
+	pipe: P,
+}
+
+impl_par_dist! {
+	impl<P: ParallelPipe<Item, Output = f64>, Item> ParallelSink<Item> for MinMax<P> {
+		folder_par_sink!(
+			MinMaxFolder,
+			MinMaxFolder,
+			self,
+			MinMaxFolder,
+			MinMaxFolder
+		);
+	}
+}
+
+/// Collects the items, and selects the exact value at quantile `q` from them,
+/// interpolating linearly between the two nearest.
+#[derive(Clone, Serialize, Deserialize, new)]
+pub struct QuantileFolder {
+	q: f64,
+}
+
+impl QuantileFolder {
+	fn select(&self, mut items: Vec<f64>) -> Option<f64> {
+		if items.is_empty() {
+			return None;
+		}
+		let index = u64_to_f64(u64::try_from(items.len() - 1).unwrap()) * self.q;
+		let lower = index.floor();
+		let fraction = index - lower;
+		let lower = usize::try_from(f64_to_u64(lower)).unwrap();
+		let cmp = |a: &f64, b: &f64| a.partial_cmp(b).unwrap_or(Ordering::Equal);
+		let (_, &mut low, above) = items.select_nth_unstable_by(lower, cmp);
+		if fraction == 0.0 {
+			return Some(low);
+		}
+		let high = above.iter().copied().min_by(cmp).unwrap();
+		Some(low + fraction * (high - low))
+	}
+}
+
+impl FolderSync<f64> for QuantileFolder {
+	type State = Vec<f64>;
+	type Done = Option<f64>;
+
+	fn zero(&mut self) -> Self::State {
+		Vec::new()
+	}
+	fn push(&mut self, state: &mut Self::State, item: f64) {
+		if !item.is_nan() {
+			state.push(item);
+		}
+	}
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		self.select(state)
+	}
+}
+
+impl FolderSync<Vec<f64>> for QuantileFolder {
+	type State = Vec<f64>;
+	type Done = Option<f64>;
+
+	fn zero(&mut self) -> Self::State {
+		Vec::new()
+	}
+	fn push(&mut self, state: &mut Self::State, mut item: Vec<f64>) {
+		if state.len() < item.len() {
+			mem::swap(state, &mut item);
+		}
+		state.append(&mut item);
+	}
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		self.select(state)
+	}
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MinMaxFolder;
+
+impl FolderSync<f64> for MinMaxFolder {
+	type State = Option<(f64, f64)>;
+	type Done = Self::State;
+
+	fn zero(&mut self) -> Self::State {
+		None
+	}
+	fn push(&mut self, state: &mut Self::State, item: f64) {
+		if !item.is_nan() {
+			FolderSync::push(self, state, Some((item, item)));
+		}
+	}
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state
+	}
+}
+
+impl FolderSync<Option<(f64, f64)>> for MinMaxFolder {
+	type State = Option<(f64, f64)>;
+	type Done = Self::State;
+
+	fn zero(&mut self) -> Self::State {
+		None
+	}
+	fn push(&mut self, state: &mut Self::State, item: Option<(f64, f64)>) {
+		*state = match (*state, item) {
+			(Some((min_a, max_a)), Some((min_b, max_b))) => {
+				Some((min_a.min(min_b), max_a.max(max_b)))
+			}
+			(a, b) => a.or(b),
+		};
+	}
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state
+	}
+}
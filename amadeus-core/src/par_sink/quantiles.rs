@@ -0,0 +1,68 @@
+use amadeus_streaming::TDigest;
+use derive_new::new;
+use serde::{Deserialize, Serialize};
+
+use super::{folder_par_sink, FolderSync, FolderSyncReducer, ParallelPipe, ParallelSink};
+
+/// The compression of the [`TDigest`] used to estimate quantiles.
+const COMPRESSION: f64 = 100.0;
+
+#[derive(new)]
+#[must_use]
+pub struct Quantiles<P> {
+	pipe: P,
+	quantiles: Vec<f64>,
+}
+
+impl_par_dist! {
+	impl<P: ParallelPipe<Item, Output = f64>, Item> ParallelSink<Item> for Quantiles<P> {
+		folder_par_sink!(
+			QuantilesFolder,
+			QuantilesMergeFolder,
+			self,
+			QuantilesFolder::new(COMPRESSION),
+			QuantilesMergeFolder::new(COMPRESSION, self.quantiles)
+		);
+	}
+}
+
+#[derive(Clone, Serialize, Deserialize, new)]
+pub struct QuantilesFolder {
+	compression: f64,
+}
+
+impl FolderSync<f64> for QuantilesFolder {
+	type State = TDigest;
+	type Done = Self::State;
+
+	fn zero(&mut self) -> Self::State {
+		TDigest::new(self.compression)
+	}
+	fn push(&mut self, state: &mut Self::State, item: f64) {
+		state.push(item);
+	}
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state
+	}
+}
+
+#[derive(Clone, Serialize, Deserialize, new)]
+pub struct QuantilesMergeFolder {
+	compression: f64,
+	quantiles: Vec<f64>,
+}
+
+impl FolderSync<TDigest> for QuantilesMergeFolder {
+	type State = TDigest;
+	type Done = Option<Vec<f64>>;
+
+	fn zero(&mut self) -> Self::State {
+		TDigest::new(self.compression)
+	}
+	fn push(&mut self, state: &mut Self::State, item: TDigest) {
+		state.union(&item);
+	}
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		self.quantiles.iter().map(|&q| state.quantile(q)).collect()
+	}
+}
@@ -0,0 +1,507 @@
+#![allow(clippy::type_complexity)]
+
+use amadeus_streaming::SampleUnstable as SASampleUnstable;
+use derive_new::new;
+use educe::Educe;
+use futures::{ready, Stream};
+use pin_project::pin_project;
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use serde_closure::traits::{FnMut, FnOnce};
+use std::{
+	collections::hash_map::DefaultHasher, hash::{Hash, Hasher}, marker::PhantomData, mem, pin::Pin, task::{Context, Poll}
+};
+
+use super::{
+	spill::{Run, RunWriter}, DistributedPipe, DistributedSink, Final, FolderSync, FolderSyncReducer, Inter, ParallelPipe, ParallelSink, Reducer, ReducerProcessSend, ReducerSend
+};
+use crate::{par_stream::Repartition, pipe::Sink, pool::ProcessSend, util::IoError};
+
+#[derive(new)]
+#[must_use]
+pub struct RepartitionByKey<P, F> {
+	pipe: P,
+	n: usize,
+	f: F,
+}
+
+impl<P: ParallelPipe<Item>, Item, F, K> ParallelSink<Item> for RepartitionByKey<P, F>
+where
+	F: for<'a> FnMut<(&'a P::Output,), Output = K> + Clone + Send + 'static,
+	K: Hash + 'static,
+	P::Output: ProcessSend + 'static,
+{
+	type Done = Result<Repartition<P::Output>, IoError>;
+	type Pipe = P;
+	type ReduceA = PartitionReducer<P::Output, HashPartitioner<F, K>>;
+	type ReduceC = FolderSyncReducer<
+		Result<Vec<Vec<Run<P::Output>>>, IoError>,
+		ExchangeFolder<P::Output>,
+		Final,
+	>;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceC) {
+		ParallelSink::reducers(PartitionBy::new(
+			self.pipe,
+			self.n,
+			HashPartitioner::new(self.n, self.f),
+		))
+	}
+}
+
+impl<P: DistributedPipe<Item>, Item, F, K> DistributedSink<Item> for RepartitionByKey<P, F>
+where
+	F: for<'a> FnMut<(&'a P::Output,), Output = K> + Clone + ProcessSend + 'static,
+	K: Hash + 'static,
+	P::Output: ProcessSend + 'static,
+{
+	type Done = Result<Repartition<P::Output>, IoError>;
+	type Pipe = P;
+	type ReduceA = PartitionReducer<P::Output, HashPartitioner<F, K>>;
+	type ReduceB = FolderSyncReducer<
+		Result<Vec<Vec<Run<P::Output>>>, IoError>,
+		ExchangeFolder<P::Output>,
+		Inter,
+	>;
+	type ReduceC = FolderSyncReducer<
+		Result<Vec<Vec<Run<P::Output>>>, IoError>,
+		ExchangeFolder<P::Output>,
+		Final,
+	>;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceB, Self::ReduceC) {
+		DistributedSink::reducers(PartitionBy::new(
+			self.pipe,
+			self.n,
+			HashPartitioner::new(self.n, self.f),
+		))
+	}
+}
+
+/// Partitions items into `n` partitions, by the index that `f` returns for each.
+#[derive(new)]
+#[must_use]
+pub(crate) struct PartitionBy<P, F> {
+	pipe: P,
+	n: usize,
+	f: F,
+}
+
+impl<P: ParallelPipe<Item>, Item, F> ParallelSink<Item> for PartitionBy<P, F>
+where
+	F: for<'a> FnMut<(&'a P::Output,), Output = usize> + Clone + Send + 'static,
+	P::Output: ProcessSend + 'static,
+{
+	type Done = Result<Repartition<P::Output>, IoError>;
+	type Pipe = P;
+	type ReduceA = PartitionReducer<P::Output, F>;
+	type ReduceC = FolderSyncReducer<
+		Result<Vec<Vec<Run<P::Output>>>, IoError>,
+		ExchangeFolder<P::Output>,
+		Final,
+	>;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceC) {
+		(
+			self.pipe,
+			PartitionReducer::new(self.n, self.f),
+			FolderSyncReducer::new(ExchangeFolder::new(self.n)),
+		)
+	}
+}
+
+impl<P: DistributedPipe<Item>, Item, F> DistributedSink<Item> for PartitionBy<P, F>
+where
+	F: for<'a> FnMut<(&'a P::Output,), Output = usize> + Clone + ProcessSend + 'static,
+	P::Output: ProcessSend + 'static,
+{
+	type Done = Result<Repartition<P::Output>, IoError>;
+	type Pipe = P;
+	type ReduceA = PartitionReducer<P::Output, F>;
+	type ReduceB = FolderSyncReducer<
+		Result<Vec<Vec<Run<P::Output>>>, IoError>,
+		ExchangeFolder<P::Output>,
+		Inter,
+	>;
+	type ReduceC = FolderSyncReducer<
+		Result<Vec<Vec<Run<P::Output>>>, IoError>,
+		ExchangeFolder<P::Output>,
+		Final,
+	>;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceB, Self::ReduceC) {
+		(
+			self.pipe,
+			PartitionReducer::new(self.n, self.f),
+			FolderSyncReducer::new(ExchangeFolder::new(self.n)),
+			FolderSyncReducer::new(ExchangeFolder::new(self.n)),
+		)
+	}
+}
+
+/// The bucket of `key` out of `n`.
+///
+/// This hashes with a fixed key, so that every process puts a key in the same bucket.
+pub(crate) fn bucket<K: Hash + ?Sized>(key: &K, n: usize) -> usize {
+	let mut hasher = DefaultHasher::new();
+	key.hash(&mut hasher);
+	#[allow(clippy::cast_possible_truncation)]
+	let bucket = (hasher.finish() % n as u64) as usize;
+	bucket
+}
+
+/// Picks the partition of an item by the hash of the key `f` returns.
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone(bound = "F: Clone"))]
+#[serde(
+	bound(serialize = "F: Serialize"),
+	bound(deserialize = "F: Deserialize<'de>")
+)]
+pub struct HashPartitioner<F, K> {
+	n: usize,
+	f: F,
+	marker: PhantomData<fn() -> K>,
+}
+impl<'a, Item, F, K> FnOnce<(&'a Item,)> for HashPartitioner<F, K>
+where
+	F: for<'b> FnMut<(&'b Item,), Output = K>,
+	K: Hash,
+{
+	type Output = usize;
+
+	fn call_once(mut self, args: (&'a Item,)) -> Self::Output {
+		self.call_mut(args)
+	}
+}
+impl<'a, Item, F, K> FnMut<(&'a Item,)> for HashPartitioner<F, K>
+where
+	F: for<'b> FnMut<(&'b Item,), Output = K>,
+	K: Hash,
+{
+	fn call_mut(&mut self, (item,): (&'a Item,)) -> Self::Output {
+		bucket(&self.f.call_mut((item,)), self.n)
+	}
+}
+
+/// Writes each item to a run for the partition `f` picks for it, to be read by
+/// the task that takes that partition.
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone(bound = "F: Clone"))]
+#[serde(
+	bound(serialize = "F: Serialize"),
+	bound(deserialize = "F: Deserialize<'de>")
+)]
+pub struct PartitionReducer<Item, F> {
+	n: usize,
+	f: F,
+	marker: PhantomData<fn() -> Item>,
+}
+
+impl<Item, F> Reducer<Item> for PartitionReducer<Item, F>
+where
+	F: for<'a> FnMut<(&'a Item,), Output = usize>,
+	Item: Serialize,
+{
+	type Done = Result<Vec<Vec<Run<Item>>>, IoError>;
+	type Async = PartitionReducerAsync<Item, F>;
+
+	fn into_async(self) -> Self::Async {
+		PartitionReducerAsync {
+			f: self.f,
+			writers: (0..self.n).map(|_| None).collect(),
+		}
+	}
+}
+impl<Item, F> ReducerProcessSend<Item> for PartitionReducer<Item, F>
+where
+	F: for<'a> FnMut<(&'a Item,), Output = usize>,
+	Item: Serialize + 'static,
+{
+	type Done = Result<Vec<Vec<Run<Item>>>, IoError>;
+}
+impl<Item, F> ReducerSend<Item> for PartitionReducer<Item, F>
+where
+	F: for<'a> FnMut<(&'a Item,), Output = usize>,
+	Item: Serialize + 'static,
+{
+	type Done = Result<Vec<Vec<Run<Item>>>, IoError>;
+}
+
+#[pin_project]
+pub struct PartitionReducerAsync<Item, F> {
+	f: F,
+	writers: Vec<Option<RunWriter<Item>>>,
+}
+
+impl<Item, F> Sink<Item> for PartitionReducerAsync<Item, F>
+where
+	F: for<'a> FnMut<(&'a Item,), Output = usize>,
+	Item: Serialize,
+{
+	type Done = Result<Vec<Vec<Run<Item>>>, IoError>;
+
+	#[inline]
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context, mut stream: Pin<&mut impl Stream<Item = Item>>,
+	) -> Poll<Self::Done> {
+		let self_ = self.project();
+		while let Some(item) = ready!(stream.as_mut().poll_next(cx)) {
+			let writer = &mut self_.writers[self_.f.call_mut((&item,))];
+			if writer.is_none() {
+				match RunWriter::new() {
+					Ok(writer_) => *writer = Some(writer_),
+					Err(err) => return Poll::Ready(Err(err)),
+				}
+			}
+			if let Err(err) = writer.as_mut().unwrap().push(&item) {
+				return Poll::Ready(Err(err));
+			}
+		}
+		let runs = mem::take(self_.writers)
+			.into_iter()
+			.map(|writer| writer.map(RunWriter::finish).transpose())
+			.collect::<Result<Vec<_>, _>>()?;
+		Poll::Ready(Ok(runs
+			.into_iter()
+			.map(|run| run.into_iter().collect())
+			.collect()))
+	}
+}
+
+/// Combines the runs written for each partition by every task.
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone)]
+#[serde(bound = "")]
+pub struct ExchangeFolder<Item> {
+	n: usize,
+	marker: PhantomData<fn() -> Item>,
+}
+
+impl<Item> FolderSync<Result<Vec<Vec<Run<Item>>>, IoError>> for ExchangeFolder<Item> {
+	type State = Result<Vec<Vec<Run<Item>>>, IoError>;
+	type Done = Result<Repartition<Item>, IoError>;
+
+	fn zero(&mut self) -> Self::State {
+		Ok((0..self.n).map(|_| Vec::new()).collect())
+	}
+	fn push(&mut self, state: &mut Self::State, runs: Result<Vec<Vec<Run<Item>>>, IoError>) {
+		match (state, runs) {
+			(Ok(state), Ok(runs)) => {
+				for (partition, mut runs) in state.iter_mut().zip(runs) {
+					partition.append(&mut runs);
+				}
+			}
+			(state @ Ok(_), Err(err)) => *state = Err(err),
+			(Err(_), _) => (),
+		}
+	}
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state.map(Repartition::new)
+	}
+}
+
+/// Writes the items of each task to a run, and takes a sample of up to `samples`
+/// of them, to pick the boundaries of ranges to sort them into before they're
+/// read back.
+#[derive(new)]
+#[must_use]
+pub(crate) struct SampleRuns<P> {
+	pipe: P,
+	samples: usize,
+}
+
+impl<P: ParallelPipe<Item>, Item> ParallelSink<Item> for SampleRuns<P>
+where
+	P::Output: ProcessSend + 'static,
+{
+	type Done = Result<(Vec<Run<P::Output>>, Vec<(usize, Vec<P::Output>)>), IoError>;
+	type Pipe = P;
+	type ReduceA = SampleRunReducer<P::Output>;
+	type ReduceC = FolderSyncReducer<
+		Result<(Vec<Run<P::Output>>, Vec<(usize, Vec<P::Output>)>), IoError>,
+		SampledRunsFolder<P::Output>,
+		Final,
+	>;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceC) {
+		(
+			self.pipe,
+			SampleRunReducer::new(self.samples),
+			FolderSyncReducer::new(SampledRunsFolder::new(self.samples)),
+		)
+	}
+}
+
+impl<P: DistributedPipe<Item>, Item> DistributedSink<Item> for SampleRuns<P>
+where
+	P::Output: ProcessSend + 'static,
+{
+	type Done = Result<(Vec<Run<P::Output>>, Vec<(usize, Vec<P::Output>)>), IoError>;
+	type Pipe = P;
+	type ReduceA = SampleRunReducer<P::Output>;
+	type ReduceB = FolderSyncReducer<
+		Result<(Vec<Run<P::Output>>, Vec<(usize, Vec<P::Output>)>), IoError>,
+		SampledRunsFolder<P::Output>,
+		Inter,
+	>;
+	type ReduceC = FolderSyncReducer<
+		Result<(Vec<Run<P::Output>>, Vec<(usize, Vec<P::Output>)>), IoError>,
+		SampledRunsFolder<P::Output>,
+		Final,
+	>;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceB, Self::ReduceC) {
+		(
+			self.pipe,
+			SampleRunReducer::new(self.samples),
+			FolderSyncReducer::new(SampledRunsFolder::new(self.samples)),
+			FolderSyncReducer::new(SampledRunsFolder::new(self.samples)),
+		)
+	}
+}
+
+/// Writes the items of a task to a run, yielding it along with the number of
+/// items and a sample of them.
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone)]
+#[serde(bound = "")]
+pub(crate) struct SampleRunReducer<Item> {
+	samples: usize,
+	marker: PhantomData<fn() -> Item>,
+}
+
+impl<Item> Reducer<Item> for SampleRunReducer<Item>
+where
+	Item: Serialize,
+{
+	type Done = Result<(Vec<Run<Item>>, Vec<(usize, Vec<Item>)>), IoError>;
+	type Async = SampleRunReducerAsync<Item>;
+
+	fn into_async(self) -> Self::Async {
+		SampleRunReducerAsync {
+			writer: None,
+			len: 0,
+			sample: SASampleUnstable::new(self.samples),
+		}
+	}
+}
+impl<Item> ReducerProcessSend<Item> for SampleRunReducer<Item>
+where
+	Item: ProcessSend + 'static,
+{
+	type Done = Result<(Vec<Run<Item>>, Vec<(usize, Vec<Item>)>), IoError>;
+}
+impl<Item> ReducerSend<Item> for SampleRunReducer<Item>
+where
+	Item: Serialize + Send + 'static,
+{
+	type Done = Result<(Vec<Run<Item>>, Vec<(usize, Vec<Item>)>), IoError>;
+}
+
+#[pin_project]
+pub(crate) struct SampleRunReducerAsync<Item> {
+	writer: Option<RunWriter<Item>>,
+	len: usize,
+	sample: SASampleUnstable<Item>,
+}
+
+impl<Item> Sink<Item> for SampleRunReducerAsync<Item>
+where
+	Item: Serialize,
+{
+	type Done = Result<(Vec<Run<Item>>, Vec<(usize, Vec<Item>)>), IoError>;
+
+	#[inline]
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context, mut stream: Pin<&mut impl Stream<Item = Item>>,
+	) -> Poll<Self::Done> {
+		let self_ = self.project();
+		while let Some(item) = ready!(stream.as_mut().poll_next(cx)) {
+			if self_.writer.is_none() {
+				match RunWriter::new() {
+					Ok(writer) => *self_.writer = Some(writer),
+					Err(err) => return Poll::Ready(Err(err)),
+				}
+			}
+			if let Err(err) = self_.writer.as_mut().unwrap().push(&item) {
+				return Poll::Ready(Err(err));
+			}
+			*self_.len += 1;
+			self_.sample.push(item, &mut thread_rng());
+		}
+		let run = self_.writer.take().map(RunWriter::finish).transpose()?;
+		let sample = mem::replace(self_.sample, SASampleUnstable::new(0));
+		Poll::Ready(Ok((
+			run.into_iter().collect(),
+			vec![(*self_.len, sample.into_iter().collect())],
+		)))
+	}
+}
+
+/// Combines the runs and samples of every task, thinning the samples down to
+/// about `samples` items so that they stay small however many tasks there are.
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone)]
+#[serde(bound = "")]
+pub(crate) struct SampledRunsFolder<Item> {
+	samples: usize,
+	marker: PhantomData<fn() -> Item>,
+}
+
+impl<Item> FolderSync<Result<(Vec<Run<Item>>, Vec<(usize, Vec<Item>)>), IoError>>
+	for SampledRunsFolder<Item>
+{
+	type State = Result<(Vec<Run<Item>>, Vec<(usize, Vec<Item>)>), IoError>;
+	type Done = Self::State;
+
+	fn zero(&mut self) -> Self::State {
+		Ok((Vec::new(), Vec::new()))
+	}
+	fn push(
+		&mut self, state: &mut Self::State,
+		runs: Result<(Vec<Run<Item>>, Vec<(usize, Vec<Item>)>), IoError>,
+	) {
+		match (state, runs) {
+			(Ok((state_runs, state_samples)), Ok((mut runs, mut samples))) => {
+				state_runs.append(&mut runs);
+				state_samples.append(&mut samples);
+				let sampled = state_samples
+					.iter()
+					.map(|(_, sample)| sample.len())
+					.sum::<usize>();
+				if sampled > 2 * self.samples {
+					let sample = thin_samples(mem::take(state_samples), self.samples);
+					state_samples.push(sample);
+				}
+			}
+			(state @ Ok(_), Err(err)) => *state = Err(err),
+			(Err(_), _) => (),
+		}
+	}
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state
+	}
+}
+
+/// Thin the samples of several tasks down to a sample of `n` of the items of all
+/// of them, picking each sampled item with a probability in proportion to the
+/// number of items it stands in for.
+fn thin_samples<Item>(samples: Vec<(usize, Vec<Item>)>, n: usize) -> (usize, Vec<Item>) {
+	let len = samples.iter().map(|&(len, _)| len).sum();
+	let mut rng = thread_rng();
+	// Weighted sampling without replacement, keeping the items with the largest
+	// keys: https://doi.org/10.1016/j.ipl.2005.11.003
+	#[allow(clippy::cast_precision_loss)]
+	let mut sample = samples
+		.into_iter()
+		.filter(|(_, sample)| !sample.is_empty())
+		.flat_map(|(len, sample)| {
+			let weight = len as f64 / sample.len() as f64;
+			sample.into_iter().map(move |item| (weight, item))
+		})
+		.map(|(weight, item)| (rng.gen::<f64>().ln() / weight, item))
+		.collect::<Vec<_>>();
+	sample.sort_by(|(a, _), (b, _)| b.partial_cmp(a).unwrap());
+	sample.truncate(n);
+	(len, sample.into_iter().map(|(_, item)| item).collect())
+}
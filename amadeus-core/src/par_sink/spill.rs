@@ -0,0 +1,755 @@
+use itertools::Itertools;
+use serde::{
+	de::{self, DeserializeOwned}, ser, Deserialize, Deserializer, Serialize, Serializer
+};
+use std::{
+	cmp::Reverse, collections::{
+		hash_map::{DefaultHasher, Entry}, BinaryHeap, HashMap
+	}, env, fmt, fs::{self, File}, hash::{Hash, Hasher}, io::{self, BufReader, BufWriter, Write}, iter, marker::PhantomData, mem, net::SocketAddr, path::PathBuf, sync::atomic::{AtomicBool, Ordering}, vec
+};
+
+use super::exchange::{self, RunReader};
+use crate::util::IoError;
+
+fn hash<K: Hash>(key: &K) -> u64 {
+	let mut state = DefaultHasher::new();
+	key.hash(&mut state);
+	state.finish()
+}
+
+pub(crate) fn bincode_error(err: bincode::ErrorKind) -> IoError {
+	match err {
+		bincode::ErrorKind::Io(err) => err.into(),
+		err => io::Error::new(io::ErrorKind::InvalidData, err).into(),
+	}
+}
+
+/// Update the value held by `state` with `f`, replacing it with the error if
+/// `f` fails. Does nothing if `state` already holds an error.
+pub(crate) fn try_update<T, F>(state: &mut Result<T, IoError>, f: F)
+where
+	F: FnOnce(&mut T) -> Result<(), IoError>,
+{
+	if let Ok(value) = state {
+		if let Err(err) = f(value) {
+			*state = Err(err);
+		}
+	}
+}
+
+/// A map that holds at most `max_keys_in_memory` keys in memory, spilling the
+/// rest as runs sorted by the hash of their key to local temporary files.
+///
+/// The limit is a number of keys rather than of bytes, so it should be chosen
+/// with the size of the keys and values in mind.
+///
+/// It's returned by the spilling sinks like
+/// [`GroupBy::spill`](crate::par_sink::GroupBy::spill) and
+/// [`Histogram::spill`](crate::par_sink::Histogram::spill), in which case each
+/// key is present at most once. Iterating it streams the entries back from
+/// disk, in an unspecified order, yielding an error if a run can't be read. The
+/// temporary files are deleted when it's dropped.
+///
+/// Serializing it, as a pool of processes does to send it between them, carries
+/// only the entries held in memory and where to find its runs, which are left
+/// in the processes that spilled them and fetched from there as they're read.
+/// So the result of a pool of processes is kept out of the memory of the process
+/// driving it too, though the pool must still be running when it's read.
+pub struct SpillMap<K, V> {
+	max_keys_in_memory: usize,
+	map: HashMap<K, V>,
+	raw: Vec<(K, V)>,
+	runs: Vec<Run<(u64, K, V)>>,
+}
+
+impl<K, V> SpillMap<K, V>
+where
+	K: Eq + Hash + Serialize + DeserializeOwned,
+	V: Serialize + DeserializeOwned,
+{
+	/// Create an empty map that spills to disk once it holds more than
+	/// `max_keys_in_memory` keys.
+	pub fn new(max_keys_in_memory: usize) -> Self {
+		assert_ne!(
+			max_keys_in_memory, 0,
+			"max_keys_in_memory must be at least 1"
+		);
+		Self {
+			max_keys_in_memory,
+			map: HashMap::new(),
+			raw: Vec::new(),
+			runs: Vec::new(),
+		}
+	}
+
+	/// The number of entries held, both in memory and spilled.
+	pub fn len(&self) -> usize {
+		self.map.len() + self.raw.len() + self.runs.iter().map(Run::len).sum::<usize>()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Whether any entries have been spilled to disk.
+	pub fn is_spilled(&self) -> bool {
+		!self.runs.is_empty()
+	}
+
+	/// Insert `value` at `key`, combining it with `merge` if `key` is already
+	/// held in memory.
+	pub(crate) fn insert_with<F>(&mut self, key: K, value: V, merge: F) -> Result<(), IoError>
+	where
+		F: FnOnce(&mut V, V),
+	{
+		match self.map.entry(key) {
+			Entry::Occupied(entry) => merge(entry.into_mut(), value),
+			Entry::Vacant(entry) => {
+				let _ = entry.insert(value);
+			}
+		}
+		self.maybe_spill()
+	}
+
+	/// Insert `value` at `key` without combining it with any value already
+	/// held; they're combined by [`into_groups`](Self::into_groups).
+	pub(crate) fn push(&mut self, key: K, value: V) -> Result<(), IoError> {
+		self.raw.push((key, value));
+		self.maybe_spill()
+	}
+
+	/// Move all of `other`'s entries into `self`, without combining them.
+	pub(crate) fn append(&mut self, mut other: Self) -> Result<(), IoError> {
+		self.runs.append(&mut other.runs);
+		self.raw.extend(other.map.drain());
+		self.raw.append(&mut other.raw);
+		self.maybe_spill()
+	}
+
+	/// Consume the map, yielding each distinct key with all the values held
+	/// for it.
+	pub(crate) fn into_groups(mut self) -> Result<Groups<K, V>, IoError> {
+		let mut memory = self
+			.map
+			.drain()
+			.chain(self.raw.drain(..))
+			.map(|(key, value)| (hash(&key), key, value))
+			.collect::<Vec<_>>();
+		memory.sort_by_key(|&(hash, _, _)| hash);
+		let runs = mem::take(&mut self.runs);
+		let sources = iter::once(Ok(Source::Memory(memory.into_iter())))
+			.chain(runs.iter().map(|run| run.read().map(Source::Run)))
+			.collect::<Result<Vec<_>, _>>()?;
+		Groups::new(sources, runs)
+	}
+
+	/// Consume the map, combining the values held for each key with `merge`.
+	pub(crate) fn merge<F>(self, mut merge: F) -> Result<Self, IoError>
+	where
+		F: FnMut(V, V) -> V,
+	{
+		let mut ret = Self::new(self.max_keys_in_memory);
+		for group in self.into_groups()? {
+			let (key, values) = group?;
+			let value = values.into_iter().fold1(&mut merge).unwrap();
+			ret.push(key, value)?;
+		}
+		Ok(ret)
+	}
+
+	fn maybe_spill(&mut self) -> Result<(), IoError> {
+		if self.map.len() + self.raw.len() > self.max_keys_in_memory {
+			self.spill()?;
+		}
+		Ok(())
+	}
+
+	fn spill(&mut self) -> Result<(), IoError> {
+		let mut entries = self
+			.map
+			.drain()
+			.chain(self.raw.drain(..))
+			.map(|(key, value)| (hash(&key), key, value))
+			.collect::<Vec<_>>();
+		entries.sort_by_key(|&(hash, _, _)| hash);
+		self.runs.push(Run::write(&entries)?);
+		Ok(())
+	}
+}
+
+impl<K, V> fmt::Debug for SpillMap<K, V> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("SpillMap")
+			.field("max_keys_in_memory", &self.max_keys_in_memory)
+			.field("in_memory", &(self.map.len() + self.raw.len()))
+			.field("runs", &self.runs.len())
+			.finish()
+	}
+}
+
+impl<K, V> IntoIterator for SpillMap<K, V>
+where
+	K: DeserializeOwned,
+	V: DeserializeOwned,
+{
+	type Item = Result<(K, V), IoError>;
+	type IntoIter = SpillMapIter<K, V>;
+
+	fn into_iter(mut self) -> Self::IntoIter {
+		let memory = mem::take(&mut self.map)
+			.into_iter()
+			.chain(mem::take(&mut self.raw))
+			.collect::<Vec<_>>()
+			.into_iter();
+		SpillMapIter {
+			memory,
+			runs: RunsIter::new(mem::take(&mut self.runs)),
+		}
+	}
+}
+
+/// An iterator over the entries of a [`SpillMap`], streaming them back from
+/// disk.
+pub struct SpillMapIter<K, V> {
+	memory: vec::IntoIter<(K, V)>,
+	runs: RunsIter<(u64, K, V)>,
+}
+impl<K, V> Iterator for SpillMapIter<K, V>
+where
+	K: DeserializeOwned,
+	V: DeserializeOwned,
+{
+	type Item = Result<(K, V), IoError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if let Some(entry) = self.memory.next() {
+			return Some(Ok(entry));
+		}
+		self.runs
+			.next()
+			.map(|entry| entry.map(|(_, key, value)| (key, value)))
+	}
+}
+
+/// A list that holds at most `max_items_in_memory` items in memory, spilling
+/// the rest in order to local temporary files.
+///
+/// It's returned by [`Collect::spill`](crate::par_sink::Collect::spill).
+/// Iterating it streams the items back from disk in the order they were
+/// collected, yielding an error if a run can't be read. The temporary files are
+/// deleted when it's dropped. It's serialized like a [`SpillMap`].
+pub struct SpillVec<T> {
+	max_items_in_memory: usize,
+	runs: Vec<Run<T>>,
+	tail: Vec<T>,
+}
+
+impl<T> SpillVec<T>
+where
+	T: Serialize + DeserializeOwned,
+{
+	/// Create an empty list that spills to disk once it holds more than
+	/// `max_items_in_memory` items.
+	pub fn new(max_items_in_memory: usize) -> Self {
+		assert_ne!(
+			max_items_in_memory, 0,
+			"max_items_in_memory must be at least 1"
+		);
+		Self {
+			max_items_in_memory,
+			runs: Vec::new(),
+			tail: Vec::new(),
+		}
+	}
+
+	/// The number of items held, both in memory and spilled.
+	pub fn len(&self) -> usize {
+		self.tail.len() + self.runs.iter().map(Run::len).sum::<usize>()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Whether any items have been spilled to disk.
+	pub fn is_spilled(&self) -> bool {
+		!self.runs.is_empty()
+	}
+
+	/// Append `item`.
+	pub(crate) fn push(&mut self, item: T) -> Result<(), IoError> {
+		self.tail.push(item);
+		if self.tail.len() > self.max_items_in_memory {
+			self.spill()?;
+		}
+		Ok(())
+	}
+
+	/// Move all of `other`'s items to the end of `self`.
+	pub(crate) fn append(&mut self, mut other: Self) -> Result<(), IoError> {
+		if !other.runs.is_empty() {
+			if !self.tail.is_empty() {
+				self.spill()?;
+			}
+			self.runs.append(&mut other.runs);
+		}
+		for item in other.tail.drain(..) {
+			self.push(item)?;
+		}
+		Ok(())
+	}
+
+	fn spill(&mut self) -> Result<(), IoError> {
+		self.runs.push(Run::write(&self.tail)?);
+		self.tail.clear();
+		Ok(())
+	}
+}
+
+impl<T> fmt::Debug for SpillVec<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("SpillVec")
+			.field("max_items_in_memory", &self.max_items_in_memory)
+			.field("in_memory", &self.tail.len())
+			.field("runs", &self.runs.len())
+			.finish()
+	}
+}
+
+impl<T> IntoIterator for SpillVec<T>
+where
+	T: DeserializeOwned,
+{
+	type Item = Result<T, IoError>;
+	type IntoIter = SpillVecIter<T>;
+
+	fn into_iter(mut self) -> Self::IntoIter {
+		SpillVecIter {
+			runs: RunsIter::new(mem::take(&mut self.runs)),
+			tail: mem::take(&mut self.tail).into_iter(),
+		}
+	}
+}
+
+/// An iterator over the items of a [`SpillVec`], streaming them back from disk.
+pub struct SpillVecIter<T> {
+	runs: RunsIter<T>,
+	tail: vec::IntoIter<T>,
+}
+impl<T> Iterator for SpillVecIter<T>
+where
+	T: DeserializeOwned,
+{
+	type Item = Result<T, IoError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.runs.next().or_else(|| self.tail.next().map(Ok))
+	}
+}
+
+/// The values held for each distinct key of a [`SpillMap`], merged from memory
+/// and the spilled runs in order of hash.
+pub(crate) struct Groups<K, V> {
+	sources: Vec<Source<K, V>>,
+	heads: Vec<Option<(u64, K, V)>>,
+	heap: BinaryHeap<Reverse<(u64, usize)>>,
+	pending: vec::IntoIter<(K, Vec<V>)>,
+	_runs: Vec<Run<(u64, K, V)>>,
+}
+impl<K, V> Groups<K, V>
+where
+	K: DeserializeOwned,
+	V: DeserializeOwned,
+{
+	fn new(mut sources: Vec<Source<K, V>>, runs: Vec<Run<(u64, K, V)>>) -> Result<Self, IoError> {
+		let heads = sources
+			.iter_mut()
+			.map(|source| source.next().transpose())
+			.collect::<Result<Vec<_>, _>>()?;
+		let heap = heads
+			.iter()
+			.enumerate()
+			.filter_map(|(i, head)| head.as_ref().map(|&(hash, _, _)| Reverse((hash, i))))
+			.collect();
+		Ok(Self {
+			sources,
+			heads,
+			heap,
+			pending: Vec::new().into_iter(),
+			_runs: runs,
+		})
+	}
+}
+impl<K, V> Iterator for Groups<K, V>
+where
+	K: Eq + DeserializeOwned,
+	V: DeserializeOwned,
+{
+	type Item = Result<(K, Vec<V>), IoError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if let Some(group) = self.pending.next() {
+			return Some(Ok(group));
+		}
+		let Reverse((hash, _)) = *self.heap.peek()?;
+		let mut groups: Vec<(K, Vec<V>)> = Vec::new();
+		while let Some(&Reverse((hash_, i))) = self.heap.peek() {
+			if hash_ != hash {
+				break;
+			}
+			let _ = self.heap.pop();
+			let next = match self.sources[i].next().transpose() {
+				Ok(next) => next,
+				Err(err) => {
+					self.heap.clear();
+					return Some(Err(err));
+				}
+			};
+			let (_, key, value) = mem::replace(&mut self.heads[i], next).unwrap();
+			if let Some((hash, _, _)) = &self.heads[i] {
+				self.heap.push(Reverse((*hash, i)));
+			}
+			if let Some((_, values)) = groups.iter_mut().find(|(key_, _)| *key_ == key) {
+				values.push(value);
+			} else {
+				groups.push((key, vec![value]));
+			}
+		}
+		self.pending = groups.into_iter();
+		self.pending.next().map(Ok)
+	}
+}
+
+enum Source<K, V> {
+	Memory(vec::IntoIter<(u64, K, V)>),
+	Run(RunIter<(u64, K, V)>),
+}
+impl<K, V> Iterator for Source<K, V>
+where
+	K: DeserializeOwned,
+	V: DeserializeOwned,
+{
+	type Item = Result<(u64, K, V), IoError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		match self {
+			Self::Memory(iter) => iter.next().map(Ok),
+			Self::Run(iter) => iter.next(),
+		}
+	}
+}
+
+pub(crate) fn temp_path() -> PathBuf {
+	env::temp_dir().join(format!("amadeus-spill-{:016x}", rand::random::<u64>()))
+}
+
+/// A temporary file of items, deleted on drop.
+///
+/// It's serialized as where to find the file rather than as its contents: the
+/// file is left in the process that wrote it, which serves it to whichever
+/// process the run is sent to, even on another machine, until that process
+/// drops it. So the process that wrote it must still be running when it's read.
+pub struct Run<T> {
+	location: Location,
+	len: usize,
+	marker: PhantomData<fn() -> T>,
+}
+enum Location {
+	/// A file of this process.
+	Local(PathBuf),
+	/// A file served by the process at `addr`, which is told to delete it on drop
+	/// unless the run has been sent on to another process.
+	Remote {
+		addr: SocketAddr,
+		id: u128,
+		forwarded: AtomicBool,
+	},
+}
+impl<T> Run<T> {
+	/// Write `items` to a new temporary file.
+	pub(crate) fn write(items: &[T]) -> Result<Self, IoError>
+	where
+		T: Serialize,
+	{
+		let mut writer = RunWriter::new()?;
+		for item in items {
+			writer.push(item)?;
+		}
+		writer.finish()
+	}
+	pub(crate) fn len(&self) -> usize {
+		self.len
+	}
+	/// Stream the items back from the file.
+	pub(crate) fn read(&self) -> Result<RunIter<T>, IoError> {
+		let reader = match &self.location {
+			Location::Local(path) => RunReader::File(File::open(path)?),
+			Location::Remote { addr, id, .. } => exchange::open(*addr, *id)?,
+		};
+		Ok(RunIter::new(reader, self.len))
+	}
+}
+impl<T> fmt::Debug for Run<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let mut debug = f.debug_struct("Run");
+		let _ = match &self.location {
+			Location::Local(path) => debug.field("path", path),
+			Location::Remote { addr, id, .. } => debug.field("addr", addr).field("id", id),
+		};
+		debug.field("len", &self.len).finish()
+	}
+}
+impl<T> Drop for Run<T> {
+	fn drop(&mut self) {
+		match &self.location {
+			Location::Local(path) => {
+				let _ = fs::remove_file(path);
+			}
+			Location::Remote {
+				addr,
+				id,
+				forwarded,
+			} => {
+				if !forwarded.load(Ordering::Relaxed) {
+					exchange::release(*addr, *id);
+				}
+			}
+		}
+	}
+}
+
+/// Writes items one at a time to a new temporary file, which is deleted if
+/// this is dropped before it's finished.
+pub(crate) struct RunWriter<T> {
+	path: PathBuf,
+	len: usize,
+	file: BufWriter<File>,
+	marker: PhantomData<fn() -> T>,
+}
+impl<T> RunWriter<T>
+where
+	T: Serialize,
+{
+	pub(crate) fn new() -> Result<Self, IoError> {
+		let path = temp_path();
+		let file = BufWriter::new(File::create(&path)?);
+		Ok(Self {
+			path,
+			len: 0,
+			file,
+			marker: PhantomData,
+		})
+	}
+	pub(crate) fn push(&mut self, item: &T) -> Result<(), IoError> {
+		bincode::serialize_into(&mut self.file, item).map_err(|err| bincode_error(*err))?;
+		self.len += 1;
+		Ok(())
+	}
+	pub(crate) fn finish(mut self) -> Result<Run<T>, IoError> {
+		self.file.flush()?;
+		Ok(Run {
+			location: Location::Local(mem::take(&mut self.path)),
+			len: self.len,
+			marker: PhantomData,
+		})
+	}
+}
+impl<T> Drop for RunWriter<T> {
+	fn drop(&mut self) {
+		if !self.path.as_os_str().is_empty() {
+			let _ = fs::remove_file(&self.path);
+		}
+	}
+}
+
+pub(crate) struct RunIter<T> {
+	file: BufReader<RunReader>,
+	remaining: usize,
+	marker: PhantomData<fn() -> T>,
+}
+impl<T> RunIter<T> {
+	fn new(reader: RunReader, len: usize) -> Self {
+		Self {
+			file: BufReader::new(reader),
+			remaining: len,
+			marker: PhantomData,
+		}
+	}
+}
+impl<T> Iterator for RunIter<T>
+where
+	T: DeserializeOwned,
+{
+	type Item = Result<T, IoError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.remaining == 0 {
+			return None;
+		}
+		self.remaining -= 1;
+		let item = bincode::deserialize_from(&mut self.file).map_err(|err| bincode_error(*err));
+		if item.is_err() {
+			self.remaining = 0;
+		}
+		Some(item)
+	}
+}
+
+/// The items of a sequence of runs, read one run at a time and each deleted once
+/// read. It stops after the first error.
+pub(crate) struct RunsIter<T> {
+	runs: vec::IntoIter<Run<T>>,
+	current: Option<(Run<T>, RunIter<T>)>,
+}
+impl<T> RunsIter<T> {
+	pub(crate) fn new(runs: Vec<Run<T>>) -> Self {
+		Self {
+			runs: runs.into_iter(),
+			current: None,
+		}
+	}
+}
+impl<T> Iterator for RunsIter<T>
+where
+	T: DeserializeOwned,
+{
+	type Item = Result<T, IoError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			if let Some((_, iter)) = &mut self.current {
+				match iter.next() {
+					Some(Ok(item)) => return Some(Ok(item)),
+					Some(Err(err)) => {
+						self.runs = Vec::new().into_iter();
+						return Some(Err(err));
+					}
+					None => (),
+				}
+			}
+			let run = self.runs.next()?;
+			match run.read() {
+				Ok(iter) => self.current = Some((run, iter)),
+				Err(err) => {
+					self.runs = Vec::new().into_iter();
+					self.current = None;
+					return Some(Err(err));
+				}
+			}
+		}
+	}
+}
+
+impl<K, V> Serialize for SpillMap<K, V>
+where
+	K: Serialize,
+	V: Serialize,
+{
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		let entries = self
+			.map
+			.iter()
+			.chain(self.raw.iter().map(|(key, value)| (key, value)))
+			.collect::<Vec<_>>();
+		(self.max_keys_in_memory, &self.runs, entries).serialize(serializer)
+	}
+}
+
+impl<'de, K, V> Deserialize<'de> for SpillMap<K, V>
+where
+	K: Eq + Hash + Serialize + DeserializeOwned,
+	V: Serialize + DeserializeOwned,
+{
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let (max_keys_in_memory, runs, entries): (usize, Vec<Run<_>>, Vec<(K, V)>) =
+			Deserialize::deserialize(deserializer)?;
+		if max_keys_in_memory == 0 {
+			return Err(de::Error::custom("max_keys_in_memory must be at least 1"));
+		}
+		let mut map = SpillMap::new(max_keys_in_memory);
+		map.runs = runs;
+		for (key, value) in entries {
+			map.push(key, value).map_err(de::Error::custom)?;
+		}
+		Ok(map)
+	}
+}
+
+impl<T> Serialize for SpillVec<T>
+where
+	T: Serialize,
+{
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		(self.max_items_in_memory, &self.runs, &self.tail).serialize(serializer)
+	}
+}
+
+impl<'de, T> Deserialize<'de> for SpillVec<T>
+where
+	T: Serialize + DeserializeOwned,
+{
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let (max_items_in_memory, runs, tail): (usize, Vec<Run<T>>, Vec<T>) =
+			Deserialize::deserialize(deserializer)?;
+		if max_items_in_memory == 0 {
+			return Err(de::Error::custom("max_items_in_memory must be at least 1"));
+		}
+		let mut vec = SpillVec::new(max_items_in_memory);
+		vec.runs = runs;
+		for item in tail {
+			vec.push(item).map_err(de::Error::custom)?;
+		}
+		Ok(vec)
+	}
+}
+
+impl<T> Serialize for Run<T> {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		let (addr, id) = match &self.location {
+			Location::Local(path) => exchange::export(path).map_err(ser::Error::custom)?,
+			Location::Remote {
+				addr,
+				id,
+				forwarded,
+			} => {
+				// It's now up to the process it's sent to to release it
+				forwarded.store(true, Ordering::Relaxed);
+				(*addr, *id)
+			}
+		};
+		(self.len, addr, id).serialize(serializer)
+	}
+}
+
+impl<'de, T> Deserialize<'de> for Run<T> {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let (len, addr, id) = Deserialize::deserialize(deserializer)?;
+		Ok(Run {
+			location: Location::Remote {
+				addr,
+				id,
+				forwarded: AtomicBool::new(false),
+			},
+			len,
+			marker: PhantomData,
+		})
+	}
+}
@@ -0,0 +1,360 @@
+use derive_new::new;
+use educe::Educe;
+use futures::{future::LocalBoxFuture, ready, Stream};
+use pin_project::pin_project;
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::HashMap, convert::TryFrom, error::Error, fmt, marker::PhantomData, mem, pin::Pin, task::{Context, Poll}
+};
+
+use super::{
+	DistributedPipe, DistributedSink, Final, FolderSync, FolderSyncReducer, Inter, ParallelPipe, ParallelSink, Reducer, ReducerProcessSend, ReducerSend
+};
+use crate::{
+	file::{Create, Page}, pipe::Sink, pool::ProcessSend
+};
+
+const BUFFER_SIZE: usize = 16 * 1024 * 1024; // `WriteReducerAsync` buffers this many bytes before writing
+
+/// Serializes items into the bytes of a file, for example as CSV or Parquet.
+///
+/// Modelled on [`FolderSync`]: each worker calls `zero` once, `push` for each
+/// item, and `done` once the stream is exhausted. Bytes appended to `buf` are
+/// written sequentially to the worker's file, which is closed after the last
+/// write.
+pub trait Encoder<Item> {
+	type State;
+	type Error;
+
+	/// The suffix given to files written with this encoder, e.g. `.csv`.
+	fn extension(&self) -> String;
+	/// The directory under the target to write `item` to, such as the Hive-style
+	/// partition directory of its partition columns, or `None` for the target itself.
+	fn directory(&self, _item: &Item) -> Option<String> {
+		None
+	}
+	fn zero(&mut self) -> Self::State;
+	fn push(
+		&mut self, state: &mut Self::State, item: Item, buf: &mut Vec<u8>,
+	) -> Result<(), Self::Error>;
+	fn done(&mut self, state: Self::State, buf: &mut Vec<u8>) -> Result<(), Self::Error>;
+}
+
+/// An error creating or writing to a file. Format-specific errors implement
+/// `From<WriteError<A, B>>` so they can be returned by [`Write`].
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum WriteError<A, B> {
+	Create(A),
+	Page(B),
+}
+impl<A, B> Error for WriteError<A, B>
+where
+	A: Error,
+	B: Error,
+{
+}
+impl<A, B> fmt::Display for WriteError<A, B>
+where
+	A: fmt::Display,
+	B: fmt::Display,
+{
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Create(err) => fmt::Display::fmt(err, f),
+			Self::Page(err) => fmt::Display::fmt(err, f),
+		}
+	}
+}
+
+/// Writes each worker's items to its own file under `target`, encoded with `encoder`,
+/// or to a file of its own in each directory [`Encoder::directory`] picks.
+///
+/// Files are named `part-<job>-<worker>`, so that neither workers of the same write
+/// nor successive writes to the same target clobber each other's files. Any
+/// [`WriteMode`](crate::file::WriteMode) should be applied with
+/// [`Create::prepare`] beforehand.
+#[derive(new)]
+#[must_use]
+pub struct Write<P, D, E> {
+	pipe: P,
+	target: D,
+	encoder: E,
+}
+
+impl<P: ParallelPipe<Item>, Item, D, E> ParallelSink<Item> for Write<P, D, E>
+where
+	D: Create,
+	E: Encoder<P::Output> + Clone + Send + 'static,
+	E::Error: From<WriteError<D::Error, <D::Page as Page>::Error>> + Send + 'static,
+{
+	type Done = Result<(), E::Error>;
+	type Pipe = P;
+	type ReduceA = WriteReducer<P::Output, D, E>;
+	type ReduceC = FolderSyncReducer<Result<(), E::Error>, ResultFolder<E::Error>, Final>;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceC) {
+		(
+			self.pipe,
+			WriteReducer::new(self.target, job_prefix(), self.encoder),
+			FolderSyncReducer::new(ResultFolder::new()),
+		)
+	}
+}
+impl<P: DistributedPipe<Item>, Item, D, E> DistributedSink<Item> for Write<P, D, E>
+where
+	D: Create,
+	E: Encoder<P::Output> + Clone + ProcessSend + 'static,
+	E::Error: From<WriteError<D::Error, <D::Page as Page>::Error>> + ProcessSend + 'static,
+{
+	type Done = Result<(), E::Error>;
+	type Pipe = P;
+	type ReduceA = WriteReducer<P::Output, D, E>;
+	type ReduceB = FolderSyncReducer<Result<(), E::Error>, ResultFolder<E::Error>, Inter>;
+	type ReduceC = FolderSyncReducer<Result<(), E::Error>, ResultFolder<E::Error>, Final>;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceB, Self::ReduceC) {
+		(
+			self.pipe,
+			WriteReducer::new(self.target, job_prefix(), self.encoder),
+			FolderSyncReducer::new(ResultFolder::new()),
+			FolderSyncReducer::new(ResultFolder::new()),
+		)
+	}
+}
+
+fn job_prefix() -> String {
+	format!("part-{:016x}-", rand::random::<u64>())
+}
+
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone(bound = "D: Clone, E: Clone"))]
+#[serde(
+	bound(serialize = "D: Serialize, E: Serialize"),
+	bound(deserialize = "D: Deserialize<'de>, E: Deserialize<'de>")
+)]
+pub struct WriteReducer<Item, D, E> {
+	target: D,
+	prefix: String,
+	encoder: E,
+	marker: PhantomData<fn() -> Item>,
+}
+
+impl<Item, D, E> Reducer<Item> for WriteReducer<Item, D, E>
+where
+	D: Create,
+	E: Encoder<Item>,
+	E::Error: From<WriteError<D::Error, <D::Page as Page>::Error>> + 'static,
+{
+	type Done = Result<(), E::Error>;
+	type Async = WriteReducerAsync<Item, D, E>;
+
+	fn into_async(self) -> Self::Async {
+		let name = format!(
+			"{}{:016x}{}",
+			self.prefix,
+			rand::random::<u64>(),
+			self.encoder.extension()
+		);
+		WriteReducerAsync {
+			target: self.target,
+			encoder: self.encoder,
+			name,
+			files: HashMap::new(),
+			finishing: None,
+			pending: None,
+			marker: PhantomData,
+		}
+	}
+}
+impl<Item, D, E> ReducerProcessSend<Item> for WriteReducer<Item, D, E>
+where
+	D: Create,
+	E: Encoder<Item>,
+	E::Error: From<WriteError<D::Error, <D::Page as Page>::Error>> + ProcessSend + 'static,
+{
+	type Done = Result<(), E::Error>;
+}
+impl<Item, D, E> ReducerSend<Item> for WriteReducer<Item, D, E>
+where
+	D: Create,
+	E: Encoder<Item>,
+	E::Error: From<WriteError<D::Error, <D::Page as Page>::Error>> + Send + 'static,
+{
+	type Done = Result<(), E::Error>;
+}
+
+/// A file being written by a worker, created once there's something to write to it.
+struct WriteFile<S, P> {
+	state: S,
+	page: Option<P>,
+	offset: u64,
+	buf: Vec<u8>,
+}
+
+#[pin_project]
+pub struct WriteReducerAsync<Item, D, E>
+where
+	D: Create,
+	E: Encoder<Item>,
+{
+	target: D,
+	encoder: E,
+	name: String,
+	files: HashMap<Option<String>, WriteFile<E::State, D::Page>>,
+	/// The files left to finish, once the stream is exhausted.
+	#[allow(clippy::type_complexity)]
+	finishing: Option<Vec<(Option<String>, WriteFile<E::State, D::Page>)>>,
+	#[allow(clippy::type_complexity)]
+	pending: Option<LocalBoxFuture<'static, Result<Option<(Option<String>, D::Page)>, E::Error>>>,
+	marker: PhantomData<fn() -> Item>,
+}
+
+impl<Item, D, E> WriteReducerAsync<Item, D, E>
+where
+	D: Create,
+	E: Encoder<Item>,
+	E::Error: From<WriteError<D::Error, <D::Page as Page>::Error>> + 'static,
+{
+	/// Write `buf` to the file in `directory`, creating it if it has no page yet and
+	/// closing it if `finished`. Returns the page, to be written to again, unless it
+	/// was closed.
+	#[allow(clippy::type_complexity)]
+	fn write(
+		target: &D, name: &str, directory: Option<String>, page: Option<D::Page>, offset: u64,
+		buf: Vec<u8>, finished: bool,
+	) -> LocalBoxFuture<'static, Result<Option<(Option<String>, D::Page)>, E::Error>> {
+		let target = target.clone();
+		let name = match &directory {
+			Some(directory) => format!("{}/{}", directory, name),
+			None => name.to_owned(),
+		};
+		let buf = buf.into_boxed_slice();
+		Box::pin(async move {
+			let page = if let Some(page) = page {
+				page
+			} else {
+				target
+					.create(&name)
+					.await
+					.map_err(|err| E::Error::from(WriteError::Create(err)))?
+			};
+			if !buf.is_empty() {
+				page.write(offset, buf)
+					.await
+					.map_err(|err| E::Error::from(WriteError::Page(err)))?;
+			}
+			if finished {
+				page.close()
+					.await
+					.map_err(|err| E::Error::from(WriteError::Page(err)))?;
+				return Ok(None);
+			}
+			Ok(Some((directory, page)))
+		})
+	}
+}
+
+impl<Item, D, E> Sink<Item> for WriteReducerAsync<Item, D, E>
+where
+	D: Create,
+	E: Encoder<Item>,
+	E::Error: From<WriteError<D::Error, <D::Page as Page>::Error>> + 'static,
+{
+	type Done = Result<(), E::Error>;
+
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context, mut stream: Pin<&mut impl Stream<Item = Item>>,
+	) -> Poll<Self::Done> {
+		let self_ = self.project();
+		loop {
+			if let Some(pending) = self_.pending {
+				let page = ready!(pending.as_mut().poll(cx));
+				*self_.pending = None;
+				if let Some((directory, page)) = page? {
+					self_.files.get_mut(&directory).unwrap().page = Some(page);
+				}
+			}
+			if let Some(finishing) = self_.finishing {
+				// Finish the files one at a time once the stream is exhausted
+				let (directory, mut file) = match finishing.pop() {
+					Some(file) => file,
+					None => return Poll::Ready(Ok(())),
+				};
+				self_.encoder.done(file.state, &mut file.buf)?;
+				if !file.buf.is_empty() || file.page.is_some() {
+					*self_.pending = Some(Self::write(
+						self_.target,
+						self_.name,
+						directory,
+						file.page,
+						file.offset,
+						file.buf,
+						true,
+					));
+				}
+				continue;
+			}
+			let item = if let Some(item) = ready!(stream.as_mut().poll_next(cx)) {
+				item
+			} else {
+				*self_.finishing = Some(self_.files.drain().collect());
+				continue;
+			};
+			// Files are created lazily so that idle workers don't leave empty files behind
+			let directory = self_.encoder.directory(&item);
+			let encoder = &mut *self_.encoder;
+			let file = self_
+				.files
+				.entry(directory.clone())
+				.or_insert_with(|| WriteFile {
+					state: encoder.zero(),
+					page: None,
+					offset: 0,
+					buf: Vec::new(),
+				});
+			encoder.push(&mut file.state, item, &mut file.buf)?;
+			if file.buf.len() >= BUFFER_SIZE {
+				let buf = mem::take(&mut file.buf);
+				let offset = file.offset;
+				file.offset += u64::try_from(buf.len()).unwrap();
+				*self_.pending = Some(Self::write(
+					self_.target,
+					self_.name,
+					directory,
+					file.page.take(),
+					offset,
+					buf,
+					false,
+				));
+			}
+		}
+	}
+}
+
+/// Combines the results of each worker, keeping the first error.
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone)]
+#[serde(bound = "")]
+pub struct ResultFolder<E> {
+	marker: PhantomData<fn() -> E>,
+}
+
+impl<E> FolderSync<Result<(), E>> for ResultFolder<E> {
+	type State = Result<(), E>;
+	type Done = Self::State;
+
+	#[inline(always)]
+	fn zero(&mut self) -> Self::State {
+		Ok(())
+	}
+	#[inline(always)]
+	fn push(&mut self, state: &mut Self::State, item: Result<(), E>) {
+		if state.is_ok() {
+			*state = item;
+		}
+	}
+	#[inline(always)]
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state
+	}
+}
@@ -1,5 +1,4 @@
 // TODO: P: Pool -> impl Pool: async_trait triggers https://github.com/rust-lang/rust/issues/71869
-// TODO: how to dedup??
 
 #![allow(clippy::too_many_lines, unused_qualifications)]
 
@@ -14,8 +13,13 @@ mod inspect;
 mod join;
 mod map;
 mod map_sync;
+mod repartition;
+mod sort;
+mod stream_distinct;
+mod stream_join;
 mod sum_type;
 mod update;
+mod window;
 
 use async_trait::async_trait;
 use either::Either;
@@ -28,11 +32,11 @@ use std::{
 
 use super::{par_pipe::*, par_sink::*};
 use crate::{
-	into_par_stream::{IntoDistributedStream, IntoParallelStream}, pipe::{Sink, StreamExt}, pool::{ProcessPool, ProcessSend, ThreadPool}
+	into_par_stream::{IntoDistributedStream, IntoParallelStream}, pipe::{Sink, StreamExt}, pool::{ProcessPool, ProcessSend, ThreadPool}, util::IoError
 };
 
 pub use self::{
-	chain::*, cloned::*, filter::*, filter_map_sync::*, flat_map::*, flat_map_sync::*, identity::*, inspect::*, join::*, map::*, map_sync::*, update::*
+	chain::*, cloned::*, filter::*, filter_map_sync::*, flat_map::*, flat_map_sync::*, identity::*, inspect::*, join::*, map::*, map_sync::*, repartition::*, sort::*, stream_distinct::*, stream_join::*, update::*, window::*
 };
 
 #[must_use]
@@ -94,6 +98,24 @@ macro_rules! stream {
 				$assert_stream(FlatMap::new(self, f))
 			}
 
+			/// Pair each item with the windows of event time it falls in, by the time
+			/// `f` returns. Aggregate per window with
+			/// [`group_by_window`](Self::group_by_window).
+			///
+			/// Panics if the lengths of `windowing` aren't positive.
+			#[inline]
+			fn window<F, T, W>(self, f: F, windowing: W) -> FlatMapSync<Self, AssignWindows<F, W>>
+			where
+				F: $fns::FnMut(&Self::Item) -> T + Clone + $send + 'static,
+				T: EventTime,
+				W: Windowing<T> + Clone + $send + 'static,
+				Self::Item: Clone,
+				Self: Sized,
+			{
+				windowing.assert_positive();
+				$assert_stream(FlatMapSync::new(self, AssignWindows::new(f, windowing)))
+			}
+
 			#[inline]
 			fn filter<F>(self, f: F) -> Filter<Self, F>
 			where
@@ -103,6 +125,19 @@ macro_rules! stream {
 				$assert_stream(Filter::new(self, f))
 			}
 
+			/// Keep only the items whose key, as returned by `f`, might be in `filter`,
+			/// such as a [`bloom_filter`](Self::bloom_filter) of another stream's keys.
+			/// This is a cheap semi-join: some items whose key isn't in it may be kept.
+			#[inline]
+			fn filter_in<S, F, K>(self, filter: S, f: F) -> Filter<Self, FilterIn<S, F>>
+			where
+				S: ::amadeus_streaming::Contains<K> + Clone + $send + 'static,
+				F: $fns::FnMut(&Self::Item) -> K + Clone + $send + 'static,
+				Self: Sized,
+			{
+				$assert_stream(Filter::new(self, FilterIn::new(filter, f)))
+			}
+
 			#[inline]
 			fn left_join<K, V1, V2>(self, right: impl IntoIterator<Item = (K, V2)>) -> LeftJoin<Self, K, V1, V2>
 			where
@@ -165,6 +200,199 @@ macro_rules! stream {
 				.await
 			}
 
+			/// Hash partitions the items into `n` partitions by the key `f` returns, so that
+			/// each task of the returned stream holds every item with the keys in its partition.
+			///
+			/// Keyed operations on the returned stream, like a [`group_by`](Self::group_by)
+			/// per task, then don't need to combine partial results across tasks. Each task
+			/// writes its items to a file per partition, that the task taking the partition
+			/// fetches from it. See [`Repartition`].
+			#[inline]
+			async fn repartition_by_key<P, F, K>(
+				self, pool: &P, n: usize, f: F,
+			) -> Result<Repartition<Self::Item>, IoError>
+			where
+				P: $pool,
+				F: $fns::FnMut(&Self::Item) -> K + Clone + $send + 'static,
+				K: Hash + 'static,
+				Self::Item: ProcessSend + 'static,
+				Self::Task: 'static,
+				Self: Sized,
+			{
+				self.pipe(pool, $pipe::<Self::Item>::repartition_by_key(Identity, n, f))
+					.await
+			}
+
+			/// Joins with the `(key, value)` items of `right`, yielding
+			/// `(key, left, right)` for each pair of items with the same key.
+			///
+			/// Unlike [`inner_join`](Self::inner_join), `right` is itself a stream. See
+			/// [`JoinStrategy`] for how the two sides are brought together.
+			#[inline]
+			async fn inner_join_stream<P, S, K, V1, V2>(
+				self, pool: &P, right: S, strategy: JoinStrategy,
+			) -> Result<StreamJoin<Self, InnerJoinKind, K, V1, V2>, IoError>
+			where
+				P: $pool,
+				S: $into_stream<Item = (K, V2)>,
+				K: Eq + Hash + Clone + ProcessSend + 'static,
+				V1: Clone + ProcessSend + 'static,
+				V2: Clone + ProcessSend + 'static,
+				<S::$xxx as $stream>::Task: 'static,
+				Self::Task: 'static,
+				Self: $stream<Item = (K, V1)> + Sized,
+			{
+				self.join_stream(pool, right, strategy).await
+			}
+
+			/// Joins with the `(key, value)` items of `right`, yielding
+			/// `(key, left, Some(right))` for each pair of items with the same key and
+			/// `(key, left, None)` for the items of `self` without a match.
+			///
+			/// See [`inner_join_stream`](Self::inner_join_stream).
+			#[inline]
+			async fn left_join_stream<P, S, K, V1, V2>(
+				self, pool: &P, right: S, strategy: JoinStrategy,
+			) -> Result<StreamJoin<Self, LeftJoinKind, K, V1, V2>, IoError>
+			where
+				P: $pool,
+				S: $into_stream<Item = (K, V2)>,
+				K: Eq + Hash + Clone + ProcessSend + 'static,
+				V1: Clone + ProcessSend + 'static,
+				V2: Clone + ProcessSend + 'static,
+				<S::$xxx as $stream>::Task: 'static,
+				Self::Task: 'static,
+				Self: $stream<Item = (K, V1)> + Sized,
+			{
+				self.join_stream(pool, right, strategy).await
+			}
+
+			/// Joins with the `(key, value)` items of `right`, yielding
+			/// `(key, Some(left), right)` for each pair of items with the same key and
+			/// `(key, None, right)` for the items of `right` without a match.
+			///
+			/// Both sides are hash partitioned by key into `n` partitions, as the items of
+			/// `right` without a match are only known once every item of `self` with the same
+			/// key has been seen. See [`inner_join_stream`](Self::inner_join_stream).
+			#[inline]
+			async fn right_join_stream<P, S, K, V1, V2>(
+				self, pool: &P, right: S, n: usize,
+			) -> Result<StreamJoin<Self, RightJoinKind, K, V1, V2>, IoError>
+			where
+				P: $pool,
+				S: $into_stream<Item = (K, V2)>,
+				K: Eq + Hash + Clone + ProcessSend + 'static,
+				V1: Clone + ProcessSend + 'static,
+				V2: Clone + ProcessSend + 'static,
+				<S::$xxx as $stream>::Task: 'static,
+				Self::Task: 'static,
+				Self: $stream<Item = (K, V1)> + Sized,
+			{
+				self.join_stream(pool, right, JoinStrategy::Partitioned(n))
+					.await
+			}
+
+			/// Full outer joins with the `(key, value)` items of `right`, yielding
+			/// `(key, Some(left), Some(right))` for each pair of items with the same key, and
+			/// a `None` in place of the other side for the items of either without a match.
+			///
+			/// Both sides are hash partitioned by key into `n` partitions, as the items of
+			/// `right` without a match are only known once every item of `self` with the same
+			/// key has been seen. See [`inner_join_stream`](Self::inner_join_stream).
+			#[inline]
+			async fn outer_join_stream<P, S, K, V1, V2>(
+				self, pool: &P, right: S, n: usize,
+			) -> Result<StreamJoin<Self, OuterJoinKind, K, V1, V2>, IoError>
+			where
+				P: $pool,
+				S: $into_stream<Item = (K, V2)>,
+				K: Eq + Hash + Clone + ProcessSend + 'static,
+				V1: Clone + ProcessSend + 'static,
+				V2: Clone + ProcessSend + 'static,
+				<S::$xxx as $stream>::Task: 'static,
+				Self::Task: 'static,
+				Self: $stream<Item = (K, V1)> + Sized,
+			{
+				self.join_stream(pool, right, JoinStrategy::Partitioned(n))
+					.await
+			}
+
+			/// Yields the items of `self` that have the same key as an item of `right`.
+			///
+			/// See [`inner_join_stream`](Self::inner_join_stream).
+			#[inline]
+			async fn semi_join_stream<P, S, K, V1, V2>(
+				self, pool: &P, right: S, strategy: JoinStrategy,
+			) -> Result<StreamJoin<Self, SemiJoinKind, K, V1, V2>, IoError>
+			where
+				P: $pool,
+				S: $into_stream<Item = (K, V2)>,
+				K: Eq + Hash + Clone + ProcessSend + 'static,
+				V1: ProcessSend + 'static,
+				V2: Clone + ProcessSend + 'static,
+				<S::$xxx as $stream>::Task: 'static,
+				Self::Task: 'static,
+				Self: $stream<Item = (K, V1)> + Sized,
+			{
+				self.join_stream(pool, right, strategy).await
+			}
+
+			/// Yields the items of `self` that don't have the same key as any item of `right`.
+			///
+			/// See [`inner_join_stream`](Self::inner_join_stream).
+			#[inline]
+			async fn anti_join_stream<P, S, K, V1, V2>(
+				self, pool: &P, right: S, strategy: JoinStrategy,
+			) -> Result<StreamJoin<Self, AntiJoinKind, K, V1, V2>, IoError>
+			where
+				P: $pool,
+				S: $into_stream<Item = (K, V2)>,
+				K: Eq + Hash + Clone + ProcessSend + 'static,
+				V1: ProcessSend + 'static,
+				V2: Clone + ProcessSend + 'static,
+				<S::$xxx as $stream>::Task: 'static,
+				Self::Task: 'static,
+				Self: $stream<Item = (K, V1)> + Sized,
+			{
+				self.join_stream(pool, right, strategy).await
+			}
+
+			#[doc(hidden)]
+			async fn join_stream<P, S, J, K, V1, V2>(
+				self, pool: &P, right: S, strategy: JoinStrategy,
+			) -> Result<StreamJoin<Self, J, K, V1, V2>, IoError>
+			where
+				P: $pool,
+				S: $into_stream<Item = (K, V2)>,
+				J: JoinKind<K, V1, V2>,
+				K: Eq + Hash + Clone + ProcessSend + 'static,
+				V1: ProcessSend + 'static,
+				V2: Clone + ProcessSend + 'static,
+				<S::$xxx as $stream>::Task: 'static,
+				Self::Task: 'static,
+				Self: $stream<Item = (K, V1)> + Sized,
+			{
+				let right = right.$into_stream_fn();
+				let n = match strategy {
+					JoinStrategy::Partitioned(n) => n,
+					JoinStrategy::Broadcast => {
+						assert!(!J::UNMATCHED_RIGHT, "right and outer joins can't broadcast");
+						let right = right
+							.pipe(pool, $pipe::<(K, V2)>::collect(Identity))
+							.await;
+						return Ok(StreamJoin::broadcast(self, right));
+					}
+				};
+				assert_ne!(n, 0, "can't join into 0 partitions");
+				let left = self
+					.pipe(pool, RepartitionByKey::new(Identity, n, JoinKey::new()))
+					.await?;
+				let right = right
+					.pipe(pool, RepartitionByKey::new(Identity, n, JoinKey::new()))
+					.await?;
+				Ok(StreamJoin::partitioned(left, right))
+			}
+
 			#[inline]
 			async fn histogram<P>(self, pool: &P) -> Vec<(Self::Item, usize)>
 			where
@@ -177,6 +405,67 @@ macro_rules! stream {
 					.await
 			}
 
+			/// Sorts the items with `cmp`, returning a stream of `n` tasks that each yield one
+			/// range of the sorted items in order, the first task the first range and so on.
+			///
+			/// The items of each task are written to the
+			/// [temporary directory](std::env::temp_dir) and sampled, the range boundaries
+			/// are picked from the samples, and each item is then sent to the task of its
+			/// range to be sorted there, as described on [`Repartition`]. See
+			/// [`StreamSort`].
+			#[inline]
+			async fn sort_by<P, F>(self, pool: &P, n: usize, cmp: F) -> Result<StreamSort<Self::Item, F>, IoError>
+			where
+				P: $pool,
+				F: $fns::Fn(&Self::Item, &Self::Item) -> Ordering + Clone + $send + 'static,
+				Self::Item: ProcessSend + 'static,
+				Self::Task: 'static,
+				Self: Sized,
+			{
+				self.sort_by_cmp(pool, n, cmp).await
+			}
+
+			/// Sorts the items by the key `f` returns. See [`sort_by`](Self::sort_by).
+			#[inline]
+			async fn sort_by_key<P, F, K>(
+				self, pool: &P, n: usize, f: F,
+			) -> Result<StreamSort<Self::Item, ByKey<F, K>>, IoError>
+			where
+				P: $pool,
+				F: $fns::Fn(&Self::Item) -> K + Clone + $send + 'static,
+				K: Ord + 'static,
+				Self::Item: ProcessSend + 'static,
+				Self::Task: 'static,
+				Self: Sized,
+			{
+				self.sort_by_cmp(pool, n, ByKey::new(f)).await
+			}
+
+			#[doc(hidden)]
+			async fn sort_by_cmp<P, F>(self, pool: &P, n: usize, cmp: F) -> Result<StreamSort<Self::Item, F>, IoError>
+			where
+				P: $pool,
+				F: for<'a, 'b> traits::Fn<(&'a Self::Item, &'b Self::Item), Output = Ordering>
+					+ Clone
+					+ $send
+					+ 'static,
+				Self::Item: ProcessSend + 'static,
+				Self::Task: 'static,
+				Self: Sized,
+			{
+				assert_ne!(n, 0, "can't sort into 0 ranges");
+				let (runs, samples) = self
+					.pipe(pool, SampleRuns::new(Identity, n * SAMPLES_PER_RANGE))
+					.await?;
+				let runs = Repartition::new(runs.into_iter().map(|run| vec![run]).collect());
+				let boundaries = range_boundaries(samples, n, &cmp);
+				let partitioner = RangePartitioner::new(&boundaries, cmp.clone())?;
+				let partitioner = ResultPartitioner::new(partitioner);
+				let ranges = $stream::pipe(runs, pool, PartitionBy::new(Identity, n, partitioner))
+					.await?;
+				Ok(StreamSort::new(ranges, cmp))
+			}
+
 			#[inline]
 			async fn sort_n_by<P, F>(self, pool: &P, n: usize, cmp: F) -> ::amadeus_streaming::Sort<Self::Item, F>
 			where
@@ -227,6 +516,21 @@ macro_rules! stream {
 				.await
 			}
 
+			/// Estimates the value at each of the given quantiles, such as `0.5` for
+			/// the median, with a [`TDigest`](::amadeus_streaming::TDigest). Returns
+			/// `None` if the stream is empty.
+			#[inline]
+			async fn quantiles<P>(self, pool: &P, quantiles: &[f64]) -> Option<Vec<f64>>
+			where
+				P: $pool,
+				Self::Item: 'static,
+				Self::Task: 'static,
+				Self: $stream<Item = f64> + Sized,
+			{
+				self.pipe(pool, $pipe::<Self::Item>::quantiles(Identity, quantiles))
+					.await
+			}
+
 			#[inline]
 			async fn stddev<P>(self, pool: &P) -> f64
 			where
@@ -239,6 +543,91 @@ macro_rules! stream {
 				.await
 			}
 
+			/// The exact median, averaging the middle two items if there's an even
+			/// number of them. All items are collected in memory; use
+			/// [`quantiles`](Self::quantiles) for an estimate where they wouldn't fit.
+			#[inline]
+			async fn median<P>(self, pool: &P) -> Option<f64>
+			where
+				P: $pool,
+				Self::Item: 'static,
+				Self::Task: 'static,
+				Self: $stream<Item = f64> + Sized,
+			{
+				self.pipe(pool, $pipe::<Self::Item>::median(Identity))
+					.await
+			}
+
+			/// The exact value at quantile `q`, interpolating linearly between the
+			/// two nearest items. All items are collected in memory; use
+			/// [`quantiles`](Self::quantiles) for an estimate where they wouldn't fit.
+			///
+			/// Panics if `q` isn't between `0` and `1`.
+			#[inline]
+			async fn quantile<P>(self, pool: &P, q: f64) -> Option<f64>
+			where
+				P: $pool,
+				Self::Item: 'static,
+				Self::Task: 'static,
+				Self: $stream<Item = f64> + Sized,
+			{
+				self.pipe(pool, $pipe::<Self::Item>::quantile(Identity, q))
+					.await
+			}
+
+			/// The smallest and largest items, ignoring NaNs, or `None` if there are none.
+			#[inline]
+			async fn min_max<P>(self, pool: &P) -> Option<(f64, f64)>
+			where
+				P: $pool,
+				Self::Item: 'static,
+				Self::Task: 'static,
+				Self: $stream<Item = f64> + Sized,
+			{
+				self.pipe(pool, $pipe::<Self::Item>::min_max(Identity))
+					.await
+			}
+
+			/// The count, mean, variance, standard deviation, min, max, skewness and
+			/// kurtosis, in one pass.
+			#[inline]
+			async fn describe<P>(self, pool: &P) -> Description
+			where
+				P: $pool,
+				Self::Item: 'static,
+				Self::Task: 'static,
+				Self: $stream<Item = f64> + Sized,
+			{
+				self.pipe(pool, $pipe::<Self::Item>::describe(Identity))
+					.await
+			}
+
+			/// The population covariance of pairs of items.
+			#[inline]
+			async fn covariance<P>(self, pool: &P) -> f64
+			where
+				P: $pool,
+				Self::Item: 'static,
+				Self::Task: 'static,
+				Self: $stream<Item = (f64, f64)> + Sized,
+			{
+				self.pipe(pool, $pipe::<Self::Item>::covariance(Identity))
+					.await
+			}
+
+			/// The Pearson correlation coefficient of pairs of items.
+			#[inline]
+			async fn correlation<P>(self, pool: &P) -> f64
+			where
+				P: $pool,
+				Self::Item: 'static,
+				Self::Task: 'static,
+				Self: $stream<Item = (f64, f64)> + Sized,
+			{
+				self.pipe(pool, $pipe::<Self::Item>::correlation(Identity))
+					.await
+			}
+
 			#[inline]
 			async fn combine<P, F>(self, pool: &P, f: F) -> Option<Self::Item>
 			where
@@ -371,6 +760,106 @@ macro_rules! stream {
 				.await
 			}
 
+			/// Removes duplicate items, hash partitioning the items into `n` partitions so
+			/// that each task of the returned stream removes the duplicates within its own.
+			///
+			/// Each task fetches its partition directly from the processes that wrote it, as
+			/// described on [`Repartition`], so the items don't pass through the process
+			/// driving the pool, and the tasks can run on many machines. The distinct items
+			/// of each partition do need to fit in the memory of the process running its
+			/// task; use [`count_distinct`](Self::count_distinct) for an estimate where they
+			/// don't. A task yields an error in place of its items if it can't read its
+			/// partition back.
+			#[inline]
+			async fn distinct<P>(self, pool: &P, n: usize) -> Result<StreamDistinct<Self::Item>, IoError>
+			where
+				P: $pool,
+				Self::Item: Eq + Hash + ProcessSend + 'static,
+				Self::Task: 'static,
+				Self: Sized,
+			{
+				assert_ne!(n, 0, "can't repartition into 0 partitions");
+				let partitions = self
+					.pipe(pool, RepartitionByKey::new(Identity, n, ItemHash::new()))
+					.await?;
+				Ok(StreamDistinct::new(partitions))
+			}
+
+			/// Removes items with the same key as an earlier one, as returned by `f`, hash
+			/// partitioning the items into `n` partitions by key.
+			///
+			/// Which of the items with the same key is kept is unspecified. See
+			/// [`distinct`](Self::distinct).
+			#[inline]
+			async fn distinct_by_key<P, F, K>(
+				self, pool: &P, n: usize, f: F,
+			) -> Result<StreamDistinctByKey<Self::Item, F, K>, IoError>
+			where
+				P: $pool,
+				F: $fns::FnMut(&Self::Item) -> K + Clone + $send + 'static,
+				K: Eq + Hash + 'static,
+				Self::Item: ProcessSend + 'static,
+				Self::Task: 'static,
+				Self: Sized,
+			{
+				assert_ne!(n, 0, "can't repartition into 0 partitions");
+				let partitions = self
+					.pipe(pool, RepartitionByKey::new(Identity, n, f.clone()))
+					.await?;
+				Ok(StreamDistinctByKey::new(partitions, f))
+			}
+
+			/// Estimates the number of distinct items with a
+			/// [`HyperLogLog`](::amadeus_streaming::HyperLogLog) with the given error rate.
+			#[inline]
+			async fn count_distinct<P>(
+				self, pool: &P, error_rate: f64,
+			) -> ::amadeus_streaming::HyperLogLog<Self::Item>
+			where
+				P: $pool,
+				Self::Item: Hash + 'static,
+				Self::Task: 'static,
+				Self: Sized,
+			{
+				self.pipe(pool, $pipe::<Self::Item>::count_distinct(Identity, error_rate))
+					.await
+			}
+
+			/// Builds a [`BloomFilter`](::amadeus_streaming::BloomFilter) of the items,
+			/// sized for `n` of them with the given false positive rate. It can then be
+			/// passed to [`filter_in`](Self::filter_in) on another stream, to cheaply keep
+			/// only the items whose key is likely in this one.
+			#[inline]
+			async fn bloom_filter<P>(
+				self, pool: &P, n: usize, fp_rate: f64,
+			) -> ::amadeus_streaming::BloomFilter<Self::Item>
+			where
+				P: $pool,
+				Self::Item: Hash + 'static,
+				Self::Task: 'static,
+				Self: Sized,
+			{
+				self.pipe(pool, $pipe::<Self::Item>::bloom_filter(Identity, n, fp_rate))
+					.await
+			}
+
+			/// Builds a [`CuckooFilter`](::amadeus_streaming::CuckooFilter) of the items,
+			/// sized for `n` of them with the given false positive rate. Unlike a
+			/// [`bloom_filter`](Self::bloom_filter), items can later be removed from it.
+			#[inline]
+			async fn cuckoo_filter<P>(
+				self, pool: &P, n: usize, fp_rate: f64,
+			) -> ::amadeus_streaming::CuckooFilter<Self::Item>
+			where
+				P: $pool,
+				Self::Item: Hash + 'static,
+				Self::Task: 'static,
+				Self: Sized,
+			{
+				self.pipe(pool, $pipe::<Self::Item>::cuckoo_filter(Identity, n, fp_rate))
+					.await
+			}
+
 			#[inline]
 			async fn sample_unstable<P>(
 				self, pool: &P, samples: usize,
@@ -563,6 +1052,28 @@ stream!(ParallelStream ParallelPipe ParallelSink FromParallelStream IntoParallel
 			.await
 	}
 
+	async fn group_by_window<P, S, T, B>(
+		self, pool: &P, sink: S,
+	) -> IndexMap<Window<T>, S::Done>
+	where
+		P: ThreadPool,
+		T: EventTime + Hash + Send + 'static,
+		B: 'static,
+		S: ParallelSink<B>,
+		<S::Pipe as ParallelPipe<B>>::Task: Clone + Send + 'static,
+		S::ReduceA: 'static,
+		S::ReduceC: Clone,
+		S::Done: Send + 'static,
+		Self::Task: 'static,
+		Self: ParallelStream<Item = (Window<T>, B)> + Sized,
+	{
+		self.pipe(
+			pool,
+			ParallelPipe::<Self::Item>::group_by_window(Identity, sink),
+		)
+		.await
+	}
+
 	async fn collect<P, B>(self, pool: &P) -> B
 	where
 		P: ThreadPool,
@@ -797,6 +1308,29 @@ stream!(DistributedStream DistributedPipe DistributedSink FromDistributedStream
 		.await
 	}
 
+	async fn group_by_window<P, S, T, B>(
+		self, pool: &P, sink: S,
+	) -> IndexMap<Window<T>, S::Done>
+	where
+		P: ProcessPool,
+		T: EventTime + Hash + ProcessSend + 'static,
+		B: 'static,
+		S: DistributedSink<B>,
+		<S::Pipe as DistributedPipe<B>>::Task: Clone + ProcessSend + 'static,
+		S::ReduceA: 'static,
+		S::ReduceB: 'static,
+		S::ReduceC: Clone,
+		S::Done: ProcessSend + 'static,
+		Self::Task: 'static,
+		Self: DistributedStream<Item = (Window<T>, B)> + Sized,
+	{
+		self.pipe(
+			pool,
+			DistributedPipe::<Self::Item>::group_by_window(Identity, sink),
+		)
+		.await
+	}
+
 	async fn collect<P, B>(self, pool: &P) -> B
 	where
 		P: ProcessPool,
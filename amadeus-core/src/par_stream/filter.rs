@@ -1,7 +1,8 @@
+use amadeus_streaming::Contains;
 use derive_new::new;
 use pin_project::pin_project;
 use serde::{Deserialize, Serialize};
-use serde_closure::traits::FnMut;
+use serde_closure::traits::{FnMut, FnOnce};
 use std::{
 	pin::Pin, task::{Context, Poll}
 };
@@ -83,3 +84,36 @@ where
 		crate::pipe::Filter::new(self.task.into_async(), self.f)
 	}
 }
+
+/// Whether the key `f` returns for an item might be in `filter`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FilterIn<S, F> {
+	filter: S,
+	f: F,
+}
+impl<S, F> FilterIn<S, F> {
+	pub(crate) fn new(filter: S, f: F) -> Self {
+		Self { filter, f }
+	}
+}
+impl<'a, Item, S, F, K> FnOnce<(&'a Item,)> for FilterIn<S, F>
+where
+	F: for<'b> FnMut<(&'b Item,), Output = K>,
+	S: Contains<K>,
+{
+	type Output = bool;
+
+	fn call_once(mut self, args: (&'a Item,)) -> Self::Output {
+		self.call_mut(args)
+	}
+}
+impl<'a, Item, S, F, K> FnMut<(&'a Item,)> for FilterIn<S, F>
+where
+	F: for<'b> FnMut<(&'b Item,), Output = K>,
+	S: Contains<K>,
+{
+	fn call_mut(&mut self, (item,): (&'a Item,)) -> Self::Output {
+		let key = self.f.call_mut((item,));
+		self.filter.contains(&key)
+	}
+}
@@ -0,0 +1,147 @@
+use futures::Stream;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+	collections::VecDeque, fmt, pin::Pin, task::{Context, Poll}
+};
+
+use super::{DistributedStream, ParallelStream, StreamTask};
+use crate::{
+	par_sink::{Run, RunsIter}, pool::ProcessSend, util::IoError
+};
+
+/// A stream whose items have been partitioned, returned by
+/// [`repartition_by_key`](DistributedStream::repartition_by_key).
+///
+/// Each task is one partition, so for example all the items with a given key are
+/// processed by the same task, and per-key state can be kept by that task alone.
+///
+/// The tasks that partitioned the items wrote those of each partition to a file
+/// in their [temporary directory](std::env::temp_dir). Only where to find the
+/// files is sent via the pool: they're left in the processes that wrote them,
+/// which serve them to the task that takes each partition, so the items don't
+/// pass through the process driving the pool and the processes of a pool can be
+/// on different machines (see [`set_exchange_host`](crate::par_sink::set_exchange_host)).
+/// The files are deleted once read, or when this is dropped.
+///
+/// A task of this stream yields an error, and then ends, if it can't read its
+/// partition back, for example if a process that wrote it has exited, or if the
+/// task was resubmitted after it had already read it.
+#[must_use]
+pub struct Repartition<T> {
+	partitions: VecDeque<Vec<Run<T>>>,
+}
+impl<T> Repartition<T> {
+	pub(crate) fn new(partitions: Vec<Vec<Run<T>>>) -> Self {
+		Self {
+			partitions: partitions.into(),
+		}
+	}
+	pub(crate) fn len(&self) -> usize {
+		self.partitions.len()
+	}
+	/// Take the next partition as a task.
+	pub(crate) fn pop(&mut self) -> Option<RepartitionTask<T>> {
+		self.partitions.pop_front().map(RepartitionTask)
+	}
+	/// Read back the items of each partition not yet taken as a task.
+	pub fn partitions(&self) -> impl Iterator<Item = Result<Vec<T>, IoError>> + '_
+	where
+		T: DeserializeOwned,
+	{
+		self.partitions.iter().map(|runs| {
+			let mut items = Vec::with_capacity(runs.iter().map(Run::len).sum());
+			for run in runs {
+				for item in run.read()? {
+					items.push(item?);
+				}
+			}
+			Ok(items)
+		})
+	}
+	/// Read back the items of the partitions not yet taken as a task, in order.
+	pub fn into_vec(self) -> Result<Vec<T>, IoError>
+	where
+		T: DeserializeOwned,
+	{
+		let mut items = Vec::new();
+		for partition in self.partitions() {
+			items.append(&mut partition?);
+		}
+		Ok(items)
+	}
+}
+impl<T> fmt::Debug for Repartition<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Repartition")
+			.field("partitions", &self.partitions)
+			.finish()
+	}
+}
+
+impl_par_dist_rename! {
+	impl<T> ParallelStream for Repartition<T>
+	where
+		T: ProcessSend + 'static,
+	{
+		type Item = Result<T, IoError>;
+		type Task = RepartitionTask<T>;
+
+		#[inline]
+		fn size_hint(&self) -> (usize, Option<usize>) {
+			(self.len(), Some(self.len()))
+		}
+		#[inline]
+		fn next_task(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Option<Self::Task>> {
+			Poll::Ready(self.get_mut().pop())
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct RepartitionTask<T>(Vec<Run<T>>);
+impl<T> RepartitionTask<T> {
+	/// Read the partition back, deleting its runs as they're read.
+	pub(crate) fn into_runs(self) -> RunsIter<T> {
+		RunsIter::new(self.0)
+	}
+}
+
+impl<T> StreamTask for RepartitionTask<T>
+where
+	T: DeserializeOwned,
+{
+	type Item = Result<T, IoError>;
+	type Async = RepartitionTaskAsync<T>;
+
+	#[inline]
+	fn into_async(self) -> Self::Async {
+		RepartitionTaskAsync(self.into_runs())
+	}
+}
+
+pub struct RepartitionTaskAsync<T>(RunsIter<T>);
+
+impl<T> Iterator for RepartitionTaskAsync<T>
+where
+	T: DeserializeOwned,
+{
+	type Item = Result<T, IoError>;
+
+	#[inline]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.0.next()
+	}
+}
+
+impl<T> Stream for RepartitionTaskAsync<T>
+where
+	T: DeserializeOwned,
+{
+	type Item = Result<T, IoError>;
+
+	#[inline]
+	fn poll_next(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Option<Self::Item>> {
+		Poll::Ready(self.get_mut().next())
+	}
+}
@@ -0,0 +1,639 @@
+use educe::Educe;
+use futures::Stream;
+use pin_project::pin_project;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_closure::traits::{Fn, FnMut, FnOnce};
+use std::{
+	cmp::Ordering, collections::{btree_map, BTreeMap}, marker::PhantomData, mem, pin::Pin, task::{Context, Poll}, vec
+};
+
+use super::{ParallelStream, Repartition, RepartitionTask, RepartitionTaskAsync, StreamTask};
+use crate::{
+	par_sink::{
+		bincode_error, ExtendReducer, FromDistributedStream, FromParallelStream, IntoReducer, PushReducer, Run, RunIter, SpillVec, SpillVecIter
+	}, pool::ProcessSend, util::IoError
+};
+
+/// How many items to sample per range when picking the range boundaries.
+pub(crate) const SAMPLES_PER_RANGE: usize = 64;
+
+/// Picks the boundaries between `n` ranges ordered by `cmp`, so that each range
+/// holds about as many items as the others.
+///
+/// `samples` holds a sample of the items of each task, along with how many items
+/// it was taken from.
+pub(crate) fn range_boundaries<T, F>(samples: Vec<(usize, Vec<T>)>, n: usize, cmp: &F) -> Vec<T>
+where
+	F: for<'a, 'b> Fn<(&'a T, &'b T), Output = Ordering>,
+{
+	// Each sampled item stands in for its share of the items it was sampled from.
+	let mut sample = samples
+		.into_iter()
+		.filter(|(_, sample)| !sample.is_empty())
+		.flat_map(|(len, sample)| {
+			let weight = (len + sample.len() / 2) / sample.len();
+			sample.into_iter().map(move |item| (weight, item))
+		})
+		.collect::<Vec<_>>();
+	sample.sort_by(|(_, a), (_, b)| cmp.call((a, b)));
+	let total = sample.iter().map(|&(weight, _)| weight).sum::<usize>();
+	let mut boundaries = Vec::with_capacity(n - 1);
+	let mut seen = 0;
+	for (weight, item) in sample {
+		if boundaries.len() == n - 1 {
+			break;
+		}
+		seen += weight;
+		if seen * n > total * (boundaries.len() + 1) {
+			boundaries.push(item);
+		}
+	}
+	boundaries
+}
+
+/// Picks the range of an item, out of the ranges between `boundaries`.
+///
+/// The boundaries are held serialized, so that they can be sent along with every
+/// task without the items needing to be `Clone`, and are read back by each task
+/// on its first item.
+#[derive(Serialize, Deserialize)]
+#[serde(
+	bound(serialize = "F: Serialize"),
+	bound(deserialize = "F: Deserialize<'de>")
+)]
+pub struct RangePartitioner<T, F> {
+	boundaries: Vec<u8>,
+	cmp: F,
+	#[serde(skip)]
+	cache: Option<Vec<T>>,
+}
+impl<T, F> RangePartitioner<T, F> {
+	pub(crate) fn new(boundaries: &[T], cmp: F) -> Result<Self, IoError>
+	where
+		T: Serialize,
+	{
+		Ok(Self {
+			boundaries: bincode::serialize(boundaries).map_err(|err| bincode_error(*err))?,
+			cmp,
+			cache: None,
+		})
+	}
+}
+impl<T, F: Clone> Clone for RangePartitioner<T, F> {
+	fn clone(&self) -> Self {
+		Self {
+			boundaries: self.boundaries.clone(),
+			cmp: self.cmp.clone(),
+			cache: None,
+		}
+	}
+}
+impl<'a, T, F> FnOnce<(&'a T,)> for RangePartitioner<T, F>
+where
+	T: DeserializeOwned,
+	F: for<'b, 'c> Fn<(&'b T, &'c T), Output = Ordering>,
+{
+	type Output = usize;
+
+	fn call_once(mut self, args: (&'a T,)) -> Self::Output {
+		self.call_mut(args)
+	}
+}
+impl<'a, T, F> FnMut<(&'a T,)> for RangePartitioner<T, F>
+where
+	T: DeserializeOwned,
+	F: for<'b, 'c> Fn<(&'b T, &'c T), Output = Ordering>,
+{
+	fn call_mut(&mut self, (item,): (&'a T,)) -> Self::Output {
+		let bytes = &self.boundaries;
+		let boundaries = self.cache.get_or_insert_with(|| {
+			bincode::deserialize(bytes)
+				.unwrap_or_else(|err| panic!("failed to read range boundaries back: {}", err))
+		});
+		let cmp = &self.cmp;
+		match boundaries.binary_search_by(|boundary| cmp.call((boundary, item))) {
+			Ok(range) | Err(range) => range,
+		}
+	}
+}
+
+/// Picks the range of an item read back from a run, with `P`, putting any error
+/// in the first range so that it's yielded by the task sorting that range.
+#[derive(Educe, Serialize, Deserialize)]
+#[educe(Clone(bound = "P: Clone"))]
+#[serde(
+	bound(serialize = "P: Serialize"),
+	bound(deserialize = "P: Deserialize<'de>")
+)]
+pub struct ResultPartitioner<P, T>(P, PhantomData<fn() -> T>);
+impl<P, T> ResultPartitioner<P, T> {
+	pub(crate) fn new(partitioner: P) -> Self {
+		Self(partitioner, PhantomData)
+	}
+}
+impl<'a, P, T> FnOnce<(&'a Result<T, IoError>,)> for ResultPartitioner<P, T>
+where
+	P: for<'b> FnMut<(&'b T,), Output = usize>,
+{
+	type Output = usize;
+
+	fn call_once(mut self, args: (&'a Result<T, IoError>,)) -> Self::Output {
+		self.call_mut(args)
+	}
+}
+impl<'a, P, T> FnMut<(&'a Result<T, IoError>,)> for ResultPartitioner<P, T>
+where
+	P: for<'b> FnMut<(&'b T,), Output = usize>,
+{
+	fn call_mut(&mut self, (item,): (&'a Result<T, IoError>,)) -> Self::Output {
+		match item {
+			Ok(item) => self.0.call_mut((item,)),
+			Err(_) => 0,
+		}
+	}
+}
+
+/// Sorts the items of a range with `cmp`, holding at most `max_items_in_memory` of
+/// them in memory at a time and spilling sorted runs of the rest to disk.
+pub(crate) fn sort_range<T, F>(
+	items: impl Iterator<Item = Result<T, IoError>>, cmp: F, max_items_in_memory: usize,
+) -> Result<SortedRange<T, F>, IoError>
+where
+	T: Serialize + DeserializeOwned,
+	F: for<'a, 'b> Fn<(&'a T, &'b T), Output = Ordering>,
+{
+	let mut runs = Vec::new();
+	let mut tail = Vec::new();
+	for item in items {
+		tail.push(item?);
+		if tail.len() > max_items_in_memory {
+			tail.sort_by(|a, b| cmp.call((a, b)));
+			runs.push(Run::write(&tail)?);
+			tail.clear();
+		}
+	}
+	tail.sort_by(|a, b| cmp.call((a, b)));
+	let mut sources = runs
+		.iter()
+		.map(|run| run.read().map(Source::Run))
+		.collect::<Result<Vec<_>, _>>()?;
+	sources.push(Source::Memory(tail.into_iter()));
+	let heads = sources
+		.iter_mut()
+		.map(|source| source.next().transpose())
+		.collect::<Result<_, _>>()?;
+	Ok(SortedRange {
+		sources,
+		heads,
+		cmp,
+		_runs: runs,
+	})
+}
+
+/// The sorted items of a range, merged from its spilled runs and the items held
+/// in memory. It stops after the first error.
+pub(crate) struct SortedRange<T, F> {
+	sources: Vec<Source<T>>,
+	heads: Vec<Option<T>>,
+	cmp: F,
+	_runs: Vec<Run<T>>,
+}
+impl<T, F> Iterator for SortedRange<T, F>
+where
+	T: DeserializeOwned,
+	F: for<'a, 'b> Fn<(&'a T, &'b T), Output = Ordering>,
+{
+	type Item = Result<T, IoError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let cmp = &self.cmp;
+		let mut min: Option<(usize, &T)> = None;
+		for (i, head) in self.heads.iter().enumerate() {
+			if let Some(head) = head {
+				// Earlier sources hold earlier items, so ties keep the order of the range.
+				match min {
+					Some((_, min)) if cmp.call((head, min)) != Ordering::Less => (),
+					_ => min = Some((i, head)),
+				}
+			}
+		}
+		let (i, _) = min?;
+		let next = match self.sources[i].next().transpose() {
+			Ok(next) => next,
+			Err(err) => {
+				self.heads.clear();
+				return Some(Err(err));
+			}
+		};
+		mem::replace(&mut self.heads[i], next).map(Ok)
+	}
+}
+
+enum Source<T> {
+	Memory(vec::IntoIter<T>),
+	Run(RunIter<T>),
+}
+impl<T> Iterator for Source<T>
+where
+	T: DeserializeOwned,
+{
+	type Item = Result<T, IoError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		match self {
+			Self::Memory(iter) => iter.next().map(Ok),
+			Self::Run(iter) => iter.next(),
+		}
+	}
+}
+
+/// The sorted items of a stream, returned by methods like
+/// [`sort_by`](super::DistributedStream::sort_by).
+///
+/// Each task yields one range of the sorted items in order, the first task the
+/// first range and so on. The items are exchanged between the tasks as described
+/// on [`Repartition`], and each range is sorted in memory by its task unless
+/// [`spill`](Self::spill) is used.
+///
+/// The tasks finish in any order, so collecting the items of this stream doesn't
+/// keep the ranges in order; collecting its [`ranges`](Self::ranges) into
+/// [`Sorted`] does.
+///
+/// A task yields an error, and then ends, if it can't read its range back or
+/// spill it.
+#[pin_project]
+#[must_use]
+pub struct StreamSort<T, F> {
+	ranges: Repartition<Result<T, IoError>>,
+	cmp: F,
+	max_items_in_memory: usize,
+}
+impl<T, F> StreamSort<T, F> {
+	pub(crate) fn new(ranges: Repartition<Result<T, IoError>>, cmp: F) -> Self {
+		Self {
+			ranges,
+			cmp,
+			max_items_in_memory: usize::MAX,
+		}
+	}
+
+	/// Sort each range holding at most `max_items_in_memory` of its items in memory
+	/// at a time, spilling sorted runs of the rest to the
+	/// [temporary directory](std::env::temp_dir) and merging them.
+	pub fn spill(self, max_items_in_memory: usize) -> Self {
+		assert_ne!(
+			max_items_in_memory, 0,
+			"max_items_in_memory must be at least 1"
+		);
+		Self {
+			max_items_in_memory,
+			..self
+		}
+	}
+
+	/// Yield each range whole, sorted by the task that takes it and tagged with its
+	/// index, the first range 0 and so on.
+	///
+	/// Collected into a `Result` of [`Sorted`], or of a
+	/// [`BTreeMap`](std::collections::BTreeMap) whose values are the sorted ranges in
+	/// order, every item can be read back in order without sorting them again.
+	pub fn ranges(self) -> SortedRanges<T, F> {
+		SortedRanges {
+			sort: self,
+			next: 0,
+		}
+	}
+}
+
+impl_par_dist! {
+	impl<T, F> ParallelStream for StreamSort<T, F>
+	where
+		T: ProcessSend + 'static,
+		F: for<'a, 'b> Fn<(&'a T, &'b T), Output = Ordering> + Clone + Send + 'static,
+	{
+		type Item = Result<T, IoError>;
+		type Task = StreamSortTask<T, F>;
+
+		fn size_hint(&self) -> (usize, Option<usize>) {
+			(self.ranges.len(), Some(self.ranges.len()))
+		}
+		fn next_task(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Option<Self::Task>> {
+			let self_ = self.project();
+			let (cmp, max_items_in_memory) = (self_.cmp, *self_.max_items_in_memory);
+			Poll::Ready(self_.ranges.pop().map(|task| StreamSortTask {
+				task,
+				cmp: cmp.clone(),
+				max_items_in_memory,
+			}))
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(
+	bound(serialize = "F: Serialize"),
+	bound(deserialize = "F: Deserialize<'de>")
+)]
+pub struct StreamSortTask<T, F> {
+	task: RepartitionTask<Result<T, IoError>>,
+	cmp: F,
+	max_items_in_memory: usize,
+}
+
+impl<T, F> StreamTask for StreamSortTask<T, F>
+where
+	T: Serialize + DeserializeOwned,
+	F: for<'a, 'b> Fn<(&'a T, &'b T), Output = Ordering>,
+{
+	type Item = Result<T, IoError>;
+	type Async = StreamSortTaskAsync<T, F>;
+
+	fn into_async(self) -> Self::Async {
+		StreamSortTaskAsync {
+			range: Some((self.task.into_async(), self.cmp)),
+			max_items_in_memory: self.max_items_in_memory,
+			sorted: Ok(None),
+		}
+	}
+}
+
+#[pin_project]
+pub struct StreamSortTaskAsync<T, F> {
+	range: Option<(RepartitionTaskAsync<Result<T, IoError>>, F)>,
+	max_items_in_memory: usize,
+	sorted: Result<Option<SortedRange<T, F>>, Option<IoError>>,
+}
+
+impl<T, F> Stream for StreamSortTaskAsync<T, F>
+where
+	T: Serialize + DeserializeOwned,
+	F: for<'a, 'b> Fn<(&'a T, &'b T), Output = Ordering>,
+{
+	type Item = Result<T, IoError>;
+
+	fn poll_next(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let self_ = self.project();
+		if let Some((range, cmp)) = self_.range.take() {
+			let items = range.map(|item| item.and_then(|item| item));
+			*self_.sorted = sort_range(items, cmp, *self_.max_items_in_memory)
+				.map(Some)
+				.map_err(Some);
+		}
+		Poll::Ready(match self_.sorted {
+			Ok(sorted) => sorted.as_mut().unwrap().next(),
+			Err(err) => err.take().map(Err),
+		})
+	}
+}
+
+/// The sorted ranges of a stream, returned by [`StreamSort::ranges`].
+///
+/// Each task yields its range as a single item, held in memory unless
+/// [`StreamSort::spill`] was used, in which case it's spilled to disk as it's
+/// sorted, or the error encountered reading it back or spilling it.
+#[pin_project]
+#[must_use]
+pub struct SortedRanges<T, F> {
+	sort: StreamSort<T, F>,
+	next: usize,
+}
+
+impl_par_dist! {
+	impl<T, F> ParallelStream for SortedRanges<T, F>
+	where
+		T: ProcessSend + 'static,
+		F: for<'a, 'b> Fn<(&'a T, &'b T), Output = Ordering> + Clone + Send + 'static,
+	{
+		type Item = Result<(usize, SpillVec<T>), IoError>;
+		type Task = SortedRangeTask<T, F>;
+
+		fn size_hint(&self) -> (usize, Option<usize>) {
+			let len = self.sort.ranges.len();
+			(len, Some(len))
+		}
+		fn next_task(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Option<Self::Task>> {
+			let self_ = self.project();
+			let (sort, index) = (self_.sort, self_.next);
+			let task = sort.ranges.pop().map(|task| SortedRangeTask {
+				index: *index,
+				task: StreamSortTask {
+					task,
+					cmp: sort.cmp.clone(),
+					max_items_in_memory: sort.max_items_in_memory,
+				},
+			});
+			*index += 1;
+			Poll::Ready(task)
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(
+	bound(serialize = "F: Serialize"),
+	bound(deserialize = "F: Deserialize<'de>")
+)]
+pub struct SortedRangeTask<T, F> {
+	index: usize,
+	task: StreamSortTask<T, F>,
+}
+
+impl<T, F> StreamTask for SortedRangeTask<T, F>
+where
+	T: Serialize + DeserializeOwned,
+	F: for<'a, 'b> Fn<(&'a T, &'b T), Output = Ordering>,
+{
+	type Item = Result<(usize, SpillVec<T>), IoError>;
+	type Async = SortedRangeTaskAsync<T, F>;
+
+	fn into_async(self) -> Self::Async {
+		SortedRangeTaskAsync {
+			range: Some((self.index, self.task)),
+		}
+	}
+}
+
+#[pin_project]
+pub struct SortedRangeTaskAsync<T, F> {
+	range: Option<(usize, StreamSortTask<T, F>)>,
+}
+
+impl<T, F> Stream for SortedRangeTaskAsync<T, F>
+where
+	T: Serialize + DeserializeOwned,
+	F: for<'a, 'b> Fn<(&'a T, &'b T), Output = Ordering>,
+{
+	type Item = Result<(usize, SpillVec<T>), IoError>;
+
+	fn poll_next(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let self_ = self.project();
+		Poll::Ready(self_.range.take().map(|(index, task)| {
+			let max_items_in_memory = task.max_items_in_memory;
+			let items = task.task.into_runs().map(|item| item.and_then(|item| item));
+			let sorted = sort_range(items, task.cmp, max_items_in_memory)?;
+			let mut range = SpillVec::new(max_items_in_memory);
+			for item in sorted {
+				range.push(item?)?;
+			}
+			Ok((index, range))
+		}))
+	}
+}
+
+/// The sorted items of a stream, collected from its
+/// [`ranges`](StreamSort::ranges).
+///
+/// Iterating it yields every item in order, range by range, streaming those of
+/// ranges that were spilled back from disk, and yields an error, and then ends,
+/// if a range can't be read back. Unless [`StreamSort::spill`] was used each
+/// range is held in memory, and so is sent whole to the process collecting it.
+pub struct Sorted<T> {
+	ranges: BTreeMap<usize, SpillVec<T>>,
+}
+impl<T> Sorted<T> {
+	/// The number of items.
+	pub fn len(&self) -> usize
+	where
+		T: Serialize + DeserializeOwned,
+	{
+		self.ranges.values().map(SpillVec::len).sum()
+	}
+
+	pub fn is_empty(&self) -> bool
+	where
+		T: Serialize + DeserializeOwned,
+	{
+		self.len() == 0
+	}
+
+	/// Read every item back into memory, in order.
+	pub fn into_vec(self) -> Result<Vec<T>, IoError>
+	where
+		T: DeserializeOwned,
+	{
+		self.into_iter().collect()
+	}
+}
+impl<T> From<BTreeMap<usize, SpillVec<T>>> for Sorted<T> {
+	fn from(ranges: BTreeMap<usize, SpillVec<T>>) -> Self {
+		Self { ranges }
+	}
+}
+
+impl<T> IntoIterator for Sorted<T>
+where
+	T: DeserializeOwned,
+{
+	type Item = Result<T, IoError>;
+	type IntoIter = SortedIter<T>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		SortedIter {
+			ranges: self.ranges.into_iter(),
+			range: None,
+		}
+	}
+}
+
+/// An iterator over the items of [`Sorted`], in order.
+pub struct SortedIter<T> {
+	ranges: btree_map::IntoIter<usize, SpillVec<T>>,
+	range: Option<SpillVecIter<T>>,
+}
+impl<T> Iterator for SortedIter<T>
+where
+	T: DeserializeOwned,
+{
+	type Item = Result<T, IoError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			if let Some(range) = &mut self.range {
+				match range.next() {
+					Some(Ok(item)) => return Some(Ok(item)),
+					Some(Err(err)) => {
+						self.ranges = BTreeMap::new().into_iter();
+						self.range = None;
+						return Some(Err(err));
+					}
+					None => (),
+				}
+			}
+			self.range = Some(self.ranges.next()?.1.into_iter());
+		}
+	}
+}
+
+impl<T> FromParallelStream<(usize, SpillVec<T>)> for Sorted<T>
+where
+	T: Send + 'static,
+{
+	type ReduceA = PushReducer<(usize, SpillVec<T>), BTreeMap<usize, SpillVec<T>>>;
+	type ReduceC = IntoReducer<ExtendReducer<BTreeMap<usize, SpillVec<T>>>, Self>;
+
+	fn reducers() -> (Self::ReduceA, Self::ReduceC) {
+		Default::default()
+	}
+}
+
+impl<T> FromDistributedStream<(usize, SpillVec<T>)> for Sorted<T>
+where
+	T: ProcessSend + 'static,
+{
+	type ReduceA = PushReducer<(usize, SpillVec<T>), BTreeMap<usize, SpillVec<T>>>;
+	type ReduceB = ExtendReducer<BTreeMap<usize, SpillVec<T>>>;
+	type ReduceC = IntoReducer<ExtendReducer<BTreeMap<usize, SpillVec<T>>>, Self>;
+
+	fn reducers() -> (Self::ReduceA, Self::ReduceB, Self::ReduceC) {
+		Default::default()
+	}
+}
+
+/// Compares items by the key `f` returns.
+#[derive(Educe, Serialize, Deserialize)]
+#[educe(Clone(bound = "F: Clone"))]
+#[serde(
+	bound(serialize = "F: Serialize"),
+	bound(deserialize = "F: Deserialize<'de>")
+)]
+pub struct ByKey<F, K> {
+	f: F,
+	marker: PhantomData<fn() -> K>,
+}
+impl<F, K> ByKey<F, K> {
+	pub(crate) fn new(f: F) -> Self {
+		Self {
+			f,
+			marker: PhantomData,
+		}
+	}
+}
+impl<'a, 'b, T, F, K> FnOnce<(&'a T, &'b T)> for ByKey<F, K>
+where
+	F: for<'c> Fn<(&'c T,), Output = K>,
+	K: Ord,
+{
+	type Output = Ordering;
+
+	fn call_once(self, args: (&'a T, &'b T)) -> Self::Output {
+		self.call(args)
+	}
+}
+impl<'a, 'b, T, F, K> FnMut<(&'a T, &'b T)> for ByKey<F, K>
+where
+	F: for<'c> Fn<(&'c T,), Output = K>,
+	K: Ord,
+{
+	fn call_mut(&mut self, args: (&'a T, &'b T)) -> Self::Output {
+		self.call(args)
+	}
+}
+impl<'a, 'b, T, F, K> Fn<(&'a T, &'b T)> for ByKey<F, K>
+where
+	F: for<'c> Fn<(&'c T,), Output = K>,
+	K: Ord,
+{
+	fn call(&self, (a, b): (&'a T, &'b T)) -> Self::Output {
+		self.f.call((a,)).cmp(&self.f.call((b,)))
+	}
+}
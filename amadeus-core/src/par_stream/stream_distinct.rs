@@ -0,0 +1,231 @@
+use educe::Educe;
+use futures::Stream;
+use pin_project::pin_project;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_closure::traits::{FnMut, FnOnce};
+use std::{
+	collections::{
+		hash_map::{self, DefaultHasher}, hash_set, HashMap, HashSet
+	}, hash::{Hash, Hasher}, marker::PhantomData, pin::Pin, task::{Context, Poll}
+};
+
+use super::{
+	DistributedStream, ParallelStream, Repartition, RepartitionTask, RepartitionTaskAsync, StreamTask
+};
+use crate::{pool::ProcessSend, util::IoError};
+
+/// The hash of an item, to partition by where the item itself is the key.
+#[derive(Educe, Serialize, Deserialize)]
+#[educe(Clone)]
+#[serde(bound = "")]
+pub struct ItemHash<T>(PhantomData<fn() -> T>);
+impl<T> ItemHash<T> {
+	pub(crate) fn new() -> Self {
+		Self(PhantomData)
+	}
+}
+impl<'a, T: Hash> FnOnce<(&'a T,)> for ItemHash<T> {
+	type Output = u64;
+
+	fn call_once(mut self, args: (&'a T,)) -> Self::Output {
+		self.call_mut(args)
+	}
+}
+impl<'a, T: Hash> FnMut<(&'a T,)> for ItemHash<T> {
+	fn call_mut(&mut self, (item,): (&'a T,)) -> Self::Output {
+		let mut hasher = DefaultHasher::new();
+		item.hash(&mut hasher);
+		hasher.finish()
+	}
+}
+
+/// The distinct items of a stream, returned by
+/// [`distinct`](DistributedStream::distinct).
+///
+/// The items are hash partitioned, and each task fetches its partition from the
+/// processes that wrote it, as described on [`Repartition`], and removes the
+/// duplicates within it. So more partitions spread the work over more processes,
+/// but the distinct items of a partition need to fit in the memory of the process
+/// running its task. A task yields only an error if it can't read its partition
+/// back.
+#[must_use]
+pub struct StreamDistinct<T> {
+	partitions: Repartition<T>,
+}
+impl<T> StreamDistinct<T> {
+	pub(crate) fn new(partitions: Repartition<T>) -> Self {
+		Self { partitions }
+	}
+}
+
+impl_par_dist_rename! {
+	impl<T> ParallelStream for StreamDistinct<T>
+	where
+		T: Eq + Hash + ProcessSend + 'static,
+	{
+		type Item = Result<T, IoError>;
+		type Task = StreamDistinctTask<T>;
+
+		fn size_hint(&self) -> (usize, Option<usize>) {
+			(self.partitions.len(), Some(self.partitions.len()))
+		}
+		fn next_task(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Option<Self::Task>> {
+			Poll::Ready(self.get_mut().partitions.pop().map(StreamDistinctTask))
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct StreamDistinctTask<T>(RepartitionTask<T>);
+
+impl<T> StreamTask for StreamDistinctTask<T>
+where
+	T: Eq + Hash + DeserializeOwned,
+{
+	type Item = Result<T, IoError>;
+	type Async = StreamDistinctTaskAsync<T>;
+
+	fn into_async(self) -> Self::Async {
+		StreamDistinctTaskAsync {
+			partition: Some(self.0.into_async()),
+			distinct: Ok(HashSet::new().into_iter()),
+		}
+	}
+}
+
+#[pin_project]
+pub struct StreamDistinctTaskAsync<T> {
+	partition: Option<RepartitionTaskAsync<T>>,
+	distinct: Result<hash_set::IntoIter<T>, Option<IoError>>,
+}
+
+impl<T> Stream for StreamDistinctTaskAsync<T>
+where
+	T: Eq + Hash + DeserializeOwned,
+{
+	type Item = Result<T, IoError>;
+
+	fn poll_next(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let self_ = self.project();
+		if let Some(partition) = self_.partition.take() {
+			*self_.distinct = partition
+				.collect::<Result<HashSet<_>, _>>()
+				.map(IntoIterator::into_iter)
+				.map_err(Some);
+		}
+		Poll::Ready(match self_.distinct {
+			Ok(distinct) => distinct.next().map(Ok),
+			Err(err) => err.take().map(Err),
+		})
+	}
+}
+
+/// The items of a stream with distinct keys, returned by
+/// [`distinct_by_key`](DistributedStream::distinct_by_key).
+///
+/// The items are hash partitioned by key, and each task keeps one item per key
+/// within its own partition. See [`StreamDistinct`].
+#[pin_project]
+#[must_use]
+pub struct StreamDistinctByKey<T, F, K> {
+	partitions: Repartition<T>,
+	f: F,
+	marker: PhantomData<fn() -> K>,
+}
+impl<T, F, K> StreamDistinctByKey<T, F, K> {
+	pub(crate) fn new(partitions: Repartition<T>, f: F) -> Self {
+		Self {
+			partitions,
+			f,
+			marker: PhantomData,
+		}
+	}
+}
+
+impl_par_dist! {
+	impl<T, F, K> ParallelStream for StreamDistinctByKey<T, F, K>
+	where
+		T: ProcessSend + 'static,
+		F: for<'a> FnMut<(&'a T,), Output = K> + Clone + Send + 'static,
+		K: Eq + Hash + 'static,
+	{
+		type Item = Result<T, IoError>;
+		type Task = StreamDistinctByKeyTask<T, F, K>;
+
+		fn size_hint(&self) -> (usize, Option<usize>) {
+			(self.partitions.len(), Some(self.partitions.len()))
+		}
+		fn next_task(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Option<Self::Task>> {
+			let self_ = self.project();
+			let f = self_.f;
+			Poll::Ready(self_.partitions.pop().map(|task| StreamDistinctByKeyTask {
+				task,
+				f: f.clone(),
+				marker: PhantomData,
+			}))
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(
+	bound(serialize = "F: Serialize"),
+	bound(deserialize = "F: Deserialize<'de>")
+)]
+pub struct StreamDistinctByKeyTask<T, F, K> {
+	task: RepartitionTask<T>,
+	f: F,
+	marker: PhantomData<fn() -> K>,
+}
+
+impl<T, F, K> StreamTask for StreamDistinctByKeyTask<T, F, K>
+where
+	T: DeserializeOwned,
+	F: for<'a> FnMut<(&'a T,), Output = K>,
+	K: Eq + Hash,
+{
+	type Item = Result<T, IoError>;
+	type Async = StreamDistinctByKeyTaskAsync<T, F, K>;
+
+	fn into_async(self) -> Self::Async {
+		StreamDistinctByKeyTaskAsync {
+			partition: Some((self.task.into_async(), self.f)),
+			distinct: Ok(HashMap::new().into_iter()),
+		}
+	}
+}
+
+#[pin_project]
+pub struct StreamDistinctByKeyTaskAsync<T, F, K> {
+	partition: Option<(RepartitionTaskAsync<T>, F)>,
+	distinct: Result<hash_map::IntoIter<K, T>, Option<IoError>>,
+}
+
+impl<T, F, K> Stream for StreamDistinctByKeyTaskAsync<T, F, K>
+where
+	T: DeserializeOwned,
+	F: for<'a> FnMut<(&'a T,), Output = K>,
+	K: Eq + Hash,
+{
+	type Item = Result<T, IoError>;
+
+	fn poll_next(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let self_ = self.project();
+		if let Some((mut partition, mut f)) = self_.partition.take() {
+			let mut distinct = HashMap::new();
+			*self_.distinct = partition
+				.try_for_each(|item| {
+					let item = item?;
+					let _ = distinct.entry(f.call_mut((&item,))).or_insert(item);
+					Ok(())
+				})
+				.map(|()| distinct.into_iter())
+				.map_err(Some);
+		}
+		Poll::Ready(match self_.distinct {
+			Ok(distinct) => distinct.next().map(|(_, item)| Ok(item)),
+			Err(err) => err.take().map(Err),
+		})
+	}
+}
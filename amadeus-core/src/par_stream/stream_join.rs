@@ -0,0 +1,418 @@
+#![allow(clippy::type_complexity)]
+
+use educe::Educe;
+use futures::{future, ready, stream, Stream, StreamExt};
+use indexmap::IndexMap;
+use pin_project::pin_project;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_closure::traits::{FnMut, FnOnce};
+use std::{
+	collections::VecDeque, hash::Hash, marker::PhantomData, mem, pin::Pin, task::{Context, Poll}, vec
+};
+
+use super::{
+	DistributedStream, ParallelStream, Repartition, RepartitionTask, RepartitionTaskAsync, StreamTask
+};
+use crate::{pool::ProcessSend, util::IoError};
+
+/// How the two sides of a join between streams are brought together.
+///
+/// Right and full outer joins need to know which right items no task matched, so
+/// they can't broadcast, and take a number of partitions instead.
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum JoinStrategy {
+	/// Hash partition both sides by key into this many partitions, and join each
+	/// partition in its own task. Both sides are exchanged between the tasks as
+	/// described on [`Repartition`](super::Repartition), each task fetching its
+	/// partition of both from the processes that wrote them, so neither side passes
+	/// through the process driving the pool. The right side of each partition needs
+	/// to fit in memory.
+	Partitioned(usize),
+	/// Collect the right side, and send all of it along with every task of the left
+	/// side. The right side needs to be small enough to fit in memory on every
+	/// process.
+	Broadcast,
+}
+
+/// The kind of a join between streams, which decides what it yields for the
+/// matching and unmatched items of each side.
+pub trait JoinKind<K, V1, V2> {
+	type Item;
+
+	/// Whether right items that no left item matched are yielded.
+	const UNMATCHED_RIGHT: bool;
+
+	/// Yield the items for a left item and the right items with the same key, which
+	/// is empty if there are none.
+	fn matched(key: K, left: V1, right: &[V2], items: &mut VecDeque<Self::Item>);
+	/// Yield the items for right items that no left item matched.
+	fn unmatched_right(key: K, right: Vec<V2>, items: &mut VecDeque<Self::Item>);
+}
+
+/// Yields `(key, left, right)` for each pair of matching items.
+pub struct InnerJoinKind;
+impl<K: Clone, V1: Clone, V2: Clone> JoinKind<K, V1, V2> for InnerJoinKind {
+	type Item = (K, V1, V2);
+
+	const UNMATCHED_RIGHT: bool = false;
+
+	fn matched(key: K, left: V1, right: &[V2], items: &mut VecDeque<Self::Item>) {
+		items.extend(
+			right
+				.iter()
+				.map(|right| (key.clone(), left.clone(), right.clone())),
+		);
+	}
+	fn unmatched_right(_key: K, _right: Vec<V2>, _items: &mut VecDeque<Self::Item>) {}
+}
+
+/// Yields `(key, left, Some(right))` for each pair of matching items, and
+/// `(key, left, None)` for left items without a match.
+pub struct LeftJoinKind;
+impl<K: Clone, V1: Clone, V2: Clone> JoinKind<K, V1, V2> for LeftJoinKind {
+	type Item = (K, V1, Option<V2>);
+
+	const UNMATCHED_RIGHT: bool = false;
+
+	fn matched(key: K, left: V1, right: &[V2], items: &mut VecDeque<Self::Item>) {
+		if right.is_empty() {
+			items.push_back((key, left, None));
+		} else {
+			items.extend(
+				right
+					.iter()
+					.map(|right| (key.clone(), left.clone(), Some(right.clone()))),
+			);
+		}
+	}
+	fn unmatched_right(_key: K, _right: Vec<V2>, _items: &mut VecDeque<Self::Item>) {}
+}
+
+/// Yields `(key, Some(left), right)` for each pair of matching items, and
+/// `(key, None, right)` for right items without a match.
+pub struct RightJoinKind;
+impl<K: Clone, V1: Clone, V2: Clone> JoinKind<K, V1, V2> for RightJoinKind {
+	type Item = (K, Option<V1>, V2);
+
+	const UNMATCHED_RIGHT: bool = true;
+
+	fn matched(key: K, left: V1, right: &[V2], items: &mut VecDeque<Self::Item>) {
+		items.extend(
+			right
+				.iter()
+				.map(|right| (key.clone(), Some(left.clone()), right.clone())),
+		);
+	}
+	fn unmatched_right(key: K, right: Vec<V2>, items: &mut VecDeque<Self::Item>) {
+		items.extend(right.into_iter().map(|right| (key.clone(), None, right)));
+	}
+}
+
+/// Yields `(key, Some(left), Some(right))` for each pair of matching items, and
+/// `(key, Some(left), None)` or `(key, None, Some(right))` for items of either side
+/// without a match.
+pub struct OuterJoinKind;
+impl<K: Clone, V1: Clone, V2: Clone> JoinKind<K, V1, V2> for OuterJoinKind {
+	type Item = (K, Option<V1>, Option<V2>);
+
+	const UNMATCHED_RIGHT: bool = true;
+
+	fn matched(key: K, left: V1, right: &[V2], items: &mut VecDeque<Self::Item>) {
+		if right.is_empty() {
+			items.push_back((key, Some(left), None));
+		} else {
+			items.extend(
+				right
+					.iter()
+					.map(|right| (key.clone(), Some(left.clone()), Some(right.clone()))),
+			);
+		}
+	}
+	fn unmatched_right(key: K, right: Vec<V2>, items: &mut VecDeque<Self::Item>) {
+		items.extend(
+			right
+				.into_iter()
+				.map(|right| (key.clone(), None, Some(right))),
+		);
+	}
+}
+
+/// Yields `(key, left)` for left items with a match.
+pub struct SemiJoinKind;
+impl<K, V1, V2> JoinKind<K, V1, V2> for SemiJoinKind {
+	type Item = (K, V1);
+
+	const UNMATCHED_RIGHT: bool = false;
+
+	fn matched(key: K, left: V1, right: &[V2], items: &mut VecDeque<Self::Item>) {
+		if !right.is_empty() {
+			items.push_back((key, left));
+		}
+	}
+	fn unmatched_right(_key: K, _right: Vec<V2>, _items: &mut VecDeque<Self::Item>) {}
+}
+
+/// Yields `(key, left)` for left items without a match.
+pub struct AntiJoinKind;
+impl<K, V1, V2> JoinKind<K, V1, V2> for AntiJoinKind {
+	type Item = (K, V1);
+
+	const UNMATCHED_RIGHT: bool = false;
+
+	fn matched(key: K, left: V1, right: &[V2], items: &mut VecDeque<Self::Item>) {
+		if right.is_empty() {
+			items.push_back((key, left));
+		}
+	}
+	fn unmatched_right(_key: K, _right: Vec<V2>, _items: &mut VecDeque<Self::Item>) {}
+}
+
+/// The key of a `(key, value)` item, to partition both sides of a join by.
+#[derive(Educe, Serialize, Deserialize)]
+#[educe(Clone)]
+#[serde(bound = "")]
+pub struct JoinKey<K, V>(PhantomData<fn() -> (K, V)>);
+impl<K, V> JoinKey<K, V> {
+	pub(crate) fn new() -> Self {
+		Self(PhantomData)
+	}
+}
+impl<'a, K: Clone, V> FnOnce<(&'a (K, V),)> for JoinKey<K, V> {
+	type Output = K;
+
+	fn call_once(mut self, args: (&'a (K, V),)) -> Self::Output {
+		self.call_mut(args)
+	}
+}
+impl<'a, K: Clone, V> FnMut<(&'a (K, V),)> for JoinKey<K, V> {
+	fn call_mut(&mut self, (item,): (&'a (K, V),)) -> Self::Output {
+		item.0.clone()
+	}
+}
+
+fn group<K: Eq + Hash, V>(items: Vec<(K, V)>) -> IndexMap<K, Vec<V>> {
+	let mut map = IndexMap::<K, Vec<V>>::new();
+	for (key, value) in items {
+		map.entry(key).or_default().push(value);
+	}
+	map
+}
+
+/// A join between two streams, returned by methods like
+/// [`inner_join_stream`](DistributedStream::inner_join_stream).
+///
+/// Where both sides were partitioned, a task yields an error, and then ends, if it
+/// can't read its partition of either side back.
+#[pin_project]
+#[must_use]
+pub struct StreamJoin<S, J, K, V1, V2> {
+	#[pin]
+	left: Option<S>,
+	right: IndexMap<K, Vec<V2>>,
+	partitions: Option<(Repartition<(K, V1)>, Repartition<(K, V2)>)>,
+	marker: PhantomData<fn() -> J>,
+}
+impl<S, J, K: Eq + Hash, V1, V2> StreamJoin<S, J, K, V1, V2> {
+	pub(crate) fn broadcast(left: S, right: Vec<(K, V2)>) -> Self {
+		Self {
+			left: Some(left),
+			right: group(right),
+			partitions: None,
+			marker: PhantomData,
+		}
+	}
+	pub(crate) fn partitioned(left: Repartition<(K, V1)>, right: Repartition<(K, V2)>) -> Self {
+		Self {
+			left: None,
+			right: IndexMap::new(),
+			partitions: Some((left, right)),
+			marker: PhantomData,
+		}
+	}
+}
+
+impl_par_dist_rename! {
+	impl<S, J, K, V1, V2> ParallelStream for StreamJoin<S, J, K, V1, V2>
+	where
+		S: ParallelStream<Item = (K, V1)>,
+		J: JoinKind<K, V1, V2>,
+		K: Eq + Hash + Clone + ProcessSend + 'static,
+		V1: ProcessSend + 'static,
+		V2: Clone + ProcessSend + 'static,
+	{
+		type Item = Result<J::Item, IoError>;
+		type Task = StreamJoinTask<S::Task, J, K, V1, V2>;
+
+		fn size_hint(&self) -> (usize, Option<usize>) {
+			if let Some(left) = &self.left {
+				left.size_hint()
+			} else {
+				let (left, _) = self.partitions.as_ref().unwrap();
+				(left.len(), Some(left.len()))
+			}
+		}
+		fn next_task(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Task>> {
+			let mut self_ = self.project();
+			let task = if let Some(left) = self_.left.as_mut().as_pin_mut() {
+				let right = self_.right;
+				ready!(left.next_task(cx)).map(|task| Inner::Broadcast(task, right.clone()))
+			} else {
+				let (left, right) = self_.partitions.as_mut().unwrap();
+				left.pop()
+					.zip(right.pop())
+					.map(|(left, right)| Inner::Partitioned(left, right))
+			};
+			Poll::Ready(task.map(|task| StreamJoinTask::new(task)))
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(
+	bound(serialize = "T: Serialize, K: Serialize + Eq + Hash, V1: Serialize, V2: Serialize"),
+	bound(
+		deserialize = "T: Deserialize<'de>, K: Deserialize<'de> + Eq + Hash, V1: Deserialize<'de>, V2: Deserialize<'de>"
+	)
+)]
+enum Inner<T, K, V1, V2> {
+	Broadcast(T, IndexMap<K, Vec<V2>>),
+	Partitioned(RepartitionTask<(K, V1)>, RepartitionTask<(K, V2)>),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(
+	bound(serialize = "T: Serialize, K: Serialize + Eq + Hash, V1: Serialize, V2: Serialize"),
+	bound(
+		deserialize = "T: Deserialize<'de>, K: Deserialize<'de> + Eq + Hash, V1: Deserialize<'de>, V2: Deserialize<'de>"
+	)
+)]
+pub struct StreamJoinTask<T, J, K, V1, V2> {
+	task: Inner<T, K, V1, V2>,
+	marker: PhantomData<fn() -> J>,
+}
+impl<T, J, K, V1, V2> StreamJoinTask<T, J, K, V1, V2> {
+	fn new(task: Inner<T, K, V1, V2>) -> Self {
+		Self {
+			task,
+			marker: PhantomData,
+		}
+	}
+}
+
+impl<T, J, K, V1, V2> StreamTask for StreamJoinTask<T, J, K, V1, V2>
+where
+	T: StreamTask<Item = (K, V1)>,
+	J: JoinKind<K, V1, V2>,
+	K: Eq + Hash + DeserializeOwned,
+	V1: DeserializeOwned,
+	V2: DeserializeOwned,
+{
+	type Item = Result<J::Item, IoError>;
+	type Async = StreamJoinTaskAsync<T::Async, J, K, V1, V2>;
+
+	fn into_async(self) -> Self::Async {
+		let (left, right) = match self.task {
+			Inner::Broadcast(task, right) => (
+				future::Either::Left(task.into_async().map(Ok as fn(_) -> _)),
+				Ok(right),
+			),
+			Inner::Partitioned(left, right) => (
+				future::Either::Right(left.into_async()),
+				right.into_runs().collect::<Result<_, _>>().map(group),
+			),
+		};
+		let (right, err) = match right {
+			Ok(right) => (right, None),
+			Err(err) => (IndexMap::new(), Some(err)),
+		};
+		let matched = if J::UNMATCHED_RIGHT {
+			vec![false; right.len()]
+		} else {
+			Vec::new()
+		};
+		StreamJoinTaskAsync {
+			left,
+			right,
+			matched,
+			items: VecDeque::new(),
+			err,
+			done: false,
+			marker: PhantomData,
+		}
+	}
+}
+
+#[pin_project]
+pub struct StreamJoinTaskAsync<T, J, K, V1, V2>
+where
+	J: JoinKind<K, V1, V2>,
+{
+	#[pin]
+	left: future::Either<
+		stream::Map<T, fn((K, V1)) -> Result<(K, V1), IoError>>,
+		RepartitionTaskAsync<(K, V1)>,
+	>,
+	right: IndexMap<K, Vec<V2>>,
+	matched: Vec<bool>,
+	items: VecDeque<J::Item>,
+	err: Option<IoError>,
+	done: bool,
+	marker: PhantomData<fn() -> J>,
+}
+
+impl<T, J, K, V1, V2> Stream for StreamJoinTaskAsync<T, J, K, V1, V2>
+where
+	T: Stream<Item = (K, V1)>,
+	J: JoinKind<K, V1, V2>,
+	K: Eq + Hash + DeserializeOwned,
+	V1: DeserializeOwned,
+{
+	type Item = Result<J::Item, IoError>;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let mut self_ = self.project();
+		loop {
+			if let Some(item) = self_.items.pop_front() {
+				break Poll::Ready(Some(Ok(item)));
+			}
+			// Which right items went unmatched isn't known once a partition is cut short
+			if let Some(err) = self_.err.take() {
+				*self_.done = true;
+				break Poll::Ready(Some(Err(err)));
+			}
+			if *self_.done {
+				break Poll::Ready(None);
+			}
+			if let Some(left) = ready!(self_.left.as_mut().poll_next(cx)) {
+				let (key, left) = match left {
+					Ok(left) => left,
+					Err(err) => {
+						*self_.err = Some(err);
+						continue;
+					}
+				};
+				let right = if let Some((i, _, right)) = self_.right.get_full(&key) {
+					if J::UNMATCHED_RIGHT {
+						self_.matched[i] = true;
+					}
+					&**right
+				} else {
+					&[]
+				};
+				J::matched(key, left, right, self_.items);
+				continue;
+			}
+			*self_.done = true;
+			if J::UNMATCHED_RIGHT {
+				let right = mem::take(self_.right);
+				for ((key, right), _) in right
+					.into_iter()
+					.zip(self_.matched.iter())
+					.filter(|(_, matched)| !**matched)
+				{
+					J::unmatched_right(key, right, self_.items);
+				}
+			}
+		}
+	}
+}
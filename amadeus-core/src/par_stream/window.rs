@@ -0,0 +1,246 @@
+use serde::{Deserialize, Serialize};
+use serde_closure::traits::{FnMut, FnOnce};
+use std::{
+	convert::TryFrom, time::{Duration, SystemTime, UNIX_EPOCH}, vec
+};
+
+/// A point in event time, that items can be assigned to windows by.
+pub trait EventTime: Clone + Ord {
+	type Duration: Clone;
+
+	/// Round down to a multiple of `duration` since the Unix epoch.
+	#[must_use]
+	fn truncate(&self, duration: &Self::Duration) -> Self;
+	#[must_use]
+	fn add_duration(&self, duration: &Self::Duration) -> Self;
+	#[must_use]
+	fn sub_duration(&self, duration: &Self::Duration) -> Self;
+	/// Whether `duration` is longer than zero, as window lengths must be.
+	fn is_positive(duration: &Self::Duration) -> bool;
+}
+
+impl EventTime for u64 {
+	type Duration = Self;
+
+	fn truncate(&self, duration: &Self::Duration) -> Self {
+		self - self % duration
+	}
+	fn add_duration(&self, duration: &Self::Duration) -> Self {
+		self.saturating_add(*duration)
+	}
+	fn sub_duration(&self, duration: &Self::Duration) -> Self {
+		self.saturating_sub(*duration)
+	}
+	fn is_positive(duration: &Self::Duration) -> bool {
+		*duration > 0
+	}
+}
+impl EventTime for i64 {
+	type Duration = Self;
+
+	fn truncate(&self, duration: &Self::Duration) -> Self {
+		self.saturating_sub(self.rem_euclid(*duration))
+	}
+	fn add_duration(&self, duration: &Self::Duration) -> Self {
+		self.saturating_add(*duration)
+	}
+	fn sub_duration(&self, duration: &Self::Duration) -> Self {
+		self.saturating_sub(*duration)
+	}
+	fn is_positive(duration: &Self::Duration) -> bool {
+		*duration > 0
+	}
+}
+impl EventTime for SystemTime {
+	type Duration = Duration;
+
+	fn truncate(&self, duration: &Self::Duration) -> Self {
+		let duration = duration.as_nanos();
+		match self.duration_since(UNIX_EPOCH) {
+			Ok(after) => {
+				let after = after.as_nanos();
+				UNIX_EPOCH + nanos(after - after % duration)
+			}
+			// Round away from the epoch, so that the result is still at or before `self`.
+			Err(before) => {
+				let before = before.duration().as_nanos();
+				let rem = before % duration;
+				let before = if rem == 0 {
+					before
+				} else {
+					before + duration - rem
+				};
+				UNIX_EPOCH.checked_sub(nanos(before)).unwrap_or(*self)
+			}
+		}
+	}
+	fn add_duration(&self, duration: &Self::Duration) -> Self {
+		self.checked_add(*duration).unwrap_or(*self)
+	}
+	fn sub_duration(&self, duration: &Self::Duration) -> Self {
+		self.checked_sub(*duration).unwrap_or(*self)
+	}
+	fn is_positive(duration: &Self::Duration) -> bool {
+		*duration > Duration::from_secs(0)
+	}
+}
+
+fn nanos(nanos: u128) -> Duration {
+	Duration::new(
+		u64::try_from(nanos / 1_000_000_000).unwrap(),
+		u32::try_from(nanos % 1_000_000_000).unwrap(),
+	)
+}
+
+/// An interval of event time `[start, end)`, that items are grouped by.
+///
+/// Session windows are merged with those they overlap by
+/// [`group_by_window`](crate::par_pipe::ParallelPipe::group_by_window).
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, Debug)]
+pub struct Window<T> {
+	session: bool,
+	start: T,
+	end: T,
+}
+impl<T> Window<T> {
+	pub(crate) fn new(start: T, end: T, session: bool) -> Self {
+		Self {
+			session,
+			start,
+			end,
+		}
+	}
+	pub fn start(&self) -> &T {
+		&self.start
+	}
+	pub fn end(&self) -> &T {
+		&self.end
+	}
+	pub fn is_session(&self) -> bool {
+		self.session
+	}
+	pub(crate) fn merge(&mut self, other: &Self) -> bool
+	where
+		T: Clone + Ord,
+	{
+		if self.session && other.session && other.start < self.end && self.start < other.end {
+			if other.end > self.end {
+				self.end = other.end.clone();
+			}
+			if other.start < self.start {
+				self.start = other.start.clone();
+			}
+			true
+		} else {
+			self == other
+		}
+	}
+}
+
+/// How items are assigned to windows by their event time.
+pub trait Windowing<T: EventTime> {
+	/// Push the windows that `time` falls in.
+	fn windows(&self, time: &T, windows: &mut Vec<Window<T>>);
+	/// Panic if any of the lengths this windowing is made of isn't positive.
+	fn assert_positive(&self);
+}
+
+/// Fixed-size, non-overlapping windows of the given length.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug)]
+pub struct Tumbling<D>(pub D);
+
+/// Fixed-size windows of the length of the first field, starting at every
+/// multiple of the second. An item falls in every window that covers it.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug)]
+pub struct Sliding<D>(pub D, pub D);
+
+/// Windows of activity, that close after a gap of the given length with no
+/// items.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug)]
+pub struct Session<D>(pub D);
+
+impl<T: EventTime> Windowing<T> for Tumbling<T::Duration> {
+	fn windows(&self, time: &T, windows: &mut Vec<Window<T>>) {
+		let start = time.truncate(&self.0);
+		let end = start.add_duration(&self.0);
+		windows.push(Window::new(start, end, false));
+	}
+	fn assert_positive(&self) {
+		assert!(T::is_positive(&self.0), "window length must be positive");
+	}
+}
+impl<T: EventTime> Windowing<T> for Sliding<T::Duration> {
+	fn windows(&self, time: &T, windows: &mut Vec<Window<T>>) {
+		let mut start = time.truncate(&self.1);
+		loop {
+			let end = start.add_duration(&self.0);
+			if end <= *time {
+				break;
+			}
+			let next = start.sub_duration(&self.1);
+			let done = next == start;
+			windows.push(Window::new(start, end, false));
+			if done {
+				break;
+			}
+			start = next;
+		}
+	}
+	fn assert_positive(&self) {
+		assert!(T::is_positive(&self.0), "window length must be positive");
+		assert!(T::is_positive(&self.1), "window slide must be positive");
+	}
+}
+impl<T: EventTime> Windowing<T> for Session<T::Duration> {
+	fn windows(&self, time: &T, windows: &mut Vec<Window<T>>) {
+		windows.push(Window::new(time.clone(), time.add_duration(&self.0), true));
+	}
+	fn assert_positive(&self) {
+		assert!(T::is_positive(&self.0), "session gap must be positive");
+	}
+}
+
+/// Pairs an item with each window its event time falls in.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AssignWindows<F, W> {
+	f: F,
+	windowing: W,
+}
+impl<F, W> AssignWindows<F, W> {
+	pub(crate) fn new(f: F, windowing: W) -> Self {
+		Self { f, windowing }
+	}
+}
+impl<Item, F, T, W> FnOnce<(Item,)> for AssignWindows<F, W>
+where
+	F: for<'a> FnMut<(&'a Item,), Output = T>,
+	T: EventTime,
+	W: Windowing<T>,
+	Item: Clone,
+{
+	type Output = vec::IntoIter<(Window<T>, Item)>;
+
+	fn call_once(mut self, args: (Item,)) -> Self::Output {
+		self.call_mut(args)
+	}
+}
+impl<Item, F, T, W> FnMut<(Item,)> for AssignWindows<F, W>
+where
+	F: for<'a> FnMut<(&'a Item,), Output = T>,
+	T: EventTime,
+	W: Windowing<T>,
+	Item: Clone,
+{
+	fn call_mut(&mut self, (item,): (Item,)) -> Self::Output {
+		let time = self.f.call_mut((&item,));
+		let mut windows = Vec::new();
+		self.windowing.windows(&time, &mut windows);
+		let last = windows.pop();
+		let mut items = windows
+			.into_iter()
+			.map(|window| (window, item.clone()))
+			.collect::<Vec<_>>();
+		items.extend(last.map(|window| (window, item)));
+		items.into_iter()
+	}
+}
@@ -3,7 +3,7 @@ use futures::{ready, Stream};
 use pin_project::pin_project;
 use serde::{de::Deserializer, ser::Serializer, Deserialize, Serialize};
 use std::{
-	any::{Any, TypeId}, error, fmt, hash::{Hash, Hasher}, io, marker::PhantomData, pin::Pin, sync::Arc, task::{Context, Poll}
+	any::{Any, TypeId}, error, fmt, future::Future, hash::{Hash, Hasher}, io, marker::PhantomData, pin::Pin, sync::Arc, task::{Context, Poll}, time::Duration
 };
 
 use crate::par_stream::{DistributedStream, ParallelStream};
@@ -91,6 +91,111 @@ impl From<IoError> for io::Error {
 	}
 }
 
+/// How requests to remote sources are retried: how many attempts are made,
+/// how long to back off between them, and how long each may take.
+///
+/// Backoff is exponential with "full jitter": the wait before the `n`th retry
+/// is uniformly random up to `initial_backoff * 2ⁿ`, capped at `max_backoff`,
+/// so that throttled workers don't all retry in lockstep.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub struct RetryPolicy {
+	max_attempts: u32,
+	initial_backoff: Duration,
+	max_backoff: Duration,
+	timeout: Option<Duration>,
+}
+impl RetryPolicy {
+	/// 10 attempts, backing off from 50ms up to 10s, with a 30s timeout.
+	pub fn new() -> Self {
+		Self {
+			max_attempts: 10,
+			initial_backoff: Duration::from_millis(50),
+			max_backoff: Duration::from_secs(10),
+			timeout: Some(Duration::from_secs(30)),
+		}
+	}
+	/// Make each request only once.
+	pub fn never() -> Self {
+		Self::new().max_attempts(1)
+	}
+	/// The number of attempts, including the first, before the error is returned.
+	#[must_use]
+	pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+		assert_ne!(max_attempts, 0, "max_attempts must be at least 1");
+		self.max_attempts = max_attempts;
+		self
+	}
+	#[must_use]
+	pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+		self.initial_backoff = initial;
+		self.max_backoff = max;
+		self
+	}
+	/// How long each attempt may take before it's abandoned and retried.
+	#[must_use]
+	pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+		self.timeout = timeout;
+		self
+	}
+	pub fn get_max_attempts(&self) -> u32 {
+		self.max_attempts
+	}
+	pub fn get_timeout(&self) -> Option<Duration> {
+		self.timeout
+	}
+	/// The randomised wait before retry number `retry`, counting from 0.
+	pub fn delay(&self, retry: u32) -> Duration {
+		let cap = self
+			.initial_backoff
+			.checked_mul(1 << retry.min(31))
+			.map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff));
+		cap.mul_f64(rand::random())
+	}
+	/// Wait for [`delay(retry)`](Self::delay).
+	pub async fn sleep(&self, retry: u32) {
+		tokio::time::delay_for(self.delay(retry)).await;
+	}
+	/// Call `f` until it succeeds, fails with an error that isn't `retryable`,
+	/// or attempts are exhausted. Attempts that time out are always retried;
+	/// `timed_out` converts the last of them into an error.
+	pub async fn retry<F, Fut, T, E, R, TO>(
+		&self, mut f: F, mut retryable: R, timed_out: TO,
+	) -> Result<T, E>
+	where
+		F: FnMut() -> Fut,
+		Fut: Future<Output = Result<T, E>>,
+		R: FnMut(&E) -> bool,
+		TO: Fn(Duration) -> E,
+	{
+		let mut attempt = 0;
+		loop {
+			attempt += 1;
+			let res = match self.timeout {
+				Some(timeout) => tokio::time::timeout(timeout, f())
+					.await
+					.map_err(|_| timeout),
+				None => Ok(f().await),
+			};
+			let (res, retry) = match res {
+				Ok(res) => {
+					let retry = matches!(&res, Err(err) if retryable(err));
+					(res, retry)
+				}
+				Err(timeout) => (Err(timed_out(timeout)), true),
+			};
+			if !retry || attempt >= self.max_attempts {
+				break res;
+			}
+			self.sleep(attempt - 1).await;
+		}
+	}
+}
+impl Default for RetryPolicy {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 #[pin_project]
 #[derive(new)]
 #[repr(transparent)]
@@ -61,6 +61,21 @@ use syn::{
 /// }
 /// ```
 ///
+/// Fields can instead be filled from the Hive-style partition columns of the path a
+/// row is read from, like `year` in `logs/year=2018/part-0.parquet`. Such fields aren't
+/// read from the file, and their type must implement
+/// [`PartitionValue`](amadeus::data::PartitionValue):
+///
+/// ```text
+/// #[derive(Data, Debug)]
+/// struct MyRow {
+///     #[amadeus(partition)]
+///     year: u16,
+///     id: u64,
+///     event: String,
+/// }
+/// ```
+///
 /// ## Implementation
 ///
 /// This macro works by creating two new structs: StructSchema and StructReader
@@ -100,6 +115,8 @@ fn impl_struct(
 	let schema_name = Ident::new(&format!("{}Schema", name), Span::call_site());
 	let reader_name = Ident::new(&format!("{}Reader", name), Span::call_site());
 	let predicate_name = Ident::new(&format!("{}Predicate", name), Span::call_site());
+	let predicate_visitor_name =
+		Ident::new(&format!("{}PredicateVisitor", name), Span::call_site());
 
 	let mut amadeus_path = None;
 
@@ -204,11 +221,12 @@ fn impl_struct(
 	let num_fields = field_names.len();
 
 	// The field names specified via `#[amadeus(rename = "foo")]`, falling back to struct
-	// field names
-	let field_renames = fields
+	// field names, and whether they're filled from the path via `#[amadeus(partition)]`
+	let (field_renames, field_partitions): (Vec<_>, Vec<_>) = fields
 		.iter()
 		.map(|field| {
 			let mut rename = None;
+			let mut partition = false;
 			for meta_items in field.attrs.iter().filter_map(get_amadeus_meta_items) {
 				for meta_item in meta_items {
 					match meta_item {
@@ -224,6 +242,16 @@ fn impl_struct(
 							}
 							rename = Some(s.clone());
 						}
+						// Parse `#[amadeus(partition)]`
+						NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("partition") => {
+							if partition {
+								return Err(Error::new_spanned(
+									path,
+									"duplicate amadeus attribute `partition`",
+								));
+							}
+							partition = true;
+						}
 						NestedMeta::Meta(ref meta_item) => {
 							let path = meta_item
 								.path()
@@ -244,20 +272,85 @@ fn impl_struct(
 					}
 				}
 			}
-			Ok(rename.unwrap_or_else(|| {
+			let rename = rename.unwrap_or_else(|| {
 				LitStr::new(&field.ident.as_ref().unwrap().to_string(), field.span())
-			}))
+			});
+			Ok((rename, partition))
 		})
-		.collect::<Result<Vec<_>, _>>()?;
+		.collect::<Result<Vec<_>, _>>()?
+		.into_iter()
+		.unzip();
 	let field_renames1 = &field_renames;
-	let field_renames2 = &field_renames;
 
 	// The struct field types
 	let field_types = fields.iter().map(|field| &field.ty).collect::<Vec<_>>();
 	let field_types1 = &field_types;
 
+	// The fields filled from the path rather than read from the file
+	let partition_fields = (0..num_fields)
+		.filter(|&i| field_partitions[i])
+		.collect::<Vec<_>>();
+	let partition_names = partition_fields
+		.iter()
+		.map(|&i| field_names[i])
+		.collect::<Vec<_>>();
+	let partition_names1 = &partition_names;
+	let partition_renames = partition_fields
+		.iter()
+		.map(|&i| &field_renames[i])
+		.collect::<Vec<_>>();
+	let partition_renames1 = &partition_renames;
+	let partition_types = partition_fields
+		.iter()
+		.map(|&i| field_types[i])
+		.collect::<Vec<_>>();
+	let partition_types1 = &partition_types;
+
+	// The fields read from the file
+	let column_fields = (0..num_fields)
+		.filter(|&i| !field_partitions[i])
+		.collect::<Vec<_>>();
+	let column_names = column_fields
+		.iter()
+		.map(|&i| field_names[i])
+		.collect::<Vec<_>>();
+	let column_names1 = &column_names;
+	let column_names2 = &column_names;
+	let column_renames = column_fields
+		.iter()
+		.map(|&i| &field_renames[i])
+		.collect::<Vec<_>>();
+	let column_renames1 = &column_renames;
+	let column_renames2 = &column_renames;
+	let column_types = column_fields
+		.iter()
+		.map(|&i| field_types[i])
+		.collect::<Vec<_>>();
+	let column_types1 = &column_types;
+
+	let num_columns = column_names.len();
+	let column_indices = (0..num_columns).collect::<Vec<_>>();
+
+	// `#[serde(skip)]` on the fields filled from the path
+	let serde_skips = field_partitions
+		.iter()
+		.map(|&partition| {
+			if partition {
+				Some(quote! { #[serde(skip)] })
+			} else {
+				None
+			}
+		})
+		.collect::<Vec<_>>();
+
 	let name_str = LitStr::new(&name.to_string(), name.span());
 
+	let mut de_generics = ast.generics.clone();
+	de_generics
+		.params
+		.insert(0, syn::parse2(quote! { 'de }).unwrap());
+	let (de_impl_generics, _, _) = de_generics.split_for_impl();
+
 	let mut parquet_includes = None;
 	let mut parquet_derives = None;
 	if cfg!(feature = "parquet") {
@@ -269,13 +362,13 @@ fn impl_struct(
 
 		parquet_derives = Some(quote! {
 			#visibility struct #schema_name #impl_generics #where_clause_with_parquet_data {
-				#(#field_names1: <#field_types1 as __::ParquetData>::Schema,)*
+				#(#column_names1: <#column_types1 as __::ParquetData>::Schema,)*
 			}
 			// #[automatically_derived]
 			// impl #impl_generics __::Default for #schema_name #ty_generics #where_clause_with_data_default {
 			// 	fn default() -> Self {
 			// 		Self {
-			// 			#(#field_names1: __::Default::default(),)*
+			// 			#(#column_names1: __::Default::default(),)*
 			// 		}
 			// 	}
 			// }
@@ -283,7 +376,7 @@ fn impl_struct(
 			impl #impl_generics __::Debug for #schema_name #ty_generics #where_clause_with_parquet_data_debug {
 				fn fmt(&self, f: &mut __::fmt::Formatter) -> __::fmt::Result {
 					f.debug_struct(stringify!(#schema_name))
-						#(.field(stringify!(#field_names1), &self.#field_names2))*
+						#(.field(stringify!(#column_names1), &self.#column_names2))*
 						.finish()
 				}
 			}
@@ -292,16 +385,71 @@ fn impl_struct(
 				fn fmt(self_: __::Option<&Self>, r: __::Option<__::Repetition>, name: __::Option<&str>, f: &mut __::fmt::Formatter) -> __::fmt::Result {
 					__::DisplaySchemaGroup::new(r, name, __::None, f)
 					#(
-						.field(__::Some(#field_renames1), self_.map(|self_|&self_.#field_names1))
+						.field(__::Some(#column_renames1), self_.map(|self_|&self_.#column_names1))
 					)*
 						.finish()
 				}
 			}
 			#visibility struct #reader_name #impl_generics #where_clause_with_parquet_data {
-				#(#field_names1: <#field_types1 as __::ParquetData>::Reader,)*
+				#(#column_names1: <#column_types1 as __::ParquetData>::Reader,)*
 			}
 			#visibility struct #predicate_name #impl_generics #where_clause_with_parquet_data {
-				#(#field_names1: __::Option<<#field_types1 as __::ParquetData>::Predicate>,)*
+				#(#column_names1: __::Option<<#column_types1 as __::ParquetData>::Predicate>,)*
+			}
+			#[automatically_derived]
+			impl #impl_generics __::Clone for #predicate_name #ty_generics #where_clause_with_parquet_data {
+				fn clone(&self) -> Self {
+					#predicate_name {
+						#(#column_names1: __::Clone::clone(&self.#column_names2),)*
+					}
+				}
+			}
+			#[automatically_derived]
+			impl #impl_generics __::Debug for #predicate_name #ty_generics #where_clause_with_parquet_data {
+				fn fmt(&self, f: &mut __::fmt::Formatter) -> __::fmt::Result {
+					f.debug_struct(stringify!(#predicate_name))
+						#(.field(stringify!(#column_names1), &self.#column_names2))*
+						.finish()
+				}
+			}
+			#[automatically_derived]
+			impl #impl_generics __::Serialize_ for #predicate_name #ty_generics #where_clause_with_parquet_data {
+				fn serialize<S>(&self, serializer: S) -> __::Result<S::Ok, S::Error>
+				where
+					S: __::Serializer_,
+				{
+					let mut tuple = __::Serializer_::serialize_tuple(serializer, #num_columns)?;
+					#(__::SerializeTuple::serialize_element(&mut tuple, &self.#column_names1)?;)*
+					__::SerializeTuple::end(tuple)
+				}
+			}
+			#visibility struct #predicate_visitor_name #impl_generics #where_clause_with_parquet_data {
+				marker: __::PhantomData<fn() -> #predicate_name #ty_generics>,
+			}
+			#[automatically_derived]
+			impl #de_impl_generics __::Visitor<'de> for #predicate_visitor_name #ty_generics #where_clause_with_parquet_data {
+				type Value = #predicate_name #ty_generics;
+
+				fn expecting(&self, f: &mut __::fmt::Formatter) -> __::fmt::Result {
+					f.write_str(stringify!(#predicate_name))
+				}
+				fn visit_seq<A>(self, mut seq: A) -> __::Result<Self::Value, A::Error>
+				where
+					A: __::SeqAccess<'de>,
+				{
+					__::Ok(#predicate_name {
+						#(#column_names1: __::SeqAccess::next_element(&mut seq)?.ok_or_else(|| __::SerdeError::invalid_length(#column_indices, &self))?,)*
+					})
+				}
+			}
+			#[automatically_derived]
+			impl #de_impl_generics __::Deserialize_<'de> for #predicate_name #ty_generics #where_clause_with_parquet_data {
+				fn deserialize<D>(deserializer: D) -> __::Result<Self, D::Error>
+				where
+					D: __::Deserializer_<'de>,
+				{
+					__::Deserializer_::deserialize_tuple(deserializer, #num_columns, #predicate_visitor_name { marker: __::PhantomData })
+				}
 			}
 			#[automatically_derived]
 			impl #impl_generics __::Reader for #reader_name #ty_generics #where_clause_with_parquet_data {
@@ -310,41 +458,42 @@ fn impl_struct(
 				#[allow(unused_variables, non_snake_case)]
 				fn read(&mut self, def_level: i16, rep_level: i16) -> __::ParquetResult<Self::Item> {
 					#(
-						let #field_names1 = self.#field_names2.read(def_level, rep_level);
+						let #column_names1 = self.#column_names2.read(def_level, rep_level);
 					)*
-					if #(#field_names1.is_err() ||)* false { // TODO: unlikely
-						#(#field_names1?;)*
+					if #(#column_names1.is_err() ||)* false { // TODO: unlikely
+						#(#column_names1?;)*
 						__::panic!("unreachable")
 					}
 					__::Ok(#name {
-						#(#field_names1: #field_names2.unwrap(),)*
+						#(#column_names1: #column_names2.unwrap(),)*
+						#(#partition_names1: __::Default::default(),)*
 					})
 				}
 				fn advance_columns(&mut self) -> __::ParquetResult<()> {
 					#[allow(unused_mut)]
 					let mut res = __::Ok(());
 					#(
-						res = res.and(self.#field_names1.advance_columns());
+						res = res.and(self.#column_names1.advance_columns());
 					)*
 					res
 				}
 				#[inline]
 				fn has_next(&self) -> bool {
-					#(if true { self.#field_names1.has_next() } else)*
+					#(if true { self.#column_names1.has_next() } else)*
 					{
 						true
 					}
 				}
 				#[inline]
 				fn current_def_level(&self) -> i16 {
-					#(if true { self.#field_names1.current_def_level() } else)*
+					#(if true { self.#column_names1.current_def_level() } else)*
 					{
 						__::panic!("Current definition level: empty group reader")
 					}
 				}
 				#[inline]
 				fn current_rep_level(&self) -> i16 {
-					#(if true { self.#field_names1.current_rep_level() } else)*
+					#(if true { self.#column_names1.current_rep_level() } else)*
 					{
 						__::panic!("Current repetition level: empty group reader")
 					}
@@ -362,7 +511,7 @@ fn impl_struct(
 						let fields = __::Iterator::collect::<__::HashMap<_,_>>(__::Iterator::map(schema.get_fields().iter(), |field|(field.name(),field)));
 						let name = stringify!(#name);
 						let schema_ = #schema_name{
-							#(#field_names1: fields.get(#field_renames1).ok_or_else(|| __::ParquetError::General(__::format!("Struct \"{}\" has field \"{}\" not in the schema", name, #field_renames2))).and_then(|x|<#field_types1 as __::ParquetData>::parse(&**x, predicate.and_then(|predicate| predicate.#field_names2.as_ref()), __::Some(x.get_basic_info().repetition())))?.1,)*
+							#(#column_names1: fields.get(#column_renames1).ok_or_else(|| __::ParquetError::General(__::format!("Struct \"{}\" has field \"{}\" not in the schema", name, #column_renames2))).and_then(|x|<#column_types1 as __::ParquetData>::parse(&**x, predicate.and_then(|predicate| predicate.#column_names2.as_ref()), __::Some(x.get_basic_info().repetition())))?.1,)*
 						};
 						return __::Ok((__::ToOwned::to_owned(schema.name()), schema_))
 					}
@@ -370,11 +519,11 @@ fn impl_struct(
 				}
 				fn reader(schema: &Self::Schema, mut path: &mut __::Vec<__::String>, def_level: i16, rep_level: i16, paths: &mut __::HashMap<__::ColumnPath, __::ColumnReader>, batch_size: usize) -> Self::Reader {
 					#(
-						path.push(__::ToOwned::to_owned(#field_renames1));
-						let #field_names1 = <#field_types1 as __::ParquetData>::reader(&schema.#field_names2, path, def_level, rep_level, paths, batch_size);
+						path.push(__::ToOwned::to_owned(#column_renames1));
+						let #column_names1 = <#column_types1 as __::ParquetData>::reader(&schema.#column_names2, path, def_level, rep_level, paths, batch_size);
 						path.pop().unwrap();
 					)*
-					#reader_name { #(#field_names1,)* }
+					#reader_name { #(#column_names1,)* }
 				}
 			}
 		});
@@ -384,7 +533,7 @@ fn impl_struct(
 	let mut postgres_derives = None;
 	if cfg!(feature = "postgres") {
 		postgres_includes = Some(quote! {
-			pub use #amadeus_path::amadeus_postgres::{Names,read_be_i32,read_value,_internal as postgres,PostgresData};
+			pub use #amadeus_path::amadeus_postgres::{Names,read_be_i32,read_field,_internal as postgres,BytesMut,PostgresData,PostgresError};
 		});
 		postgres_derives = Some(quote! {
 			#[automatically_derived]
@@ -409,11 +558,10 @@ fn impl_struct(
 					}
 				}
 				fn decode(type_: &__::postgres::types::Type, buf: __::Option<&[u8]>) -> __::Result<Self, __::Box<__::Error + __::Sync + __::Send>> {
-					let buf = buf.unwrap();
+					let mut buf = buf.ok_or_else(|| __::Box::new(__::postgres::types::WasNull))?;
 					if type_ != &__::postgres::types::Type::RECORD {
-						__::panic!("{:?} != {:?}", type_, __::postgres::types::Type::RECORD);
+						return __::Err(__::Into::into(__::format!("invalid type: {:?} vs {:?}", type_, __::postgres::types::Type::RECORD)));
 					}
-					let mut buf = buf;
 					let num_fields = __::read_be_i32(&mut buf)?;
 					if num_fields as usize != #num_fields {
 						return __::Err(__::Into::into(__::format!("invalid field count: {} vs {}", num_fields, #num_fields)));
@@ -421,17 +569,62 @@ fn impl_struct(
 
 					__::Ok(Self {
 						#(
-							#field_names1: {
-								let oid = __::read_be_i32(&mut buf)? as u32;
-								__::read_value(&__::postgres::types::Type::from_oid(oid).unwrap_or(__::postgres::types::Type::OPAQUE), &mut buf)?
-							},
+							#field_names1: __::read_field(#field_renames1, &mut buf)?,
 						)*
 					})
 				}
+				fn sql_type(_nullable: bool) -> __::Result<__::String, __::PostgresError> {
+					// Nested structs aren't yet supported. Tracking at https://github.com/constellation-rs/amadeus/issues/63
+					__::Err(__::PostgresError::Unsupported(__::From::from(#name_str)))
+				}
+				fn columns(types: bool) -> __::Result<__::String, __::PostgresError> {
+					let mut columns = __::Vec::<__::String>::new();
+					#(
+						if types {
+							columns.push(__::format!("{} {}", __::Names(__::None, #field_renames1), <#field_types1 as __::PostgresData>::sql_type(false)?));
+						} else {
+							columns.push(__::format!("{}", __::Names(__::None, #field_renames1)));
+						}
+					)*
+					__::Ok(columns.join(", "))
+				}
+				fn encode(&self, _buf: &mut __::BytesMut) -> __::Result<(), __::Box<__::Error + __::Sync + __::Send>> {
+					// Nested structs aren't yet supported. Tracking at https://github.com/constellation-rs/amadeus/issues/63
+					__::Err(__::Box::new(__::PostgresError::Unsupported(__::From::from(#name_str))))
+				}
+				fn encode_row(&self, buf: &mut __::BytesMut) -> __::Result<(), __::Box<__::Error + __::Sync + __::Send>> {
+					buf.extend_from_slice(&(#num_fields as i16).to_be_bytes());
+					#(
+						<#field_types1 as __::PostgresData>::encode(&self.#field_names1, buf)?;
+					)*
+					__::Ok(())
+				}
 			}
 		});
 	}
 
+	let set_partition = quote! {
+		#[allow(unused_variables)]
+		fn set_partition(&mut self, values: &__::PartitionValues) -> __::Result<(), __::PartitionError> {
+			#(
+				let value = values.get(#partition_renames1).ok_or_else(|| __::PartitionError::Missing(__::ToOwned::to_owned(#partition_renames1)))?;
+				self.#partition_names1 = <#partition_types1 as __::PartitionValue>::from_partition(value).ok_or_else(|| __::PartitionError::Invalid(__::ToOwned::to_owned(#partition_renames1), value.map(__::ToOwned::to_owned)))?;
+			)*
+			__::Ok(())
+		}
+	};
+
+	let partition_values = quote! {
+		#[allow(unused_mut)]
+		fn partition_values(&self) -> __::PartitionValues {
+			let mut values = __::Vec::new();
+			#(
+				values.push((__::ToOwned::to_owned(#partition_renames1), <#partition_types1 as __::PartitionValue>::to_partition(&self.#partition_names1)));
+			)*
+			__::PartitionValues::new(values)
+		}
+	};
+
 	let mut serde_includes = None;
 	let mut serde_derives = None;
 	if cfg!(feature = "serde") {
@@ -445,6 +638,7 @@ fn impl_struct(
 			#[serde(bound = "")]
 			#visibility struct #serde_name #impl_generics #where_clause_with_serde_data {
 				#(
+					#serde_skips
 					#[serde(with = "__::serde_data", rename = #field_renames1)]
 					#field_names1: #field_types1,
 				)*
@@ -462,6 +656,8 @@ fn impl_struct(
 					__D: __::Deserializer<'de> {
 					<#serde_name #ty_generics>::deserialize(deserializer)
 				}
+				#set_partition
+				#partition_values
 			}
 		});
 	}
@@ -471,8 +667,8 @@ fn impl_struct(
 			#parquet_includes
 			#postgres_includes
 			#serde_includes
-			pub use #amadeus_path::amadeus_core::util::Wrapper;
-			pub use #amadeus_path::amadeus_types::{AmadeusOrd, Data as CoreData, DowncastFrom, Downcast, DowncastError, Value, Group, SchemaIncomplete, ListVec, __internal::{Serialize as Serialize_, Deserialize as Deserialize_, Serializer as Serializer_, Deserializer as Deserializer_, SerializeTuple, Error as SerdeError, Visitor, SeqAccess}};
+			pub use #amadeus_path::amadeus_core::{file::PartitionValues, util::Wrapper};
+			pub use #amadeus_path::amadeus_types::{AmadeusOrd, Data as CoreData, DowncastFrom, Downcast, DowncastError, Value, Group, SchemaIncomplete, ListVec, PartitionError, PartitionValue, __internal::{Serialize as Serialize_, Deserialize as Deserialize_, Serializer as Serializer_, Deserializer as Deserializer_, SerializeTuple, Error as SerdeError, Visitor, SeqAccess}};
 			pub use #amadeus_path::data::Data;
 			pub use ::std::{borrow::ToOwned, boxed::Box, clone::Clone, collections::HashMap, convert::{From, Into}, cmp::{Ordering, PartialEq}, default::Default, error::Error, fmt::{self, Debug, Write}, format, hash::{Hash, Hasher}, iter::{ExactSizeIterator, IntoIterator, Iterator}, marker::{PhantomData, Send, Sized, Sync}, result::Result::{self, Ok, Err}, string::String, panic, vec, vec::{IntoIter, Vec}, option::Option::{self, Some, None}};
 		}
@@ -499,6 +695,7 @@ fn impl_struct(
 					__len: 0,
 				}
 			}
+			#set_partition
 		}
 		impl #impl_generics __::ListVec<#name #ty_generics> for #vec_name #ty_generics #where_clause_with_core_data {
 			type IntoIter = __::IntoIter<#name #ty_generics>;
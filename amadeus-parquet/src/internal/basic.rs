@@ -18,6 +18,7 @@
 //! Contains Rust mappings for Thrift definition.
 //! Refer to `parquet.thrift` file to see raw definitions.
 
+use serde::{Deserialize, Serialize};
 use std::{fmt, str};
 
 use crate::internal::{errors::ParquetError, format as parquet};
@@ -220,7 +221,7 @@ pub enum Encoding {
 // Mirrors `parquet::CompressionCodec`
 
 /// Supported compression algorithms.
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum Compression {
 	Uncompressed,
 	Snappy,
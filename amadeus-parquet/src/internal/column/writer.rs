@@ -17,14 +17,16 @@
 
 //! Contains column writer API.
 
-use std::{cmp, collections::VecDeque, mem, rc::Rc};
+use std::{
+	cmp::{self, Ordering}, collections::VecDeque, convert::TryInto, mem, rc::Rc
+};
 
 use crate::internal::{
-	basic::{Compression, Encoding, PageType, Type}, column::page::{CompressedPage, Page, PageWriteSpec, PageWriter}, compression::{create_codec, Codec}, data_type::*, encodings::{
+	basic::{ColumnOrder, Compression, Encoding, PageType, SortOrder, Type}, column::page::{CompressedPage, Page, PageWriteSpec, PageWriter}, compression::{create_codec, Codec}, data_type::*, encodings::{
 		encoding::{get_encoder, DictEncoder, Encoder}, levels::{max_buffer_size, LevelEncoder}
 	}, errors::{ParquetError, Result}, file::{
-		metadata::ColumnChunkMetaData, properties::{WriterProperties, WriterPropertiesPtr, WriterVersion}
-	}, schema::types::ColumnDescPtr, util::memory::{ByteBufferPtr, MemTracker}
+		metadata::ColumnChunkMetaData, properties::{WriterProperties, WriterPropertiesPtr, WriterVersion}, statistics::{from_thrift, Statistics}
+	}, format::Statistics as TStatistics, schema::types::ColumnDescPtr, util::memory::{ByteBufferPtr, MemTracker}
 };
 
 /// Column writer for a Parquet type.
@@ -89,6 +91,29 @@ pub fn get_typed_column_writer<T: DataType>(col_writer: ColumnWriter) -> ColumnW
 	}
 }
 
+/// Compares the bytes of two values of `physical_type`, as returned by
+/// [`AsBytes::as_bytes`], in `sort_order`.
+fn compare(physical_type: Type, sort_order: SortOrder, a: &[u8], b: &[u8]) -> Ordering {
+	match (physical_type, sort_order) {
+		(Type::Int32, SortOrder::Signed) => i32::from_ne_bytes(a.try_into().unwrap())
+			.cmp(&i32::from_ne_bytes(b.try_into().unwrap())),
+		(Type::Int32, _) => u32::from_ne_bytes(a.try_into().unwrap())
+			.cmp(&u32::from_ne_bytes(b.try_into().unwrap())),
+		(Type::Int64, SortOrder::Signed) => i64::from_ne_bytes(a.try_into().unwrap())
+			.cmp(&i64::from_ne_bytes(b.try_into().unwrap())),
+		(Type::Int64, _) => u64::from_ne_bytes(a.try_into().unwrap())
+			.cmp(&u64::from_ne_bytes(b.try_into().unwrap())),
+		(Type::Float, _) => f32::from_ne_bytes(a.try_into().unwrap())
+			.partial_cmp(&f32::from_ne_bytes(b.try_into().unwrap()))
+			.unwrap(),
+		(Type::Double, _) => f64::from_ne_bytes(a.try_into().unwrap())
+			.partial_cmp(&f64::from_ne_bytes(b.try_into().unwrap()))
+			.unwrap(),
+		// Booleans and unsigned byte arrays
+		_ => a.cmp(b),
+	}
+}
+
 /// Typed column writer for a primitive column.
 pub struct ColumnWriterImpl<T: DataType> {
 	// Column writer properties
@@ -112,6 +137,13 @@ pub struct ColumnWriterImpl<T: DataType> {
 	total_num_values: u64,
 	dictionary_page_offset: Option<u64>,
 	data_page_offset: Option<u64>,
+	// Column chunk statistics, if enabled
+	statistics_enabled: bool,
+	sort_order: Option<(Type, SortOrder)>,
+	// The bytes of the min and max values, as the type is erased by `get_typed_column_writer`
+	min_value: Option<Vec<u8>>,
+	max_value: Option<Vec<u8>>,
+	num_column_nulls: u64,
 	// Reused buffers
 	def_levels_sink: Vec<i16>,
 	rep_levels_sink: Vec<i16>,
@@ -146,6 +178,9 @@ impl<T: DataType> ColumnWriterImpl<T> {
 		)
 		.unwrap();
 
+		let statistics_enabled = props.statistics_enabled(descr.path());
+		let sort_order = Self::min_max_sort_order(&descr);
+
 		Self {
 			descr,
 			props,
@@ -165,6 +200,11 @@ impl<T: DataType> ColumnWriterImpl<T> {
 			total_num_values: 0,
 			dictionary_page_offset: None,
 			data_page_offset: None,
+			statistics_enabled,
+			sort_order,
+			min_value: None,
+			max_value: None,
+			num_column_nulls: 0,
 			def_levels_sink: vec![],
 			rep_levels_sink: vec![],
 			data_pages: VecDeque::new(),
@@ -333,7 +373,10 @@ impl<T: DataType> ColumnWriterImpl<T> {
 			));
 		}
 
-		// TODO: update page statistics
+		if self.statistics_enabled {
+			self.num_column_nulls += (num_values - values_to_write) as u64;
+			self.update_min_max(&values[0..values_to_write]);
+		}
 
 		self.write_values(&values[0..values_to_write])?;
 
@@ -351,6 +394,59 @@ impl<T: DataType> ColumnWriterImpl<T> {
 		Ok(values_to_write)
 	}
 
+	/// Returns the physical type and sort order to compute min and max values with,
+	/// or `None` if they can't be compared byte-wise or as native values, in which
+	/// case only the null count is kept.
+	fn min_max_sort_order(descr: &ColumnDescPtr) -> Option<(Type, SortOrder)> {
+		let physical_type = descr.physical_type();
+		match (
+			physical_type,
+			ColumnOrder::get_sort_order(descr.logical_type(), physical_type),
+		) {
+			(_, SortOrder::Undefined) | (Type::Int96, _) => None,
+			// Decimals held as bytes are compared as signed big-endian integers
+			(Type::ByteArray, SortOrder::Signed) | (Type::FixedLenByteArray, SortOrder::Signed) => {
+				None
+			}
+			(physical_type, sort_order) => Some((physical_type, sort_order)),
+		}
+	}
+
+	/// Updates the column chunk's min and max values with `values`.
+	fn update_min_max(&mut self, values: &[T::Type]) {
+		let (physical_type, sort_order) = match self.sort_order {
+			Some(sort_order) => sort_order,
+			None => return,
+		};
+		for value in values {
+			let value_bytes = value.as_bytes();
+			// NaNs are ignored, as they're unordered
+			let nan = match physical_type {
+				Type::Float => f32::from_ne_bytes(value_bytes.try_into().unwrap()).is_nan(),
+				Type::Double => f64::from_ne_bytes(value_bytes.try_into().unwrap()).is_nan(),
+				_ => false,
+			};
+			if nan {
+				continue;
+			}
+			for (bound, ordering) in &mut [
+				(&mut self.min_value, Ordering::Less),
+				(&mut self.max_value, Ordering::Greater),
+			] {
+				match bound {
+					Some(bound)
+						if compare(physical_type, sort_order, value_bytes, bound) == *ordering =>
+					{
+						bound.clear();
+						bound.extend_from_slice(value_bytes);
+					}
+					Some(_) => (),
+					None => **bound = Some(value_bytes.to_vec()),
+				}
+			}
+		}
+	}
+
 	#[inline]
 	fn write_definition_levels(&mut self, def_levels: &[i16]) {
 		self.def_levels_sink.extend_from_slice(def_levels);
@@ -590,14 +686,44 @@ impl<T: DataType> ColumnWriterImpl<T> {
 			.set_total_uncompressed_size(total_uncompressed_size)
 			.set_num_values(num_values)
 			.set_data_page_offset(data_page_offset)
-			.set_dictionary_page_offset(dict_page_offset)
-			.build()?;
+			.set_dictionary_page_offset(dict_page_offset);
+		let metadata = match self.column_statistics() {
+			Some(statistics) => metadata.set_statistics(statistics),
+			None => metadata,
+		}
+		.build()?;
 
 		self.page_writer.write_metadata(&metadata)?;
 
 		Ok(metadata)
 	}
 
+	/// Returns the statistics of the column chunk, if enabled. Min and max values
+	/// larger than the maximum statistics size are left out.
+	fn column_statistics(&self) -> Option<Statistics> {
+		if !self.statistics_enabled {
+			return None;
+		}
+		let max_size = self.props.max_statistics_size(self.descr.path());
+		let (min, max) = match (&self.min_value, &self.max_value) {
+			(Some(min), Some(max)) if min.len() <= max_size && max.len() <= max_size => {
+				(Some(min.clone()), Some(max.clone()))
+			}
+			_ => (None, None),
+		};
+		from_thrift(
+			T::get_physical_type(),
+			Some(TStatistics {
+				max: None,
+				min: None,
+				null_count: Some(self.num_column_nulls as i64),
+				distinct_count: None,
+				max_value: max,
+				min_value: min,
+			}),
+		)
+	}
+
 	/// Encodes definition or repetition levels for Data Page v1.
 	#[inline]
 	fn encode_levels_v1(
@@ -1195,6 +1321,28 @@ mod tests {
 		assert_eq!(metadata.dictionary_page_offset(), Some(0));
 	}
 
+	#[test]
+	fn test_column_writer_check_statistics() {
+		let page_writer = get_test_page_writer();
+		let props = Rc::new(WriterProperties::builder().build());
+		let mut writer = get_test_column_writer::<Int32Type>(page_writer, 1, 0, props);
+		let res = writer
+			.write_batch(&[3, -1, 7], Some(&[1, 0, 1, 1, 0]), None)
+			.unwrap();
+		assert_eq!(res, 3);
+
+		let (_, _, metadata) = writer.close().unwrap();
+		let statistics = metadata.statistics().unwrap();
+		assert_eq!(statistics.null_count(), 2);
+		match statistics {
+			Statistics::Int32(stats) => {
+				assert_eq!(*stats.min(), -1);
+				assert_eq!(*stats.max(), 7);
+			}
+			stats => panic!("unexpected statistics: {:?}", stats),
+		}
+	}
+
 	#[test]
 	fn test_column_writer_empty_column_roundtrip() {
 		let props = WriterProperties::builder().build();
@@ -0,0 +1,332 @@
+//! Contains [`Filter`], for skipping row groups using column chunk statistics.
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+use amadeus_types::Value;
+
+use crate::internal::{
+	basic::{ColumnOrder, LogicalType, SortOrder}, errors::{ParquetError, Result}, file::{metadata::ColumnChunkMetaData, statistics::Statistics}, schema::types::SchemaDescriptor
+};
+
+const NANOS_PER_SECOND: i128 = 1_000_000_000;
+
+/// A condition on the values of one column, such as `status == 200`.
+///
+/// Filters let whole row groups be skipped, without fetching or decoding any of their
+/// pages, when the statistics of the column show that none of their rows can satisfy
+/// the condition. Rows of row groups that can't be skipped are all read, so rows that
+/// don't satisfy the condition still need to be filtered out downstream.
+///
+/// The column is named by its dotted path, e.g. `"a.b"` for the field `b` of the group
+/// `a`. The value should be of the type the column is read as; otherwise, or if the
+/// file has no usable statistics for the column, no row groups are skipped.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Filter {
+	column: String,
+	comparison: Comparison,
+	value: Value,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+enum Comparison {
+	Eq,
+	Lt,
+	Le,
+	Gt,
+	Ge,
+}
+
+impl Filter {
+	/// `column == value`
+	pub fn eq(column: impl Into<String>, value: impl Into<Value>) -> Self {
+		Self::new(column.into(), Comparison::Eq, value.into())
+	}
+	/// `column < value`
+	pub fn lt(column: impl Into<String>, value: impl Into<Value>) -> Self {
+		Self::new(column.into(), Comparison::Lt, value.into())
+	}
+	/// `column <= value`
+	pub fn le(column: impl Into<String>, value: impl Into<Value>) -> Self {
+		Self::new(column.into(), Comparison::Le, value.into())
+	}
+	/// `column > value`
+	pub fn gt(column: impl Into<String>, value: impl Into<Value>) -> Self {
+		Self::new(column.into(), Comparison::Gt, value.into())
+	}
+	/// `column >= value`
+	pub fn ge(column: impl Into<String>, value: impl Into<Value>) -> Self {
+		Self::new(column.into(), Comparison::Ge, value.into())
+	}
+	fn new(column: String, comparison: Comparison, value: Value) -> Self {
+		Self {
+			column,
+			comparison,
+			value,
+		}
+	}
+
+	/// Returns the index of the filtered column in `schema`.
+	pub(crate) fn column_index(&self, schema: &SchemaDescriptor) -> Result<usize> {
+		schema
+			.columns()
+			.iter()
+			.position(|column| column.path().string() == self.column)
+			.ok_or_else(|| {
+				ParquetError::General(format!(
+					"Filter has column \"{}\" not in the schema",
+					self.column
+				))
+			})
+	}
+
+	/// Returns `false` if the statistics of `column` show that none of its values
+	/// satisfy the filter, and `true` otherwise.
+	pub(crate) fn may_match(&self, column: &ColumnChunkMetaData, order: ColumnOrder) -> bool {
+		let stats = match column.statistics() {
+			Some(stats) => stats,
+			None => return true,
+		};
+		// Nulls don't satisfy any comparison
+		if column.num_values() > 0 && stats.null_count() == column.num_values() as u64 {
+			return false;
+		}
+		if !stats.has_min_max_set() {
+			return true;
+		}
+		let descr = column.column_descr();
+		let logical_type = descr.logical_type();
+		let sort_order = ColumnOrder::get_sort_order(logical_type, descr.physical_type());
+		// Legacy statistics were computed with a signed comparison whatever the type, so
+		// they're only usable for signed columns
+		let legacy = stats.is_min_max_deprecated() || order == ColumnOrder::Undefined;
+		if sort_order == SortOrder::Undefined || (legacy && sort_order != SortOrder::Signed) {
+			return true;
+		}
+		let (min, max, value) = match (
+			Bound::from_statistics(stats, logical_type, sort_order),
+			Bound::from_value(&self.value),
+		) {
+			(Some((min, max)), Some(value)) => (min, max, value),
+			_ => return true,
+		};
+		let (min, max) = match (value.partial_cmp(&min), value.partial_cmp(&max)) {
+			(Some(min), Some(max)) => (min, max),
+			_ => return true,
+		};
+		match self.comparison {
+			Comparison::Eq => min != Ordering::Less && max != Ordering::Greater,
+			Comparison::Lt => min == Ordering::Greater,
+			Comparison::Le => min != Ordering::Less,
+			Comparison::Gt => max == Ordering::Less,
+			Comparison::Ge => max != Ordering::Greater,
+		}
+	}
+}
+
+/// A statistic or filter value, in a form where the two can be compared. Values of
+/// different variants are incomparable.
+#[derive(Debug)]
+enum Bound {
+	Bool(bool),
+	Int(i128),
+	Float(f64),
+	Bytes(Vec<u8>),
+	/// Days since the Unix epoch
+	Date(i64),
+	/// Nanoseconds since the Unix epoch
+	DateTime(i128),
+}
+
+impl Bound {
+	fn from_statistics(
+		stats: &Statistics, logical_type: LogicalType, sort_order: SortOrder,
+	) -> Option<(Self, Self)> {
+		let unsigned = sort_order == SortOrder::Unsigned;
+		Some(match (stats, logical_type) {
+			(Statistics::Boolean(stats), _) => {
+				(Bound::Bool(*stats.min()), Bound::Bool(*stats.max()))
+			}
+			(Statistics::Int32(stats), LogicalType::Date) => (
+				Bound::Date(i64::from(*stats.min())),
+				Bound::Date(i64::from(*stats.max())),
+			),
+			(Statistics::Int32(_), LogicalType::Decimal)
+			| (Statistics::Int32(_), LogicalType::TimeMillis) => return None,
+			(Statistics::Int32(stats), _) => {
+				let int = |x: i32| {
+					if unsigned {
+						i128::from(x as u32)
+					} else {
+						i128::from(x)
+					}
+				};
+				(Bound::Int(int(*stats.min())), Bound::Int(int(*stats.max())))
+			}
+			(Statistics::Int64(stats), LogicalType::TimestampMillis)
+			| (Statistics::Int64(stats), LogicalType::TimestampMicros) => {
+				let nanos = if logical_type == LogicalType::TimestampMillis {
+					1_000_000
+				} else {
+					1_000
+				};
+				(
+					Bound::DateTime(i128::from(*stats.min()) * nanos),
+					Bound::DateTime(i128::from(*stats.max()) * nanos),
+				)
+			}
+			(Statistics::Int64(_), LogicalType::Decimal)
+			| (Statistics::Int64(_), LogicalType::TimeMicros) => return None,
+			(Statistics::Int64(stats), _) => {
+				let int = |x: i64| {
+					if unsigned {
+						i128::from(x as u64)
+					} else {
+						i128::from(x)
+					}
+				};
+				(Bound::Int(int(*stats.min())), Bound::Int(int(*stats.max())))
+			}
+			(Statistics::Float(stats), _) => (
+				Bound::Float(f64::from(*stats.min())),
+				Bound::Float(f64::from(*stats.max())),
+			),
+			(Statistics::Double(stats), _) => {
+				(Bound::Float(*stats.min()), Bound::Float(*stats.max()))
+			}
+			(Statistics::ByteArray(_), LogicalType::Decimal)
+			| (Statistics::FixedLenByteArray(_), LogicalType::Decimal)
+			| (Statistics::FixedLenByteArray(_), LogicalType::Interval) => return None,
+			(Statistics::ByteArray(stats), _) => (
+				Bound::Bytes(stats.min().data().to_vec()),
+				Bound::Bytes(stats.max().data().to_vec()),
+			),
+			(Statistics::FixedLenByteArray(stats), _) => (
+				Bound::Bytes(stats.min().data().to_vec()),
+				Bound::Bytes(stats.max().data().to_vec()),
+			),
+			(Statistics::Int96(_), _) => return None,
+		})
+	}
+
+	fn from_value(value: &Value) -> Option<Self> {
+		Some(match value {
+			Value::Bool(value) => Bound::Bool(*value),
+			Value::U8(value) => Bound::Int(i128::from(*value)),
+			Value::I8(value) => Bound::Int(i128::from(*value)),
+			Value::U16(value) => Bound::Int(i128::from(*value)),
+			Value::I16(value) => Bound::Int(i128::from(*value)),
+			Value::U32(value) => Bound::Int(i128::from(*value)),
+			Value::I32(value) => Bound::Int(i128::from(*value)),
+			Value::U64(value) => Bound::Int(i128::from(*value)),
+			Value::I64(value) => Bound::Int(i128::from(*value)),
+			Value::F32(value) => Bound::Float(f64::from(*value)),
+			Value::F64(value) => Bound::Float(*value),
+			Value::Date(value) => Bound::Date(value.as_days()),
+			Value::DateWithoutTimezone(value) => Bound::Date(value.as_days()),
+			Value::DateTime(value) => {
+				let value = value.as_chrono()?;
+				Bound::DateTime(
+					i128::from(value.timestamp()) * NANOS_PER_SECOND
+						+ i128::from(value.timestamp_subsec_nanos()),
+				)
+			}
+			Value::DateTimeWithoutTimezone(value) => {
+				let value = value.as_chrono()?;
+				Bound::DateTime(
+					i128::from(value.timestamp()) * NANOS_PER_SECOND
+						+ i128::from(value.timestamp_subsec_nanos()),
+				)
+			}
+			Value::String(value) => Bound::Bytes(value.as_bytes().to_vec()),
+			_ => return None,
+		})
+	}
+}
+
+impl PartialEq for Bound {
+	fn eq(&self, other: &Self) -> bool {
+		self.partial_cmp(other) == Some(Ordering::Equal)
+	}
+}
+impl PartialOrd for Bound {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		match (self, other) {
+			(Bound::Bool(a), Bound::Bool(b)) => a.partial_cmp(b),
+			(Bound::Int(a), Bound::Int(b)) => a.partial_cmp(b),
+			(Bound::Float(a), Bound::Float(b)) => a.partial_cmp(b),
+			(Bound::Bytes(a), Bound::Bytes(b)) => a.partial_cmp(b),
+			(Bound::Date(a), Bound::Date(b)) => a.partial_cmp(b),
+			(Bound::DateTime(a), Bound::DateTime(b)) => a.partial_cmp(b),
+			_ => None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::internal::schema::types::{ColumnDescriptor, ColumnPath, Type as SchemaType};
+	use std::rc::Rc;
+
+	fn column(stats: Statistics, num_values: i64) -> ColumnChunkMetaData {
+		let physical_type = stats.physical_type();
+		let descr = ColumnDescriptor::new(
+			Rc::new(
+				SchemaType::primitive_type_builder("a", physical_type)
+					.build()
+					.unwrap(),
+			),
+			None,
+			0,
+			0,
+			ColumnPath::from("a"),
+		);
+		ColumnChunkMetaData::builder(Rc::new(descr))
+			.set_num_values(num_values)
+			.set_statistics(stats)
+			.build()
+			.unwrap()
+	}
+
+	#[test]
+	fn test_filter_int_range() {
+		let order = ColumnOrder::TypeDefinedOrder(SortOrder::Signed);
+		let column = column(Statistics::int32(Some(10), Some(20), None, 0, false), 100);
+
+		assert!(Filter::eq("a", 10).may_match(&column, order));
+		assert!(Filter::eq("a", 20).may_match(&column, order));
+		assert!(!Filter::eq("a", 9).may_match(&column, order));
+		assert!(!Filter::eq("a", 21).may_match(&column, order));
+		assert!(Filter::lt("a", 11).may_match(&column, order));
+		assert!(!Filter::lt("a", 10).may_match(&column, order));
+		assert!(Filter::le("a", 10).may_match(&column, order));
+		assert!(!Filter::le("a", 9).may_match(&column, order));
+		assert!(Filter::gt("a", 19).may_match(&column, order));
+		assert!(!Filter::gt("a", 20).may_match(&column, order));
+		assert!(Filter::ge("a", 20).may_match(&column, order));
+		assert!(!Filter::ge("a", 21).may_match(&column, order));
+
+		// Values of another type are never used to skip
+		assert!(Filter::eq("a", String::from("x")).may_match(&column, order));
+	}
+
+	#[test]
+	fn test_filter_unusable_statistics() {
+		let order = ColumnOrder::TypeDefinedOrder(SortOrder::Unsigned);
+		let stats = Statistics::byte_array(Some("b".into()), Some("c".into()), None, 0, false);
+		assert!(!Filter::eq("a", String::from("a")).may_match(&column(stats, 100), order));
+
+		// Legacy byte array statistics were compared as signed bytes
+		let stats = Statistics::byte_array(Some("b".into()), Some("c".into()), None, 0, true);
+		assert!(Filter::eq("a", String::from("a")).may_match(&column(stats, 100), order));
+
+		let stats = Statistics::int32(None, None, None, 0, false);
+		assert!(Filter::eq("a", 1).may_match(&column(stats, 100), order));
+
+		// A chunk of only nulls doesn't match anything
+		let stats = Statistics::int32(None, None, None, 100, false);
+		assert!(!Filter::eq("a", 1).may_match(&column(stats, 100), order));
+	}
+}
@@ -420,6 +420,13 @@ impl ColumnChunkMetaData {
 		self.dictionary_page_offset
 	}
 
+	/// Returns the offset and length of the bytes of this column chunk in the file,
+	/// starting from the dictionary page if there is one.
+	pub fn byte_range(&self) -> (u64, u64) {
+		let start = self.dictionary_page_offset.unwrap_or(self.data_page_offset);
+		(start as u64, self.total_compressed_size as u64)
+	}
+
 	/// Returns statistics that are set for this column chunk,
 	/// or `None` if no statistics are available.
 	pub fn statistics(&self) -> Option<&Statistics> {
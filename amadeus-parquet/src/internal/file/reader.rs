@@ -136,6 +136,13 @@ pub trait RowGroupReader {
 	where
 		T: ParquetData,
 		Self: Sized;
+
+	/// Get the indices of the column chunks that [`get_row_iter`](Self::get_row_iter)
+	/// would read with the same projection, without reading anything.
+	fn get_projected_columns<T>(&self, projection: Option<&T::Predicate>) -> Result<Vec<usize>>
+	where
+		T: ParquetData,
+		Self: Sized;
 }
 
 // ----------------------------------------------------------------------
@@ -384,12 +391,8 @@ impl<R: 'static + ParquetReader> RowGroupReader for SerializedRowGroupReader<R>
 	// TODO: fix PARQUET-816
 	fn get_column_page_reader(&self, i: usize) -> Result<Box<dyn PageReader>> {
 		let col = self.metadata.column(i);
-		let mut col_start = col.data_page_offset();
-		if col.has_dictionary_page() {
-			col_start = col.dictionary_page_offset().unwrap();
-		}
-		let col_length = col.compressed_size();
-		let file_chunk = FileSource::new(self.buf.clone(), col_start as u64, col_length as u64);
+		let (col_start, col_length) = col.byte_range();
+		let file_chunk = FileSource::new(self.buf.clone(), col_start, col_length);
 		let page_reader = SerializedPageReader::new(
 			file_chunk,
 			col.num_values(),
@@ -442,6 +445,14 @@ impl<R: 'static + ParquetReader> RowGroupReader for SerializedRowGroupReader<R>
 	{
 		RowIter::from_row_group(projection, self)
 	}
+
+	fn get_projected_columns<T>(&self, projection: Option<&T::Predicate>) -> Result<Vec<usize>>
+	where
+		T: ParquetData,
+		Self: Sized,
+	{
+		RowIter::<SerializedFileReader<Never>, T>::projected_columns(projection, self)
+	}
 }
 
 /// A serialized implementation for Parquet [`PageReader`].
@@ -30,7 +30,7 @@ use crate::internal::{
 		page::{CompressedPage, Page, PageWriteSpec, PageWriter}, writer::{get_column_writer, ColumnWriter}
 	}, errors::{ParquetError, Result}, file::{
 		metadata::*, properties::WriterPropertiesPtr, statistics::to_thrift as statistics_to_thrift, FOOTER_SIZE, PARQUET_MAGIC
-	}, format as parquet, schema::types::{self, SchemaDescPtr, SchemaDescriptor, TypePtr}, util::io::{FileSink, Position}
+	}, format as parquet, schema::types::{self, SchemaDescPtr, SchemaDescriptor, TypePtr}, util::io::{FileSink, ParquetWriter, Position}
 };
 
 // ----------------------------------------------------------------------
@@ -109,8 +109,8 @@ pub trait RowGroupWriter {
 
 /// A serialized implementation for Parquet [`FileWriter`].
 /// See documentation on file writer for more information.
-pub struct SerializedFileWriter {
-	file: File,
+pub struct SerializedFileWriter<W: ParquetWriter = File> {
+	file: W,
 	schema: TypePtr,
 	descr: SchemaDescPtr,
 	props: WriterPropertiesPtr,
@@ -120,9 +120,9 @@ pub struct SerializedFileWriter {
 	is_closed: bool,
 }
 
-impl SerializedFileWriter {
+impl<W: ParquetWriter + 'static> SerializedFileWriter<W> {
 	/// Creates new file writer.
-	pub fn new(mut file: File, schema: TypePtr, properties: WriterPropertiesPtr) -> Result<Self> {
+	pub fn new(mut file: W, schema: TypePtr, properties: WriterPropertiesPtr) -> Result<Self> {
 		Self::start_file(&mut file)?;
 		Ok(Self {
 			file,
@@ -137,7 +137,7 @@ impl SerializedFileWriter {
 	}
 
 	/// Writes magic bytes at the beginning of the file.
-	fn start_file(file: &mut File) -> Result<()> {
+	fn start_file(file: &mut W) -> Result<()> {
 		file.write_all(&PARQUET_MAGIC)?;
 		Ok(())
 	}
@@ -147,6 +147,7 @@ impl SerializedFileWriter {
 		&mut self, mut row_group_writer: Box<dyn RowGroupWriter>,
 	) -> Result<()> {
 		let row_group_metadata = row_group_writer.close()?;
+		self.total_num_rows += row_group_metadata.num_rows() as u64;
 		self.row_groups.push(row_group_metadata);
 		Ok(())
 	}
@@ -165,7 +166,12 @@ impl SerializedFileWriter {
 				.collect(),
 			key_value_metadata: None,
 			created_by: Some(self.props.created_by().to_owned()),
-			column_orders: None,
+			// Statistics are written with the min and max values of each column's type
+			column_orders: Some(
+				(0..self.descr.num_columns())
+					.map(|_| parquet::ColumnOrder::TYPEORDER(parquet::TypeDefinedOrder::new()))
+					.collect(),
+			),
 			encryption_algorithm: None,
 			footer_signing_key_metadata: None,
 		};
@@ -207,7 +213,7 @@ impl SerializedFileWriter {
 	}
 }
 
-impl FileWriter for SerializedFileWriter {
+impl<W: ParquetWriter + 'static> FileWriter for SerializedFileWriter<W> {
 	#[inline]
 	fn next_row_group(&mut self) -> Result<Box<dyn RowGroupWriter>> {
 		self.assert_closed()?;
@@ -239,10 +245,10 @@ impl FileWriter for SerializedFileWriter {
 /// A serialized implementation for Parquet [`RowGroupWriter`].
 /// Coordinates writing of a row group with column writers.
 /// See documentation on row group writer for more information.
-pub struct SerializedRowGroupWriter {
+pub struct SerializedRowGroupWriter<W: ParquetWriter = File> {
 	descr: SchemaDescPtr,
 	props: WriterPropertiesPtr,
-	file: File,
+	file: W,
 	total_rows_written: Option<u64>,
 	total_bytes_written: u64,
 	column_index: usize,
@@ -251,8 +257,8 @@ pub struct SerializedRowGroupWriter {
 	column_chunks: Vec<ColumnChunkMetaDataPtr>,
 }
 
-impl SerializedRowGroupWriter {
-	pub fn new(schema_descr: SchemaDescPtr, properties: WriterPropertiesPtr, file: &File) -> Self {
+impl<W: ParquetWriter + 'static> SerializedRowGroupWriter<W> {
+	pub fn new(schema_descr: SchemaDescPtr, properties: WriterPropertiesPtr, file: &W) -> Self {
 		let num_columns = schema_descr.num_columns();
 		Self {
 			descr: schema_descr,
@@ -317,7 +323,7 @@ impl SerializedRowGroupWriter {
 	}
 }
 
-impl RowGroupWriter for SerializedRowGroupWriter {
+impl<W: ParquetWriter + 'static> RowGroupWriter for SerializedRowGroupWriter<W> {
 	#[inline]
 	fn next_column(&mut self) -> Result<Option<ColumnWriter>> {
 		self.assert_closed()?;
@@ -9,7 +9,7 @@ pub use self::{
 };
 
 #[macro_use]
-mod util;
+pub(crate) mod util;
 pub mod column;
 pub mod compression;
 mod encodings;
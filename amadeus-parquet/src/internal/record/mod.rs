@@ -46,11 +46,14 @@ mod reader;
 mod schemas;
 mod triplet;
 pub mod types;
+mod writer;
 
 use std::{
 	collections::HashMap, fmt::{self, Debug}
 };
 
+use serde::{de::DeserializeOwned, Serialize};
+
 use amadeus_types::Data;
 
 use crate::internal::{
@@ -61,11 +64,14 @@ use crate::internal::{
 pub use display::DisplaySchemaGroup;
 pub use reader::RowIter;
 pub use schemas::RootSchema;
+pub use writer::RowGroupBuffer;
 
 mod predicate {
+	use serde::{Deserialize, Serialize};
+
 	/// This is for forward compatibility when Predicate pushdown and dynamic schemas are
 	/// implemented.
-	#[derive(Clone, Debug)]
+	#[derive(Clone, Debug, Serialize, Deserialize)]
 	pub struct Predicate;
 }
 pub(crate) use self::predicate::Predicate;
@@ -154,7 +160,10 @@ pub trait ParquetData: Data + Sized {
 	// Clone + PartialEq + Debug + 'static
 	type Schema: Schema;
 	type Reader: Reader<Item = Self>;
-	type Predicate;
+	/// Projects the columns to read, e.g. a [`GroupPredicate`](predicates::GroupPredicate)
+	/// to read only some fields of a [`Group`](self::types::Group). `None` reads every
+	/// column of the schema.
+	type Predicate: Clone + Debug + Serialize + DeserializeOwned + Send + 'static;
 
 	/// Parse a [`Type`] into `Self::Schema`, using `repetition` instead of
 	/// `Type::get_basic_info().repetition()`. A `repetition` of `None` denotes a root
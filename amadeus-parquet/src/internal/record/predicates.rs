@@ -1,12 +1,13 @@
 use fxhash::FxBuildHasher;
 use hashlink::LinkedHashMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::HashMap;
 
 use amadeus_types::{Bson, Date, DateTime, Decimal, Enum, Group, Json, List, Time, Value};
 
 use crate::internal::record::ParquetData;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 /// Predicate for [`Group`]s
 pub struct MapPredicate<K, V> {
 	pub(super) key: Option<K>,
@@ -32,8 +33,26 @@ impl GroupPredicate {
 		Self(fields.into_iter().collect())
 	}
 }
+// hashlink doesn't implement serde, so serialize the fields as a sequence of pairs
+impl Serialize for GroupPredicate {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serializer.collect_seq(self.0.iter())
+	}
+}
+impl<'de> Deserialize<'de> for GroupPredicate {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		<Vec<(String, Option<<Value as ParquetData>::Predicate>)>>::deserialize(deserializer)
+			.map(Self::new)
+	}
+}
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 /// Predicate for [`Value`]s
 pub enum ValuePredicate {
 	Bool(Option<<bool as ParquetData>::Predicate>),
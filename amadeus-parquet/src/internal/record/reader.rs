@@ -789,9 +789,31 @@ where
 		})
 	}
 
-	fn get_reader_iter(
-		schema: &<Root<T> as ParquetData>::Schema, row_group_reader: &dyn RowGroupReader,
-	) -> Result<ReaderIter<T>> {
+	/// Returns the indices of the columns of a row group that would be read to read
+	/// it with the given projection. Nothing is read from the row group itself, so
+	/// this can be used to fetch only the column chunks that are needed.
+	pub fn projected_columns(
+		proj: Option<&T::Predicate>, row_group_reader: &dyn RowGroupReader,
+	) -> Result<Vec<usize>> {
+		let file_schema = row_group_reader.metadata().schema_descr_ptr();
+		let file_schema = file_schema.root_schema();
+		let schema = <Root<T> as ParquetData>::parse(file_schema, proj, None)?.1;
+
+		// Column readers don't read until they're advanced, so building the reader
+		// leaves behind exactly the columns it doesn't use
+		let mut paths = Self::get_column_readers(row_group_reader);
+		let _ = <Root<T>>::reader(&schema, &mut Vec::new(), 0, 0, &mut paths, 1);
+		let row_group_metadata = row_group_reader.metadata();
+		Ok((0..row_group_reader.num_columns())
+			.filter(|&col_index| {
+				!paths.contains_key(row_group_metadata.column(col_index).column_path())
+			})
+			.collect())
+	}
+
+	fn get_column_readers(
+		row_group_reader: &dyn RowGroupReader,
+	) -> HashMap<ColumnPath, ColumnReader> {
 		// Prepare lookup table of column path -> original column index
 		// This allows to prune columns and map schema leaf nodes to the column readers
 		let mut paths: HashMap<ColumnPath, ColumnReader> =
@@ -806,6 +828,13 @@ where
 			let x = paths.insert(col_path, col_reader);
 			assert!(x.is_none());
 		}
+		paths
+	}
+
+	fn get_reader_iter(
+		schema: &<Root<T> as ParquetData>::Schema, row_group_reader: &dyn RowGroupReader,
+	) -> Result<ReaderIter<T>> {
+		let mut paths = Self::get_column_readers(row_group_reader);
 
 		// Build reader for the message type, requires definition level 0
 		let mut path = Vec::new();
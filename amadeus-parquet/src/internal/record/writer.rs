@@ -0,0 +1,457 @@
+//! Shreds rows into columns of values and definition/repetition levels, so they can be
+//! written a row group at a time with a
+//! [`SerializedFileWriter`](crate::internal::file::writer::SerializedFileWriter).
+//!
+//! Rows are given as [`Value`]s, as produced by the `From<T> for Value` implementation
+//! of `#[derive(Data)]` types. Groups are matched to the schema positionally.
+
+use amadeus_types::{Group, Value};
+use std::convert::TryFrom;
+
+use crate::internal::{
+	basic::{LogicalType, Repetition, Type as PhysicalType}, column::writer::ColumnWriter, data_type::{ByteArray, Int96}, errors::{ParquetError, Result}, file::writer::RowGroupWriter, schema::types::{SchemaDescriptor, Type, TypePtr}
+};
+
+const JULIAN_DAY_OF_EPOCH: i64 = 2_440_588;
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+const NANOS_PER_DAY: i64 = 86_400 * NANOS_PER_SECOND;
+
+/// Buffers rows as columns, to be written out as a row group with [`flush`](Self::flush).
+pub struct RowGroupBuffer {
+	schema: TypePtr,
+	columns: Vec<ColumnBuffer>,
+	num_rows: usize,
+}
+
+struct ColumnBuffer {
+	values: Values,
+	logical_type: LogicalType,
+	def_levels: Vec<i16>,
+	rep_levels: Vec<i16>,
+	max_def_level: i16,
+	max_rep_level: i16,
+}
+
+enum Values {
+	Bool(Vec<bool>),
+	Int32(Vec<i32>),
+	Int64(Vec<i64>),
+	Int96(Vec<Int96>),
+	Float(Vec<f32>),
+	Double(Vec<f64>),
+	ByteArray(Vec<ByteArray>),
+	FixedLenByteArray(Vec<ByteArray>),
+}
+
+impl Values {
+	fn len(&self) -> usize {
+		match self {
+			Values::Bool(values) => values.len(),
+			Values::Int32(values) => values.len(),
+			Values::Int64(values) => values.len(),
+			Values::Int96(values) => values.len(),
+			Values::Float(values) => values.len(),
+			Values::Double(values) => values.len(),
+			Values::ByteArray(values) | Values::FixedLenByteArray(values) => values.len(),
+		}
+	}
+	fn truncate(&mut self, len: usize) {
+		match self {
+			Values::Bool(values) => values.truncate(len),
+			Values::Int32(values) => values.truncate(len),
+			Values::Int64(values) => values.truncate(len),
+			Values::Int96(values) => values.truncate(len),
+			Values::Float(values) => values.truncate(len),
+			Values::Double(values) => values.truncate(len),
+			Values::ByteArray(values) | Values::FixedLenByteArray(values) => values.truncate(len),
+		}
+	}
+}
+
+impl RowGroupBuffer {
+	pub fn new(schema: TypePtr) -> Self {
+		let descr = SchemaDescriptor::new(schema.clone());
+		let columns = descr
+			.columns()
+			.iter()
+			.map(|column| ColumnBuffer {
+				values: match column.physical_type() {
+					PhysicalType::Boolean => Values::Bool(Vec::new()),
+					PhysicalType::Int32 => Values::Int32(Vec::new()),
+					PhysicalType::Int64 => Values::Int64(Vec::new()),
+					PhysicalType::Int96 => Values::Int96(Vec::new()),
+					PhysicalType::Float => Values::Float(Vec::new()),
+					PhysicalType::Double => Values::Double(Vec::new()),
+					PhysicalType::ByteArray => Values::ByteArray(Vec::new()),
+					PhysicalType::FixedLenByteArray => Values::FixedLenByteArray(Vec::new()),
+				},
+				logical_type: column.logical_type(),
+				def_levels: Vec::new(),
+				rep_levels: Vec::new(),
+				max_def_level: column.max_def_level(),
+				max_rep_level: column.max_rep_level(),
+			})
+			.collect();
+		Self {
+			schema,
+			columns,
+			num_rows: 0,
+		}
+	}
+
+	/// The number of rows buffered since the last flush.
+	pub fn len(&self) -> usize {
+		self.num_rows
+	}
+
+	/// Shreds `row` into the column buffers. On error the buffers are left unchanged.
+	pub fn push(&mut self, row: Value) -> Result<()> {
+		let lens = self
+			.columns
+			.iter()
+			.map(|column| (column.values.len(), column.def_levels.len()))
+			.collect::<Vec<_>>();
+		let mut column = 0;
+		let res = shred_content(
+			&self.schema,
+			row,
+			0,
+			0,
+			0,
+			false,
+			&mut self.columns,
+			&mut column,
+		);
+		if res.is_err() {
+			for (column, (values, levels)) in self.columns.iter_mut().zip(lens) {
+				column.values.truncate(values);
+				column.def_levels.truncate(levels);
+				column.rep_levels.truncate(levels);
+			}
+			return res;
+		}
+		self.num_rows += 1;
+		Ok(())
+	}
+
+	/// Writes the buffered rows as the columns of `row_group`, and clears the buffers.
+	pub fn flush(&mut self, row_group: &mut dyn RowGroupWriter) -> Result<()> {
+		for column in &mut self.columns {
+			let column_writer = row_group
+				.next_column()?
+				.ok_or_else(|| general_err!("Row group has fewer columns than schema"))?;
+			let def_levels = Some(&*column.def_levels).filter(|_| column.max_def_level > 0);
+			let rep_levels = Some(&*column.rep_levels).filter(|_| column.max_rep_level > 0);
+			macro_rules! write {
+				($($values:ident $writer:ident)*) => {
+					match (column_writer, &column.values) {
+						$((ColumnWriter::$writer(mut typed), Values::$values(values)) => {
+							let _ = typed.write_batch(values, def_levels, rep_levels)?;
+							row_group.close_column(ColumnWriter::$writer(typed))?;
+						})*
+						_ => return Err(general_err!("Column type doesn't match schema")),
+					}
+				};
+			}
+			write!(
+				Bool BoolColumnWriter
+				Int32 Int32ColumnWriter
+				Int64 Int64ColumnWriter
+				Int96 Int96ColumnWriter
+				Float FloatColumnWriter
+				Double DoubleColumnWriter
+				ByteArray ByteArrayColumnWriter
+				FixedLenByteArray FixedLenByteArrayColumnWriter
+			);
+			column.values.truncate(0);
+			column.def_levels.clear();
+			column.rep_levels.clear();
+		}
+		self.num_rows = 0;
+		Ok(())
+	}
+}
+
+/// Shreds `value` (or a null, if `None`) for the field `type_`.
+fn shred(
+	type_: &Type, value: Option<Value>, def_level: i16, rep_level: i16, max_rep_level: i16,
+	list_element: bool, columns: &mut [ColumnBuffer], column: &mut usize,
+) -> Result<()> {
+	let value = match value {
+		Some(value) => value,
+		None => return nulls(type_, def_level, rep_level, columns, column),
+	};
+	let repetition = if type_.get_basic_info().has_repetition() {
+		type_.get_basic_info().repetition()
+	} else {
+		Repetition::Required
+	};
+	match repetition {
+		Repetition::Required => shred_content(
+			type_,
+			value,
+			def_level,
+			rep_level,
+			max_rep_level,
+			list_element,
+			columns,
+			column,
+		),
+		Repetition::Optional => match value {
+			Value::Option(None) => nulls(type_, def_level, rep_level, columns, column),
+			Value::Option(Some(value)) => shred_content(
+				type_,
+				value.into(),
+				def_level + 1,
+				rep_level,
+				max_rep_level,
+				list_element,
+				columns,
+				column,
+			),
+			value => shred_content(
+				type_,
+				value,
+				def_level + 1,
+				rep_level,
+				max_rep_level,
+				list_element,
+				columns,
+				column,
+			),
+		},
+		Repetition::Repeated => {
+			let list = match value {
+				Value::List(list) => list,
+				value => {
+					return Err(general_err!(
+						"Expected a list for repeated field {}, got {:?}",
+						type_.name(),
+						value
+					))
+				}
+			};
+			if list.is_empty() {
+				return nulls(type_, def_level, rep_level, columns, column);
+			}
+			let start = *column;
+			for (i, value) in list.into_iter().enumerate() {
+				*column = start;
+				let rep_level = if i == 0 { rep_level } else { max_rep_level + 1 };
+				shred_content(
+					type_,
+					value,
+					def_level + 1,
+					rep_level,
+					max_rep_level + 1,
+					list_element,
+					columns,
+					column,
+				)?;
+			}
+			Ok(())
+		}
+	}
+}
+
+/// Shreds the non-null `value` of the field `type_`.
+fn shred_content(
+	type_: &Type, value: Value, def_level: i16, rep_level: i16, max_rep_level: i16,
+	list_element: bool, columns: &mut [ColumnBuffer], column: &mut usize,
+) -> Result<()> {
+	if type_.is_primitive() {
+		leaf(&mut columns[*column], value, def_level, rep_level)?;
+		*column += 1;
+		return Ok(());
+	}
+	let fields = type_.get_fields();
+	let logical_type = type_.get_basic_info().logical_type();
+	match value {
+		// LIST and MAP annotated groups wrap a single repeated field
+		Value::List(_) if fields.len() == 1 => shred(
+			&fields[0],
+			Some(value),
+			def_level,
+			rep_level,
+			max_rep_level,
+			logical_type == LogicalType::List,
+			columns,
+			column,
+		),
+		Value::Map(map) if fields.len() == 1 => {
+			let entries = map
+				.into_iter()
+				.map(|(key, value)| Value::Group(Group::new(vec![key, value], None)))
+				.collect::<Vec<_>>()
+				.into();
+			shred(
+				&fields[0],
+				Some(Value::List(entries)),
+				def_level,
+				rep_level,
+				max_rep_level,
+				false,
+				columns,
+				column,
+			)
+		}
+		// The repeated group of a 3-level list wraps the element
+		value if list_element && fields.len() == 1 => shred(
+			&fields[0],
+			Some(value),
+			def_level,
+			rep_level,
+			max_rep_level,
+			false,
+			columns,
+			column,
+		),
+		Value::Group(group) => {
+			if group.fields().len() != fields.len() {
+				return Err(general_err!(
+					"Expected {} fields for group {}, got {}",
+					fields.len(),
+					type_.name(),
+					group.fields().len()
+				));
+			}
+			for (field, value) in fields.iter().zip(group.into_fields()) {
+				shred(
+					field,
+					Some(value),
+					def_level,
+					rep_level,
+					max_rep_level,
+					false,
+					columns,
+					column,
+				)?;
+			}
+			Ok(())
+		}
+		value => Err(general_err!(
+			"Expected a group for field {}, got {:?}",
+			type_.name(),
+			value
+		)),
+	}
+}
+
+/// Records a null for each leaf under `type_`.
+fn nulls(
+	type_: &Type, def_level: i16, rep_level: i16, columns: &mut [ColumnBuffer], column: &mut usize,
+) -> Result<()> {
+	if type_.is_primitive() {
+		let column_ = &mut columns[*column];
+		column_.def_levels.push(def_level);
+		column_.rep_levels.push(rep_level);
+		*column += 1;
+		return Ok(());
+	}
+	for field in type_.get_fields() {
+		nulls(field, def_level, rep_level, columns, column)?;
+	}
+	Ok(())
+}
+
+/// Converts `value` to the physical type of `column` and appends it.
+fn leaf(column: &mut ColumnBuffer, value: Value, def_level: i16, rep_level: i16) -> Result<()> {
+	let logical_type = column.logical_type;
+	let invalid = |value: &Value| {
+		general_err!(
+			"Can't write {:?} to a column of logical type {}",
+			value,
+			logical_type
+		)
+	};
+	match (&mut column.values, value) {
+		(Values::Bool(values), Value::Bool(value)) => values.push(value),
+		(Values::Int32(values), Value::U8(value)) => values.push(value.into()),
+		(Values::Int32(values), Value::I8(value)) => values.push(value.into()),
+		(Values::Int32(values), Value::U16(value)) => values.push(value.into()),
+		(Values::Int32(values), Value::I16(value)) => values.push(value.into()),
+		(Values::Int32(values), Value::U32(value)) => values.push(value as i32),
+		(Values::Int32(values), Value::I32(value)) => values.push(value),
+		(Values::Int32(values), Value::Date(value)) => values.push(
+			i32::try_from(value.as_days())
+				.map_err(|_| general_err!("Date out of range: {:?}", value))?,
+		),
+		(Values::Int32(values), Value::Time(value)) => {
+			let time = value.without_timezone();
+			values.push(
+				i32::try_from(
+					time_nanos(time.hour(), time.minute(), time.second(), time.nanosecond())
+						/ 1_000_000,
+				)
+				.unwrap(),
+			)
+		}
+		(Values::Int64(values), Value::U64(value)) => values.push(value as i64),
+		(Values::Int64(values), Value::I64(value)) => values.push(value),
+		(Values::Int64(values), Value::Time(value)) => {
+			let time = value.without_timezone();
+			values.push(
+				time_nanos(time.hour(), time.minute(), time.second(), time.nanosecond()) / 1_000,
+			)
+		}
+		(Values::Int64(values), Value::DateTime(value)) => {
+			let nanos = date_time_nanos(&value)
+				.ok_or_else(|| general_err!("DateTime out of range: {:?}", value))?;
+			values.push(match logical_type {
+				LogicalType::TimestampMillis => nanos.div_euclid(1_000_000),
+				_ => nanos.div_euclid(1_000),
+			})
+		}
+		(Values::Int96(values), Value::DateTime(value)) => {
+			let (date, time) = (value.date(), value.time());
+			let day = date.as_days() + JULIAN_DAY_OF_EPOCH;
+			let nanos = time_nanos(time.hour(), time.minute(), time.second(), time.nanosecond());
+			values.push(Int96::new(
+				nanos as u32,
+				(nanos >> 32) as u32,
+				u32::try_from(day)
+					.map_err(|_| general_err!("DateTime out of range: {:?}", value))?,
+			))
+		}
+		(Values::Float(values), Value::F32(value)) => values.push(value),
+		(Values::Double(values), Value::F64(value)) => values.push(value),
+		(Values::ByteArray(values), value) | (Values::FixedLenByteArray(values), value) => {
+			let bytes: Vec<u8> = match value {
+				Value::String(value) => value.into_bytes(),
+				Value::Json(value) => String::from(value).into_bytes(),
+				Value::Enum(value) => String::from(value).into_bytes(),
+				Value::Bson(value) => value.into(),
+				Value::List(list) => list
+					.into_iter()
+					.map(|value| match value {
+						Value::U8(byte) => Ok(byte),
+						value => Err(invalid(&value)),
+					})
+					.collect::<Result<_>>()?,
+				value => return Err(invalid(&value)),
+			};
+			values.push(bytes.into())
+		}
+		(_, value) => return Err(invalid(&value)),
+	}
+	column.def_levels.push(def_level);
+	column.rep_levels.push(rep_level);
+	Ok(())
+}
+
+fn time_nanos(hour: u8, minute: u8, second: u8, nanosecond: u32) -> i64 {
+	((i64::from(hour) * 60 + i64::from(minute)) * 60 + i64::from(second)) * NANOS_PER_SECOND
+		+ i64::from(nanosecond)
+}
+
+fn date_time_nanos(value: &amadeus_types::DateTime) -> Option<i64> {
+	let (date, time) = (value.date(), value.time());
+	date.as_days()
+		.checked_mul(NANOS_PER_DAY)?
+		.checked_add(time_nanos(
+			time.hour(),
+			time.minute(),
+			time.second(),
+			time.nanosecond(),
+		))
+}
@@ -16,7 +16,7 @@
 // under the License.
 
 use std::{
-	cell::RefCell, cmp, fs::File, io::{self, BufWriter, Cursor, Read, Seek, SeekFrom, Write}, rc::Rc
+	cell::RefCell, cmp, fs::File, io::{self, BufWriter, Cursor, Read, Seek, SeekFrom, Write}, mem, rc::Rc
 };
 
 use crate::internal::file::reader::ParquetReader;
@@ -133,19 +133,162 @@ impl<R: ParquetReader> Position for FileSource<R> {
 	}
 }
 
+/// A [`ParquetReader`] over only those byte ranges of a file that have been fetched.
+/// Clones share the fetched ranges, so they can be replaced while a file reader is
+/// using them, e.g. as it moves from one row group to the next. Reading bytes that
+/// haven't been fetched is an error.
+#[derive(Clone)]
+pub struct SparseReader {
+	ranges: Rc<RefCell<Vec<(u64, Box<[u8]>)>>>,
+	len: u64,
+	pos: u64,
+}
+
+impl SparseReader {
+	/// Creates a reader over a file of `len` bytes, with nothing yet fetched.
+	pub fn new(len: u64) -> Self {
+		Self {
+			ranges: Rc::new(RefCell::new(Vec::new())),
+			len,
+			pos: 0,
+		}
+	}
+	/// Makes the fetched bytes `buf`, which start at `offset` in the file, available
+	/// to read.
+	pub fn insert(&self, offset: u64, buf: Box<[u8]>) {
+		self.ranges.borrow_mut().push((offset, buf));
+	}
+	/// Discards all fetched bytes.
+	pub fn clear(&self) {
+		self.ranges.borrow_mut().clear();
+	}
+}
+
+impl Read for SparseReader {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let ranges = self.ranges.borrow();
+		let pos = self.pos;
+		let (offset, range) = ranges
+			.iter()
+			.find(|(offset, range)| *offset <= pos && pos < offset + range.len() as u64)
+			.ok_or_else(|| {
+				io::Error::new(
+					io::ErrorKind::Other,
+					format!("byte {} of the Parquet file hasn't been fetched", pos),
+				)
+			})?;
+		let range = &range[(pos - offset) as usize..];
+		let read = cmp::min(buf.len(), range.len());
+		buf[..read].copy_from_slice(&range[..read]);
+		self.pos += read as u64;
+		Ok(read)
+	}
+}
+
+impl Seek for SparseReader {
+	fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+		let pos = match pos {
+			SeekFrom::Start(n) => n as i64,
+			SeekFrom::Current(n) => self.pos as i64 + n,
+			SeekFrom::End(n) => self.len as i64 + n,
+		};
+		if pos < 0 {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"invalid seek to a negative position",
+			));
+		}
+		self.pos = pos as u64;
+		Ok(self.pos)
+	}
+}
+
+impl ParquetReader for SparseReader {
+	fn len(&self) -> u64 {
+		self.len
+	}
+}
+
+/// Output that a file writer can write through. Each column writer gets its own handle
+/// via `try_clone`, which must share the underlying stream and position.
+pub trait ParquetWriter: Write + Seek {
+	fn try_clone(&self) -> io::Result<Self>
+	where
+		Self: Sized;
+}
+
+impl ParquetWriter for File {
+	fn try_clone(&self) -> io::Result<Self> {
+		File::try_clone(self)
+	}
+}
+
+/// An in-memory [`ParquetWriter`] whose written bytes can be taken incrementally, so
+/// that a file can be produced piecewise without ever being held in memory in full.
+#[derive(Clone, Default)]
+pub struct SharedBuffer {
+	inner: Rc<RefCell<(Vec<u8>, u64)>>,
+}
+
+impl SharedBuffer {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Removes and returns the bytes written since the last call.
+	pub fn take(&self) -> Vec<u8> {
+		let mut inner = self.inner.borrow_mut();
+		let buf = mem::take(&mut inner.0);
+		inner.1 += buf.len() as u64;
+		buf
+	}
+}
+
+impl Write for SharedBuffer {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.inner.borrow_mut().0.extend_from_slice(buf);
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+impl Seek for SharedBuffer {
+	fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+		let inner = self.inner.borrow();
+		let end = inner.1 + inner.0.len() as u64;
+		match pos {
+			SeekFrom::Current(0) | SeekFrom::End(0) => Ok(end),
+			SeekFrom::Start(n) if n == end => Ok(end),
+			_ => Err(io::Error::new(
+				io::ErrorKind::Other,
+				"SharedBuffer only supports appending",
+			)),
+		}
+	}
+}
+
+impl ParquetWriter for SharedBuffer {
+	fn try_clone(&self) -> io::Result<Self> {
+		Ok(self.clone())
+	}
+}
+
 /// Struct that represents `File` output stream with position tracking.
 /// Used as a sink in file writer.
-pub struct FileSink {
-	buf: BufWriter<File>,
+pub struct FileSink<W: ParquetWriter = File> {
+	buf: BufWriter<W>,
 	// This is not necessarily position in the underlying file,
 	// but rather current position in the sink.
 	pos: u64,
 }
 
-impl FileSink {
+impl<W: ParquetWriter> FileSink<W> {
 	/// Creates new file sink.
 	/// Position is set to whatever position file has.
-	pub fn new(file: &File) -> Self {
+	pub fn new(file: &W) -> Self {
 		let mut owned_file = file.try_clone().unwrap();
 		let pos = owned_file.seek(SeekFrom::Current(0)).unwrap();
 		Self {
@@ -155,7 +298,7 @@ impl FileSink {
 	}
 }
 
-impl Write for FileSink {
+impl<W: ParquetWriter> Write for FileSink<W> {
 	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
 		let num_bytes = self.buf.write(buf)?;
 		self.pos += num_bytes as u64;
@@ -167,7 +310,7 @@ impl Write for FileSink {
 	}
 }
 
-impl Position for FileSink {
+impl<W: ParquetWriter> Position for FileSink<W> {
 	fn pos(&self) -> u64 {
 		self.pos
 	}
@@ -265,6 +408,32 @@ mod tests {
 		assert_eq!(buf, vec![b'P', b'A', b'R', b'1']);
 	}
 
+	#[test]
+	fn test_io_sparse_reader() {
+		let reader = SparseReader::new(16);
+		reader.insert(4, vec![b'P', b'A', b'R', b'1'].into_boxed_slice());
+		reader.insert(12, vec![b'P', b'A', b'R', b'1'].into_boxed_slice());
+
+		let mut src = reader.clone();
+		let mut buf = vec![0; 8];
+		let _ = src.seek(SeekFrom::Start(6)).unwrap();
+		let bytes_read = src.read(&mut buf[..]).unwrap();
+		assert_eq!(bytes_read, 2);
+		assert_eq!(&buf[..2], &[b'R', b'1']);
+
+		// Bytes 8 to 12 weren't fetched
+		assert!(src.read(&mut buf[..]).is_err());
+
+		let _ = src.seek(SeekFrom::End(-4)).unwrap();
+		let bytes_read = src.read(&mut buf[..]).unwrap();
+		assert_eq!(bytes_read, 4);
+		assert_eq!(&buf[..4], &[b'P', b'A', b'R', b'1']);
+
+		reader.clear();
+		let _ = src.seek(SeekFrom::Start(4)).unwrap();
+		assert!(src.read(&mut buf[..]).is_err());
+	}
+
 	#[test]
 	fn test_io_write_with_pos() {
 		let mut file = get_temp_file("file_sink_test", &[b'a', b'b', b'c']);
@@ -45,21 +45,30 @@ mod wrap {
 	use super::internal;
 	use async_trait::async_trait;
 	use educe::Educe;
-	use futures::{pin_mut, stream, AsyncReadExt, FutureExt, StreamExt};
+	use futures::{future, stream, FutureExt, Stream, StreamExt};
 	use internal::{
-		errors::ParquetError as InternalParquetError, file::reader::{FileReader, ParquetReader, SerializedFileReader}
+		errors::ParquetError as InternalParquetError, file::{
+			properties::WriterProperties, reader::{FileReader, RowGroupReader, SerializedFileReader}, writer::{FileWriter, SerializedFileWriter}, FOOTER_SIZE
+		}, record::{types::Root, RowGroupBuffer, Schema}, schema::{parser::parse_message_type, types::TypePtr}, util::io::{SharedBuffer, SparseReader}
 	};
 	use serde::{Deserialize, Serialize};
 	use serde_closure::*;
 	use std::{
-		error, fmt::{self, Debug, Display}, io::Cursor, marker::PhantomData, ops::FnMut
+		cmp, convert::TryInto, error, fmt::{self, Debug, Display}, marker::PhantomData, ops::FnMut, rc::Rc
 	};
 
 	use amadeus_core::{
-		file::{Directory, File, Page, Partition, PathBuf}, into_par_stream::IntoDistributedStream, par_stream::DistributedStream, util::{DistParStream, ResultExpandIter}, Source
+		file::{
+			Create, Directory, File, HiveDirectory, HivePartition, Page, Partition, PartitionValues, PathBuf, WriteMode
+		}, into_par_stream::IntoDistributedStream, par_sink::{Encoder, Write, WriteError}, par_stream::{DistributedStream, Identity}, util::{DistParStream, ResultExpandIter}, Destination, Source
 	};
+	use amadeus_types::{PartitionError, Value};
 
-	pub use internal::record::ParquetData;
+	pub use internal::{
+		basic::Compression, file::filter::Filter, record::{
+			predicates::{GroupPredicate, MapPredicate, ValuePredicate}, ParquetData
+		}
+	};
 
 	#[doc(hidden)]
 	pub mod derive {
@@ -78,6 +87,8 @@ mod wrap {
 		Row: ParquetData,
 	{
 		partitions: Vec<File::Partition>,
+		predicate: Option<Row::Predicate>,
+		filters: Vec<Filter>,
 		marker: PhantomData<fn() -> Row>,
 	}
 	impl<F, Row> Parquet<F, Row>
@@ -85,12 +96,40 @@ mod wrap {
 		F: File,
 		Row: ParquetData + 'static,
 	{
+		/// Reads the columns of `file` that `Row` needs. Only those column chunks are
+		/// fetched, so a struct with a few fields is cheap to read out of a wide table.
 		pub async fn new(file: F) -> Result<Self, <Self as Source>::Error> {
+			Self::new_inner(file, None).await
+		}
+		/// Like [`new`](Self::new), but reads only the columns selected by `predicate`,
+		/// e.g. a [`GroupPredicate`] naming the fields of a
+		/// [`Group`](amadeus_types::Group) to read.
+		pub async fn new_with_projection(
+			file: F, predicate: Row::Predicate,
+		) -> Result<Self, <Self as Source>::Error> {
+			Self::new_inner(file, Some(predicate)).await
+		}
+		async fn new_inner(
+			file: F, predicate: Option<Row::Predicate>,
+		) -> Result<Self, <Self as Source>::Error> {
 			Ok(Self {
 				partitions: file.partitions().await.map_err(ParquetError::File)?,
+				predicate,
+				filters: Vec::new(),
 				marker: PhantomData,
 			})
 		}
+		/// Skips the row groups whose column statistics show that none of their rows
+		/// satisfy `filter`. Multiple filters are combined, so a row group is skipped if
+		/// any of them rules it out.
+		///
+		/// This only prunes whole row groups: the rows that are read still need to be
+		/// filtered.
+		#[must_use]
+		pub fn filter(mut self, filter: Filter) -> Self {
+			self.filters.push(filter);
+			self
+		}
 	}
 	impl<F, Row> Source for Parquet<F, Row>
 	where
@@ -114,37 +153,362 @@ mod wrap {
 		}
 		#[allow(clippy::let_and_return)]
 		fn dist_stream(self) -> Self::DistStream {
+			let (predicate, filters) = (self.predicate, self.filters);
 			self.partitions
 				.into_dist_stream()
-				.flat_map(FnMut!(|partition: F::Partition| async move {
-					Ok(stream::iter(
-						partition
-							.pages()
-							.await
-							.map_err(ParquetError::Partition)?
-							.into_iter(),
-					)
-					.flat_map(|page| {
-						async move {
-							let mut buf = Vec::with_capacity(10 * 1024 * 1024);
-							let reader = Page::reader(page);
-							pin_mut!(reader);
-							let buf = PassError::new(
-								reader.read_to_end(&mut buf).await.map(|_| Cursor::new(buf)),
-							);
-							Ok(stream::iter(
-								SerializedFileReader::new(buf)?.get_row_iter::<Row>(None)?,
-							))
+				.flat_map(FnMut!(move |partition: F::Partition| {
+					let (predicate, filters) = (predicate.clone(), filters.clone());
+					let values = partition.partition_values().cloned();
+					async move {
+						Ok(stream::iter(
+							partition
+								.pages()
+								.await
+								.map_err(ParquetError::Partition)?
+								.into_iter(),
+						)
+						.flat_map(move |page| {
+							read_rows::<_, Row, _, _>(page, predicate.clone(), filters.clone())
+						}))
+					}
+					.map(ResultExpandIter::new)
+					.flatten_stream()
+					.map(move |row: Result<Result<Row, Self::Error>, Self::Error>| {
+						let mut row = row??;
+						if let Some(values) = &values {
+							row.set_partition(values)
+								.map_err(ParquetError::PartitionValues)?;
 						}
-						.map(ResultExpandIter::new)
-						.flatten_stream()
+						Ok(row)
 					})
-					.map(|row: Result<Result<Row, _>, Self::Error>| Ok(row??)))
+				}))
+		}
+	}
+
+	/// The number of bytes fetched from the end of a file in the hope they contain
+	/// the whole footer, saving a second fetch.
+	const FOOTER_PREFETCH: u64 = 64 * 1024;
+
+	/// Streams the rows of the Parquet file `page` one row group at a time. Only the
+	/// footer and the column chunks of the row group being read are fetched, so
+	/// memory use is bounded by row group size rather than file size. Column chunks
+	/// not selected by `predicate`, and row groups ruled out by `filters`, aren't
+	/// fetched at all.
+	fn read_rows<P, Row, A, B>(
+		page: P, predicate: Option<Row::Predicate>, filters: Vec<Filter>,
+	) -> impl Stream<Item = Result<Row, ParquetError<A, B, P::Error>>>
+	where
+		P: Page + 'static,
+		Row: ParquetData + 'static,
+		A: 'static,
+		B: 'static,
+	{
+		async move {
+			let len = page.len().await.map_err(ParquetError::Page)?;
+			let reader = SparseReader::new(len);
+			let tail = cmp::min(len, FOOTER_PREFETCH);
+			let buf = page
+				.read(len - tail, tail as usize)
+				.await
+				.map_err(ParquetError::Page)?;
+			// If the metadata is longer than what was fetched, fetch the rest. Invalid
+			// footers are left for `SerializedFileReader` to report.
+			if tail >= FOOTER_SIZE as u64 {
+				let metadata_len = i32::from_le_bytes(
+					buf[buf.len() - FOOTER_SIZE..buf.len() - 4]
+						.try_into()
+						.unwrap(),
+				);
+				let metadata_start = len as i64 - FOOTER_SIZE as i64 - i64::from(metadata_len);
+				if metadata_len >= 0 && metadata_start >= 0 && (metadata_start as u64) < len - tail
+				{
+					let metadata_start = metadata_start as u64;
+					let rest = page
+						.read(metadata_start, (len - tail - metadata_start) as usize)
+						.await
+						.map_err(ParquetError::Page)?;
+					reader.insert(metadata_start, rest);
+				}
+			}
+			reader.insert(len - tail, buf);
+			let file = Rc::new(SerializedFileReader::new(reader.clone())?);
+			// The metadata has been parsed, so its bytes are no longer needed
+			reader.clear();
+			let metadata = file.metadata();
+			let filters = filters
+				.into_iter()
+				.map(|filter| {
+					let column = filter.column_index(metadata.file_metadata().schema_descr())?;
+					Ok((column, filter))
+				})
+				.collect::<Result<Vec<_>, InternalParquetError>>()?;
+			let row_groups = (0..file.num_row_groups())
+				.filter(|&i| {
+					let row_group = metadata.row_group(i);
+					filters.iter().all(|(column, filter)| {
+						let order = metadata.file_metadata().column_order(*column);
+						filter.may_match(row_group.column(*column), order)
+					})
+				})
+				.collect::<Vec<_>>();
+			Ok(stream::iter(row_groups)
+				.then(move |i| {
+					let chunks = file.get_row_group(i).and_then(|row_group| {
+						let columns = row_group.get_projected_columns::<Row>(predicate.as_ref())?;
+						let row_group = row_group.metadata();
+						Ok(columns
+							.into_iter()
+							.map(|column| {
+								let (offset, len) = row_group.column(column).byte_range();
+								page.read(offset, len as usize)
+									.map(move |buf| buf.map(|buf| (offset, buf)))
+							})
+							.collect::<Vec<_>>())
+					});
+					let (reader, file, predicate) =
+						(reader.clone(), file.clone(), predicate.clone());
+					async move {
+						let chunks = future::try_join_all(chunks?)
+							.await
+							.map_err(ParquetError::Page)?;
+						reader.clear();
+						for (offset, buf) in chunks {
+							reader.insert(offset, buf);
+						}
+						Ok(file.get_row_group(i)?.get_row_iter::<Row>(predicate)?)
+					}
+				})
+				.map(|rows| stream::iter(ResultExpandIter::new(rows)))
+				.flatten()
+				.map(
+					|row: Result<
+						Result<Row, InternalParquetError>,
+						ParquetError<A, B, P::Error>,
+					>| Ok(row??),
+				))
+		}
+		.map(ResultExpandIter::new)
+		.flatten_stream()
+		.map(
+			|row: Result<
+				Result<Row, ParquetError<A, B, P::Error>>,
+				ParquetError<A, B, P::Error>,
+			>| Ok(row??),
+		)
+	}
+
+	/// Writes rows to Parquet files under `target`, one file per worker.
+	///
+	/// The schema is inferred from `Row`, which must be a struct or tuple.
+	#[derive(Educe, Serialize, Deserialize)]
+	#[educe(Clone(bound = "D: Clone"), Debug(bound = "D: Debug"))]
+	#[serde(
+		bound(serialize = "D: Serialize"),
+		bound(deserialize = "D: Deserialize<'de>")
+	)]
+	pub struct ParquetDestination<D, Row> {
+		target: D,
+		row_group_size: usize,
+		compression: Compression,
+		marker: PhantomData<fn() -> Row>,
+	}
+	impl<D, Row> ParquetDestination<D, Row>
+	where
+		D: Create,
+		Row: ParquetData + Into<Value> + 'static,
+	{
+		/// Writes to `target`, failing if it already has files.
+		pub async fn new(target: D) -> Result<Self, <Self as Destination>::Error> {
+			Self::builder(target).build().await
+		}
+		/// Configure row groups, compression and the write mode before writing to `target`.
+		pub fn builder(target: D) -> ParquetDestinationBuilder<D, Row> {
+			ParquetDestinationBuilder {
+				destination: Self {
+					target,
+					row_group_size: 128 * 1024,
+					compression: Compression::Snappy,
+					marker: PhantomData,
+				},
+				mode: WriteMode::default(),
+			}
+		}
+		fn encoder(&self) -> ParquetEncoder<D, Row> {
+			ParquetEncoder {
+				row_group_size: self.row_group_size,
+				compression: self.compression,
+				marker: PhantomData,
+			}
+		}
+	}
+
+	/// Builds a [`ParquetDestination`], e.g.
+	/// `ParquetDestination::builder(dir).mode(WriteMode::Create).row_group_size(1024).build().await`.
+	///
+	/// The write mode is applied to the files already under the target when the
+	/// destination is built, before any rows are written.
+	#[derive(Educe)]
+	#[educe(Clone(bound = "D: Clone"), Debug(bound = "D: Debug"))]
+	#[must_use]
+	pub struct ParquetDestinationBuilder<D, Row> {
+		destination: ParquetDestination<D, Row>,
+		mode: WriteMode,
+	}
+	impl<D, Row> ParquetDestinationBuilder<D, Row>
+	where
+		D: Create,
+		Row: ParquetData + Into<Value> + 'static,
+	{
+		/// What to do with files already under the target. Defaults to
+		/// [`WriteMode::FailIfExists`].
+		pub fn mode(mut self, mode: WriteMode) -> Self {
+			self.mode = mode;
+			self
+		}
+		/// The maximum number of rows in each row group. Defaults to 131072.
+		pub fn row_group_size(mut self, row_group_size: usize) -> Self {
+			assert_ne!(row_group_size, 0);
+			self.destination.row_group_size = row_group_size;
+			self
+		}
+		/// The compression codec for column chunks. Defaults to Snappy.
+		pub fn compression(mut self, compression: Compression) -> Self {
+			self.destination.compression = compression;
+			self
+		}
+		pub async fn build(
+			self,
+		) -> Result<ParquetDestination<D, Row>, <ParquetDestination<D, Row> as Destination>::Error>
+		{
+			let Self { destination, mode } = self;
+			destination
+				.target
+				.prepare(mode)
+				.await
+				.map_err(ParquetError::File)?;
+			Ok(destination)
+		}
+	}
+	impl<D, Row> Destination for ParquetDestination<D, Row>
+	where
+		D: Create,
+		Row: ParquetData + Into<Value> + 'static,
+	{
+		type Item = Row;
+		type Error = ParquetError<D::Error, D::Error, <D::Page as Page>::Error>;
+
+		type ParSink = Write<Identity, D, ParquetEncoder<D, Row>>;
+		type DistSink = Write<Identity, D, ParquetEncoder<D, Row>>;
+
+		fn par_sink(self) -> Self::ParSink {
+			let encoder = self.encoder();
+			Write::new(Identity, self.target, encoder)
+		}
+		fn dist_sink(self) -> Self::DistSink {
+			let encoder = self.encoder();
+			Write::new(Identity, self.target, encoder)
+		}
+	}
+
+	#[doc(hidden)]
+	#[derive(Educe, Serialize, Deserialize)]
+	#[educe(Clone, Debug)]
+	#[serde(bound = "")]
+	pub struct ParquetEncoder<D, Row> {
+		row_group_size: usize,
+		compression: Compression,
+		marker: PhantomData<fn() -> (D, Row)>,
+	}
+	#[doc(hidden)]
+	pub struct ParquetEncoderState {
+		buffer: SharedBuffer,
+		writer: SerializedFileWriter<SharedBuffer>,
+		rows: RowGroupBuffer,
+	}
+	impl ParquetEncoderState {
+		fn flush_row_group(&mut self) -> Result<(), InternalParquetError> {
+			let mut row_group = self.writer.next_row_group()?;
+			self.rows.flush(&mut *row_group)?;
+			self.writer.close_row_group(row_group)
+		}
+	}
+	impl<D, Row> ParquetEncoder<D, Row>
+	where
+		Row: ParquetData + Into<Value>,
+	{
+		fn start(&self) -> Result<ParquetEncoderState, InternalParquetError> {
+			let schema = schema::<Row>()?;
+			let properties = WriterProperties::builder()
+				.set_compression(self.compression)
+				.set_max_row_group_size(self.row_group_size)
+				.build();
+			let buffer = SharedBuffer::new();
+			let writer =
+				SerializedFileWriter::new(buffer.clone(), schema.clone(), Rc::new(properties))?;
+			Ok(ParquetEncoderState {
+				buffer,
+				writer,
+				rows: RowGroupBuffer::new(schema),
+			})
+		}
+	}
+	impl<D, Row> Encoder<Row> for ParquetEncoder<D, Row>
+	where
+		D: Create,
+		Row: ParquetData + Into<Value>,
+	{
+		type State = Option<ParquetEncoderState>;
+		type Error = ParquetError<D::Error, D::Error, <D::Page as Page>::Error>;
+
+		fn extension(&self) -> String {
+			String::from(".parquet")
+		}
+		fn zero(&mut self) -> Self::State {
+			None
+		}
+		fn push(
+			&mut self, state: &mut Self::State, row: Row, buf: &mut Vec<u8>,
+		) -> Result<(), Self::Error> {
+			if state.is_none() {
+				*state = Some(self.start()?);
+			}
+			let state = state.as_mut().unwrap();
+			state.rows.push(row.into())?;
+			if state.rows.len() >= self.row_group_size {
+				state.flush_row_group()?;
+			}
+			buf.extend(state.buffer.take());
+			Ok(())
+		}
+		fn done(&mut self, state: Self::State, buf: &mut Vec<u8>) -> Result<(), Self::Error> {
+			if let Some(mut state) = state {
+				if state.rows.len() != 0 {
+					state.flush_row_group()?;
 				}
-				.map(ResultExpandIter::new)
-				.flatten_stream()
-				.map(|row: Result<Result<Row, Self::Error>, Self::Error>| Ok(row??))))
+				state.writer.close()?;
+				buf.extend(state.buffer.take());
+			}
+			Ok(())
+		}
+	}
+
+	/// Infers the Parquet schema of `Row`, checking that it can be read back as `Row`.
+	fn schema<Row>() -> Result<TypePtr, InternalParquetError>
+	where
+		Row: ParquetData,
+	{
+		struct DisplaySchema<Row>(PhantomData<fn() -> Row>);
+		impl<Row> Display for DisplaySchema<Row>
+		where
+			Row: ParquetData,
+		{
+			fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+				<Row::Schema as Schema>::fmt(None, None, Some("amadeus"), f)
+			}
 		}
+		let schema = parse_message_type(&DisplaySchema::<Row>(PhantomData).to_string())?;
+		let _ = <Root<Row> as ParquetData>::parse(&schema, None, None)?;
+		Ok(Rc::new(schema))
 	}
 
 	// impl<P> ParquetReader for amadeus_core::file::Reader<P>
@@ -156,13 +520,30 @@ mod wrap {
 	// 	}
 	// }
 
+	/// A directory of Parquet files, as written by Spark, Hive and Arrow among others.
+	///
+	/// Hive-style partition columns in its paths, like `year=2018/`, are available to
+	/// fields of the row marked `#[amadeus(partition)]`.
 	#[derive(Serialize, Deserialize)]
 	pub struct ParquetDirectory<D> {
-		directory: D,
+		directory: HiveDirectory<D>,
 	}
 	impl<D> ParquetDirectory<D> {
 		pub fn new(directory: D) -> Self {
-			Self { directory }
+			Self {
+				directory: HiveDirectory::new(directory),
+			}
+		}
+		/// Only reads the partitions whose partition column values satisfy `filter`,
+		/// pruning directories without listing them. See [`HiveDirectory::filter`].
+		#[must_use]
+		pub fn filter<F>(self, filter: F) -> Self
+		where
+			F: Fn(&PartitionValues) -> bool + 'static,
+		{
+			Self {
+				directory: self.directory.filter(filter),
+			}
 		}
 	}
 	#[async_trait(?Send)]
@@ -171,11 +552,16 @@ mod wrap {
 		D: Directory,
 		D::Partition: Debug,
 	{
-		type Partition = D::Partition;
+		type Partition = HivePartition<D::Partition>;
 		type Error = D::Error;
 
 		async fn partitions(self) -> Result<Vec<Self::Partition>, Self::Error> {
-			self.partitions_filter(|_| true).await
+			Ok(self
+				.partitions_filter(|_| true)
+				.await?
+				.into_iter()
+				.map(|(_, partition)| partition)
+				.collect())
 		}
 	}
 	#[async_trait(?Send)]
@@ -186,7 +572,7 @@ mod wrap {
 	{
 		async fn partitions_filter<F>(
 			self, mut f: F,
-		) -> Result<Vec<<Self as File>::Partition>, <Self as File>::Error>
+		) -> Result<Vec<(PathBuf, <Self as File>::Partition)>, <Self as File>::Error>
 		where
 			F: FnMut(&PathBuf) -> bool,
 		{
@@ -275,6 +661,7 @@ mod wrap {
 		Partition(B),
 		Page(C),
 		Parquet(#[serde(with = "misc_serde")] InternalParquetError),
+		PartitionValues(PartitionError),
 	}
 	impl<A, B, C> PartialEq for ParquetError<A, B, C>
 	where
@@ -288,6 +675,7 @@ mod wrap {
 				(Self::Partition(a), Self::Partition(b)) => a == b,
 				(Self::Page(a), Self::Page(b)) => a == b,
 				(Self::Parquet(a), Self::Parquet(b)) => a == b,
+				(Self::PartitionValues(a), Self::PartitionValues(b)) => a == b,
 				_ => false,
 			}
 		}
@@ -311,6 +699,7 @@ mod wrap {
 				Self::Partition(err) => Display::fmt(err, f),
 				Self::Page(err) => Display::fmt(err, f),
 				Self::Parquet(err) => Display::fmt(err, f),
+				Self::PartitionValues(err) => Display::fmt(err, f),
 			}
 		}
 	}
@@ -319,40 +708,11 @@ mod wrap {
 			Self::Parquet(err)
 		}
 	}
-
-	use std::io;
-
-	impl ParquetReader for PassError<Cursor<Vec<u8>>> {
-		fn len(&self) -> u64 {
-			self.0.as_ref().unwrap().get_ref().len() as u64
-		}
-	}
-
-	struct PassError<R>(Result<R, Option<io::Error>>);
-	impl<R> PassError<R> {
-		fn new(r: Result<R, io::Error>) -> Self {
-			Self(r.map_err(Some))
-		}
-	}
-	impl<R> io::Read for PassError<R>
-	where
-		R: io::Read,
-	{
-		fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-			match &mut self.0 {
-				Ok(r) => r.read(buf),
-				Err(r) => Err(r.take().unwrap()),
-			}
-		}
-	}
-	impl<R> io::Seek for PassError<R>
-	where
-		R: io::Seek,
-	{
-		fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
-			match &mut self.0 {
-				Ok(r) => r.seek(pos),
-				Err(r) => Err(r.take().unwrap()),
+	impl<A, B, C> From<WriteError<A, C>> for ParquetError<A, B, C> {
+		fn from(err: WriteError<A, C>) -> Self {
+			match err {
+				WriteError::Create(err) => Self::File(err),
+				WriteError::Page(err) => Self::Page(err),
 			}
 		}
 	}
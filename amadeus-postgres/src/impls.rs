@@ -1,10 +1,11 @@
+use bytes::BytesMut;
 use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
 use postgres::types::{FromSql, Type, WasNull};
 use std::{
 	collections::HashMap, error::Error, fmt::{self, Display}, hash::{BuildHasher, Hash}
 };
 
-use super::{Names, PostgresData};
+use super::{sql_type, unsupported, write_value, Names, PostgresData, PostgresError};
 use amadeus_types::{
 	Bson, Data, Date, DateTime, DateTimeWithoutTimezone, DateWithoutTimezone, Decimal, Enum, Group, IpAddr, Json, List, Time, TimeWithoutTimezone, Timezone, Url, Value, Webpage
 };
@@ -21,10 +22,22 @@ where
 	) -> Result<Self, Box<dyn Error + Sync + Send>> {
 		T::decode(type_, buf).map(Box::new)
 	}
+	fn sql_type(nullable: bool) -> Result<String, PostgresError> {
+		T::sql_type(nullable)
+	}
+	fn columns(types: bool) -> Result<String, PostgresError> {
+		T::columns(types)
+	}
+	fn encode(&self, buf: &mut BytesMut) -> Result<(), Box<dyn Error + Sync + Send>> {
+		(**self).encode(buf)
+	}
+	fn encode_row(&self, buf: &mut BytesMut) -> Result<(), Box<dyn Error + Sync + Send>> {
+		(**self).encode_row(buf)
+	}
 }
 
 macro_rules! forward {
-	($($t:ty : $pt:ty),*) => (
+	($($t:ty : $pt:ty : $type_:ident : $sql:literal),*) => (
 		$(
 			#[allow(clippy::use_self)]
 			impl PostgresData for $t {
@@ -38,23 +51,30 @@ macro_rules! forward {
 					#[allow(trivial_numeric_casts)]
 					<$pt as FromSql>::from_sql(type_, buf.ok_or_else(||Box::new(WasNull))?).map(|x|x as Self)
 				}
+				fn sql_type(nullable: bool) -> Result<String, PostgresError> {
+					Ok(sql_type($sql, nullable))
+				}
+				fn encode(&self, buf: &mut BytesMut) -> Result<(), Box<dyn Error + Sync + Send>> {
+					#[allow(trivial_numeric_casts, clippy::cast_possible_wrap)]
+					write_value(&(self.clone() as $pt), &Type::$type_, buf)
+				}
 			}
 		)*
 	);
 }
 forward!(
-	bool: bool,
-	u8: i8,
-	i8: i8,
-	u16: i16,
-	i16: i16,
-	u32: i32,
-	i32: i32,
-	u64: i64,
-	i64: i64,
-	f32: f32,
-	f64: f64,
-	String: String
+	bool: bool: BOOL: "BOOLEAN",
+	u8: i8: CHAR: "\"char\"",
+	i8: i8: CHAR: "\"char\"",
+	u16: i16: INT2: "SMALLINT",
+	i16: i16: INT2: "SMALLINT",
+	u32: i32: INT4: "INTEGER",
+	i32: i32: INT4: "INTEGER",
+	u64: i64: INT8: "BIGINT",
+	i64: i64: INT8: "BIGINT",
+	f32: f32: FLOAT4: "REAL",
+	f64: f64: FLOAT8: "DOUBLE PRECISION",
+	String: String: TEXT: "TEXT"
 );
 
 impl<T> PostgresData for Option<T>
@@ -70,6 +90,17 @@ where
 			None => Ok(None),
 		}
 	}
+	fn sql_type(_nullable: bool) -> Result<String, PostgresError> {
+		T::sql_type(true)
+	}
+	fn encode(&self, buf: &mut BytesMut) -> Result<(), Box<dyn Error + Sync + Send>> {
+		if let Some(value) = self {
+			value.encode(buf)
+		} else {
+			buf.extend_from_slice(&(-1_i32).to_be_bytes()); // Null
+			Ok(())
+		}
+	}
 }
 
 impl PostgresData for Bson {
@@ -77,7 +108,13 @@ impl PostgresData for Bson {
 		name.unwrap().fmt(f)
 	}
 	fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
-		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
+		Err(Box::new(unsupported::<Self>()))
+	}
+	fn sql_type(_nullable: bool) -> Result<String, PostgresError> {
+		Err(unsupported::<Self>())
+	}
+	fn encode(&self, _buf: &mut BytesMut) -> Result<(), Box<dyn Error + Sync + Send>> {
+		Err(Box::new(unsupported::<Self>()))
 	}
 }
 
@@ -86,7 +123,13 @@ impl PostgresData for Json {
 		name.unwrap().fmt(f)
 	}
 	fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
-		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
+		Err(Box::new(unsupported::<Self>()))
+	}
+	fn sql_type(_nullable: bool) -> Result<String, PostgresError> {
+		Err(unsupported::<Self>())
+	}
+	fn encode(&self, _buf: &mut BytesMut) -> Result<(), Box<dyn Error + Sync + Send>> {
+		Err(Box::new(unsupported::<Self>()))
 	}
 }
 
@@ -95,7 +138,13 @@ impl PostgresData for Enum {
 		name.unwrap().fmt(f)
 	}
 	fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
-		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
+		Err(Box::new(unsupported::<Self>()))
+	}
+	fn sql_type(_nullable: bool) -> Result<String, PostgresError> {
+		Err(unsupported::<Self>())
+	}
+	fn encode(&self, _buf: &mut BytesMut) -> Result<(), Box<dyn Error + Sync + Send>> {
+		Err(Box::new(unsupported::<Self>()))
 	}
 }
 
@@ -104,7 +153,13 @@ impl PostgresData for Url {
 		name.unwrap().fmt(f)
 	}
 	fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
-		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
+		Err(Box::new(unsupported::<Self>()))
+	}
+	fn sql_type(_nullable: bool) -> Result<String, PostgresError> {
+		Err(unsupported::<Self>())
+	}
+	fn encode(&self, _buf: &mut BytesMut) -> Result<(), Box<dyn Error + Sync + Send>> {
+		Err(Box::new(unsupported::<Self>()))
 	}
 }
 
@@ -113,7 +168,13 @@ impl PostgresData for Webpage<'static> {
 		name.unwrap().fmt(f)
 	}
 	fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
-		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
+		Err(Box::new(unsupported::<Self>()))
+	}
+	fn sql_type(_nullable: bool) -> Result<String, PostgresError> {
+		Err(unsupported::<Self>())
+	}
+	fn encode(&self, _buf: &mut BytesMut) -> Result<(), Box<dyn Error + Sync + Send>> {
+		Err(Box::new(unsupported::<Self>()))
 	}
 }
 
@@ -122,7 +183,13 @@ impl PostgresData for IpAddr {
 		name.unwrap().fmt(f)
 	}
 	fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
-		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
+		Err(Box::new(unsupported::<Self>()))
+	}
+	fn sql_type(_nullable: bool) -> Result<String, PostgresError> {
+		Err(unsupported::<Self>())
+	}
+	fn encode(&self, _buf: &mut BytesMut) -> Result<(), Box<dyn Error + Sync + Send>> {
+		Err(Box::new(unsupported::<Self>()))
 	}
 }
 
@@ -131,7 +198,13 @@ impl PostgresData for Decimal {
 		name.unwrap().fmt(f)
 	}
 	fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
-		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
+		Err(Box::new(unsupported::<Self>()))
+	}
+	fn sql_type(_nullable: bool) -> Result<String, PostgresError> {
+		Err(unsupported::<Self>())
+	}
+	fn encode(&self, _buf: &mut BytesMut) -> Result<(), Box<dyn Error + Sync + Send>> {
+		Err(Box::new(unsupported::<Self>()))
 	}
 }
 
@@ -140,7 +213,13 @@ impl PostgresData for Group {
 		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
 	}
 	fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
-		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
+		Err(Box::new(unsupported::<Self>()))
+	}
+	fn sql_type(_nullable: bool) -> Result<String, PostgresError> {
+		Err(unsupported::<Self>())
+	}
+	fn encode(&self, _buf: &mut BytesMut) -> Result<(), Box<dyn Error + Sync + Send>> {
+		Err(Box::new(unsupported::<Self>()))
 	}
 }
 
@@ -153,7 +232,13 @@ where
 	}
 	fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
 		// List<u8> is BYTEA
-		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
+		Err(Box::new(unsupported::<Self>()))
+	}
+	fn sql_type(_nullable: bool) -> Result<String, PostgresError> {
+		Err(unsupported::<Self>())
+	}
+	fn encode(&self, _buf: &mut BytesMut) -> Result<(), Box<dyn Error + Sync + Send>> {
+		Err(Box::new(unsupported::<Self>()))
 	}
 }
 
@@ -167,7 +252,13 @@ where
 		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
 	}
 	fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
-		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
+		Err(Box::new(unsupported::<Self>()))
+	}
+	fn sql_type(_nullable: bool) -> Result<String, PostgresError> {
+		Err(unsupported::<Self>())
+	}
+	fn encode(&self, _buf: &mut BytesMut) -> Result<(), Box<dyn Error + Sync + Send>> {
+		Err(Box::new(unsupported::<Self>()))
 	}
 }
 
@@ -176,7 +267,13 @@ impl PostgresData for Date {
 		name.unwrap().fmt(f)
 	}
 	fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
-		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
+		Err(Box::new(unsupported::<Self>()))
+	}
+	fn sql_type(_nullable: bool) -> Result<String, PostgresError> {
+		Err(unsupported::<Self>())
+	}
+	fn encode(&self, _buf: &mut BytesMut) -> Result<(), Box<dyn Error + Sync + Send>> {
+		Err(Box::new(unsupported::<Self>()))
 	}
 }
 
@@ -191,6 +288,13 @@ impl PostgresData for DateWithoutTimezone {
 		<NaiveDate as FromSql>::from_sql(type_, buf.ok_or_else(|| Box::new(WasNull))?)
 			.map(|date| Self::from_chrono(&date))
 	}
+	fn sql_type(nullable: bool) -> Result<String, PostgresError> {
+		Ok(sql_type("DATE", nullable))
+	}
+	fn encode(&self, buf: &mut BytesMut) -> Result<(), Box<dyn Error + Sync + Send>> {
+		let value = self.as_chrono().ok_or("out of range")?;
+		write_value(&value, &Type::DATE, buf)
+	}
 }
 
 impl PostgresData for Time {
@@ -198,7 +302,13 @@ impl PostgresData for Time {
 		name.unwrap().fmt(f)
 	}
 	fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
-		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
+		Err(Box::new(unsupported::<Self>()))
+	}
+	fn sql_type(_nullable: bool) -> Result<String, PostgresError> {
+		Err(unsupported::<Self>())
+	}
+	fn encode(&self, _buf: &mut BytesMut) -> Result<(), Box<dyn Error + Sync + Send>> {
+		Err(Box::new(unsupported::<Self>()))
 	}
 }
 
@@ -213,6 +323,13 @@ impl PostgresData for TimeWithoutTimezone {
 		<NaiveTime as FromSql>::from_sql(type_, buf.ok_or_else(|| Box::new(WasNull))?)
 			.map(|date| Self::from_chrono(&date))
 	}
+	fn sql_type(nullable: bool) -> Result<String, PostgresError> {
+		Ok(sql_type("TIME", nullable))
+	}
+	fn encode(&self, buf: &mut BytesMut) -> Result<(), Box<dyn Error + Sync + Send>> {
+		let value = self.as_chrono().ok_or("out of range")?;
+		write_value(&value, &Type::TIME, buf)
+	}
 }
 
 impl PostgresData for DateTime {
@@ -229,6 +346,16 @@ impl PostgresData for DateTime {
 		)
 		.map(|date| Self::from_chrono(&date))
 	}
+	fn sql_type(nullable: bool) -> Result<String, PostgresError> {
+		Ok(sql_type("TIMESTAMP WITH TIME ZONE", nullable))
+	}
+	fn encode(&self, buf: &mut BytesMut) -> Result<(), Box<dyn Error + Sync + Send>> {
+		let value = self
+			.as_chrono()
+			.ok_or("out of range")?
+			.with_timezone(&chrono::Utc);
+		write_value(&value, &Type::TIMESTAMPTZ, buf)
+	}
 }
 
 impl PostgresData for DateTimeWithoutTimezone {
@@ -242,6 +369,13 @@ impl PostgresData for DateTimeWithoutTimezone {
 		<NaiveDateTime as FromSql>::from_sql(type_, buf.ok_or_else(|| Box::new(WasNull))?)
 			.map(|date| Self::from_chrono(&date))
 	}
+	fn sql_type(nullable: bool) -> Result<String, PostgresError> {
+		Ok(sql_type("TIMESTAMP", nullable))
+	}
+	fn encode(&self, buf: &mut BytesMut) -> Result<(), Box<dyn Error + Sync + Send>> {
+		let value = self.as_chrono().ok_or("out of range")?;
+		write_value(&value, &Type::TIMESTAMP, buf)
+	}
 }
 
 impl PostgresData for Timezone {
@@ -249,7 +383,13 @@ impl PostgresData for Timezone {
 		name.unwrap().fmt(f)
 	}
 	fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
-		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
+		Err(Box::new(unsupported::<Self>()))
+	}
+	fn sql_type(_nullable: bool) -> Result<String, PostgresError> {
+		Err(unsupported::<Self>())
+	}
+	fn encode(&self, _buf: &mut BytesMut) -> Result<(), Box<dyn Error + Sync + Send>> {
+		Err(Box::new(unsupported::<Self>()))
 	}
 }
 
@@ -258,7 +398,13 @@ impl PostgresData for Value {
 		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
 	}
 	fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
-		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
+		Err(Box::new(unsupported::<Self>()))
+	}
+	fn sql_type(_nullable: bool) -> Result<String, PostgresError> {
+		Err(unsupported::<Self>())
+	}
+	fn encode(&self, _buf: &mut BytesMut) -> Result<(), Box<dyn Error + Sync + Send>> {
+		Err(Box::new(unsupported::<Self>()))
 	}
 }
 
@@ -276,7 +422,13 @@ macro_rules! array {
 			fn decode(
 				_type_: &Type, _buf: Option<&[u8]>,
 			) -> Result<Self, Box<dyn Error + Sync + Send>> {
-				todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
+				Err(Box::new(unsupported::<Self>()))
+			}
+			fn sql_type(_nullable: bool) -> Result<String, PostgresError> {
+				Err(unsupported::<Self>())
+			}
+			fn encode(&self, _buf: &mut BytesMut) -> Result<(), Box<dyn Error + Sync + Send>> {
+				Err(Box::new(unsupported::<Self>()))
 			}
 		}
 	)*};
@@ -291,7 +443,13 @@ macro_rules! tuple {
 				todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
 			}
 			fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
-				todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
+				Err(Box::new(unsupported::<Self>()))
+			}
+			fn sql_type(_nullable: bool) -> Result<String, PostgresError> {
+				Err(unsupported::<Self>())
+			}
+			fn encode(&self, _buf: &mut BytesMut) -> Result<(), Box<dyn Error + Sync + Send>> {
+				Err(Box::new(unsupported::<Self>()))
 			}
 		}
 	);
@@ -39,23 +39,30 @@ mod impls;
 #[doc(hidden)]
 pub use postgres as _internal;
 
+pub use bytes::BytesMut;
+
 use bytes::{Buf, Bytes};
 use educe::Educe;
-use futures::{ready, stream, FutureExt, Stream, StreamExt, TryStreamExt};
+use futures::{future::BoxFuture, ready, stream, FutureExt, Sink as _, Stream, StreamExt};
 use pin_project::pin_project;
-use postgres::{CopyOutStream, Error as InternalPostgresError};
+use postgres::{
+	types::{IsNull, ToSql, Type}, Client, CopyInSink, CopyOutStream, Error as InternalPostgresError
+};
 use serde::{Deserialize, Serialize};
 use serde_closure::FnMutNamed;
 use std::{
-	convert::TryFrom, error, fmt::{self, Debug, Display}, io, io::Cursor, marker::PhantomData, ops::Fn, path::PathBuf, pin::Pin, str, sync::Arc, task::{Context, Poll}, time::Duration
+	any, convert::TryFrom, error, fmt::{self, Debug, Display}, io, io::Cursor, marker::PhantomData, mem, ops::Fn, path::PathBuf, pin::Pin, str, sync::Arc, task::{Context, Poll}, time::Duration
 };
 
 use amadeus_core::{
-	into_par_stream::IntoDistributedStream, par_stream::DistributedStream, util::{DistParStream, IoError}, Source
+	file::WriteMode, into_par_stream::IntoDistributedStream, par_sink::{
+		DistributedSink, Final, FolderSyncReducer, Inter, ParallelSink, Reducer, ReducerProcessSend, ReducerSend, ResultFolder
+	}, par_stream::{DistributedStream, Identity}, pipe::Sink, util::{DistParStream, IoError, ResultExpandIter}, Destination, Source
 };
 
 const MAGIC: &[u8] = b"PGCOPY\n\xff\r\n\0";
 const HEADER_LEN: usize = MAGIC.len() + 4 + 4;
+const BUFFER_SIZE: usize = 1024 * 1024; // `PostgresReducerAsync` buffers this many bytes before sending
 
 pub trait PostgresData
 where
@@ -65,6 +72,28 @@ where
 	fn decode(
 		type_: &::postgres::types::Type, buf: Option<&[u8]>,
 	) -> Result<Self, Box<dyn std::error::Error + Sync + Send>>;
+	/// The SQL type of a column holding this type, e.g. `BIGINT NOT NULL`, or
+	/// [`PostgresError::Unsupported`] if it can't yet be written to Postgres.
+	fn sql_type(nullable: bool) -> Result<String, PostgresError>;
+	/// The comma-separated columns a row of this type is written to, with their SQL
+	/// types if `types` is set. Structs have a column per field, other types a single
+	/// column named `value`.
+	fn columns(types: bool) -> Result<String, PostgresError> {
+		let mut columns = EscapeIdentifier("value").to_string();
+		if types {
+			columns.push(' ');
+			columns.push_str(&Self::sql_type(false)?);
+		}
+		Ok(columns)
+	}
+	/// Encode as the length-prefixed value of a column in the binary `COPY` format.
+	fn encode(&self, buf: &mut BytesMut) -> Result<(), Box<dyn error::Error + Sync + Send>>;
+	/// Encode as a tuple of the binary `COPY` format, with the columns given by
+	/// [`columns`](Self::columns).
+	fn encode_row(&self, buf: &mut BytesMut) -> Result<(), Box<dyn error::Error + Sync + Send>> {
+		buf.extend_from_slice(&1_i16.to_be_bytes());
+		self.encode(buf)
+	}
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -226,15 +255,8 @@ FnMutNamed! {
 		#[allow(clippy::let_and_return)]
 		let ret = async move {
 			let (config, tables): (ConnectParams, Vec<PostgresSelect>) = (config, tables);
-			let (client, connection) = postgres::config::Config::from(config)
-				.connect(postgres::tls::NoTls)
-				.await
-				.expect("Error handling not yet implemented. Tracking at https://github.com/constellation-rs/amadeus/issues/63");
-			let _ = tokio::spawn(async move {
-				let _ = connection.await;
-			});
-			let client = Arc::new(client);
-			stream::iter(tables.into_iter()).flat_map(move |table: PostgresSelect| {
+			let client = Arc::new(connect(config).await?);
+			Ok(stream::iter(tables.into_iter()).flat_map(move |table: PostgresSelect| {
 				let client = client.clone();
 				async move {
 					let table = match table {
@@ -246,22 +268,29 @@ FnMutNamed! {
 						DisplayFmt::new(|f| Row::query(f, None)),
 						table
 					);
-					let stmt = client.prepare(&query).await.expect("Error handling not yet implemented. Tracking at https://github.com/constellation-rs/amadeus/issues/63");
-					let stream = client.copy_out(&stmt).await.expect("Error handling not yet implemented. Tracking at https://github.com/constellation-rs/amadeus/issues/63");
-					BinaryCopyOutStream::new(stream)
-						.map_ok(|row| {
-							Row::decode(
-								&postgres::types::Type::RECORD,
-								row.as_ref().map(AsRef::as_ref),
-							)
-							.expect("Error handling not yet implemented. Tracking at https://github.com/constellation-rs/amadeus/issues/63")
-						})
-						.map_err(Into::into)
+					let stmt = client
+						.prepare(&query)
+						.await
+						.map_err(|err| PostgresError::query(&query, &err))?;
+					let stream = client
+						.copy_out(&stmt)
+						.await
+						.map_err(|err| PostgresError::query(&query, &err))?;
+					Ok(BinaryCopyOutStream::new(stream).enumerate().map(
+						move |(row, value): (usize, Result<Option<Bytes>, PostgresError>)| {
+							Row::decode(&Type::RECORD, value?.as_ref().map(AsRef::as_ref))
+								.map_err(|err| PostgresError::decode(&table, row, &*err))
+						},
+					))
 				}
+				.map(ResultExpandIter::new)
 				.flatten_stream()
-			})
+				.map(|row: Result<Result<Row, PostgresError>, PostgresError>| row?)
+			}))
 		}
-		.flatten_stream();
+		.map(ResultExpandIter::new)
+		.flatten_stream()
+		.map(|row: Result<Result<Row, PostgresError>, PostgresError>| row?);
 		#[cfg(not(nightly))]
 		let ret = ret.boxed();
 		ret
@@ -295,12 +324,346 @@ where
 	}
 }
 
+/// Writes rows to a table with `COPY ... FROM STDIN (FORMAT BINARY)`, over a connection
+/// per worker.
+///
+/// The table is prepared when the destination is built: created if
+/// [`create_table`](PostgresDestinationBuilder::create_table) is set, and then
+/// handled according to [`mode`](PostgresDestinationBuilder::mode).
+#[derive(Educe, Serialize, Deserialize)]
+#[educe(Clone, Debug)]
+#[serde(bound = "")]
+pub struct PostgresDestination<Row> {
+	config: ConnectParams,
+	table: PostgresTable,
+	marker: PhantomData<fn() -> Row>,
+}
+impl<Row> PostgresDestination<Row>
+where
+	Row: PostgresData,
+{
+	/// Writes to the existing table `table`, failing if it already has rows.
+	pub async fn new(config: ConnectParams, table: PostgresTable) -> Result<Self, PostgresError> {
+		Self::builder(config, table).build().await
+	}
+	pub fn builder(config: ConnectParams, table: PostgresTable) -> PostgresDestinationBuilder<Row> {
+		PostgresDestinationBuilder {
+			config,
+			table,
+			mode: WriteMode::default(),
+			create_table: false,
+			marker: PhantomData,
+		}
+	}
+}
+
+/// Configures a [`PostgresDestination`]. Created with [`PostgresDestination::builder`].
+#[derive(Educe)]
+#[educe(Clone, Debug)]
+pub struct PostgresDestinationBuilder<Row> {
+	config: ConnectParams,
+	table: PostgresTable,
+	mode: WriteMode,
+	create_table: bool,
+	marker: PhantomData<fn() -> Row>,
+}
+impl<Row> PostgresDestinationBuilder<Row>
+where
+	Row: PostgresData,
+{
+	/// What to do with rows already in the table: [`WriteMode::Create`] truncates it,
+	/// [`WriteMode::Append`] keeps them, and [`WriteMode::FailIfExists`] fails if
+	/// there are any. Defaults to [`WriteMode::FailIfExists`].
+	#[must_use]
+	pub fn mode(self, mode: WriteMode) -> Self {
+		Self { mode, ..self }
+	}
+	/// Whether to create the table, with a column per field of `Row`, if it doesn't
+	/// exist. Defaults to `false`.
+	#[must_use]
+	pub fn create_table(self, create_table: bool) -> Self {
+		Self {
+			create_table,
+			..self
+		}
+	}
+	pub async fn build(self) -> Result<PostgresDestination<Row>, PostgresError> {
+		let Self {
+			config,
+			table,
+			mode,
+			create_table,
+			..
+		} = self;
+		let client = connect(config.clone()).await?;
+		if create_table {
+			client
+				.batch_execute(&format!(
+					"CREATE TABLE IF NOT EXISTS {} ({})",
+					table,
+					Row::columns(true)?
+				))
+				.await?;
+		}
+		match mode {
+			WriteMode::Create => client.batch_execute(&format!("TRUNCATE {}", table)).await?,
+			WriteMode::Append => (),
+			WriteMode::FailIfExists => {
+				let query = format!("SELECT EXISTS (SELECT FROM {})", table);
+				if client.query_one(query.as_str(), &[]).await?.get(0) {
+					return Err(io::Error::new(
+						io::ErrorKind::AlreadyExists,
+						format!("{} already has rows", table),
+					)
+					.into());
+				}
+			}
+		}
+		Ok(PostgresDestination {
+			config,
+			table,
+			marker: PhantomData,
+		})
+	}
+}
+
+impl<Row> Destination for PostgresDestination<Row>
+where
+	Row: PostgresData,
+{
+	type Item = Row;
+	type Error = PostgresError;
+
+	type ParSink = Self;
+	type DistSink = Self;
+
+	fn par_sink(self) -> Self::ParSink {
+		self
+	}
+	fn dist_sink(self) -> Self::DistSink {
+		self
+	}
+}
+impl<Row> ParallelSink<Row> for PostgresDestination<Row>
+where
+	Row: PostgresData,
+{
+	type Done = Result<(), PostgresError>;
+	type Pipe = Identity;
+	type ReduceA = PostgresReducer<Row>;
+	type ReduceC = FolderSyncReducer<Result<(), PostgresError>, ResultFolder<PostgresError>, Final>;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceC) {
+		(
+			Identity,
+			PostgresReducer::new(self.config, self.table),
+			FolderSyncReducer::new(ResultFolder::new()),
+		)
+	}
+}
+impl<Row> DistributedSink<Row> for PostgresDestination<Row>
+where
+	Row: PostgresData,
+{
+	type Done = Result<(), PostgresError>;
+	type Pipe = Identity;
+	type ReduceA = PostgresReducer<Row>;
+	type ReduceB = FolderSyncReducer<Result<(), PostgresError>, ResultFolder<PostgresError>, Inter>;
+	type ReduceC = FolderSyncReducer<Result<(), PostgresError>, ResultFolder<PostgresError>, Final>;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceB, Self::ReduceC) {
+		(
+			Identity,
+			PostgresReducer::new(self.config, self.table),
+			FolderSyncReducer::new(ResultFolder::new()),
+			FolderSyncReducer::new(ResultFolder::new()),
+		)
+	}
+}
+
+#[doc(hidden)]
+#[derive(Educe, Serialize, Deserialize)]
+#[educe(Clone, Debug)]
+#[serde(bound = "")]
+pub struct PostgresReducer<Row> {
+	config: ConnectParams,
+	table: PostgresTable,
+	marker: PhantomData<fn() -> Row>,
+}
+impl<Row> PostgresReducer<Row> {
+	fn new(config: ConnectParams, table: PostgresTable) -> Self {
+		Self {
+			config,
+			table,
+			marker: PhantomData,
+		}
+	}
+}
+impl<Row> Reducer<Row> for PostgresReducer<Row>
+where
+	Row: PostgresData,
+{
+	type Done = Result<(), PostgresError>;
+	type Async = PostgresReducerAsync<Row>;
+
+	fn into_async(self) -> Self::Async {
+		let mut buf = BytesMut::new();
+		buf.extend_from_slice(MAGIC);
+		buf.extend_from_slice(&0_i32.to_be_bytes()); // Flags
+		buf.extend_from_slice(&0_i32.to_be_bytes()); // Header extension length
+		PostgresReducerAsync {
+			config: self.config,
+			table: self.table,
+			connect: None,
+			sink: None,
+			buf,
+			pushed: false,
+			finished: false,
+			marker: PhantomData,
+		}
+	}
+}
+impl<Row> ReducerProcessSend<Row> for PostgresReducer<Row>
+where
+	Row: PostgresData,
+{
+	type Done = Result<(), PostgresError>;
+}
+impl<Row> ReducerSend<Row> for PostgresReducer<Row>
+where
+	Row: PostgresData,
+{
+	type Done = Result<(), PostgresError>;
+}
+
+type Connect = BoxFuture<'static, Result<(Client, CopyInSink<Bytes>), PostgresError>>;
+
+#[doc(hidden)]
+#[pin_project]
+pub struct PostgresReducerAsync<Row> {
+	config: ConnectParams,
+	table: PostgresTable,
+	connect: Option<Connect>,
+	sink: Option<(Client, Pin<Box<CopyInSink<Bytes>>>)>,
+	buf: BytesMut,
+	pushed: bool,
+	finished: bool,
+	marker: PhantomData<fn() -> Row>,
+}
+impl<Row> Sink<Row> for PostgresReducerAsync<Row>
+where
+	Row: PostgresData,
+{
+	type Done = Result<(), PostgresError>;
+
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context, mut stream: Pin<&mut impl Stream<Item = Row>>,
+	) -> Poll<Self::Done> {
+		let self_ = self.project();
+		loop {
+			if let Some(connect) = self_.connect {
+				let (client, sink) = ready!(connect.as_mut().poll(cx))?;
+				*self_.connect = None;
+				*self_.sink = Some((client, Box::pin(sink)));
+			}
+			if let Some((_, sink)) = self_.sink {
+				if !self_.buf.is_empty() && (*self_.finished || self_.buf.len() >= BUFFER_SIZE) {
+					ready!(sink.as_mut().poll_ready(cx))?;
+					sink.as_mut().start_send(mem::take(self_.buf).freeze())?;
+					continue;
+				}
+				if *self_.finished {
+					ready!(sink.as_mut().poll_close(cx))?;
+					*self_.sink = None;
+					return Poll::Ready(Ok(()));
+				}
+			} else if *self_.finished {
+				// Nothing was written, so no connection was made
+				return Poll::Ready(Ok(()));
+			}
+			if let Some(row) = ready!(stream.as_mut().poll_next(cx)) {
+				row.encode_row(self_.buf)
+					.map_err(|err| match err.downcast::<PostgresError>() {
+						Ok(err) => *err,
+						Err(err) => io::Error::new(io::ErrorKind::InvalidInput, err).into(),
+					})?;
+				if !*self_.pushed {
+					// Connections are made lazily so that idle workers don't open one
+					*self_.pushed = true;
+					let query = format!(
+						"COPY {} ({}) FROM STDIN (FORMAT BINARY)",
+						self_.table,
+						Row::columns(false)?
+					);
+					let config = self_.config.clone();
+					*self_.connect = Some(
+						async move {
+							let client = connect(config).await?;
+							let sink = client.copy_in(query.as_str()).await?;
+							Ok((client, sink))
+						}
+						.boxed(),
+					);
+				}
+			} else {
+				*self_.finished = true;
+				if *self_.pushed {
+					self_.buf.extend_from_slice(&(-1_i16).to_be_bytes()); // Trailer
+				}
+			}
+		}
+	}
+}
+
+async fn connect(config: ConnectParams) -> Result<Client, PostgresError> {
+	let (client, connection) = postgres::config::Config::from(config)
+		.connect(postgres::tls::NoTls)
+		.await
+		.map_err(|err| PostgresError::Connection(err.to_string()))?;
+	drop(tokio::spawn(async move {
+		let _ = connection.await;
+	}));
+	Ok(client)
+}
+
+/// Writes `value` as the length-prefixed value of a column in the binary `COPY` format.
+pub fn write_value<T>(
+	value: &T, type_: &Type, buf: &mut BytesMut,
+) -> Result<(), Box<dyn error::Error + Sync + Send>>
+where
+	T: ToSql,
+{
+	let start = buf.len();
+	buf.extend_from_slice(&0_i32.to_be_bytes());
+	let len = match value.to_sql(type_, buf)? {
+		IsNull::Yes => -1,
+		IsNull::No => i32::try_from(buf.len() - start - 4)?,
+	};
+	buf[start..start + 4].copy_from_slice(&len.to_be_bytes());
+	Ok(())
+}
+
+fn sql_type(name: &str, nullable: bool) -> String {
+	if nullable {
+		name.to_owned()
+	} else {
+		format!("{} NOT NULL", name)
+	}
+}
+
+fn unsupported<T: ?Sized>() -> PostgresError {
+	PostgresError::Unsupported(any::type_name::<T>().to_owned())
+}
+
 /// A stream of rows deserialized from the PostgreSQL binary copy format.
+///
+/// The stream ends after the first error.
 #[pin_project]
 pub struct BinaryCopyOutStream {
 	#[pin]
 	stream: CopyOutStream,
 	header: bool,
+	done: bool,
 }
 
 impl BinaryCopyOutStream {
@@ -309,6 +672,7 @@ impl BinaryCopyOutStream {
 		BinaryCopyOutStream {
 			stream,
 			header: false,
+			done: false,
 		}
 	}
 }
@@ -318,65 +682,86 @@ impl Stream for BinaryCopyOutStream {
 
 	fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
 		let self_ = self.project();
+		if *self_.done {
+			return Poll::Ready(None);
+		}
+		let ret = ready!(poll_row(self_.stream, self_.header, cx));
+		if !matches!(ret, Some(Ok(_))) {
+			*self_.done = true;
+		}
+		Poll::Ready(ret)
+	}
+}
 
-		let chunk = match ready!(self_.stream.poll_next(cx)) {
-			Some(Ok(chunk)) => chunk,
-			Some(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
-			None => {
-				return Poll::Ready(Some(Err(io::Error::new(
-					io::ErrorKind::UnexpectedEof,
-					"connection closed",
-				)
-				.into())))
-			}
-		};
-		let mut chunk = Cursor::new(chunk);
-
-		if !*self_.header {
-			check_remaining(&chunk, HEADER_LEN)?;
-			if &chunk.bytes()[..MAGIC.len()] != MAGIC {
-				return Poll::Ready(Some(Err(io::Error::new(
-					io::ErrorKind::InvalidData,
-					"error parsing response from server: invalid magic value",
-				)
-				.into())));
-			}
-			chunk.advance(MAGIC.len());
+fn poll_row(
+	stream: Pin<&mut CopyOutStream>, header: &mut bool, cx: &mut Context<'_>,
+) -> Poll<Option<Result<Option<Bytes>, PostgresError>>> {
+	let chunk = match ready!(stream.poll_next(cx)) {
+		Some(Ok(chunk)) => chunk,
+		Some(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
+		None => {
+			return Poll::Ready(Some(Err(io::Error::new(
+				io::ErrorKind::UnexpectedEof,
+				"connection closed",
+			)
+			.into())))
+		}
+	};
+	let mut chunk = Cursor::new(chunk);
 
-			let flags = chunk.get_i32();
-			let has_oids = (flags & (1 << 16)) != 0;
+	if !*header {
+		check_remaining(&chunk, HEADER_LEN)?;
+		if &chunk.bytes()[..MAGIC.len()] != MAGIC {
+			return Poll::Ready(Some(Err(invalid_data("invalid magic value"))));
+		}
+		chunk.advance(MAGIC.len());
 
-			let header_extension = chunk.get_u32() as usize;
-			check_remaining(&chunk, header_extension)?;
-			chunk.advance(header_extension);
+		let flags = chunk.get_i32();
+		let has_oids = (flags & (1 << 16)) != 0;
 
-			assert!(!has_oids);
-			*self_.header = true;
-		}
+		let header_extension = chunk.get_u32() as usize;
+		check_remaining(&chunk, header_extension)?;
+		chunk.advance(header_extension);
 
-		check_remaining(&chunk, 2)?;
-		let row_len = chunk.get_i16();
-		if row_len == -1 {
-			return Poll::Ready(None);
+		if has_oids {
+			return Poll::Ready(Some(Err(invalid_data("unexpected OIDs"))));
 		}
+		*header = true;
+	}
 
-		assert_eq!(row_len, 1);
+	check_remaining(&chunk, 2)?;
+	let row_len = chunk.get_i16();
+	if row_len == -1 {
+		return Poll::Ready(None);
+	}
 
-		check_remaining(&chunk, 4)?;
-		let field_len = chunk.get_i32();
-		if field_len == -1 {
-			Poll::Ready(Some(Ok(None)))
-		} else {
-			let field_len = usize::try_from(field_len).unwrap();
-			check_remaining(&chunk, field_len)?;
-			let start = usize::try_from(chunk.position()).unwrap();
-			Poll::Ready(Some(Ok(Some(
-				chunk.into_inner().slice(start..start + field_len),
-			))))
-		}
+	if row_len != 1 {
+		return Poll::Ready(Some(Err(invalid_data("invalid field count"))));
+	}
+
+	check_remaining(&chunk, 4)?;
+	let field_len = chunk.get_i32();
+	if field_len == -1 {
+		Poll::Ready(Some(Ok(None)))
+	} else {
+		let field_len =
+			usize::try_from(field_len).map_err(|_| invalid_data("invalid field length"))?;
+		check_remaining(&chunk, field_len)?;
+		let start = usize::try_from(chunk.position()).unwrap();
+		Poll::Ready(Some(Ok(Some(
+			chunk.into_inner().slice(start..start + field_len),
+		))))
 	}
 }
 
+fn invalid_data(message: &str) -> PostgresError {
+	io::Error::new(
+		io::ErrorKind::InvalidData,
+		format!("error parsing response from server: {}", message),
+	)
+	.into()
+}
+
 fn check_remaining(buf: &Cursor<Bytes>, len: usize) -> Result<(), PostgresError> {
 	if buf.remaining() < len {
 		Err(io::Error::new(
@@ -421,6 +806,22 @@ where
 	T::decode(type_, value)
 }
 
+/// Reads the field `name` of a record, as its type OID followed by its value, wrapping
+/// any error in a [`ColumnError`].
+pub fn read_field<T>(
+	name: &'static str, buf: &mut &[u8],
+) -> Result<T, Box<dyn error::Error + Sync + Send>>
+where
+	T: PostgresData,
+{
+	let read = |buf: &mut &[u8]| {
+		#[allow(clippy::cast_sign_loss)]
+		let oid = read_be_i32(buf)? as u32;
+		read_value(&Type::from_oid(oid).unwrap_or(Type::OPAQUE), buf)
+	};
+	read(buf).map_err(|err| Box::new(ColumnError(name, err)) as _)
+}
+
 // https://www.postgresql.org/docs/11/sql-syntax-lexical.html#SQL-SYNTAX-IDENTIFIERS
 struct EscapeIdentifier<T>(T);
 impl<T: Display> Display for EscapeIdentifier<T> {
@@ -443,16 +844,97 @@ impl<'a> Display for Names<'a> {
 	}
 }
 
+/// An error decoding the column `.0`, which [`PostgresError::Decode`] unwraps to report
+/// the column at fault.
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct ColumnError(pub &'static str, pub Box<dyn error::Error + Sync + Send>);
+impl error::Error for ColumnError {
+	fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+		Some(&*self.1)
+	}
+}
+impl Display for ColumnError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "column {}: {}", self.0, self.1)
+	}
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub enum PostgresError {
 	Io(IoError),
 	Postgres(String),
+	/// Connecting to the server failed.
+	Connection(String),
+	/// The server rejected `query`.
+	Query {
+		query: String,
+		error: String,
+	},
+	/// Row number `row` (counting from 0) of `table` couldn't be decoded. `column` is
+	/// the path of the column at fault, like `invent.price`, if it's known.
+	Decode {
+		table: String,
+		row: usize,
+		column: Option<String>,
+		error: String,
+	},
+	/// Values of this type can't yet be read from or written to Postgres.
+	Unsupported(String),
+}
+impl PostgresError {
+	fn query(query: &str, err: &InternalPostgresError) -> Self {
+		Self::Query {
+			query: query.to_owned(),
+			error: err.to_string(),
+		}
+	}
+	fn decode(table: &str, row: usize, mut err: &(dyn error::Error + 'static)) -> Self {
+		let mut column = Vec::new();
+		while let Some(ColumnError(name, err_)) = err.downcast_ref::<ColumnError>() {
+			column.push(*name);
+			err = &**err_;
+		}
+		Self::Decode {
+			table: table.to_owned(),
+			row,
+			column: if column.is_empty() {
+				None
+			} else {
+				Some(column.join("."))
+			},
+			error: err.to_string(),
+		}
+	}
 }
 impl PartialEq for PostgresError {
 	fn eq(&self, other: &Self) -> bool {
 		match (self, other) {
 			(Self::Io(a), Self::Io(b)) => a.to_string() == b.to_string(),
-			(Self::Postgres(a), Self::Postgres(b)) => a == b,
+			(Self::Postgres(a), Self::Postgres(b))
+			| (Self::Connection(a), Self::Connection(b))
+			| (Self::Unsupported(a), Self::Unsupported(b)) => a == b,
+			(
+				Self::Query { query, error },
+				Self::Query {
+					query: query_,
+					error: error_,
+				},
+			) => query == query_ && error == error_,
+			(
+				Self::Decode {
+					table,
+					row,
+					column,
+					error,
+				},
+				Self::Decode {
+					table: table_,
+					row: row_,
+					column: column_,
+					error: error_,
+				},
+			) => table == table_ && row == row_ && column == column_ && error == error_,
 			_ => false,
 		}
 	}
@@ -463,6 +945,27 @@ impl Display for PostgresError {
 		match self {
 			Self::Io(err) => Display::fmt(err, f),
 			Self::Postgres(err) => Display::fmt(err, f),
+			Self::Connection(err) => write!(f, "error connecting to server: {}", err),
+			Self::Query { query, error } => write!(f, "error running {}: {}", query, error),
+			Self::Decode {
+				table,
+				row,
+				column,
+				error,
+			} => {
+				write!(f, "error decoding row {} of {}", row, table)?;
+				if let Some(column) = column {
+					write!(f, ", column {}", column)?;
+				}
+				write!(f, ": {}", error)
+			}
+			Self::Unsupported(type_) => {
+				write!(
+					f,
+					"reading or writing {} with Postgres isn't yet supported",
+					type_
+				)
+			}
 		}
 	}
 }
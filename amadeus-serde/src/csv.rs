@@ -4,44 +4,21 @@ use futures::{pin_mut, stream, AsyncReadExt, FutureExt, Stream, StreamExt};
 use serde::{Deserialize, Serialize};
 use serde_closure::FnMutNamed;
 use std::{
-	error, fmt::{self, Display}, io::Cursor, marker::PhantomData
+	error, fmt::{self, Debug, Display}, io::{self, Cursor}, marker::PhantomData
 };
 
 use amadeus_core::{
-	file::{File, Page, Partition}, into_par_stream::IntoDistributedStream, par_stream::DistributedStream, util::{DistParStream, ResultExpandIter}, Source
+	file::{Create, File, Page, Partition, WriteMode}, into_par_stream::IntoDistributedStream, par_sink::{Encoder, Write, WriteError}, par_stream::{DistributedStream, Identity}, util::{DistParStream, ResultExpandIter}, Destination, Source
 };
+use amadeus_types::PartitionError;
 
-use super::{SerdeData, SerdeDeserializeGroup};
+use super::{SerdeData, SerdeDeserializeGroup, SerdeSerialize, SharedBuffer};
 
 // #[doc(inline)]
 // pub type Trim = csv::Trim;
 // #[doc(inline)]
 // pub type Terminator = csv::Terminator;
 
-// open (assume exists,
-
-// open: append/overwrite (fail on not existing)
-// create: append/overwrite/fail on existing
-
-// create, fail if exists
-// create, overwrite if exists
-// create, append if exists
-
-// open, fail if not exists
-// open, overwrite if exists
-// open, append if exists
-
-// open fail on exist/fail on not exist/no fail overwrite/append
-
-// fail on exist
-// fail on not exist; overwrite/append
-// no fail; overwrite/append
-
-// open: must exist; append
-// create: overwrite
-
-// trait File
-
 #[derive(Educe)]
 #[educe(Clone, Debug)]
 pub struct Csv<File, Row>
@@ -49,16 +26,7 @@ where
 	File: amadeus_core::file::File,
 	Row: SerdeData,
 {
-	// delimiter: u8,
-	// has_headers: bool,
-	// flexible: bool,
-	// trim: Trim,
-	// terminator: Terminator,
-	// quote: u8,
-	// escape: Option<u8>,
-	// double_quote: bool,
-	// quoting: bool,
-	// comment: Option<u8>,
+	options: CsvOptions,
 	partitions: Vec<File::Partition>,
 	marker: PhantomData<fn() -> Row>,
 }
@@ -68,13 +36,125 @@ where
 	Row: SerdeData,
 {
 	pub async fn new(file: F) -> Result<Self, <Self as Source>::Error> {
-		Ok(Self {
-			partitions: file.partitions().await.map_err(CsvError::File)?,
+		Self::builder(file).build().await
+	}
+	/// Configure the CSV dialect before reading `file`.
+	pub fn builder(file: F) -> CsvBuilder<F, Row> {
+		CsvBuilder {
+			file,
+			options: CsvOptions::default(),
+			marker: PhantomData,
+		}
+	}
+}
+
+/// Builds a [`Csv`] source with a non-default dialect, e.g.
+/// `Csv::builder(file).delimiter(b'\t').has_headers(true).build().await`.
+#[derive(Educe)]
+#[educe(Clone(bound = "F: Clone"), Debug(bound = "F: Debug"))]
+#[must_use]
+pub struct CsvBuilder<F, Row> {
+	file: F,
+	options: CsvOptions,
+	marker: PhantomData<fn() -> Row>,
+}
+impl<F, Row> CsvBuilder<F, Row>
+where
+	F: File,
+	Row: SerdeData,
+{
+	/// Whether the first row of each file is a header. If so, fields are matched
+	/// to columns by name rather than by position. Defaults to `false`.
+	pub fn has_headers(mut self, has_headers: bool) -> Self {
+		self.options.has_headers = has_headers;
+		self
+	}
+	/// The field delimiter. Defaults to `b','`.
+	pub fn delimiter(mut self, delimiter: u8) -> Self {
+		self.options.delimiter = delimiter;
+		self
+	}
+	/// Whether rows may have differing numbers of fields. Defaults to `false`.
+	pub fn flexible(mut self, flexible: bool) -> Self {
+		self.options.flexible = flexible;
+		self
+	}
+	/// The quote character. Defaults to `b'"'`.
+	pub fn quote(mut self, quote: u8) -> Self {
+		self.options.quote = quote;
+		self
+	}
+	/// Whether quotes are recognised at all. Defaults to `true`.
+	pub fn quoting(mut self, quoting: bool) -> Self {
+		self.options.quoting = quoting;
+		self
+	}
+	/// Whether two adjacent quotes in a quoted field are read as one. Defaults to `true`.
+	pub fn double_quote(mut self, double_quote: bool) -> Self {
+		self.options.double_quote = double_quote;
+		self
+	}
+	/// The escape character for quotes in quoted fields, if any. Defaults to `None`.
+	pub fn escape(mut self, escape: Option<u8>) -> Self {
+		self.options.escape = escape;
+		self
+	}
+	/// Lines starting with this character are skipped. Defaults to `None`.
+	pub fn comment(mut self, comment: Option<u8>) -> Self {
+		self.options.comment = comment;
+		self
+	}
+	pub async fn build(self) -> Result<Csv<F, Row>, <Csv<F, Row> as Source>::Error> {
+		Ok(Csv {
+			options: self.options,
+			partitions: self.file.partitions().await.map_err(CsvError::File)?,
 			marker: PhantomData,
 		})
 	}
-	// pub fn open<Row>(files: Vec<PathBuf>) -> Csv<Row> {}
-	// pub fn create<Row>(files: Vec<PathBuf>) -> Csv<Row> {}
+}
+
+#[doc(hidden)]
+#[derive(Copy, Clone, Serialize, Deserialize, Debug)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct CsvOptions {
+	has_headers: bool,
+	delimiter: u8,
+	flexible: bool,
+	quote: u8,
+	quoting: bool,
+	double_quote: bool,
+	escape: Option<u8>,
+	comment: Option<u8>,
+	// trim: Trim,
+	// terminator: Terminator,
+}
+impl Default for CsvOptions {
+	fn default() -> Self {
+		Self {
+			has_headers: false,
+			delimiter: b',',
+			flexible: false,
+			quote: b'"',
+			quoting: true,
+			double_quote: true,
+			escape: None,
+			comment: None,
+		}
+	}
+}
+impl CsvOptions {
+	fn reader<R: io::Read>(&self, reader: R) -> csv::Reader<R> {
+		csv::ReaderBuilder::new()
+			.has_headers(self.has_headers)
+			.delimiter(self.delimiter)
+			.flexible(self.flexible)
+			.quote(self.quote)
+			.quoting(self.quoting)
+			.double_quote(self.double_quote)
+			.escape(self.escape)
+			.comment(self.comment)
+			.from_reader(reader)
+	}
 }
 
 type Error<P, E> = CsvError<E, <P as Partition>::Error, <<P as Partition>::Page as Page>::Error>;
@@ -84,12 +164,14 @@ type Output<P, Row: SerdeData, E> = std::pin::Pin<Box<dyn Stream<Item = Result<R
 type Output<P: Partition, Row: SerdeData, E> = impl Stream<Item = Result<Row, Error<P, E>>>;
 
 FnMutNamed! {
-	pub type Closure<P, Row, E> = |self|partition=> P| -> Output<P, Row, E>
+	pub type Closure<P, Row, E> = |self, options: CsvOptions|partition=> P| -> Output<P, Row, E>
 	where
 		P: Partition,
 		Row: SerdeData,
 		E: 'static
 	{
+		let options = self.options;
+		let values = partition.partition_values().cloned();
 		#[allow(clippy::let_and_return)]
 		let ret = async move {
 				Ok(stream::iter(
@@ -99,7 +181,7 @@ FnMutNamed! {
 						.map_err(CsvError::Partition)?
 						.into_iter(),
 				)
-				.flat_map(|page| {
+				.flat_map(move |page| {
 					async move {
 						let mut buf = Vec::with_capacity(10 * 1024 * 1024);
 						let reader = Page::reader(page);
@@ -109,9 +191,8 @@ FnMutNamed! {
 							.await
 							.map_err(InternalCsvError::from)?;
 						Ok(stream::iter(
-							csv::ReaderBuilder::new()
-								.has_headers(false)
-								.from_reader(Cursor::new(buf))
+							options
+								.reader(Cursor::new(buf))
 								.into_deserialize()
 								.map(|x: Result<SerdeDeserializeGroup<Row>, InternalCsvError>| {
 									Ok(x?.0)
@@ -125,7 +206,13 @@ FnMutNamed! {
 			}
 			.map(ResultExpandIter::new)
 			.flatten_stream()
-			.map(|row: Result<Result<Row, Error<P, E>>, Error<P, E>>| Ok(row??));
+			.map(move |row: Result<Result<Row, Error<P, E>>, Error<P, E>>| {
+				let mut row = row??;
+				if let Some(values) = &values {
+					SerdeData::set_partition(&mut row, values).map_err(CsvError::PartitionValues)?;
+				}
+				Ok(row)
+			});
 		#[cfg(not(nightly))]
 		let ret = ret.boxed_local();
 		ret
@@ -160,24 +247,254 @@ where
 	}
 	#[allow(clippy::let_and_return)]
 	fn dist_stream(self) -> Self::DistStream {
-		self.partitions.into_dist_stream().flat_map(Closure::new())
+		self.partitions
+			.into_dist_stream()
+			.flat_map(Closure::new(self.options))
 	}
 }
 
+/// Writes rows to CSV files under `target`, one file per worker.
+///
+/// The header row, if enabled, is taken from the field names of `Row`.
+#[derive(Educe, Serialize, Deserialize)]
+#[educe(Clone(bound = "D: Clone"), Debug(bound = "D: Debug"))]
+#[serde(
+	bound(serialize = "D: Serialize"),
+	bound(deserialize = "D: Deserialize<'de>")
+)]
+pub struct CsvDestination<D, Row> {
+	target: D,
+	has_headers: bool,
+	delimiter: u8,
+	quote: u8,
+	quote_style: QuoteStyle,
+	double_quote: bool,
+	escape: u8,
+	marker: PhantomData<fn() -> Row>,
+}
+impl<D, Row> CsvDestination<D, Row>
+where
+	D: Create,
+	Row: SerdeData,
+{
+	/// Writes to `target`, failing if it already has files.
+	pub async fn new(target: D) -> Result<Self, <Self as Destination>::Error> {
+		Self::builder(target).build().await
+	}
+	/// Configure the CSV dialect and write mode before writing to `target`.
+	pub fn builder(target: D) -> CsvDestinationBuilder<D, Row> {
+		CsvDestinationBuilder {
+			destination: Self {
+				target,
+				has_headers: true,
+				delimiter: b',',
+				quote: b'"',
+				quote_style: QuoteStyle::Necessary,
+				double_quote: true,
+				escape: b'\\',
+				marker: PhantomData,
+			},
+			mode: WriteMode::default(),
+		}
+	}
+	fn encoder(&self) -> CsvEncoder<D, Row> {
+		CsvEncoder {
+			has_headers: self.has_headers,
+			delimiter: self.delimiter,
+			quote: self.quote,
+			quote_style: self.quote_style,
+			double_quote: self.double_quote,
+			escape: self.escape,
+			marker: PhantomData,
+		}
+	}
+}
+
+/// Builds a [`CsvDestination`], e.g.
+/// `CsvDestination::builder(dir).mode(WriteMode::Create).delimiter(b'\t').build().await`.
+///
+/// The write mode is applied to the files already under the target when the
+/// destination is built, before any rows are written.
+#[derive(Educe)]
+#[educe(Clone(bound = "D: Clone"), Debug(bound = "D: Debug"))]
+#[must_use]
+pub struct CsvDestinationBuilder<D, Row> {
+	destination: CsvDestination<D, Row>,
+	mode: WriteMode,
+}
+impl<D, Row> CsvDestinationBuilder<D, Row>
+where
+	D: Create,
+	Row: SerdeData,
+{
+	/// What to do with files already under the target. Defaults to
+	/// [`WriteMode::FailIfExists`].
+	pub fn mode(mut self, mode: WriteMode) -> Self {
+		self.mode = mode;
+		self
+	}
+	/// Whether to write a header row to each file. Defaults to `true`.
+	pub fn has_headers(mut self, has_headers: bool) -> Self {
+		self.destination.has_headers = has_headers;
+		self
+	}
+	/// The field delimiter. Defaults to `b','`.
+	pub fn delimiter(mut self, delimiter: u8) -> Self {
+		self.destination.delimiter = delimiter;
+		self
+	}
+	/// The quote character. Defaults to `b'"'`.
+	pub fn quote(mut self, quote: u8) -> Self {
+		self.destination.quote = quote;
+		self
+	}
+	/// When to quote fields. Defaults to [`QuoteStyle::Necessary`].
+	pub fn quote_style(mut self, quote_style: QuoteStyle) -> Self {
+		self.destination.quote_style = quote_style;
+		self
+	}
+	/// Whether quotes in fields are escaped by doubling them, rather than with
+	/// the escape character. Defaults to `true`.
+	pub fn double_quote(mut self, double_quote: bool) -> Self {
+		self.destination.double_quote = double_quote;
+		self
+	}
+	/// The escape character, used when `double_quote` is disabled. Defaults to `b'\\'`.
+	pub fn escape(mut self, escape: u8) -> Self {
+		self.destination.escape = escape;
+		self
+	}
+	pub async fn build(
+		self,
+	) -> Result<CsvDestination<D, Row>, <CsvDestination<D, Row> as Destination>::Error> {
+		let Self { destination, mode } = self;
+		destination
+			.target
+			.prepare(mode)
+			.await
+			.map_err(CsvError::File)?;
+		Ok(destination)
+	}
+}
+impl<D, Row> Destination for CsvDestination<D, Row>
+where
+	D: Create,
+	Row: SerdeData,
+{
+	type Item = Row;
+	type Error = CsvError<D::Error, D::Error, <D::Page as Page>::Error>;
+
+	type ParSink = Write<Identity, D, CsvEncoder<D, Row>>;
+	type DistSink = Write<Identity, D, CsvEncoder<D, Row>>;
+
+	fn par_sink(self) -> Self::ParSink {
+		let encoder = self.encoder();
+		Write::new(Identity, self.target, encoder)
+	}
+	fn dist_sink(self) -> Self::DistSink {
+		let encoder = self.encoder();
+		Write::new(Identity, self.target, encoder)
+	}
+}
+
+/// When [`CsvDestination`] quotes fields.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub enum QuoteStyle {
+	/// Quote every field.
+	Always,
+	/// Quote fields only when they contain a delimiter, quote or line terminator.
+	Necessary,
+	/// Quote every field that isn't numeric.
+	NonNumeric,
+	/// Never quote fields, even if that produces invalid CSV.
+	Never,
+}
+impl From<QuoteStyle> for csv::QuoteStyle {
+	fn from(quote_style: QuoteStyle) -> Self {
+		match quote_style {
+			QuoteStyle::Always => Self::Always,
+			QuoteStyle::Necessary => Self::Necessary,
+			QuoteStyle::NonNumeric => Self::NonNumeric,
+			QuoteStyle::Never => Self::Never,
+		}
+	}
+}
+
+#[doc(hidden)]
+#[derive(Educe, Serialize, Deserialize)]
+#[educe(Clone, Debug)]
+#[serde(bound = "")]
+pub struct CsvEncoder<D, Row> {
+	has_headers: bool,
+	delimiter: u8,
+	quote: u8,
+	quote_style: QuoteStyle,
+	double_quote: bool,
+	escape: u8,
+	marker: PhantomData<fn() -> (D, Row)>,
+}
+#[doc(hidden)]
+pub struct CsvEncoderState {
+	buffer: SharedBuffer,
+	writer: csv::Writer<SharedBuffer>,
+}
+impl<D, Row> Encoder<Row> for CsvEncoder<D, Row>
+where
+	D: Create,
+	Row: SerdeData,
+{
+	type State = CsvEncoderState;
+	type Error = CsvError<D::Error, D::Error, <D::Page as Page>::Error>;
+
+	fn extension(&self) -> String {
+		String::from(".csv")
+	}
+	fn zero(&mut self) -> Self::State {
+		let buffer = SharedBuffer::default();
+		let writer = csv::WriterBuilder::new()
+			.has_headers(self.has_headers)
+			.delimiter(self.delimiter)
+			.quote(self.quote)
+			.quote_style(self.quote_style.into())
+			.double_quote(self.double_quote)
+			.escape(self.escape)
+			.from_writer(buffer.clone());
+		CsvEncoderState { buffer, writer }
+	}
+	fn push(
+		&mut self, state: &mut Self::State, row: Row, buf: &mut Vec<u8>,
+	) -> Result<(), Self::Error> {
+		state.writer.serialize(SerdeSerialize(&row))?;
+		state.buffer.drain_into(buf);
+		Ok(())
+	}
+	fn done(&mut self, mut state: Self::State, buf: &mut Vec<u8>) -> Result<(), Self::Error> {
+		state.writer.flush().map_err(InternalCsvError::from)?;
+		state.buffer.drain_into(buf);
+		Ok(())
+	}
+}
+
+/// Sends a CSV error as its message, so that it can be returned from another process,
+/// where it's read back as an I/O error with that message.
 mod csverror {
-	use serde::{Deserializer, Serializer};
+	use serde::{Deserialize, Deserializer, Serialize, Serializer};
+	use std::io;
+
+	use super::InternalCsvError;
 
-	pub(crate) fn serialize<T, S>(_t: &T, _serializer: S) -> Result<S::Ok, S::Error>
+	pub(crate) fn serialize<S>(t: &InternalCsvError, serializer: S) -> Result<S::Ok, S::Error>
 	where
 		S: Serializer,
 	{
-		unimplemented!()
+		t.to_string().serialize(serializer)
 	}
-	pub(crate) fn deserialize<'de, T, D>(_deserializer: D) -> Result<T, D::Error>
+	pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<InternalCsvError, D::Error>
 	where
 		D: Deserializer<'de>,
 	{
-		unimplemented!()
+		String::deserialize(deserializer)
+			.map(|message| io::Error::new(io::ErrorKind::Other, message).into())
 	}
 
 	// TODO
@@ -242,6 +559,7 @@ pub enum CsvError<A, B, C> {
 	Partition(B),
 	Page(C),
 	Csv(#[serde(with = "csverror")] InternalCsvError),
+	PartitionValues(PartitionError),
 }
 impl<A, B, C> Clone for CsvError<A, B, C>
 where
@@ -254,6 +572,7 @@ where
 			Self::File(err) => Self::File(err.clone()),
 			Self::Partition(err) => Self::Partition(err.clone()),
 			Self::Page(err) => Self::Page(err.clone()),
+			Self::PartitionValues(err) => Self::PartitionValues(err.clone()),
 			Self::Csv(err) => Self::Csv(serde::ser::Error::custom(err)),
 		}
 	}
@@ -270,6 +589,7 @@ where
 			(Self::Partition(a), Self::Partition(b)) => a.eq(b),
 			(Self::Page(a), Self::Page(b)) => a.eq(b),
 			(Self::Csv(a), Self::Csv(b)) => a.to_string() == b.to_string(),
+			(Self::PartitionValues(a), Self::PartitionValues(b)) => a == b,
 			_ => false,
 		}
 	}
@@ -293,6 +613,7 @@ where
 			Self::Partition(err) => Display::fmt(err, f),
 			Self::Page(err) => Display::fmt(err, f),
 			Self::Csv(err) => Display::fmt(err, f),
+			Self::PartitionValues(err) => Display::fmt(err, f),
 		}
 	}
 }
@@ -301,3 +622,11 @@ impl<A, B, C> From<InternalCsvError> for CsvError<A, B, C> {
 		Self::Csv(err)
 	}
 }
+impl<A, B, C> From<WriteError<A, C>> for CsvError<A, B, C> {
+	fn from(err: WriteError<A, C>) -> Self {
+		match err {
+			WriteError::Create(err) => Self::File(err),
+			WriteError::Page(err) => Self::Page(err),
+		}
+	}
+}
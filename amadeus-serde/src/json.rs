@@ -1,17 +1,19 @@
 use educe::Educe;
+use flate2::{write::GzEncoder, Compression};
 use futures::{pin_mut, stream, AsyncReadExt, FutureExt, Stream, StreamExt};
 use serde::{Deserialize, Serialize};
 use serde_closure::FnMutNamed;
 use serde_json::Error as InternalJsonError;
 use std::{
-	error, fmt::{self, Debug, Display}, io::{self, Cursor}, marker::PhantomData
+	error, fmt::{self, Debug, Display}, io::{self, Cursor, Write as _}, marker::PhantomData
 };
 
 use amadeus_core::{
-	file::{File, Page, Partition}, into_par_stream::IntoDistributedStream, par_stream::DistributedStream, util::{DistParStream, ResultExpandIter}, Source
+	file::{Create, File, Page, Partition, WriteMode}, into_par_stream::IntoDistributedStream, par_sink::{Encoder, Write, WriteError}, par_stream::{DistributedStream, Identity}, util::{DistParStream, ResultExpandIter}, Destination, Source
 };
+use amadeus_types::PartitionError;
 
-use super::{SerdeData, SerdeDeserialize};
+use super::{SerdeData, SerdeDeserialize, SerdeSerialize, SharedBuffer};
 
 #[derive(Educe)]
 #[educe(Clone, Debug)]
@@ -49,6 +51,7 @@ FnMutNamed! {
 		Row: SerdeData,
 		E: 'static
 	{
+		let values = partition.partition_values().cloned();
 		#[allow(clippy::let_and_return)]
 		let ret = async move {
 				Ok(stream::iter(
@@ -79,7 +82,13 @@ FnMutNamed! {
 			}
 			.map(ResultExpandIter::new)
 			.flatten_stream()
-			.map(|row: Result<Result<Row, Error<P, E>>, Error<P, E>>| Ok(row??));
+			.map(move |row: Result<Result<Row, Error<P, E>>, Error<P, E>>| {
+				let mut row = row??;
+				if let Some(values) = &values {
+					SerdeData::set_partition(&mut row, values).map_err(JsonError::PartitionValues)?;
+				}
+				Ok(row)
+			});
 		#[cfg(not(nightly))]
 		let ret = ret.boxed_local();
 		ret
@@ -118,20 +127,222 @@ where
 	}
 }
 
+/// Writes rows as [JSON Lines](https://jsonlines.org/) to files under `target`,
+/// one file per worker, or per worker and partition if
+/// [`partitioned`](JsonDestinationBuilder::partitioned).
+#[derive(Educe, Serialize, Deserialize)]
+#[educe(Clone(bound = "D: Clone"), Debug(bound = "D: Debug"))]
+#[serde(
+	bound(serialize = "D: Serialize"),
+	bound(deserialize = "D: Deserialize<'de>")
+)]
+pub struct JsonDestination<D, Row> {
+	target: D,
+	gzip: bool,
+	partitioned: bool,
+	marker: PhantomData<fn() -> Row>,
+}
+impl<D, Row> JsonDestination<D, Row>
+where
+	D: Create,
+	Row: SerdeData,
+{
+	/// Writes to `target`, failing if it already has files.
+	pub async fn new(target: D) -> Result<Self, <Self as Destination>::Error> {
+		Self::builder(target).build().await
+	}
+	/// Configure compression and the write mode before writing to `target`.
+	pub fn builder(target: D) -> JsonDestinationBuilder<D, Row> {
+		JsonDestinationBuilder {
+			destination: Self {
+				target,
+				gzip: false,
+				partitioned: false,
+				marker: PhantomData,
+			},
+			mode: WriteMode::default(),
+		}
+	}
+}
+
+/// Builds a [`JsonDestination`], e.g.
+/// `JsonDestination::builder(dir).mode(WriteMode::Create).gzip(true).build().await`.
+///
+/// The write mode is applied to the files already under the target when the
+/// destination is built, before any rows are written.
+#[derive(Educe)]
+#[educe(Clone(bound = "D: Clone"), Debug(bound = "D: Debug"))]
+#[must_use]
+pub struct JsonDestinationBuilder<D, Row> {
+	destination: JsonDestination<D, Row>,
+	mode: WriteMode,
+}
+impl<D, Row> JsonDestinationBuilder<D, Row>
+where
+	D: Create,
+	Row: SerdeData,
+{
+	/// What to do with files already under the target. Defaults to
+	/// [`WriteMode::FailIfExists`].
+	pub fn mode(mut self, mode: WriteMode) -> Self {
+		self.mode = mode;
+		self
+	}
+	/// Whether to gzip-compress each file. Defaults to `false`.
+	pub fn gzip(mut self, gzip: bool) -> Self {
+		self.destination.gzip = gzip;
+		self
+	}
+	/// Whether to write each row under the Hive-style partition directory of its
+	/// fields marked `#[amadeus(partition)]`, like `year=2018/country=uk/`, so that
+	/// reading the target as a [`HiveDirectory`](amadeus_core::file::HiveDirectory)
+	/// fills them back in. Those fields aren't written to the files themselves, so
+	/// are lost if this isn't set. Defaults to `false`.
+	pub fn partitioned(mut self, partitioned: bool) -> Self {
+		self.destination.partitioned = partitioned;
+		self
+	}
+	pub async fn build(
+		self,
+	) -> Result<JsonDestination<D, Row>, <JsonDestination<D, Row> as Destination>::Error> {
+		let Self { destination, mode } = self;
+		destination
+			.target
+			.prepare(mode)
+			.await
+			.map_err(JsonError::File)?;
+		Ok(destination)
+	}
+}
+impl<D, Row> Destination for JsonDestination<D, Row>
+where
+	D: Create,
+	Row: SerdeData,
+{
+	type Item = Row;
+	type Error = JsonError<D::Error, D::Error, <D::Page as Page>::Error>;
+
+	type ParSink = Write<Identity, D, JsonEncoder<D, Row>>;
+	type DistSink = Write<Identity, D, JsonEncoder<D, Row>>;
+
+	fn par_sink(self) -> Self::ParSink {
+		let encoder = JsonEncoder::new(self.gzip, self.partitioned);
+		Write::new(Identity, self.target, encoder)
+	}
+	fn dist_sink(self) -> Self::DistSink {
+		let encoder = JsonEncoder::new(self.gzip, self.partitioned);
+		Write::new(Identity, self.target, encoder)
+	}
+}
+
+#[doc(hidden)]
+#[derive(Educe, Serialize, Deserialize)]
+#[educe(Clone, Debug)]
+#[serde(bound = "")]
+pub struct JsonEncoder<D, Row> {
+	gzip: bool,
+	partitioned: bool,
+	marker: PhantomData<fn() -> (D, Row)>,
+}
+impl<D, Row> JsonEncoder<D, Row> {
+	fn new(gzip: bool, partitioned: bool) -> Self {
+		Self {
+			gzip,
+			partitioned,
+			marker: PhantomData,
+		}
+	}
+}
+#[doc(hidden)]
+pub struct JsonEncoderState {
+	buffer: SharedBuffer,
+	writer: JsonWriter,
+}
+enum JsonWriter {
+	Plain(SharedBuffer),
+	Gzip(GzEncoder<SharedBuffer>),
+}
+impl io::Write for JsonWriter {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		match self {
+			Self::Plain(writer) => writer.write(buf),
+			Self::Gzip(writer) => writer.write(buf),
+		}
+	}
+	fn flush(&mut self) -> io::Result<()> {
+		match self {
+			Self::Plain(writer) => writer.flush(),
+			Self::Gzip(writer) => writer.flush(),
+		}
+	}
+}
+impl<D, Row> Encoder<Row> for JsonEncoder<D, Row>
+where
+	D: Create,
+	Row: SerdeData,
+{
+	type State = JsonEncoderState;
+	type Error = JsonError<D::Error, D::Error, <D::Page as Page>::Error>;
+
+	fn extension(&self) -> String {
+		String::from(if self.gzip { ".json.gz" } else { ".json" })
+	}
+	fn directory(&self, row: &Row) -> Option<String> {
+		let values = row.partition_values();
+		if !self.partitioned || values.is_empty() {
+			return None;
+		}
+		Some(values.path())
+	}
+	fn zero(&mut self) -> Self::State {
+		let buffer = SharedBuffer::default();
+		let writer = if self.gzip {
+			JsonWriter::Gzip(GzEncoder::new(buffer.clone(), Compression::default()))
+		} else {
+			JsonWriter::Plain(buffer.clone())
+		};
+		JsonEncoderState { buffer, writer }
+	}
+	fn push(
+		&mut self, state: &mut Self::State, row: Row, buf: &mut Vec<u8>,
+	) -> Result<(), Self::Error> {
+		serde_json::to_writer(&mut state.writer, &SerdeSerialize(&row))?;
+		state
+			.writer
+			.write_all(b"\n")
+			.map_err(InternalJsonError::io)?;
+		state.buffer.drain_into(buf);
+		Ok(())
+	}
+	fn done(&mut self, state: Self::State, buf: &mut Vec<u8>) -> Result<(), Self::Error> {
+		if let JsonWriter::Gzip(writer) = state.writer {
+			let _ = writer.finish().map_err(InternalJsonError::io)?;
+		}
+		state.buffer.drain_into(buf);
+		Ok(())
+	}
+}
+
+/// Sends a JSON error as its message, so that it can be returned from another process,
+/// where it's read back as an I/O error with that message.
 mod jsonerror {
-	use serde::{Deserializer, Serializer};
+	use serde::{Deserialize, Deserializer, Serialize, Serializer};
+	use std::io;
+
+	use super::InternalJsonError;
 
-	pub(crate) fn serialize<T, S>(_t: &T, _serializer: S) -> Result<S::Ok, S::Error>
+	pub(crate) fn serialize<S>(t: &InternalJsonError, serializer: S) -> Result<S::Ok, S::Error>
 	where
 		S: Serializer,
 	{
-		unimplemented!()
+		t.to_string().serialize(serializer)
 	}
-	pub(crate) fn deserialize<'de, T, D>(_deserializer: D) -> Result<T, D::Error>
+	pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<InternalJsonError, D::Error>
 	where
 		D: Deserializer<'de>,
 	{
-		unimplemented!()
+		String::deserialize(deserializer)
+			.map(|message| InternalJsonError::io(io::Error::new(io::ErrorKind::Other, message)))
 	}
 }
 
@@ -141,6 +352,7 @@ pub enum JsonError<A, B, C> {
 	Partition(B),
 	Page(C),
 	Json(#[serde(with = "jsonerror")] InternalJsonError),
+	PartitionValues(PartitionError),
 }
 impl<A, B, C> Clone for JsonError<A, B, C>
 where
@@ -153,6 +365,7 @@ where
 			Self::File(err) => Self::File(err.clone()),
 			Self::Partition(err) => Self::Partition(err.clone()),
 			Self::Page(err) => Self::Page(err.clone()),
+			Self::PartitionValues(err) => Self::PartitionValues(err.clone()),
 			Self::Json(err) => Self::Json(serde::de::Error::custom(err)),
 		}
 	}
@@ -169,6 +382,7 @@ where
 			(Self::Partition(a), Self::Partition(b)) => a.eq(b),
 			(Self::Page(a), Self::Page(b)) => a.eq(b),
 			(Self::Json(a), Self::Json(b)) => a.to_string() == b.to_string(),
+			(Self::PartitionValues(a), Self::PartitionValues(b)) => a == b,
 			_ => false,
 		}
 	}
@@ -192,6 +406,7 @@ where
 			Self::Partition(err) => Display::fmt(err, f),
 			Self::Page(err) => Display::fmt(err, f),
 			Self::Json(err) => Display::fmt(err, f),
+			Self::PartitionValues(err) => Display::fmt(err, f),
 		}
 	}
 }
@@ -200,6 +415,14 @@ impl<A, B, C> From<InternalJsonError> for JsonError<A, B, C> {
 		Self::Json(err)
 	}
 }
+impl<A, B, C> From<WriteError<A, C>> for JsonError<A, B, C> {
+	fn from(err: WriteError<A, C>) -> Self {
+		match err {
+			WriteError::Create(err) => Self::File(err),
+			WriteError::Page(err) => Self::Page(err),
+		}
+	}
+}
 
 struct PassError<R>(Result<R, Option<io::Error>>);
 impl<R> PassError<R> {
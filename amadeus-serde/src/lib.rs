@@ -38,12 +38,15 @@ mod json;
 #[doc(hidden)]
 pub use serde as _internal;
 
-use amadeus_types::SchemaIncomplete;
+use amadeus_core::file::PartitionValues;
+use amadeus_types::{PartitionError, SchemaIncomplete};
 use serde::{Deserializer, Serializer};
-use std::fmt::Debug;
+use std::{cell::RefCell, fmt::Debug, io, rc::Rc};
 
 pub use self::{
-	csv::*, json::{Json, JsonError}
+	csv::*, json::{
+		Json, JsonDestination, JsonDestinationBuilder, JsonEncoder, JsonEncoderState, JsonError
+	}
 };
 
 pub trait SerdeData
@@ -58,6 +61,16 @@ where
 	) -> Result<Self, D::Error>
 	where
 		D: Deserializer<'de>;
+	/// Fill the fields marked `#[amadeus(partition)]` from the Hive-style partition
+	/// columns of the path this row was read from.
+	fn set_partition(&mut self, _values: &PartitionValues) -> Result<(), PartitionError> {
+		Ok(())
+	}
+	/// The values of the fields marked `#[amadeus(partition)]`, to write this row under
+	/// the Hive-style partition directory they'd be read back from.
+	fn partition_values(&self) -> PartitionValues {
+		PartitionValues::default()
+	}
 }
 
 impl<T> SerdeData for Box<T>
@@ -78,6 +91,12 @@ where
 	{
 		T::deserialize(deserializer, schema).map(Box::new)
 	}
+	fn set_partition(&mut self, values: &PartitionValues) -> Result<(), PartitionError> {
+		(**self).set_partition(values)
+	}
+	fn partition_values(&self) -> PartitionValues {
+		(**self).partition_values()
+	}
 }
 
 #[repr(transparent)]
@@ -120,3 +139,22 @@ where
 		T::deserialize(deserializer, Some(SchemaIncomplete::Group(None))).map(Self)
 	}
 }
+
+/// A writer whose output can be taken while it's still being written to, so
+/// encoders can hand over bytes as they're produced.
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+impl SharedBuffer {
+	fn drain_into(&self, buf: &mut Vec<u8>) {
+		buf.append(&mut self.0.borrow_mut());
+	}
+}
+impl io::Write for SharedBuffer {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.0.borrow_mut().extend_from_slice(buf);
+		Ok(buf.len())
+	}
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
@@ -0,0 +1,275 @@
+#[cfg(feature = "protobuf")]
+use protobuf::CodedOutputStream;
+
+use serde::{Deserialize, Serialize};
+use std::{
+	borrow::Borrow, cmp::max, convert::TryFrom, f64::consts::LN_2, fmt, hash::{Hash, Hasher}, marker::PhantomData, ops
+};
+use twox_hash::XxHash;
+
+use super::{f64_to_usize, usize_to_f64};
+use crate::traits::{Contains, Intersect, New, UnionAssign};
+
+/// An implementation of a [Bloom filter](https://en.wikipedia.org/wiki/Bloom_filter) data structure.
+///
+/// Tests whether a value has been visited, with no false negatives and a bounded rate of false positives. Filters created with the same parameters can be unioned, so one can be built in parallel and then used to cheaply pre-filter another dataset by membership.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct BloomFilter<V: ?Sized> {
+	bits: Vec<u64>,
+	num_bits: usize,
+	k_num: usize,
+	marker: PhantomData<fn(&V)>,
+}
+
+impl<V: ?Sized> BloomFilter<V>
+where
+	V: Hash,
+{
+	/// Create an empty `BloomFilter` sized for `n` values with the specified false positive rate.
+	pub fn new(n: usize, fp_rate: f64) -> Self {
+		assert!(0.0 < fp_rate && fp_rate < 1.0);
+		let n = usize_to_f64(max(1, n));
+		// Rounded up to a whole number of words.
+		let num_bits = f64_to_usize((-n * fp_rate.ln() / (LN_2 * LN_2) / 64.0).ceil()) * 64;
+		let k_num = max(1, f64_to_usize((usize_to_f64(num_bits) / n * LN_2).round()));
+		Self {
+			bits: vec![0; num_bits / 64],
+			num_bits,
+			k_num,
+			marker: PhantomData,
+		}
+	}
+
+	/// Create an empty `BloomFilter` with the same parameters as `bloom`.
+	pub fn new_from(bloom: &Self) -> Self {
+		Self {
+			bits: vec![0; bloom.bits.len()],
+			num_bits: bloom.num_bits,
+			k_num: bloom.k_num,
+			marker: PhantomData,
+		}
+	}
+
+	/// "Visit" an element. Returns whether it might have been visited before.
+	pub fn push<Q>(&mut self, value: &Q) -> bool
+	where
+		Q: Hash + ?Sized,
+		V: Borrow<Q>,
+	{
+		let mut present = true;
+		for offset in self.offsets(value) {
+			let (word, bit) = (offset / 64, 1 << (offset % 64));
+			present &= self.bits[word] & bit != 0;
+			self.bits[word] |= bit;
+		}
+		present
+	}
+
+	/// Whether `value` might have been visited. False positives occur at around the rate the filter was created with; false negatives never do.
+	pub fn contains<Q>(&self, value: &Q) -> bool
+	where
+		Q: Hash + ?Sized,
+		V: Borrow<Q>,
+	{
+		self.offsets(value)
+			.all(|offset| self.bits[offset / 64] & (1 << (offset % 64)) != 0)
+	}
+
+	/// Whether no values have been visited.
+	pub fn is_empty(&self) -> bool {
+		self.bits.iter().all(|&word| word == 0)
+	}
+
+	/// Merge another `BloomFilter` into this one, so that it contains the values of both.
+	pub fn union(&mut self, src: &Self) {
+		assert_eq!(self.num_bits, src.num_bits);
+		assert_eq!(self.k_num, src.k_num);
+		for (a, b) in self.bits.iter_mut().zip(&src.bits) {
+			*a |= b;
+		}
+	}
+
+	/// Intersect another `BloomFilter` with this one, so that it contains at least the values in both. It may contain more false positives than a filter of only those values.
+	pub fn intersect(&mut self, src: &Self) {
+		assert_eq!(self.num_bits, src.num_bits);
+		assert_eq!(self.k_num, src.k_num);
+		for (a, b) in self.bits.iter_mut().zip(&src.bits) {
+			*a &= b;
+		}
+	}
+
+	/// Clears the `BloomFilter` data structure, as if it was new.
+	pub fn clear(&mut self) {
+		self.bits.iter_mut().for_each(|x| {
+			*x = 0;
+		});
+	}
+
+	/// Returns a serialized representation of this filter, as the protobuf message:
+	///
+	/// ```protobuf
+	/// message BloomFilter {
+	///   uint64 num_hashes = 1;
+	///   uint64 num_bits = 2;
+	///   // The bits, as little-endian 64-bit words.
+	///   bytes bits = 3;
+	/// }
+	/// ```
+	#[cfg(feature = "protobuf")]
+	pub fn serialize_as_proto(&self) -> Vec<u8> {
+		let mut buf = Vec::new();
+		let mut stream = CodedOutputStream::new(&mut buf);
+
+		stream
+			.write_uint64(1, u64::try_from(self.k_num).unwrap())
+			.unwrap();
+		stream
+			.write_uint64(2, u64::try_from(self.num_bits).unwrap())
+			.unwrap();
+		let bits = self
+			.bits
+			.iter()
+			.flat_map(|word| word.to_le_bytes())
+			.collect::<Vec<u8>>();
+		stream.write_bytes(3, &bits).unwrap();
+
+		stream.flush().unwrap();
+
+		buf
+	}
+
+	fn offsets<Q>(&self, value: &Q) -> impl Iterator<Item = usize>
+	where
+		Q: Hash + ?Sized,
+		V: Borrow<Q>,
+	{
+		// Kirsch-Mitzenmacher double hashing: the ith offset is h1 + i * h2.
+		let mut h1 = XxHash::with_seed(0);
+		let mut h2 = XxHash::with_seed(1);
+		value.hash(&mut h1);
+		value.hash(&mut h2);
+		let (h1, h2) = (h1.finish(), h2.finish());
+		let num_bits = u64::try_from(self.num_bits).unwrap();
+		(0..u64::try_from(self.k_num).unwrap())
+			.map(move |i| usize::try_from(h1.wrapping_add(i.wrapping_mul(h2)) % num_bits).unwrap())
+	}
+}
+
+impl<V: ?Sized> Clone for BloomFilter<V> {
+	fn clone(&self) -> Self {
+		Self {
+			bits: self.bits.clone(),
+			num_bits: self.num_bits,
+			k_num: self.k_num,
+			marker: PhantomData,
+		}
+	}
+}
+impl<V: ?Sized> fmt::Debug for BloomFilter<V> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("BloomFilter")
+			.field("num_bits", &self.num_bits)
+			.field("k_num", &self.k_num)
+			.finish_non_exhaustive()
+	}
+}
+impl<V: ?Sized> New for BloomFilter<V>
+where
+	V: Hash,
+{
+	type Config = (usize, f64);
+	fn new(config: &Self::Config) -> Self {
+		Self::new(config.0, config.1)
+	}
+}
+impl<V: ?Sized> Intersect for BloomFilter<V>
+where
+	V: Hash,
+{
+	fn intersect<'a>(mut iter: impl Iterator<Item = &'a Self>) -> Option<Self>
+	where
+		Self: Sized + 'a,
+	{
+		let mut ret = iter.next()?.clone();
+		iter.for_each(|x| {
+			ret.intersect(x);
+		});
+		Some(ret)
+	}
+}
+impl<V: ?Sized, Q: ?Sized> Contains<Q> for BloomFilter<V>
+where
+	V: Hash + Borrow<Q>,
+	Q: Hash,
+{
+	fn contains(&self, value: &Q) -> bool {
+		self.contains(value)
+	}
+}
+impl<'a, V: ?Sized> UnionAssign<&'a BloomFilter<V>> for BloomFilter<V>
+where
+	V: Hash,
+{
+	fn union_assign(&mut self, rhs: &'a Self) {
+		self.union(rhs);
+	}
+}
+impl<'a, V: ?Sized> ops::AddAssign<&'a V> for BloomFilter<V>
+where
+	V: Hash,
+{
+	fn add_assign(&mut self, rhs: &'a V) {
+		let _ = self.push(rhs);
+	}
+}
+impl<'a, V: ?Sized> ops::AddAssign<&'a Self> for BloomFilter<V>
+where
+	V: Hash,
+{
+	fn add_assign(&mut self, rhs: &'a Self) {
+		self.union(rhs);
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::BloomFilter;
+
+	#[test]
+	fn false_positives() {
+		let mut bloom = BloomFilter::<u64>::new(10_000, 0.01);
+		for i in 0..10_000 {
+			let _ = bloom.push(&(i * 2));
+		}
+		assert!((0..10_000).all(|i| bloom.contains(&(i * 2))));
+		let false_positives = (0..10_000).filter(|i| bloom.contains(&(i * 2 + 1))).count();
+		assert!(false_positives < 200, "{}", false_positives);
+	}
+
+	#[test]
+	fn union() {
+		let mut a = BloomFilter::<str>::new(1000, 0.01);
+		let mut b = BloomFilter::new_from(&a);
+		assert!(a.is_empty());
+		for i in 0..1000 {
+			let _ = if i % 2 == 0 { &mut a } else { &mut b }.push(&format!("key-{}", i));
+		}
+		assert!(!a.contains("key-1") || !a.contains("key-3") || !a.contains("key-5"));
+		a.union(&b);
+		assert!((0..1000).all(|i| a.contains(&format!("key-{}", i))));
+		a.clear();
+		assert!(a.is_empty());
+	}
+
+	#[test]
+	#[cfg(feature = "protobuf")]
+	fn proto() {
+		let mut bloom = BloomFilter::<u32>::new(10, 0.5);
+		let _ = bloom.push(&1);
+		let proto = bloom.serialize_as_proto();
+		assert_eq!(&proto[..4], &[0x08, 4, 0x10, 64]);
+		assert_eq!(proto[4..6], [0x1a, 8]);
+		assert_eq!(proto.len(), 6 + 8);
+	}
+}
@@ -0,0 +1,408 @@
+#[cfg(feature = "protobuf")]
+use protobuf::CodedOutputStream;
+
+use serde::{Deserialize, Serialize};
+use std::{
+	borrow::Borrow, cmp::{max, min}, convert::TryFrom, fmt, hash::{Hash, Hasher}, marker::PhantomData, ops
+};
+use twox_hash::XxHash;
+
+use super::{f64_to_usize, usize_to_f64};
+use crate::traits::{Contains, New, UnionAssign};
+
+const BUCKET_SIZE: usize = 4;
+const MAX_KICKS: usize = 500;
+
+/// An implementation of a [cuckoo filter](https://en.wikipedia.org/wiki/Cuckoo_filter) data structure.
+///
+/// Like a [`BloomFilter`](crate::BloomFilter) it tests whether a value has been visited, with no false negatives and a bounded rate of false positives, but values can also be removed. Filters created with the same parameters can be unioned. Pushing more values than it was sized for spills them into an overflow list, which keeps it correct but makes lookups slower.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct CuckooFilter<V: ?Sized> {
+	fingerprints: Vec<u64>,
+	num_buckets: usize,
+	fingerprint_bits: usize,
+	overflow: Vec<(usize, u32)>,
+	marker: PhantomData<fn(&V)>,
+}
+
+impl<V: ?Sized> CuckooFilter<V>
+where
+	V: Hash,
+{
+	/// Create an empty `CuckooFilter` sized for `n` values with the specified false positive rate.
+	pub fn new(n: usize, fp_rate: f64) -> Self {
+		assert!(0.0 < fp_rate && fp_rate < 1.0);
+		// Buckets are filled to about 95% before insertions start failing.
+		let num_buckets =
+			f64_to_usize((usize_to_f64(max(1, n)) / usize_to_f64(BUCKET_SIZE) / 0.95).ceil())
+				.next_power_of_two();
+		// Each lookup compares against up to 2 * BUCKET_SIZE fingerprints.
+		let fingerprint_bits = min(
+			32,
+			f64_to_usize((usize_to_f64(2 * BUCKET_SIZE) / fp_rate).log2().ceil()),
+		);
+		Self::with_parameters(num_buckets, fingerprint_bits)
+	}
+
+	/// Create an empty `CuckooFilter` with the same parameters as `cuckoo`.
+	pub fn new_from(cuckoo: &Self) -> Self {
+		Self::with_parameters(cuckoo.num_buckets, cuckoo.fingerprint_bits)
+	}
+
+	fn with_parameters(num_buckets: usize, fingerprint_bits: usize) -> Self {
+		let num_words = (num_buckets * BUCKET_SIZE * fingerprint_bits + 63) / 64;
+		Self {
+			fingerprints: vec![0; num_words],
+			num_buckets,
+			fingerprint_bits,
+			overflow: Vec::new(),
+			marker: PhantomData,
+		}
+	}
+
+	/// "Visit" an element. Returns whether it might have been visited before. Unlike [`BloomFilter::push`](crate::BloomFilter::push) it's always added, so that a later [`remove`](Self::remove) of it leaves any earlier visit in place.
+	pub fn push<Q>(&mut self, value: &Q) -> bool
+	where
+		Q: Hash + ?Sized,
+		V: Borrow<Q>,
+	{
+		let (index, fingerprint) = self.index_and_fingerprint(value);
+		let present = self.find(index, fingerprint).is_some();
+		self.insert(index, fingerprint);
+		present
+	}
+
+	/// Whether `value` might have been visited. False positives occur at around the rate the filter was created with; false negatives never do.
+	pub fn contains<Q>(&self, value: &Q) -> bool
+	where
+		Q: Hash + ?Sized,
+		V: Borrow<Q>,
+	{
+		let (index, fingerprint) = self.index_and_fingerprint(value);
+		self.find(index, fingerprint).is_some()
+	}
+
+	/// Remove a visit of `value`. Returns whether it might have been visited. Removing a value that wasn't visited can remove a different value whose fingerprint collides with it, introducing false negatives.
+	pub fn remove<Q>(&mut self, value: &Q) -> bool
+	where
+		Q: Hash + ?Sized,
+		V: Borrow<Q>,
+	{
+		let (index, fingerprint) = self.index_and_fingerprint(value);
+		match self.find(index, fingerprint) {
+			Some(Ok(slot)) => {
+				self.set(slot, 0);
+				true
+			}
+			Some(Err(i)) => {
+				let _ = self.overflow.swap_remove(i);
+				true
+			}
+			None => false,
+		}
+	}
+
+	/// Whether no values have been visited.
+	pub fn is_empty(&self) -> bool {
+		self.fingerprints.iter().all(|&word| word == 0) && self.overflow.is_empty()
+	}
+
+	/// Merge another `CuckooFilter` into this one, so that it contains the values of both.
+	pub fn union(&mut self, src: &Self) {
+		assert_eq!(self.num_buckets, src.num_buckets);
+		assert_eq!(self.fingerprint_bits, src.fingerprint_bits);
+		for slot in 0..src.num_buckets * BUCKET_SIZE {
+			let fingerprint = src.get(slot);
+			if fingerprint != 0 {
+				self.insert(slot / BUCKET_SIZE, fingerprint);
+			}
+		}
+		for &(index, fingerprint) in &src.overflow {
+			self.insert(index, fingerprint);
+		}
+	}
+
+	/// Clears the `CuckooFilter` data structure, as if it was new.
+	pub fn clear(&mut self) {
+		self.fingerprints.iter_mut().for_each(|x| {
+			*x = 0;
+		});
+		self.overflow.clear();
+	}
+
+	/// Returns a serialized representation of this filter, as the protobuf message:
+	///
+	/// ```protobuf
+	/// message CuckooFilter {
+	///   uint64 fingerprint_bits = 1;
+	///   uint64 num_buckets = 2;
+	///   // The fingerprints, 4 per bucket, packed into little-endian 64-bit words.
+	///   bytes fingerprints = 3;
+	///   // Fingerprints that didn't fit, as bucket << 32 | fingerprint.
+	///   repeated uint64 overflow = 4;
+	/// }
+	/// ```
+	#[cfg(feature = "protobuf")]
+	pub fn serialize_as_proto(&self) -> Vec<u8> {
+		let mut buf = Vec::new();
+		let mut stream = CodedOutputStream::new(&mut buf);
+
+		stream
+			.write_uint64(1, u64::try_from(self.fingerprint_bits).unwrap())
+			.unwrap();
+		stream
+			.write_uint64(2, u64::try_from(self.num_buckets).unwrap())
+			.unwrap();
+		let fingerprints = self
+			.fingerprints
+			.iter()
+			.flat_map(|word| word.to_le_bytes())
+			.collect::<Vec<u8>>();
+		stream.write_bytes(3, &fingerprints).unwrap();
+		for &(index, fingerprint) in &self.overflow {
+			stream
+				.write_uint64(
+					4,
+					(u64::try_from(index).unwrap() << 32) | u64::from(fingerprint),
+				)
+				.unwrap();
+		}
+
+		stream.flush().unwrap();
+
+		buf
+	}
+
+	fn index_and_fingerprint<Q>(&self, value: &Q) -> (usize, u32)
+	where
+		Q: Hash + ?Sized,
+		V: Borrow<Q>,
+	{
+		let mut hasher = XxHash::with_seed(0);
+		value.hash(&mut hasher);
+		let hash = hasher.finish();
+		let mask = (1 << self.fingerprint_bits) - 1;
+		// Fingerprint 0 marks an empty slot.
+		let fingerprint = max(1, u32::try_from((hash >> 32) & mask).unwrap());
+		let index = usize::try_from(hash % u64::try_from(self.num_buckets).unwrap()).unwrap();
+		(index, fingerprint)
+	}
+
+	// Partial-key cuckoo hashing: the alternate bucket can be found from either bucket
+	// and the fingerprint, as `num_buckets` is a power of two.
+	fn alternate(&self, index: usize, fingerprint: u32) -> usize {
+		let hash = u64::from(fingerprint).wrapping_mul(0x5bd1_e995);
+		index ^ usize::try_from(hash % u64::try_from(self.num_buckets).unwrap()).unwrap()
+	}
+
+	// The slot holding `fingerprint` in either of its buckets, or its position in the
+	// overflow list.
+	fn find(&self, index: usize, fingerprint: u32) -> Option<Result<usize, usize>> {
+		let alternate = self.alternate(index, fingerprint);
+		(0..BUCKET_SIZE)
+			.map(|i| index * BUCKET_SIZE + i)
+			.chain((0..BUCKET_SIZE).map(|i| alternate * BUCKET_SIZE + i))
+			.find(|&slot| self.get(slot) == fingerprint)
+			.map(Ok)
+			.or_else(|| {
+				self.overflow
+					.iter()
+					.position(|&(index_, fingerprint_)| {
+						fingerprint_ == fingerprint && (index_ == index || index_ == alternate)
+					})
+					.map(Err)
+			})
+	}
+
+	fn insert(&mut self, mut index: usize, mut fingerprint: u32) {
+		for &candidate in &[index, self.alternate(index, fingerprint)] {
+			if let Some(slot) = self.empty_slot(candidate) {
+				self.set(slot, fingerprint);
+				return;
+			}
+		}
+		for kick in 0..MAX_KICKS {
+			// Evict a fingerprint and move it to its alternate bucket.
+			let slot =
+				index * BUCKET_SIZE + (usize::try_from(fingerprint).unwrap() + kick) % BUCKET_SIZE;
+			let evicted = self.get(slot);
+			self.set(slot, fingerprint);
+			fingerprint = evicted;
+			index = self.alternate(index, fingerprint);
+			if let Some(slot) = self.empty_slot(index) {
+				self.set(slot, fingerprint);
+				return;
+			}
+		}
+		self.overflow.push((index, fingerprint));
+	}
+
+	fn empty_slot(&self, index: usize) -> Option<usize> {
+		(index * BUCKET_SIZE..(index + 1) * BUCKET_SIZE).find(|&slot| self.get(slot) == 0)
+	}
+
+	fn get(&self, slot: usize) -> u32 {
+		let (bits, mask) = (self.fingerprint_bits, (1_u64 << self.fingerprint_bits) - 1);
+		let (word, offset) = (slot * bits / 64, slot * bits % 64);
+		let mut value = self.fingerprints[word] >> offset;
+		if offset + bits > 64 {
+			value |= self.fingerprints[word + 1] << (64 - offset);
+		}
+		u32::try_from(value & mask).unwrap()
+	}
+
+	fn set(&mut self, slot: usize, fingerprint: u32) {
+		let (bits, mask) = (self.fingerprint_bits, (1_u64 << self.fingerprint_bits) - 1);
+		let (word, offset) = (slot * bits / 64, slot * bits % 64);
+		let value = u64::from(fingerprint);
+		self.fingerprints[word] = (self.fingerprints[word] & !(mask << offset)) | (value << offset);
+		if offset + bits > 64 {
+			let shift = 64 - offset;
+			self.fingerprints[word + 1] =
+				(self.fingerprints[word + 1] & !(mask >> shift)) | (value >> shift);
+		}
+	}
+}
+
+impl<V: ?Sized> Clone for CuckooFilter<V> {
+	fn clone(&self) -> Self {
+		Self {
+			fingerprints: self.fingerprints.clone(),
+			num_buckets: self.num_buckets,
+			fingerprint_bits: self.fingerprint_bits,
+			overflow: self.overflow.clone(),
+			marker: PhantomData,
+		}
+	}
+}
+impl<V: ?Sized> fmt::Debug for CuckooFilter<V> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("CuckooFilter")
+			.field("num_buckets", &self.num_buckets)
+			.field("fingerprint_bits", &self.fingerprint_bits)
+			.field("overflow", &self.overflow.len())
+			.finish_non_exhaustive()
+	}
+}
+impl<V: ?Sized> New for CuckooFilter<V>
+where
+	V: Hash,
+{
+	type Config = (usize, f64);
+	fn new(config: &Self::Config) -> Self {
+		Self::new(config.0, config.1)
+	}
+}
+impl<V: ?Sized, Q: ?Sized> Contains<Q> for CuckooFilter<V>
+where
+	V: Hash + Borrow<Q>,
+	Q: Hash,
+{
+	fn contains(&self, value: &Q) -> bool {
+		self.contains(value)
+	}
+}
+impl<'a, V: ?Sized> UnionAssign<&'a CuckooFilter<V>> for CuckooFilter<V>
+where
+	V: Hash,
+{
+	fn union_assign(&mut self, rhs: &'a Self) {
+		self.union(rhs);
+	}
+}
+impl<'a, V: ?Sized> ops::AddAssign<&'a V> for CuckooFilter<V>
+where
+	V: Hash,
+{
+	fn add_assign(&mut self, rhs: &'a V) {
+		let _ = self.push(rhs);
+	}
+}
+impl<'a, V: ?Sized> ops::AddAssign<&'a Self> for CuckooFilter<V>
+where
+	V: Hash,
+{
+	fn add_assign(&mut self, rhs: &'a Self) {
+		self.union(rhs);
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::CuckooFilter;
+
+	#[test]
+	fn false_positives() {
+		let mut cuckoo = CuckooFilter::<u64>::new(10_000, 0.01);
+		for i in 0..10_000 {
+			let _ = cuckoo.push(&(i * 2));
+		}
+		assert!(cuckoo.overflow.is_empty());
+		assert!((0..10_000).all(|i| cuckoo.contains(&(i * 2))));
+		let false_positives = (0..10_000)
+			.filter(|i| cuckoo.contains(&(i * 2 + 1)))
+			.count();
+		assert!(false_positives < 200, "{}", false_positives);
+	}
+
+	#[test]
+	fn remove() {
+		let mut cuckoo = CuckooFilter::<u64>::new(1000, 0.001);
+		for i in 0..1000 {
+			let _ = cuckoo.push(&i);
+		}
+		for i in (0..1000).step_by(2) {
+			assert!(cuckoo.remove(&i));
+		}
+		assert!((0..1000)
+			.filter(|i| i % 2 == 1)
+			.all(|i| cuckoo.contains(&i)));
+		assert!(
+			(0..1000)
+				.filter(|i| i % 2 == 0 && cuckoo.contains(i))
+				.count() < 10
+		);
+		for i in (0..1000).filter(|i| i % 2 == 1) {
+			assert!(cuckoo.remove(&i));
+		}
+		assert!(cuckoo.is_empty());
+	}
+
+	#[test]
+	fn overflow() {
+		let mut cuckoo = CuckooFilter::<u64>::new(100, 0.01);
+		for i in 0..1000 {
+			let _ = cuckoo.push(&i);
+		}
+		assert!(!cuckoo.overflow.is_empty());
+		assert!((0..1000).all(|i| cuckoo.contains(&i)));
+	}
+
+	#[test]
+	fn union() {
+		let mut a = CuckooFilter::<str>::new(1000, 0.01);
+		let mut b = CuckooFilter::new_from(&a);
+		assert!(a.is_empty());
+		for i in 0..1000 {
+			let _ = if i % 2 == 0 { &mut a } else { &mut b }.push(&format!("key-{}", i));
+		}
+		assert!(!a.contains("key-1") || !a.contains("key-3") || !a.contains("key-5"));
+		a.union(&b);
+		assert!((0..1000).all(|i| a.contains(&format!("key-{}", i))));
+		a.clear();
+		assert!(a.is_empty());
+	}
+
+	#[test]
+	#[cfg(feature = "protobuf")]
+	fn proto() {
+		let mut cuckoo = CuckooFilter::<u32>::new(10, 0.3);
+		let _ = cuckoo.push(&1);
+		let proto = cuckoo.serialize_as_proto();
+		// 5 bit fingerprints in 4 buckets of 4 is 80 bits, so 2 words
+		assert_eq!(&proto[..4], &[0x08, 5, 0x10, 4]);
+		assert_eq!(proto[4..6], [0x1a, 16]);
+		assert_eq!(proto.len(), 6 + 16);
+	}
+}
@@ -0,0 +1,271 @@
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, f64::consts::PI, ops};
+
+use super::{New, UnionAssign};
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+struct Centroid {
+	mean: f64,
+	weight: f64,
+}
+
+/// An implementation of the [t-digest](https://github.com/tdunning/t-digest/blob/master/docs/t-digest-paper/histo.pdf) data structure, for estimating quantiles.
+///
+/// Values are merged into at most around `compression` centroids, that are smaller towards the extremes, so that tail quantiles like p99 are estimated more accurately than the median.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TDigest {
+	compression: f64,
+	centroids: Vec<Centroid>,
+	buffer: Vec<f64>,
+	min: f64,
+	max: f64,
+}
+
+impl TDigest {
+	/// Create an empty `TDigest` with the specified `compression`. 100 is typical; higher is more accurate but uses more memory.
+	pub fn new(compression: f64) -> Self {
+		assert!(compression >= 1.0, "compression must be at least 1");
+		Self {
+			compression,
+			centroids: Vec::new(),
+			buffer: Vec::new(),
+			min: f64::INFINITY,
+			max: f64::NEG_INFINITY,
+		}
+	}
+
+	/// Create an empty `TDigest` with the same `compression` as `tdigest`.
+	pub fn new_from(tdigest: &Self) -> Self {
+		Self::new(tdigest.compression)
+	}
+
+	/// "Visit" a value. NaNs are ignored.
+	pub fn push(&mut self, value: f64) {
+		if value.is_nan() {
+			return;
+		}
+		self.min = self.min.min(value);
+		self.max = self.max.max(value);
+		self.buffer.push(value);
+		if self.buffer.len() >= self.buffer_capacity() {
+			self.compress();
+		}
+	}
+
+	/// The number of values visited.
+	pub fn len(&self) -> usize {
+		#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+		let merged = self.centroids.iter().map(|c| c.weight).sum::<f64>() as usize;
+		merged + self.buffer.len()
+	}
+
+	/// Whether no values have been visited.
+	pub fn is_empty(&self) -> bool {
+		self.centroids.is_empty() && self.buffer.is_empty()
+	}
+
+	/// The smallest value visited.
+	pub fn min(&self) -> Option<f64> {
+		if !self.is_empty() {
+			Some(self.min)
+		} else {
+			None
+		}
+	}
+
+	/// The largest value visited.
+	pub fn max(&self) -> Option<f64> {
+		if !self.is_empty() {
+			Some(self.max)
+		} else {
+			None
+		}
+	}
+
+	/// Union another `TDigest` into this one.
+	pub fn union(&mut self, src: &Self) {
+		if src.is_empty() {
+			return;
+		}
+		self.min = self.min.min(src.min);
+		self.max = self.max.max(src.max);
+		self.centroids.extend_from_slice(&src.centroids);
+		self.buffer.extend_from_slice(&src.buffer);
+		self.compress();
+	}
+
+	/// Estimate the value at quantile `q`, which is clamped to `0.0..=1.0`. Returns `None` if no values have been visited.
+	pub fn quantile(&self, q: f64) -> Option<f64> {
+		if self.is_empty() {
+			return None;
+		}
+		if !self.buffer.is_empty() {
+			let mut self_ = self.clone();
+			self_.compress();
+			return self_.quantile(q);
+		}
+		let q = q.clamp(0.0, 1.0);
+		let total = self.centroids.iter().map(|c| c.weight).sum::<f64>();
+		let index = q * total;
+		let first = self.centroids[0];
+		if index <= first.weight / 2.0 {
+			// Between the minimum and the centre of the first centroid.
+			let t = if first.weight > 0.0 {
+				index / (first.weight / 2.0)
+			} else {
+				0.0
+			};
+			return Some(self.min + t * (first.mean - self.min));
+		}
+		let mut cumulative = 0.0;
+		for pair in self.centroids.windows(2) {
+			let (a, b) = (pair[0], pair[1]);
+			let centre_a = cumulative + a.weight / 2.0;
+			let centre_b = cumulative + a.weight + b.weight / 2.0;
+			if index <= centre_b {
+				let t = (index - centre_a) / (centre_b - centre_a);
+				return Some(a.mean + t * (b.mean - a.mean));
+			}
+			cumulative += a.weight;
+		}
+		// Between the centre of the last centroid and the maximum.
+		let last = *self.centroids.last().unwrap();
+		let centre = total - last.weight / 2.0;
+		let t = (index - centre) / (last.weight / 2.0);
+		Some(last.mean + t * (self.max - last.mean))
+	}
+
+	/// Clears the `TDigest`.
+	pub fn clear(&mut self) {
+		*self = Self::new_from(self);
+	}
+
+	fn buffer_capacity(&self) -> usize {
+		#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+		let capacity = (self.compression * 5.0).ceil() as usize;
+		capacity
+	}
+
+	/// The scale function `k₁`, that maps quantiles to indices such that each centroid spans at most an index of 1.
+	fn k(&self, q: f64) -> f64 {
+		self.compression / (2.0 * PI) * (2.0 * q - 1.0).asin()
+	}
+	fn k_inverse(&self, k: f64) -> f64 {
+		let k = k.min(self.compression / 4.0);
+		0.5 * (1.0 + (2.0 * PI * k / self.compression).sin())
+	}
+
+	/// Merge the buffer and the centroids into as few centroids as the scale function allows.
+	fn compress(&mut self) {
+		let mut items = self.centroids.split_off(0);
+		items.extend(
+			self.buffer
+				.drain(..)
+				.map(|mean| Centroid { mean, weight: 1.0 }),
+		);
+		if items.is_empty() {
+			return;
+		}
+		items.sort_by(|a, b| a.mean.partial_cmp(&b.mean).unwrap_or(Ordering::Equal));
+		let total = items.iter().map(|c| c.weight).sum::<f64>();
+		let mut items = items.into_iter();
+		let mut current = items.next().unwrap();
+		let mut weight_so_far = 0.0;
+		let mut limit = total * self.k_inverse(self.k(0.0) + 1.0);
+		for item in items {
+			if weight_so_far + current.weight + item.weight <= limit {
+				let weight = current.weight + item.weight;
+				current.mean += (item.mean - current.mean) * item.weight / weight;
+				current.weight = weight;
+			} else {
+				weight_so_far += current.weight;
+				self.centroids.push(current);
+				limit = total * self.k_inverse(self.k(weight_so_far / total) + 1.0);
+				current = item;
+			}
+		}
+		self.centroids.push(current);
+	}
+}
+impl New for TDigest {
+	type Config = f64;
+	fn new(config: &Self::Config) -> Self {
+		Self::new(*config)
+	}
+}
+impl<'a> UnionAssign<&'a TDigest> for TDigest {
+	fn union_assign(&mut self, rhs: &'a Self) {
+		self.union(rhs);
+	}
+}
+impl<'a> ops::AddAssign<&'a f64> for TDigest {
+	fn add_assign(&mut self, rhs: &'a f64) {
+		self.push(*rhs);
+	}
+}
+impl<'a> ops::AddAssign<&'a Self> for TDigest {
+	fn add_assign(&mut self, rhs: &'a Self) {
+		self.union(rhs);
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::TDigest;
+	use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+	#[test]
+	fn uniform() {
+		let mut rng = SmallRng::seed_from_u64(0);
+		let mut tdigest = TDigest::new(100.0);
+		let mut values = (0..100_000)
+			.map(|_| rng.gen_range(0.0, 1000.0))
+			.collect::<Vec<f64>>();
+		for &value in &values {
+			tdigest.push(value);
+		}
+		values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+		assert_eq!(tdigest.len(), values.len());
+		for &q in &[0.001, 0.01, 0.25, 0.5, 0.75, 0.95, 0.99, 0.999] {
+			#[allow(
+				clippy::cast_possible_truncation,
+				clippy::cast_precision_loss,
+				clippy::cast_sign_loss
+			)]
+			let exact = values[(q * values.len() as f64) as usize];
+			let estimate = tdigest.quantile(q).unwrap();
+			assert!(
+				(estimate - exact).abs() < 5.0,
+				"{} {} {}",
+				q,
+				estimate,
+				exact
+			);
+		}
+		assert_eq!(tdigest.quantile(0.0), Some(values[0]));
+		assert_eq!(tdigest.quantile(1.0), Some(values[values.len() - 1]));
+	}
+
+	#[test]
+	fn union() {
+		let mut rng = SmallRng::seed_from_u64(1);
+		let mut whole = TDigest::new(100.0);
+		let mut parts = (0..10).map(|_| TDigest::new(100.0)).collect::<Vec<_>>();
+		for i in 0..100_000 {
+			let value: f64 = rng.gen_range(0.0, 1.0);
+			let value = value.powi(4) * 1000.0;
+			whole.push(value);
+			parts[i % 10].push(value);
+		}
+		let mut merged = TDigest::new(100.0);
+		for part in &parts {
+			merged.union(part);
+		}
+		assert_eq!(merged.len(), whole.len());
+		for &q in &[0.01, 0.5, 0.95, 0.99] {
+			let (a, b) = (whole.quantile(q).unwrap(), merged.quantile(q).unwrap());
+			assert!((a - b).abs() / a.max(1.0) < 0.02, "{} {} {}", q, a, b);
+		}
+		assert_eq!(TDigest::new(100.0).quantile(0.5), None);
+	}
+}
@@ -82,6 +82,12 @@ impl Intersect for usize {
 	}
 }
 
+/// Membership of a set of values, possibly with false positives.
+pub trait Contains<Q: ?Sized> {
+	/// Whether `value` might be in the set.
+	fn contains(&self, value: &Q) -> bool;
+}
+
 /// New instances are instantiable given a specified input of `<Self as New>::Config`.
 pub trait New {
 	/// The type of data required to instantiate a new `Self`.
@@ -1,7 +1,9 @@
 // TODO associated_type_defaults https://github.com/rust-lang/rust/issues/29661
 
+use amadeus_core::file::PartitionValues;
+use serde::{Deserialize, Serialize};
 use std::{
-	collections::HashMap, hash::{BuildHasher, Hash}
+	collections::HashMap, hash::{BuildHasher, Hash}, str::FromStr
 };
 
 use super::*;
@@ -11,6 +13,71 @@ pub trait Data: Clone + Debug + Send + Sized + 'static {
 	type Vec: ListVec<Self>;
 	type DynamicType;
 	fn new_vec(_type: Self::DynamicType) -> Self::Vec;
+	/// Fill the fields marked `#[amadeus(partition)]` from the Hive-style partition
+	/// columns of the path this row was read from.
+	fn set_partition(&mut self, _values: &PartitionValues) -> Result<(), PartitionError> {
+		Ok(())
+	}
+}
+
+/// A type that a field filled from a Hive-style partition column can have.
+pub trait PartitionValue: Default {
+	/// Parse the value of a partition column, which is `None` for Hive's default
+	/// partition, i.e. null.
+	fn from_partition(value: Option<&str>) -> Option<Self>;
+	/// The value of the partition column to write this to, `None` being null.
+	fn to_partition(&self) -> Option<String>;
+}
+impl<T> PartitionValue for Option<T>
+where
+	T: PartitionValue,
+{
+	fn from_partition(value: Option<&str>) -> Option<Self> {
+		match value {
+			Some(value) => T::from_partition(Some(value)).map(Some),
+			None => Some(None),
+		}
+	}
+	fn to_partition(&self) -> Option<String> {
+		self.as_ref().and_then(T::to_partition)
+	}
+}
+macro_rules! impl_partition_value {
+	($($t:ty)*) => ($(
+		impl PartitionValue for $t {
+			fn from_partition(value: Option<&str>) -> Option<Self> {
+				value.and_then(|value| <$t>::from_str(value).ok())
+			}
+			fn to_partition(&self) -> Option<String> {
+				Some(self.to_string())
+			}
+		}
+	)*);
+}
+impl_partition_value!(bool u8 i8 u16 i16 u32 i32 u64 i64 f32 f64 String);
+
+/// The error returned when a partition column needed by a row is missing from its path,
+/// or can't be parsed.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum PartitionError {
+	Missing(String),
+	Invalid(String, Option<String>),
+}
+impl Error for PartitionError {}
+impl Display for PartitionError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Missing(column) => write!(f, "partition column \"{}\" not in path", column),
+			Self::Invalid(column, Some(value)) => write!(
+				f,
+				"could not parse \"{}\" for partition column \"{}\"",
+				value, column
+			),
+			Self::Invalid(column, None) => {
+				write!(f, "partition column \"{}\" can't be null", column)
+			}
+		}
+	}
 }
 
 impl<T> Data for Option<T>
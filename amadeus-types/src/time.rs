@@ -10,10 +10,14 @@ use std::{
 	cmp::Ordering, convert::TryInto, error::Error, fmt::{self, Display}, str::FromStr
 };
 
+use amadeus_core::par_stream::EventTime;
+
 use super::AmadeusOrd;
 
 const JULIAN_DAY_OF_EPOCH: i64 = 2_440_588;
 const GREGORIAN_DAY_OF_EPOCH: i64 = 719_163;
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+const NANOS_PER_DAY: i64 = 86_400 * NANOS_PER_SECOND;
 
 const TODO: &str = "not implemented yet";
 
@@ -576,6 +580,53 @@ impl DateTime {
 			timezone: self.timezone,
 		}
 	}
+	/// The number of nanoseconds since the Unix epoch.
+	fn as_epoch_nanos(&self) -> i128 {
+		let time = self.time();
+		i128::from(self.date().as_days()) * i128::from(NANOS_PER_DAY)
+			+ i128::from(time.0.num_seconds_from_midnight()) * i128::from(NANOS_PER_SECOND)
+			+ i128::from(time.nanosecond())
+	}
+	/// The date time `nanos` nanoseconds since the Unix epoch, saturating at the
+	/// first and last representable instants.
+	fn with_epoch_nanos(&self, nanos: i128) -> Self {
+		let min = i128::from(JULIAN_DAY_OF_EPOCH + i64::from(i32::MIN)) * i128::from(NANOS_PER_DAY);
+		let max = (i128::from(i32::MAX) + 1) * i128::from(NANOS_PER_DAY) - 1;
+		let nanos = nanos.clamp(min, max);
+		let days = nanos.div_euclid(NANOS_PER_DAY.into());
+		let nanos = nanos.rem_euclid(NANOS_PER_DAY.into());
+		let date = DateWithoutTimezone::from_days(days.try_into().unwrap()).unwrap();
+		let time = TimeWithoutTimezone::from_seconds(
+			(nanos / i128::from(NANOS_PER_SECOND)).try_into().unwrap(),
+			(nanos % i128::from(NANOS_PER_SECOND)).try_into().unwrap(),
+		)
+		.unwrap();
+		Self {
+			date_time: DateTimeWithoutTimezone::from_date_time(date, time).unwrap(),
+			timezone: self.timezone,
+		}
+	}
+}
+/// Windows of [`DateTime`]s are aligned to the Unix epoch in UTC, and must be
+/// of a fixed length, i.e. a [`Duration`] without months.
+impl EventTime for DateTime {
+	type Duration = Duration;
+
+	fn truncate(&self, duration: &Self::Duration) -> Self {
+		let length = duration.as_fixed_nanos();
+		assert!(length > 0, "window length must be positive");
+		let nanos = self.as_epoch_nanos();
+		self.with_epoch_nanos(nanos - nanos.rem_euclid(length))
+	}
+	fn add_duration(&self, duration: &Self::Duration) -> Self {
+		self.with_epoch_nanos(self.as_epoch_nanos() + duration.as_fixed_nanos())
+	}
+	fn sub_duration(&self, duration: &Self::Duration) -> Self {
+		self.with_epoch_nanos(self.as_epoch_nanos() - duration.as_fixed_nanos())
+	}
+	fn is_positive(duration: &Self::Duration) -> bool {
+		duration.as_fixed_nanos() > 0
+	}
 }
 impl AmadeusOrd for DateTime {
 	fn amadeus_cmp(&self, other: &Self) -> Ordering {
@@ -611,6 +662,48 @@ pub struct Duration {
 	days: i64,
 	nanos: i64,
 }
+impl Duration {
+	pub fn new(months: i64, days: i64, nanos: i64) -> Self {
+		Self {
+			months,
+			days,
+			nanos,
+		}
+	}
+	pub fn from_days(days: i64) -> Self {
+		Self::new(0, days, 0)
+	}
+	pub fn from_hours(hours: i64) -> Self {
+		Self::from_seconds(hours.checked_mul(3600).expect("duration out of range"))
+	}
+	pub fn from_minutes(minutes: i64) -> Self {
+		Self::from_seconds(minutes.checked_mul(60).expect("duration out of range"))
+	}
+	/// Panics if the duration is more nanoseconds than fit in an `i64`, which is
+	/// about 292 years either way.
+	pub fn from_seconds(seconds: i64) -> Self {
+		let nanos = seconds
+			.checked_mul(NANOS_PER_SECOND)
+			.expect("duration out of range");
+		Self::new(0, 0, nanos)
+	}
+	pub fn from_nanos(nanos: i64) -> Self {
+		Self::new(0, 0, nanos)
+	}
+	pub fn months(&self) -> i64 {
+		self.months
+	}
+	pub fn days(&self) -> i64 {
+		self.days
+	}
+	pub fn nanos(&self) -> i64 {
+		self.nanos
+	}
+	fn as_fixed_nanos(&self) -> i128 {
+		assert_eq!(self.months, 0, "months aren't a fixed length of time");
+		i128::from(self.days) * i128::from(NANOS_PER_DAY) + i128::from(self.nanos)
+	}
+}
 impl AmadeusOrd for Duration {
 	fn amadeus_cmp(&self, other: &Self) -> Ordering {
 		Ord::cmp(self, other)
@@ -1086,4 +1179,56 @@ mod tests {
 		check_datetime_conversion(2013, 5, 12, 16, 38, 0);
 		check_datetime_conversion(2014, 11, 28, 21, 15, 12);
 	}
+
+	#[test]
+	fn test_event_time() {
+		let utc = Timezone::UTC;
+		let time = DateTime::new(2020, 2, 29, 23, 47, 12, 5, utc).unwrap();
+		assert_eq!(
+			time.truncate(&Duration::from_hours(1)),
+			DateTime::new(2020, 2, 29, 23, 0, 0, 0, utc).unwrap()
+		);
+		assert_eq!(
+			time.truncate(&Duration::from_days(1)),
+			DateTime::new(2020, 2, 29, 0, 0, 0, 0, utc).unwrap()
+		);
+		assert_eq!(
+			time.add_duration(&Duration::from_minutes(13)),
+			DateTime::new(2020, 3, 1, 0, 0, 12, 5, utc).unwrap()
+		);
+		assert_eq!(
+			time.sub_duration(&Duration::from_days(366)),
+			DateTime::new(2019, 2, 28, 23, 47, 12, 5, utc).unwrap()
+		);
+		let before_epoch = DateTime::new(1969, 12, 31, 23, 59, 59, 0, utc).unwrap();
+		assert_eq!(
+			before_epoch.truncate(&Duration::from_minutes(15)),
+			DateTime::new(1969, 12, 31, 23, 45, 0, 0, utc).unwrap()
+		);
+
+		// Window boundaries beyond the representable range saturate
+		let first = DateTime::from_date_time(
+			Date::from_days(JULIAN_DAY_OF_EPOCH + i64::from(i32::MIN), utc).unwrap(),
+			Time::from_seconds(0, 0, utc).unwrap(),
+		)
+		.unwrap();
+		let last = DateTime::from_date_time(
+			Date::from_days(i64::from(i32::MAX), utc).unwrap(),
+			Time::from_seconds(86_399, 999_999_999, utc).unwrap(),
+		)
+		.unwrap();
+		assert_eq!(first.sub_duration(&Duration::from_days(1)), first);
+		assert_eq!(last.add_duration(&Duration::from_days(1)), last);
+		assert_eq!(
+			last.truncate(&Duration::from_days(1))
+				.add_duration(&Duration::from_days(1)),
+			last
+		);
+	}
+
+	#[test]
+	#[should_panic(expected = "duration out of range")]
+	fn test_duration_out_of_range() {
+		let _ = Duration::from_hours(i64::MAX / 3600);
+	}
 }
@@ -66,8 +66,8 @@ pub mod dist {
 		#[doc(no_inline)]
 		pub use crate::{
 			data::{
-				Date, DateTime, DateTimeWithoutTimezone, DateWithoutTimezone, Decimal, Downcast, DowncastFrom, Enum, Group, Time, TimeWithoutTimezone, Timezone
-			}, par_pipe::DistributedPipe, par_stream::Identity, pool::ThreadPool, source::*, Data, DistributedStream, FromDistributedStream, IntoDistributedStream, IteratorExt, List, Value
+				Date, DateTime, DateTimeWithoutTimezone, DateWithoutTimezone, Decimal, Downcast, DowncastFrom, Duration, Enum, Group, Time, TimeWithoutTimezone, Timezone
+			}, par_pipe::DistributedPipe, par_stream::{Identity, JoinStrategy, Session, Sliding, Tumbling}, pool::ThreadPool, source::*, Data, DistributedStream, FromDistributedStream, IntoDistributedStream, IteratorExt, List, Value
 		};
 		#[doc(no_inline)]
 		pub use serde_closure::{Fn, FnMut, FnOnce};
@@ -85,8 +85,8 @@ pub mod prelude {
 	#[doc(no_inline)]
 	pub use crate::{
 		data::{
-			Date, DateTime, DateTimeWithoutTimezone, DateWithoutTimezone, Decimal, Downcast, DowncastFrom, Enum, Group, Time, TimeWithoutTimezone, Timezone
-		}, par_pipe::ParallelPipe, par_stream::Identity, pool::ThreadPool, source::*, Data, FromParallelStream, IntoParallelStream, IteratorExt, List, ParallelStream, Value
+			Date, DateTime, DateTimeWithoutTimezone, DateWithoutTimezone, Decimal, Downcast, DowncastFrom, Duration, Enum, Group, Time, TimeWithoutTimezone, Timezone
+		}, par_pipe::ParallelPipe, par_stream::{Identity, JoinStrategy, Session, Sliding, Tumbling}, pool::ThreadPool, source::*, Data, FromParallelStream, IntoParallelStream, IteratorExt, List, ParallelStream, Value
 	};
 }
 
@@ -1,12 +1,18 @@
-use constellation::{spawn, Receiver, Resources, Sender, SpawnError};
-use futures::{future::LocalBoxFuture, FutureExt};
+use constellation::{pid, spawn, Receiver, Resources, Sender, SpawnError};
+use constellation_internal::PidInternal;
+use futures::{
+	future::{self, Either, LocalBoxFuture}, FutureExt
+};
 use serde_closure::{traits, FnOnce};
 use serde_traitobject as st;
 use std::{
-	any, collections::VecDeque, fmt, future::Future, mem, panic::{self, RefUnwindSafe, UnwindSafe}, sync::{Arc, Mutex}
+	any, collections::VecDeque, fmt, future::Future, mem, panic::{self, RefUnwindSafe, UnwindSafe}, sync::{
+		atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering}, Arc, Mutex
+	}, thread, time::Duration
 };
+use tokio::time::{delay_for, timeout};
 
-use amadeus_core::pool::ProcessSend;
+use amadeus_core::{par_sink::set_exchange_host, pool::ProcessSend};
 
 use super::{
 	util::{assert_sync_and_send, OnDrop, Panicked, RoundRobin, Synchronize}, ThreadPool
@@ -16,6 +22,9 @@ use super::{
 type Request = st::Box<dyn st::sc::FnOnce(&ThreadPool) -> LocalBoxFuture<'static, Response> + Send>;
 type Response = Box<dyn st::Any + Send>;
 
+/// How often a process running a task tells the pool it's still alive.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
 mod future_ext {
 	use futures::{future::Future, pin_mut};
 	use std::{
@@ -62,14 +71,17 @@ use future_ext::FutureExt1;
 #[derive(Debug)]
 struct Process {
 	sender: Sender<Option<Request>>,
-	receiver: Receiver<Result<Response, Panicked>>,
+	/// `None` is a heartbeat, sent while the process is running a task.
+	receiver: Receiver<Option<Result<Response, Panicked>>>,
 	inner: Mutex<ProcessInner>,
 	synchronize: Synchronize,
+	heartbeat_timeout: Option<Duration>,
 }
 struct ProcessInner {
 	queue: VecDeque<Queued<Result<Response, Panicked>>>,
 	received: usize,
 	tail: usize,
+	exited: bool,
 }
 impl fmt::Debug for ProcessInner {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -77,9 +89,194 @@ impl fmt::Debug for ProcessInner {
 			.field("queue", &())
 			.field("received", &self.received)
 			.field("tail", &self.tail)
+			.field("exited", &self.exited)
 			.finish()
 	}
 }
+impl Process {
+	#[allow(clippy::double_parens)] // TODO: work out what's triggering this
+	async fn spawn(
+		threads: Option<usize>, tasks: Option<usize>, resources: Resources,
+		heartbeat_timeout: Option<Duration>,
+	) -> Result<Self, SpawnError> {
+		let child = spawn(
+			resources,
+			FnOnce!(move |parent| {
+				// Runs written by this process are fetched from it by the processes that read them
+				set_exchange_host(pid().addr().ip());
+				tokio::runtime::Builder::new()
+					.threaded_scheduler()
+					.enable_all()
+					.build()
+					.unwrap()
+					.block_on(async {
+						let receiver = Receiver::<Option<Request>>::new(parent);
+						let sender =
+							Arc::new(Sender::<Option<Result<Response, Panicked>>>::new(parent));
+
+						// The parent can't otherwise tell a slow task from an exited process.
+						// They're sent from a thread of their own so that a task blocking the
+						// runtime doesn't stop them.
+						let running = Arc::new(AtomicBool::new(false));
+						let _ = thread::spawn({
+							let (sender, running) = (Arc::downgrade(&sender), running.clone());
+							move || loop {
+								thread::sleep(HEARTBEAT_INTERVAL);
+								let sender = match sender.upgrade() {
+									Some(sender) => sender,
+									None => break,
+								};
+								if running.load(Ordering::SeqCst) {
+									sender.send(None).block();
+								}
+							}
+						});
+
+						let thread_pool = ThreadPool::new(threads, tasks).unwrap();
+
+						// Requests are also received while a task runs, so that the parent can
+						// terminate a process it has given up on
+						let mut queue = VecDeque::new();
+						'process: loop {
+							let work = match queue.pop_front() {
+								Some(work) => work,
+								None => match receiver.recv().await.unwrap() {
+									Some(work) => work,
+									None => break,
+								},
+							};
+							running.store(true, Ordering::SeqCst);
+							let ret = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+								work.into_box().call_once_box((&thread_pool,))
+							}));
+							let mut ret = match ret {
+								Ok(t) => panic::AssertUnwindSafe(t).catch_unwind().boxed_local(),
+								Err(e) => future::ready(Err(e)).boxed_local(),
+							};
+							let ret = loop {
+								match future::select(ret, delay_for(HEARTBEAT_INTERVAL)).await {
+									Either::Left((ret, _)) => break ret,
+									Either::Right(((), ret_)) => ret = ret_,
+								}
+								while let Some(recv) = receiver.try_recv() {
+									match recv().unwrap() {
+										Some(work) => queue.push_back(work),
+										// The parent has given up on this process
+										None => break 'process,
+									}
+								}
+							};
+							running.store(false, Ordering::SeqCst);
+							sender.send(Some(ret.map_err(Panicked::from))).await;
+						}
+					})
+			}),
+		)
+		.await?;
+
+		let sender = Sender::new(child);
+		let receiver = Receiver::new(child);
+
+		let (queue, received, tail, exited) = (VecDeque::new(), 0, 0, false);
+
+		Ok(Self {
+			sender,
+			receiver,
+			inner: Mutex::new(ProcessInner {
+				queue,
+				received,
+				tail,
+				exited,
+			}),
+			synchronize: Synchronize::new(),
+			heartbeat_timeout,
+		})
+	}
+	fn exited(&self) -> bool {
+		self.inner.lock().unwrap().exited
+	}
+	/// Send `request` to the process and wait for its response. Returns `None` if the process exited before responding, or had already been declared exited, in which case `request` isn't sent.
+	async fn run(&self, request: Request) -> Option<Result<Response, Panicked>> {
+		if self.exited() {
+			return None;
+		}
+		self.sender.send(Some(request)).await;
+		let index;
+		{
+			// https://github.com/rust-lang/rust/issues/57478
+			let mut process_inner_lock = self.inner.lock().unwrap();
+			process_inner_lock.queue.push_back(Queued::Awaiting);
+			index = process_inner_lock.tail + process_inner_lock.queue.len() - 1;
+			drop(process_inner_lock);
+		}
+		let on_drop = OnDrop::new(|| {
+			let mut process_inner_lock = self.inner.lock().unwrap();
+			let offset = index - process_inner_lock.tail;
+			process_inner_lock.queue[offset].drop_();
+			while let Some(Queued::Taken) = process_inner_lock.queue.front() {
+				let _ = process_inner_lock.queue.pop_front().unwrap();
+				process_inner_lock.tail += 1;
+			}
+			drop(process_inner_lock);
+		});
+		let done = || {
+			let process_inner_lock = self.inner.lock().unwrap();
+			process_inner_lock.received > index || process_inner_lock.exited
+		};
+		while !done() {
+			self.synchronize
+				.synchronize(async {
+					if done() {
+						return;
+					}
+					let recv = self.receiver.recv();
+					let z = match self.heartbeat_timeout {
+						Some(heartbeat_timeout) => timeout(heartbeat_timeout, recv).await.ok(),
+						None => Some(recv.await),
+					};
+					let mut process_inner_lock = self.inner.lock().unwrap();
+					match z {
+						Some(Ok(Some(t))) => {
+							let offset = process_inner_lock.received - process_inner_lock.tail;
+							process_inner_lock.queue[offset].received(t);
+							process_inner_lock.received += 1;
+						}
+						Some(Ok(None)) => (),
+						Some(Err(_)) => {
+							// The process has exited; everything still awaiting a response is lost
+							process_inner_lock.exited = true;
+						}
+						None => {
+							// The process is treated as exited having gone quiet, so tell it to
+							// terminate rather than carry on with work that's resubmitted. It may
+							// in fact have exited, so don't wait for the message to be sent.
+							process_inner_lock.exited = true;
+							if let Some(send) = self.sender.try_send() {
+								send(None);
+							}
+						}
+					}
+					drop(process_inner_lock);
+				})
+				.await;
+		}
+		on_drop.cancel();
+		let mut process_inner_lock = self.inner.lock().unwrap();
+		let offset = index - process_inner_lock.tail;
+		let ret = if process_inner_lock.received > index {
+			Some(process_inner_lock.queue[offset].take())
+		} else {
+			process_inner_lock.queue[offset].drop_();
+			None
+		};
+		while let Some(Queued::Taken) = process_inner_lock.queue.front() {
+			let _ = process_inner_lock.queue.pop_front().unwrap();
+			process_inner_lock.tail += 1;
+		}
+		drop(process_inner_lock);
+		ret
+	}
+}
 
 #[derive(Debug)]
 enum Queued<T> {
@@ -105,49 +302,43 @@ impl<T> Queued<T> {
 	}
 }
 
+#[derive(Debug)]
+struct Slot {
+	process: Mutex<Arc<Process>>,
+	/// Tasks dispatched to the slot that haven't finished. Counted when the slot is picked, before the task is sent, so that concurrent dispatches see each other.
+	in_flight: AtomicUsize,
+}
+
 #[derive(Debug)]
 struct ProcessPoolInner {
-	processes: Vec<Process>,
+	processes: Vec<Slot>,
 	i: RoundRobin,
+	threads: Option<usize>,
+	tasks: Option<usize>,
+	resources: Resources,
+	max_attempts: usize,
+	heartbeat_timeout: Option<Duration>,
+	bytes_sent: AtomicU64,
+	bytes_received: AtomicU64,
 }
 impl ProcessPoolInner {
-	#[allow(clippy::double_parens)] // TODO: work out what's triggering this
 	fn new(
 		processes: Option<usize>, threads: Option<usize>, tasks: Option<usize>,
-		resources: Resources,
+		resources: Resources, max_attempts: usize, heartbeat_timeout: Option<Duration>,
 	) -> Result<Self, SpawnError> {
+		assert_ne!(max_attempts, 0, "max_attempts must be at least 1");
+		if let Some(heartbeat_timeout) = heartbeat_timeout {
+			assert!(
+				heartbeat_timeout > HEARTBEAT_INTERVAL,
+				"heartbeat_timeout must be longer than {:?}",
+				HEARTBEAT_INTERVAL
+			);
+		}
+		set_exchange_host(pid().addr().ip());
 		let processes = processes.unwrap_or(3); // TODO!
 		let mut processes_vec = Vec::with_capacity(processes);
 		for _ in 0..processes {
-			let child = spawn(
-				resources,
-				FnOnce!(move |parent| {
-					tokio::runtime::Builder::new()
-						.threaded_scheduler()
-						.enable_all()
-						.build()
-						.unwrap()
-						.block_on(async {
-							let receiver = Receiver::<Option<Request>>::new(parent);
-							let sender = Sender::<Result<Response, Panicked>>::new(parent);
-
-							let thread_pool = ThreadPool::new(threads, tasks).unwrap();
-
-							while let Some(work) = receiver.recv().await.unwrap() {
-								let ret = panic::catch_unwind(panic::AssertUnwindSafe(|| {
-									work.into_box().call_once_box((&thread_pool,))
-								}));
-								let ret = match ret {
-									Ok(t) => panic::AssertUnwindSafe(t).catch_unwind().await,
-									Err(e) => Err(e),
-								}
-								.map_err(Panicked::from);
-								sender.send(ret).await;
-							}
-						})
-				}),
-			)
-			.block();
+			let child = Process::spawn(threads, tasks, resources, heartbeat_timeout).block();
 			if let Err(err) = child {
 				for Process { sender, .. } in processes_vec {
 					// TODO: select
@@ -155,95 +346,136 @@ impl ProcessPoolInner {
 				}
 				return Err(err);
 			}
-			let child = child.unwrap();
-
-			let sender = Sender::new(child);
-			let receiver = Receiver::new(child);
-
-			let (queue, received, tail) = (VecDeque::new(), 0, 0);
-
-			processes_vec.push(Process {
-				sender,
-				receiver,
-				inner: Mutex::new(ProcessInner {
-					queue,
-					received,
-					tail,
-				}),
-				synchronize: Synchronize::new(),
-			})
+			processes_vec.push(child.unwrap())
 		}
 		let i = RoundRobin::new(0, processes_vec.len());
 		Ok(Self {
-			processes: processes_vec,
+			processes: processes_vec
+				.into_iter()
+				.map(|process| Slot {
+					process: Mutex::new(Arc::new(process)),
+					in_flight: AtomicUsize::new(0),
+				})
+				.collect(),
 			i,
+			threads,
+			tasks,
+			resources,
+			max_attempts,
+			heartbeat_timeout,
+			bytes_sent: AtomicU64::new(0),
+			bytes_received: AtomicU64::new(0),
 		})
 	}
 	fn processes(&self) -> usize {
 		self.processes.len()
 	}
+	fn queue_depths(&self) -> Vec<usize> {
+		self.processes
+			.iter()
+			.map(|slot| slot.in_flight.load(Ordering::SeqCst))
+			.collect()
+	}
+	fn bytes_sent(&self) -> u64 {
+		self.bytes_sent.load(Ordering::SeqCst)
+	}
+	fn bytes_received(&self) -> u64 {
+		self.bytes_received.load(Ordering::SeqCst)
+	}
+	/// Pick the least-loaded live process and count a task against it, breaking ties round-robin so that bursts of tasks are spread evenly. The caller must decrement its `in_flight` once the task is done with it.
+	fn next(&self) -> &Slot {
+		let len = self.processes.len();
+		loop {
+			let start = self.i.get();
+			let (i, in_flight) = (0..len)
+				.map(|offset| (start + offset) % len)
+				.map(|i| (i, self.processes[i].in_flight.load(Ordering::SeqCst)))
+				.min_by_key(|&(i, in_flight)| {
+					let exited = self.processes[i].process.lock().unwrap().exited();
+					(exited, in_flight)
+				})
+				.unwrap();
+			// Retry if another dispatch changed the count since it was read
+			if self.processes[i]
+				.in_flight
+				.compare_exchange(in_flight, in_flight + 1, Ordering::SeqCst, Ordering::SeqCst)
+				.is_ok()
+			{
+				return &self.processes[i];
+			}
+		}
+	}
+	/// Run a serialized [`Request`] on the least-loaded process, resubmitting it to a respawned process if the one running it exits, up to `max_attempts` times.
+	async fn run(&self, request: Vec<u8>) -> Result<Response, Panicked> {
+		let mut attempts = 0;
+		loop {
+			let slot = self.next();
+			let _in_flight = OnDrop::new(|| {
+				let _ = slot.in_flight.fetch_sub(1, Ordering::SeqCst);
+			});
+			let process = slot.process.lock().unwrap().clone();
+			let _ = self
+				.bytes_sent
+				.fetch_add(request.len() as u64, Ordering::SeqCst);
+			let request: Request = bincode::deserialize(&request).unwrap();
+			if let Some(response) = process.run(request).await {
+				if let Ok(response) = &response {
+					let _ = self.bytes_received.fetch_add(
+						bincode::serialized_size(response).unwrap(),
+						Ordering::SeqCst,
+					);
+				}
+				return response;
+			}
+			attempts += 1;
+			self.respawn(&slot.process, &process).await;
+			if attempts == self.max_attempts {
+				return Err(Panicked::new(format!(
+					"process exited while running task, {} times",
+					attempts
+				)));
+			}
+		}
+	}
+	/// Replace `exited` in `slot` with a newly spawned process, unless another task has already done so.
+	async fn respawn(&self, slot: &Mutex<Arc<Process>>, exited: &Arc<Process>) {
+		exited
+			.synchronize
+			.synchronize(async {
+				let respawned = !Arc::ptr_eq(&*slot.lock().unwrap(), exited);
+				if respawned {
+					return;
+				}
+				// If this fails the slot keeps the exited process, and the next task sent to it will try again
+				let process = Process::spawn(
+					self.threads,
+					self.tasks,
+					self.resources,
+					self.heartbeat_timeout,
+				)
+				.await;
+				if let Ok(process) = process {
+					*slot.lock().unwrap() = Arc::new(process);
+				}
+			})
+			.await
+	}
 	async fn spawn<F, Fut, T>(&self, work: F) -> Result<T, Panicked>
 	where
 		F: for<'a> traits::FnOnce<(&'a ThreadPool,), Output = Fut> + ProcessSend + 'static,
 		Fut: Future<Output = T> + 'static,
 		T: ProcessSend + 'static,
 	{
-		let process_index = self.i.get();
-		let process = &self.processes[process_index];
-		let x = process
-			.sender
-			.send(Some(st::Box::new(FnOnce!(move |thread_pool: &_| {
-				let work: F = work;
-				work.call_once((thread_pool,))
-					.map(|res| Box::new(res) as Response)
-					.boxed_local()
-			})) as Request));
-		x.await;
-		let index;
-		{
-			// https://github.com/rust-lang/rust/issues/57478
-			let mut process_inner_lock = process.inner.lock().unwrap();
-			process_inner_lock.queue.push_back(Queued::Awaiting);
-			index = process_inner_lock.tail + process_inner_lock.queue.len() - 1;
-			drop(process_inner_lock);
-		}
-		let on_drop = OnDrop::new(|| {
-			let mut process_inner_lock = process.inner.lock().unwrap();
-			let offset = index - process_inner_lock.tail;
-			process_inner_lock.queue[offset].drop_();
-			while let Some(Queued::Taken) = process_inner_lock.queue.front() {
-				let _ = process_inner_lock.queue.pop_front().unwrap();
-				process_inner_lock.tail += 1;
-			}
-			drop(process_inner_lock);
-		});
-		while process.inner.lock().unwrap().received <= index {
-			process
-				.synchronize
-				.synchronize(async {
-					if process.inner.lock().unwrap().received > index {
-						return;
-					}
-					let z = process.receiver.recv().await;
-					let t = z.unwrap();
-					let mut process_inner_lock = process.inner.lock().unwrap();
-					let offset = process_inner_lock.received - process_inner_lock.tail;
-					process_inner_lock.queue[offset].received(t);
-					process_inner_lock.received += 1;
-					drop(process_inner_lock);
-				})
-				.await;
-		}
-		on_drop.cancel();
-		let mut process_inner_lock = process.inner.lock().unwrap();
-		let offset = index - process_inner_lock.tail;
-		let boxed = process_inner_lock.queue[offset].take();
-		while let Some(Queued::Taken) = process_inner_lock.queue.front() {
-			let _ = process_inner_lock.queue.pop_front().unwrap();
-			process_inner_lock.tail += 1;
-		}
-		drop(process_inner_lock);
-		boxed.map(|boxed| *Box::<dyn any::Any>::downcast::<T>(boxed.into_any_send()).unwrap())
+		let request = st::Box::new(FnOnce!(move |thread_pool: &_| {
+			let work: F = work;
+			work.call_once((thread_pool,))
+				.map(|res| Box::new(res) as Response)
+				.boxed_local()
+		})) as Request;
+		let request = bincode::serialize(&request).unwrap();
+		self.run(request)
+			.await
+			.map(|boxed| *Box::<dyn any::Any>::downcast::<T>(boxed.into_any_send()).unwrap())
 	}
 	#[allow(unsafe_code)]
 	async unsafe fn spawn_unchecked<'a, F, Fut, T>(&self, work: F) -> Result<T, Panicked>
@@ -252,8 +484,6 @@ impl ProcessPoolInner {
 		Fut: Future<Output = T> + 'a,
 		T: ProcessSend + 'a,
 	{
-		let process_index = self.i.get();
-		let process = &self.processes[process_index];
 		let request = st::Box::new(FnOnce!(move |thread_pool: &_| {
 			let work: F = work;
 			work.call_once((thread_pool,))
@@ -267,53 +497,8 @@ impl ProcessPoolInner {
 			st::Box<dyn st::sc::FnOnce(&ThreadPool) -> LocalBoxFuture<'a, Response> + Send>,
 			st::Box<dyn st::sc::FnOnce(&ThreadPool) -> LocalBoxFuture<'static, Response> + Send>,
 		>(request);
-		let x = process.sender.send(Some(request));
-		x.await;
-		let index;
-		{
-			// https://github.com/rust-lang/rust/issues/57478
-			let mut process_inner_lock = process.inner.lock().unwrap();
-			process_inner_lock.queue.push_back(Queued::Awaiting);
-			index = process_inner_lock.tail + process_inner_lock.queue.len() - 1;
-			drop(process_inner_lock);
-		}
-		let on_drop = OnDrop::new(|| {
-			let mut process_inner_lock = process.inner.lock().unwrap();
-			let offset = index - process_inner_lock.tail;
-			process_inner_lock.queue[offset].drop_();
-			while let Some(Queued::Taken) = process_inner_lock.queue.front() {
-				let _ = process_inner_lock.queue.pop_front().unwrap();
-				process_inner_lock.tail += 1;
-			}
-			drop(process_inner_lock);
-		});
-		while process.inner.lock().unwrap().received <= index {
-			process
-				.synchronize
-				.synchronize(async {
-					if process.inner.lock().unwrap().received > index {
-						return;
-					}
-					let z = process.receiver.recv().await;
-					let t = z.unwrap();
-					let mut process_inner_lock = process.inner.lock().unwrap();
-					let offset = process_inner_lock.received - process_inner_lock.tail;
-					process_inner_lock.queue[offset].received(t);
-					process_inner_lock.received += 1;
-					drop(process_inner_lock);
-				})
-				.await;
-		}
-		on_drop.cancel();
-		let mut process_inner_lock = process.inner.lock().unwrap();
-		let offset = index - process_inner_lock.tail;
-		let boxed = process_inner_lock.queue[offset].take();
-		while let Some(Queued::Taken) = process_inner_lock.queue.front() {
-			let _ = process_inner_lock.queue.pop_front().unwrap();
-			process_inner_lock.tail += 1;
-		}
-		drop(process_inner_lock);
-		boxed.map(|boxed| {
+		let request = bincode::serialize(&request).unwrap();
+		self.run(request).await.map(|boxed| {
 			bincode::deserialize(
 				&Box::<dyn any::Any>::downcast::<Vec<u8>>(boxed.into_any_send()).unwrap(),
 			)
@@ -323,9 +508,12 @@ impl ProcessPoolInner {
 }
 impl Drop for ProcessPoolInner {
 	fn drop(&mut self) {
-		for Process { sender, .. } in &self.processes {
-			// TODO: select, incl recv
-			sender.send(None).block();
+		for slot in &mut self.processes {
+			let process = slot.process.get_mut().unwrap();
+			if !process.exited() {
+				// TODO: select, incl recv
+				process.sender.send(None).block();
+			}
 		}
 	}
 }
@@ -337,14 +525,42 @@ impl ProcessPool {
 	pub fn new(
 		processes: Option<usize>, threads: Option<usize>, tasks: Option<usize>,
 		resources: Resources,
+	) -> Result<Self, SpawnError> {
+		Self::new_with(processes, threads, tasks, resources, 1, None)
+	}
+	/// Create a pool whose tasks are each run up to `max_attempts` times. If a process exits (for example if it is killed or runs out of memory) the tasks it was running are resubmitted to a newly spawned replacement, and only once a task has seen `max_attempts` processes exit does it fail. A task that panics fails immediately. [`new`](Self::new) uses 1 attempt and no `heartbeat_timeout`.
+	///
+	/// A process running tasks sends a heartbeat every second, from a thread of its own so that tasks blocking it don't delay them. With a `heartbeat_timeout`, a process that isn't heard from for that long is treated as having exited, and is sent no more tasks and told to terminate; without one, a process is only treated as exited once its connection to the pool is lost.
+	///
+	/// A resubmitted task may have partly or even fully run already. Tasks with side effects that aren't idempotent, such as those of the [`Write`](amadeus_core::par_sink::Write) sink, which writes a new file or opens a new connection each time it's run, can then duplicate their output, so `max_attempts` should be 1 for them.
+	pub fn new_with(
+		processes: Option<usize>, threads: Option<usize>, tasks: Option<usize>,
+		resources: Resources, max_attempts: usize, heartbeat_timeout: Option<Duration>,
 	) -> Result<Self, SpawnError> {
 		Ok(Self(Arc::new(ProcessPoolInner::new(
-			processes, threads, tasks, resources,
+			processes,
+			threads,
+			tasks,
+			resources,
+			max_attempts,
+			heartbeat_timeout,
 		)?)))
 	}
 	pub fn processes(&self) -> usize {
 		self.0.processes()
 	}
+	/// The number of tasks each process is running or has queued. Tasks are dispatched to the process with the fewest.
+	pub fn queue_depths(&self) -> Vec<usize> {
+		self.0.queue_depths()
+	}
+	/// The number of bytes of tasks sent to the processes, counting each attempt. Data exchanged between tasks, like the partitions of [`repartition_by_key`](amadeus_core::par_stream::DistributedStream::repartition_by_key), is fetched by each process from the one that wrote it, so isn't counted.
+	pub fn bytes_sent(&self) -> u64 {
+		self.0.bytes_sent()
+	}
+	/// The number of bytes of results received from the processes.
+	pub fn bytes_received(&self) -> u64 {
+		self.0.bytes_received()
+	}
 	pub fn spawn<F, Fut, T>(&self, work: F) -> impl Future<Output = Result<T, Panicked>> + Send
 	where
 		F: traits::FnOnce(&ThreadPool) -> Fut + ProcessSend + 'static,
@@ -27,6 +27,11 @@ impl RoundRobin {
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Panicked(String);
+impl Panicked {
+	pub(crate) fn new(message: String) -> Self {
+		Self(message)
+	}
+}
 impl From<Box<dyn Any + Send>> for Panicked {
 	fn from(e: Box<dyn Any + Send>) -> Self {
 		// https://github.com/rust-lang/rust/blob/b43eb4235ac43c822d903ad26ed806f34cc1a14a/src/libstd/panicking.rs#L179-L185
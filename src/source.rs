@@ -13,6 +13,10 @@ use crate::{
 #[cfg(feature = "aws")]
 #[doc(inline)]
 pub use amadeus_aws::Cloudfront;
+#[doc(inline)]
+pub use amadeus_core::{
+	file::{HiveDirectory, PartitionValues, WriteMode}, util::RetryPolicy
+};
 #[cfg(feature = "aws")]
 pub mod aws {
 	pub use crate::data::CloudfrontRow;
@@ -24,13 +28,35 @@ pub mod aws {
 pub use amadeus_commoncrawl::CommonCrawl;
 #[cfg(feature = "parquet")]
 #[doc(inline)]
-pub use amadeus_parquet::{Parquet, ParquetDirectory};
+pub use amadeus_parquet::{Parquet, ParquetDestination, ParquetDirectory};
+#[cfg(feature = "parquet")]
+pub mod parquet {
+	#[doc(inline)]
+	pub use amadeus_parquet::{
+		Compression, Filter, GroupPredicate, MapPredicate, ParquetDestinationBuilder, ParquetError, ValuePredicate
+	};
+}
 #[cfg(feature = "postgres")]
 #[doc(inline)]
-pub use amadeus_postgres::{Postgres, PostgresSelect, PostgresTable};
+pub use amadeus_postgres::{Postgres, PostgresDestination, PostgresSelect, PostgresTable};
+#[cfg(feature = "postgres")]
+pub mod postgres {
+	#[doc(inline)]
+	pub use amadeus_postgres::{ConnectParams, PostgresDestinationBuilder, PostgresError};
+}
 #[cfg(feature = "amadeus-serde")]
 #[doc(inline)]
-pub use amadeus_serde::{Csv, Json};
+pub use amadeus_serde::{Csv, CsvDestination, Json, JsonDestination};
+#[cfg(feature = "amadeus-serde")]
+pub mod csv {
+	#[doc(inline)]
+	pub use amadeus_serde::{CsvBuilder, CsvDestinationBuilder, CsvError, QuoteStyle};
+}
+#[cfg(feature = "amadeus-serde")]
+pub mod json {
+	#[doc(inline)]
+	pub use amadeus_serde::{JsonDestinationBuilder, JsonError};
+}
 
 pub trait Source: Clone + Debug {
 	type Item: crate::data::Data;
@@ -92,6 +118,44 @@ where
 		<Self as amadeus_core::Source>::dist_stream(self)
 	}
 }
+#[cfg(feature = "amadeus-serde")]
+impl<D, Row> Destination for CsvDestination<D, Row>
+where
+	D: amadeus_core::file::Create,
+	Row: super::data::Data,
+{
+	type Item = <Self as amadeus_core::Destination>::Item;
+	type Error = <Self as amadeus_core::Destination>::Error;
+
+	type ParSink = <Self as amadeus_core::Destination>::ParSink;
+	type DistSink = <Self as amadeus_core::Destination>::DistSink;
+
+	fn par_sink(self) -> Self::ParSink {
+		<Self as amadeus_core::Destination>::par_sink(self)
+	}
+	fn dist_sink(self) -> Self::DistSink {
+		<Self as amadeus_core::Destination>::dist_sink(self)
+	}
+}
+#[cfg(feature = "amadeus-serde")]
+impl<D, Row> Destination for JsonDestination<D, Row>
+where
+	D: amadeus_core::file::Create,
+	Row: super::data::Data,
+{
+	type Item = <Self as amadeus_core::Destination>::Item;
+	type Error = <Self as amadeus_core::Destination>::Error;
+
+	type ParSink = <Self as amadeus_core::Destination>::ParSink;
+	type DistSink = <Self as amadeus_core::Destination>::DistSink;
+
+	fn par_sink(self) -> Self::ParSink {
+		<Self as amadeus_core::Destination>::par_sink(self)
+	}
+	fn dist_sink(self) -> Self::DistSink {
+		<Self as amadeus_core::Destination>::dist_sink(self)
+	}
+}
 #[cfg(feature = "parquet")]
 impl<File, Row> Source for Parquet<File, Row>
 where
@@ -111,6 +175,25 @@ where
 		<Self as amadeus_core::Source>::dist_stream(self)
 	}
 }
+#[cfg(feature = "parquet")]
+impl<D, Row> Destination for ParquetDestination<D, Row>
+where
+	D: amadeus_core::file::Create,
+	Row: super::data::Data,
+{
+	type Item = <Self as amadeus_core::Destination>::Item;
+	type Error = <Self as amadeus_core::Destination>::Error;
+
+	type ParSink = <Self as amadeus_core::Destination>::ParSink;
+	type DistSink = <Self as amadeus_core::Destination>::DistSink;
+
+	fn par_sink(self) -> Self::ParSink {
+		<Self as amadeus_core::Destination>::par_sink(self)
+	}
+	fn dist_sink(self) -> Self::DistSink {
+		<Self as amadeus_core::Destination>::dist_sink(self)
+	}
+}
 #[cfg(feature = "postgres")]
 impl<Row> Source for Postgres<Row>
 where
@@ -129,6 +212,24 @@ where
 		<Self as amadeus_core::Source>::dist_stream(self)
 	}
 }
+#[cfg(feature = "postgres")]
+impl<Row> Destination for PostgresDestination<Row>
+where
+	Row: super::data::Data,
+{
+	type Item = <Self as amadeus_core::Destination>::Item;
+	type Error = <Self as amadeus_core::Destination>::Error;
+
+	type ParSink = <Self as amadeus_core::Destination>::ParSink;
+	type DistSink = <Self as amadeus_core::Destination>::DistSink;
+
+	fn par_sink(self) -> Self::ParSink {
+		<Self as amadeus_core::Destination>::par_sink(self)
+	}
+	fn dist_sink(self) -> Self::DistSink {
+		<Self as amadeus_core::Destination>::dist_sink(self)
+	}
+}
 #[cfg(feature = "aws")]
 impl Source for Cloudfront {
 	type Item = crate::data::CloudfrontRow;
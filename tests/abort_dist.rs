@@ -0,0 +1,130 @@
+#[cfg(feature = "constellation")]
+use constellation::*;
+#[cfg(feature = "constellation")]
+use std::{
+	env, fs, process::Command, thread, time::{Duration, SystemTime}
+};
+
+#[cfg(feature = "constellation")]
+use amadeus::dist::prelude::*;
+
+fn main() {
+	// Only processes can be aborted without taking the test down with them
+	#[cfg(feature = "constellation")]
+	{
+		if cfg!(miri) {
+			return;
+		}
+		// Constellation fails a job if any of its processes fails, even once the pool
+		// has recovered, so run the pool as a job of its own and check its output
+		if env::var_os("AMADEUS_ABORT_DIST_JOB").is_none() {
+			let output = Command::new(env::current_exe().unwrap())
+				.env("AMADEUS_ABORT_DIST_JOB", "1")
+				.output()
+				.unwrap();
+			let stdout = String::from_utf8_lossy(&output.stdout);
+			print!("{}", stdout);
+			eprint!("{}", String::from_utf8_lossy(&output.stderr));
+			assert!(stdout.contains("recovered"), "{:?}", output.status);
+			return;
+		}
+
+		init(Resources::default());
+
+		tokio::runtime::Builder::new()
+			.threaded_scheduler()
+			.enable_all()
+			.build()
+			.unwrap()
+			.block_on(async {
+				let start = SystemTime::now();
+				run().await;
+				println!("recovered in {:?}", start.elapsed().unwrap());
+			})
+	}
+}
+
+#[cfg(feature = "constellation")]
+async fn run() {
+	let pool = ProcessPool::new_with(
+		Some(2),
+		None,
+		None,
+		Resources::default(),
+		2,
+		Some(Duration::from_secs(3)),
+	)
+	.unwrap();
+
+	// Aborts the process running it the first time, and succeeds when resubmitted
+	let marker = env::temp_dir().join(format!("amadeus-abort-dist-{}", rand::random::<u64>()));
+	let ret = {
+		let marker = marker.clone();
+		pool.spawn(FnOnce!(move |&_| async move {
+			let first = fs::OpenOptions::new()
+				.write(true)
+				.create_new(true)
+				.open(&marker)
+				.is_ok();
+			if first {
+				abort();
+			}
+			String::from("resubmitted")
+		}))
+		.await
+	};
+	assert_eq!(ret.unwrap(), "resubmitted");
+	fs::remove_file(&marker).unwrap();
+
+	// Blocks the runtime for longer than the heartbeat timeout, but heartbeats are still sent, so isn't resubmitted
+	let ret = pool
+		.spawn(FnOnce!(|&_| async {
+			thread::sleep(Duration::from_secs(6));
+			String::from("first")
+		}))
+		.await;
+	assert_eq!(ret.unwrap(), "first");
+
+	// Stops the process for longer than the heartbeat timeout the first time, so is resubmitted
+	let marker = env::temp_dir().join(format!("amadeus-abort-dist-{}", rand::random::<u64>()));
+	let ret = {
+		let marker = marker.clone();
+		pool.spawn(FnOnce!(move |&_| async move {
+			let first = fs::OpenOptions::new()
+				.write(true)
+				.create_new(true)
+				.open(&marker)
+				.is_ok();
+			if first {
+				// Continued once the pool has given up on it, so that it then terminates
+				let pid = std::process::id().to_string();
+				let _ = Command::new("sh")
+					.arg("-c")
+					.arg(format!("sleep 6; kill -CONT {}", pid))
+					.spawn()
+					.unwrap();
+				let status = Command::new("kill").arg("-STOP").arg(&pid).status();
+				assert!(status.unwrap().success());
+				return String::from("first");
+			}
+			String::from("resubmitted")
+		}))
+		.await
+	};
+	assert_eq!(ret.unwrap(), "resubmitted");
+	fs::remove_file(&marker).unwrap();
+
+	// Aborts every time, so fails once it has run max_attempts times
+	let ret = pool.spawn(FnOnce!(|&_| async { abort() })).await;
+	assert!(ret.is_err());
+
+	// The aborted processes have been replaced
+	let ret = pool.spawn(FnOnce!(|&_| async { 1 + 1 })).await;
+	assert_eq!(ret.unwrap(), 2);
+	assert_eq!(pool.queue_depths(), [0, 0]);
+}
+
+#[cfg(feature = "constellation")]
+fn abort() {
+	std::process::abort()
+}
@@ -2,7 +2,7 @@
 
 use std::{path::PathBuf, time::SystemTime};
 
-use amadeus::prelude::*;
+use amadeus::{data::PartitionError, prelude::*, source::csv::CsvError};
 
 #[tokio::test(threaded_scheduler)]
 #[cfg_attr(miri, ignore)]
@@ -60,3 +60,238 @@ async fn csv() {
 
 	println!("in {:?}", start.elapsed().unwrap());
 }
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn csv_write() {
+	// One task per thread, so that each write produces few files
+	let pool = &ThreadPool::new(None, Some(1)).unwrap();
+
+	#[derive(Data, Clone, PartialEq, PartialOrd, Debug)]
+	struct Row {
+		a: u32,
+		b: String,
+	}
+
+	let dir = std::env::temp_dir().join(format!("amadeus-csv-write-{}", rand::random::<u64>()));
+	let rows = (0..10_000u32)
+		.map(|a| Row {
+			a,
+			b: format!("row, \"{}\"", a),
+		})
+		.collect::<Vec<_>>();
+
+	let write = |mode, rows: Vec<Row>| {
+		let dir = dir.clone();
+		async move {
+			let destination = CsvDestination::builder(dir).mode(mode).build().await?;
+			rows.into_par_stream()
+				.pipe(pool, destination.par_sink())
+				.await
+		}
+	};
+	let read = || async {
+		let files = std::fs::read_dir(&dir)
+			.unwrap()
+			.map(|entry| entry.unwrap().path())
+			.collect::<Vec<_>>();
+		let mut read: Vec<Row> = Csv::builder(files)
+			.has_headers(true)
+			.build()
+			.await
+			.unwrap()
+			.par_stream()
+			.map(|row: Result<_, _>| row.unwrap())
+			.collect(pool)
+			.await;
+		read.sort_by_key(|row| row.a);
+		read
+	};
+
+	write(WriteMode::FailIfExists, rows.clone()).await.unwrap();
+	assert_eq!(read().await, rows);
+	assert!(write(WriteMode::FailIfExists, rows.clone()).await.is_err());
+	write(WriteMode::Append, rows.clone()).await.unwrap();
+	assert_eq!(read().await.len(), 2 * rows.len());
+	write(WriteMode::Create, rows.clone()).await.unwrap();
+	assert_eq!(read().await, rows);
+	// Existing files are removed even if nothing is written
+	write(WriteMode::Create, Vec::new()).await.unwrap();
+	assert!(read().await.is_empty());
+
+	std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn csv_dialect() {
+	let pool = &ThreadPool::new(None, Some(1)).unwrap();
+
+	#[derive(Data, Clone, PartialEq, PartialOrd, Debug)]
+	struct Row {
+		a: u32,
+		b: String,
+	}
+
+	let file =
+		std::env::temp_dir().join(format!("amadeus-csv-dialect-{}.tsv", rand::random::<u64>()));
+	std::fs::write(
+		&file,
+		"# written by hand\nb\ta\n'x\ty'\t1\n# skipped\nz\t2\n",
+	)
+	.unwrap();
+
+	let mut rows: Vec<Row> = Csv::builder(file.clone())
+		.delimiter(b'\t')
+		.has_headers(true)
+		.quote(b'\'')
+		.comment(Some(b'#'))
+		.build()
+		.await
+		.unwrap()
+		.par_stream()
+		.map(|row: Result<_, _>| row.unwrap())
+		.collect(pool)
+		.await;
+	rows.sort_by_key(|row| row.a);
+	assert_eq!(
+		rows,
+		vec![
+			Row {
+				a: 1,
+				b: String::from("x\ty")
+			},
+			Row {
+				a: 2,
+				b: String::from("z")
+			}
+		]
+	);
+
+	std::fs::remove_file(&file).unwrap();
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn csv_partitioned() {
+	let pool = &ThreadPool::new(None, Some(1)).unwrap();
+
+	#[derive(Data, Clone, PartialEq, PartialOrd, Debug)]
+	struct Row {
+		a: u32,
+		#[amadeus(partition)]
+		year: u16,
+		#[amadeus(partition)]
+		country: Option<String>,
+	}
+	#[derive(Data, Clone, PartialEq, PartialOrd, Debug)]
+	struct MissingColumn {
+		a: u32,
+		#[amadeus(partition)]
+		month: u8,
+	}
+
+	let dir =
+		std::env::temp_dir().join(format!("amadeus-csv-partitioned-{}", rand::random::<u64>()));
+	for (path, contents) in &[
+		("year=2018/country=uk/part-0.csv", "a\n1\n2\n"),
+		(
+			"year=2018/country=__HIVE_DEFAULT_PARTITION__/part-0.csv",
+			"a\n3\n",
+		),
+		("year=2019/country=new%20zealand/part-0.csv", "a\n4\n"),
+		("year=2019/_temporary/part-0.csv", "not,a,row\n"),
+		("year=2019/country=uk/.part-0.csv.crc", "not,a,row\n"),
+	] {
+		let path = dir.join(path);
+		std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+		std::fs::write(path, contents).unwrap();
+	}
+
+	let read = |directory: HiveDirectory<PathBuf>| async move {
+		let mut rows: Vec<Row> = Csv::builder(directory)
+			.has_headers(true)
+			.build()
+			.await
+			.unwrap()
+			.par_stream()
+			.map(|row: Result<_, _>| row.unwrap())
+			.collect(pool)
+			.await;
+		rows.sort_by_key(|row| row.a);
+		rows
+	};
+
+	let row = |a, year, country: Option<&str>| Row {
+		a,
+		year,
+		country: country.map(String::from),
+	};
+	assert_eq!(
+		read(HiveDirectory::new(dir.clone())).await,
+		vec![
+			row(1, 2018, Some("uk")),
+			row(2, 2018, Some("uk")),
+			row(3, 2018, None),
+			row(4, 2019, Some("new zealand")),
+		]
+	);
+	assert_eq!(
+		read(
+			HiveDirectory::new(dir.clone())
+				.filter(|values| values.get("year").map_or(true, |year| year == Some("2019")))
+		)
+		.await,
+		vec![row(4, 2019, Some("new zealand"))]
+	);
+
+	// Partition columns missing from the path are reported as such
+	let errors: Vec<_> = Csv::<_, MissingColumn>::builder(HiveDirectory::new(dir.clone()))
+		.has_headers(true)
+		.build()
+		.await
+		.unwrap()
+		.par_stream()
+		.map(|row: Result<_, _>| row.unwrap_err())
+		.collect(pool)
+		.await;
+	assert_eq!(errors.len(), 4);
+	assert!(errors.iter().all(
+		|err| *err == CsvError::PartitionValues(PartitionError::Missing(String::from("month")))
+	));
+
+	std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn csv_error() {
+	let pool = &ThreadPool::new(None, Some(1)).unwrap();
+
+	#[derive(Data, Clone, PartialEq, PartialOrd, Debug)]
+	struct Row {
+		a: u32,
+	}
+
+	let file =
+		std::env::temp_dir().join(format!("amadeus-csv-error-{}.csv", rand::random::<u64>()));
+	std::fs::write(&file, "a\n1\nnot a number\n").unwrap();
+
+	let errors: Vec<_> = Csv::<_, Row>::builder(file.clone())
+		.has_headers(true)
+		.build()
+		.await
+		.unwrap()
+		.par_stream()
+		.filter(|row: &Result<_, _>| row.is_err())
+		.map(|row: Result<_, _>| row.unwrap_err())
+		.collect(pool)
+		.await;
+	assert_eq!(errors.len(), 1);
+	// Errors are sent as their message, so that they can be returned from other processes
+	let error = serde_json::from_str(&serde_json::to_string(&errors[0]).unwrap()).unwrap();
+	assert_eq!(errors[0], error);
+	assert_eq!(errors[0].to_string(), error.to_string());
+
+	std::fs::remove_file(&file).unwrap();
+}
@@ -0,0 +1,235 @@
+#[cfg(feature = "constellation")]
+use constellation::*;
+#[cfg(feature = "constellation")]
+use futures::future::join_all;
+#[cfg(feature = "constellation")]
+use std::{
+	env, fs, time::{Duration, SystemTime}
+};
+#[cfg(feature = "constellation")]
+use tokio::time::delay_for;
+
+#[cfg(feature = "constellation")]
+use amadeus::{dist::prelude::*, par_sink::SpillVec, par_stream::Sorted};
+
+fn main() {
+	#[cfg(feature = "constellation")]
+	{
+		if cfg!(miri) {
+			return;
+		}
+		init(Resources::default());
+
+		tokio::runtime::Builder::new()
+			.threaded_scheduler()
+			.enable_all()
+			.build()
+			.unwrap()
+			.block_on(async {
+				let start = SystemTime::now();
+				run().await;
+				println!("in {:?}", start.elapsed().unwrap());
+			})
+	}
+}
+
+#[cfg(feature = "constellation")]
+async fn run() {
+	let pool = ProcessPool::new(Some(2), None, None, Resources::default()).unwrap();
+
+	// Give each process, and the driver, a temp dir of its own, as if they were on different machines
+	let base = env::temp_dir().join(format!("amadeus-exchange-dist-{}", rand::random::<u64>()));
+	let tasks = (0..pool.processes())
+		.map(|_| {
+			let base = base.clone();
+			tokio::spawn(pool.spawn(FnOnce!(move |&_| async move {
+				delay_for(Duration::from_secs(1)).await;
+				let dir = base.join(std::process::id().to_string());
+				fs::create_dir_all(&dir).unwrap();
+				env::set_var("TMPDIR", &dir);
+				std::process::id()
+			})))
+		})
+		.collect::<Vec<_>>();
+	let mut processes = join_all(tasks)
+		.await
+		.into_iter()
+		.map(|task| task.unwrap().unwrap())
+		.collect::<Vec<_>>();
+	processes.sort_unstable();
+	processes.dedup();
+	assert_eq!(processes.len(), pool.processes());
+	let driver = base.join("driver");
+	fs::create_dir_all(&driver).unwrap();
+	env::set_var("TMPDIR", &driver);
+
+	let left = (0..1000_u32).map(|a| (a % 10, a)).collect::<Vec<_>>();
+	let right = (5..15_u32).map(|a| (a, a * 2)).collect::<Vec<_>>();
+	let mut inner: Vec<(u32, u32, u32)> = left
+		.clone()
+		.into_dist_stream()
+		.inner_join_stream(&pool, right.clone(), JoinStrategy::Partitioned(3))
+		.await
+		.unwrap()
+		.collect::<_, Result<_, _>>(&pool)
+		.await
+		.unwrap();
+	inner.sort_unstable();
+	assert_eq!(inner.len(), 500);
+	assert_eq!(inner[0], (5, 5, 10));
+	let outer: Vec<(u32, Option<u32>, Option<u32>)> = left
+		.into_dist_stream()
+		.outer_join_stream(&pool, right, 3)
+		.await
+		.unwrap()
+		.collect::<_, Result<_, _>>(&pool)
+		.await
+		.unwrap();
+	assert_eq!(outer.len(), 1000 + 5);
+	assert_eq!(
+		outer.iter().filter(|(_, a, b)| a.is_some() && b.is_some()).count(),
+		500
+	);
+
+	let mut distinct: Vec<u32> = (0..1000_u32)
+		.into_dist_stream()
+		.map(FnMut!(|a: u32| a % 100))
+		.distinct(&pool, 3)
+		.await
+		.unwrap()
+		.collect::<_, Result<_, _>>(&pool)
+		.await
+		.unwrap();
+	distinct.sort_unstable();
+	assert_eq!(distinct, (0..100).collect::<Vec<_>>());
+
+	let items = (0..1000_u32)
+		.map(|a| a.wrapping_mul(2_654_435_761) % 500)
+		.collect::<Vec<_>>();
+	let mut expected = items.clone();
+	expected.sort_unstable();
+	let sorted: Sorted<u32> = items
+		.into_dist_stream()
+		.sort_by_key(&pool, 3, Fn!(|a: &u32| *a))
+		.await
+		.unwrap()
+		.spill(10)
+		.ranges()
+		.collect::<_, Result<_, _>>(&pool)
+		.await
+		.unwrap();
+	assert_eq!(sorted.into_vec().unwrap(), expected);
+
+	// The partitions are fetched by each process from those that wrote them, so barely any of them pass through the driver
+	let driver_traffic = || pool.bytes_sent() + pool.bytes_received();
+	let before = driver_traffic();
+	let len: usize = (0..20_000_u32)
+		.into_dist_stream()
+		.map(FnMut!(|a: u32| (a, "a".repeat(1000))))
+		.repartition_by_key(&pool, 3, FnMut!(|(a, _): &(u32, String)| *a))
+		.await
+		.unwrap()
+		.map(FnMut!(|item: Result<(u32, String), _>| item.unwrap().1.len()))
+		.sum(&pool)
+		.await;
+	assert_eq!(len, 20_000 * 1000);
+	let traffic = driver_traffic() - before;
+	assert!(
+		traffic < 20_000 * 1000 / 50,
+		"{} bytes passed through the driver",
+		traffic
+	);
+
+	// Only a sample of the items of a sort, and those of each range that are held in memory, pass through the driver
+	let before = driver_traffic();
+	let sorted: Sorted<String> = (0..20_000_u32)
+		.into_dist_stream()
+		.map(FnMut!(|a: u32| format!("{:05}", a.wrapping_mul(7919) % 20_000).repeat(200)))
+		.sort_by(&pool, 3, Fn!(|a: &String, b: &String| a.cmp(b)))
+		.await
+		.unwrap()
+		.spill(100)
+		.ranges()
+		.collect::<_, Result<_, _>>(&pool)
+		.await
+		.unwrap();
+	let traffic = driver_traffic() - before;
+	assert!(
+		traffic < 20_000 * 1000 / 20,
+		"{} bytes passed through the driver",
+		traffic
+	);
+	let sorted = sorted.into_vec().unwrap();
+	assert_eq!(sorted.len(), 20_000);
+	assert!(sorted.windows(2).all(|pair| pair[0] <= pair[1]));
+
+	// As are those of distinct
+	let before = driver_traffic();
+	let len: usize = (0..20_000_u32)
+		.into_dist_stream()
+		.map(FnMut!(|a: u32| format!("{:05}", a % 5_000).repeat(200)))
+		.distinct(&pool, 3)
+		.await
+		.unwrap()
+		.map(FnMut!(|item: Result<String, _>| item.unwrap().len()))
+		.sum(&pool)
+		.await;
+	assert_eq!(len, 5_000 * 1000);
+	let traffic = driver_traffic() - before;
+	assert!(
+		traffic < 20_000 * 1000 / 50,
+		"{} bytes passed through the driver",
+		traffic
+	);
+
+	// As are both sides of a partitioned join
+	let before = driver_traffic();
+	let len: usize = (0..20_000_u32)
+		.into_dist_stream()
+		.map(FnMut!(|a: u32| (a % 100, "a".repeat(1000))))
+		.inner_join_stream(
+			&pool,
+			(0..100_u32).into_dist_stream().map(FnMut!(|a: u32| (a, a))),
+			JoinStrategy::Partitioned(3),
+		)
+		.await
+		.unwrap()
+		.map(FnMut!(|item: Result<(u32, String, u32), _>| item.unwrap().1.len()))
+		.sum(&pool)
+		.await;
+	assert_eq!(len, 20_000 * 1000);
+	let traffic = driver_traffic() - before;
+	assert!(
+		traffic < 20_000 * 1000 / 50,
+		"{} bytes passed through the driver",
+		traffic
+	);
+
+	// Likewise a spilled result is only fetched from the processes that spilled it as it's read
+	let before = driver_traffic();
+	let items: SpillVec<String> = (0..20_000_u32)
+		.into_dist_stream()
+		.pipe(
+			&pool,
+			Identity
+				.map(FnMut!(|_: u32| "a".repeat(1000)))
+				.collect::<Vec<_>>()
+				.spill(100),
+		)
+		.await
+		.unwrap();
+	let traffic = driver_traffic() - before;
+	assert!(
+		traffic < 20_000 * 1000 / 50,
+		"{} bytes passed through the driver",
+		traffic
+	);
+	assert!(items.is_spilled());
+	let len = items
+		.into_iter()
+		.map(|item| item.unwrap().len())
+		.sum::<usize>();
+	assert_eq!(len, 20_000 * 1000);
+
+	fs::remove_dir_all(&base).unwrap();
+}
@@ -1,6 +1,9 @@
 use either::Either;
+use std::collections::BTreeMap;
 
-use amadeus::prelude::*;
+use amadeus::{
+	par_sink::{SpillMap, SpillVec}, par_stream::Sorted, prelude::*
+};
 
 #[tokio::test(threaded_scheduler)]
 #[cfg_attr(miri, ignore)]
@@ -34,3 +37,594 @@ async fn into_par_stream() {
 	let sum: usize = slice.iter().cloned().par().sum(&pool).await;
 	assert_eq!(sum, slice.iter().sum::<usize>());
 }
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn repartition_by_key() {
+	let pool = &ThreadPool::new(None, None).unwrap();
+
+	let repartition = (0..1000_u32)
+		.into_par_stream()
+		.repartition_by_key(&pool, 7, |a: &u32| a % 10)
+		.await
+		.unwrap();
+	let partitions = repartition
+		.partitions()
+		.collect::<Result<Vec<_>, _>>()
+		.unwrap();
+	assert_eq!(partitions.len(), 7);
+	for key in 0..10 {
+		let holding = partitions
+			.iter()
+			.filter(|partition| partition.iter().any(|a| a % 10 == key))
+			.count();
+		assert_eq!(holding, 1);
+	}
+	let mut items = partitions.concat();
+	items.sort_unstable();
+	assert_eq!(items, (0..1000).collect::<Vec<_>>());
+
+	let items: Result<Vec<u32>, _> = repartition.collect(&pool).await;
+	assert_eq!(items.unwrap().iter().sum::<u32>(), (0..1000).sum::<u32>());
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn join_stream() {
+	let pool = &ThreadPool::new(None, None).unwrap();
+
+	let left = (0..100_u32).map(|a| (a % 10, a)).collect::<Vec<_>>();
+	let right = (5..15_u32)
+		.flat_map(|a| vec![(a, a * 2), (a, a * 3)])
+		.collect::<Vec<_>>();
+	let matches = |key: &u32| {
+		right
+			.iter()
+			.filter(|(b, _)| b == key)
+			.map(|&(_, b)| b)
+			.collect::<Vec<_>>()
+	};
+
+	for &strategy in &[JoinStrategy::Partitioned(3), JoinStrategy::Broadcast] {
+		let mut inner: Vec<(u32, u32, u32)> = left
+			.clone()
+			.into_par_stream()
+			.inner_join_stream(&pool, right.clone(), strategy)
+			.await
+			.unwrap()
+			.collect::<_, Result<_, _>>(&pool)
+			.await
+			.unwrap();
+		inner.sort_unstable();
+		let mut expected = left
+			.iter()
+			.flat_map(|&(a, b)| matches(&a).into_iter().map(move |c| (a, b, c)))
+			.collect::<Vec<_>>();
+		expected.sort_unstable();
+		assert_eq!(inner, expected);
+
+		let mut left_: Vec<(u32, u32, Option<u32>)> = left
+			.clone()
+			.into_par_stream()
+			.left_join_stream(&pool, right.clone(), strategy)
+			.await
+			.unwrap()
+			.collect::<_, Result<_, _>>(&pool)
+			.await
+			.unwrap();
+		left_.sort_unstable();
+		assert_eq!(left_.len(), 50 * 2 + 50);
+		assert!(left_.contains(&(4, 94, None)));
+		assert!(left_.contains(&(5, 95, Some(15))));
+
+		let mut right_: Vec<(u32, Option<u32>, u32)> = left
+			.clone()
+			.into_par_stream()
+			.right_join_stream(&pool, right.clone(), 3)
+			.await
+			.unwrap()
+			.collect::<_, Result<_, _>>(&pool)
+			.await
+			.unwrap();
+		right_.sort_unstable();
+		assert_eq!(right_.len(), 50 * 2 + 10);
+		assert!(right_.contains(&(14, None, 42)));
+		assert!(right_.contains(&(9, Some(99), 27)));
+
+		let outer: Vec<(u32, Option<u32>, Option<u32>)> = left
+			.clone()
+			.into_par_stream()
+			.outer_join_stream(&pool, right.clone(), 3)
+			.await
+			.unwrap()
+			.collect::<_, Result<_, _>>(&pool)
+			.await
+			.unwrap();
+		assert_eq!(outer.len(), 50 * 2 + 50 + 10);
+		assert!(outer.contains(&(0, Some(0), None)));
+		assert!(outer.contains(&(10, None, Some(20))));
+
+		let semi: Vec<(u32, u32)> = left
+			.clone()
+			.into_par_stream()
+			.semi_join_stream(&pool, right.clone(), strategy)
+			.await
+			.unwrap()
+			.collect::<_, Result<_, _>>(&pool)
+			.await
+			.unwrap();
+		assert_eq!(semi.len(), 50);
+		assert!(semi.iter().all(|&(a, _)| a >= 5));
+
+		let anti: Vec<(u32, u32)> = left
+			.clone()
+			.into_par_stream()
+			.anti_join_stream(&pool, right.clone(), strategy)
+			.await
+			.unwrap()
+			.collect::<_, Result<_, _>>(&pool)
+			.await
+			.unwrap();
+		assert_eq!(anti.len(), 50);
+		assert!(anti.iter().all(|&(a, _)| a < 5));
+	}
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn distinct() {
+	let pool = &ThreadPool::new(None, None).unwrap();
+
+	let distinct = (0..1000_u32)
+		.into_par_stream()
+		.map(|a: u32| a % 100)
+		.distinct(&pool, 7)
+		.await
+		.unwrap();
+	let mut items: Vec<u32> = distinct.collect::<_, Result<_, _>>(&pool).await.unwrap();
+	items.sort_unstable();
+	assert_eq!(items, (0..100).collect::<Vec<_>>());
+
+	let distinct = (0..1000_u32)
+		.into_par_stream()
+		.distinct_by_key(&pool, 3, |a: &u32| a % 10)
+		.await
+		.unwrap();
+	let items: Vec<u32> = distinct.collect::<_, Result<_, _>>(&pool).await.unwrap();
+	let mut keys = items.into_iter().map(|a| a % 10).collect::<Vec<_>>();
+	keys.sort_unstable();
+	assert_eq!(keys, (0..10).collect::<Vec<_>>());
+
+	let count = (0..10_000_u32)
+		.into_par_stream()
+		.map(|a: u32| a % 1000)
+		.count_distinct(&pool, 0.01)
+		.await;
+	assert!((count.len() - 1000.0).abs() < 50.0, "{}", count.len());
+
+	let bloom = (0..1000_u32)
+		.into_par_stream()
+		.map(|a: u32| a * 3)
+		.bloom_filter(&pool, 1000, 0.01)
+		.await;
+	let kept = (0..3000_u32)
+		.into_par_stream()
+		.filter_in(bloom, |a: &u32| *a)
+		.count(&pool)
+		.await;
+	assert!((1000..1060).contains(&kept), "{}", kept);
+
+	let mut cuckoo = (0..1000_u32)
+		.into_par_stream()
+		.map(|a: u32| a * 3)
+		.cuckoo_filter(&pool, 1000, 0.01)
+		.await;
+	let kept = (0..3000_u32)
+		.into_par_stream()
+		.pipe(
+			&pool,
+			Identity
+				.map(|a: u32| (a, a.to_string()))
+				.filter_in(cuckoo.clone(), |(a, _): &(u32, String)| *a)
+				.count(),
+		)
+		.await;
+	assert!((1000..1060).contains(&kept), "{}", kept);
+	assert!((0..1000_u32).all(|a| cuckoo.remove(&(a * 3))));
+	assert!(cuckoo.is_empty());
+
+	let set: std::collections::HashSet<u32> = (0..1000_u32)
+		.into_par_stream()
+		.pipe(&pool, Identity.map(|a: u32| a % 20).distinct())
+		.await;
+	assert_eq!(set.len(), 20);
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn sort_by() {
+	let pool = &ThreadPool::new(None, None).unwrap();
+
+	let items = (0..1000_u32)
+		.map(|a| a.wrapping_mul(2_654_435_761) % 500)
+		.collect::<Vec<_>>();
+	let mut expected = items.clone();
+	expected.sort_unstable();
+
+	let ranges: BTreeMap<usize, SpillVec<u32>> = items
+		.clone()
+		.into_par_stream()
+		.sort_by(&pool, 4, |a: &u32, b: &u32| a.cmp(b))
+		.await
+		.unwrap()
+		.ranges()
+		.collect::<_, Result<_, _>>(&pool)
+		.await
+		.unwrap();
+	assert_eq!(ranges.keys().copied().collect::<Vec<_>>(), [0, 1, 2, 3]);
+	assert!(ranges.values().all(|range| !range.is_spilled()));
+	assert_eq!(Sorted::from(ranges).into_vec().unwrap(), expected);
+
+	let ranges: BTreeMap<usize, SpillVec<u32>> = items
+		.clone()
+		.into_par_stream()
+		.sort_by(&pool, 4, |a: &u32, b: &u32| a.cmp(b))
+		.await
+		.unwrap()
+		.spill(10)
+		.ranges()
+		.collect::<_, Result<_, _>>(&pool)
+		.await
+		.unwrap();
+	assert!(ranges.values().all(SpillVec::is_spilled));
+	let sorted = Sorted::from(ranges);
+	assert_eq!(sorted.len(), 1000);
+	let sorted = sorted.into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+	assert_eq!(sorted, expected);
+
+	// Collecting the stream itself keeps every item, but not the order of the ranges
+	let mut collected: Vec<u32> = items
+		.clone()
+		.into_par_stream()
+		.sort_by(&pool, 4, |a: &u32, b: &u32| a.cmp(b))
+		.await
+		.unwrap()
+		.spill(10)
+		.collect::<_, Result<_, _>>(&pool)
+		.await
+		.unwrap();
+	collected.sort_unstable();
+	assert_eq!(collected, expected);
+
+	let sorted: Sorted<u32> = items
+		.clone()
+		.into_par_stream()
+		.sort_by_key(&pool, 3, |a: &u32| std::cmp::Reverse(*a))
+		.await
+		.unwrap()
+		.ranges()
+		.collect::<_, Result<_, _>>(&pool)
+		.await
+		.unwrap();
+	expected.reverse();
+	assert_eq!(sorted.into_vec().unwrap(), expected);
+
+	let sorted: Sorted<u32> = Vec::<u32>::new()
+		.into_par_stream()
+		.sort_by(&pool, 2, |a: &u32, b: &u32| a.cmp(b))
+		.await
+		.unwrap()
+		.ranges()
+		.collect::<_, Result<_, _>>(&pool)
+		.await
+		.unwrap();
+	assert!(sorted.is_empty());
+	assert!(sorted.into_vec().unwrap().is_empty());
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn spill() {
+	let pool = &ThreadPool::new(None, None).unwrap();
+
+	let groups = (0..10_000_u32)
+		.into_par_stream()
+		.pipe(
+			&pool,
+			Identity
+				.map(|a: u32| (a % 1000, u64::from(a)))
+				.group_by(Identity.sum::<u64>())
+				.spill(64),
+		)
+		.await
+		.unwrap();
+	assert!(groups.is_spilled());
+	// Serializing leaves the runs here to be served, so they outlive the map that wrote them
+	let json = serde_json::to_string(&groups).unwrap();
+	drop(groups);
+	let groups: SpillMap<u32, u64> = serde_json::from_str(&json).unwrap();
+	assert!(groups.is_spilled());
+	assert_eq!(groups.len(), 1000);
+	let mut groups = groups.into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+	groups.sort_unstable();
+	let expected = (0..1000_u32)
+		.map(|k| (k, (0..10).map(|i| u64::from(k + i * 1000)).sum::<u64>()))
+		.collect::<Vec<_>>();
+	assert_eq!(groups, expected);
+
+	let histogram = (0..10_000_u32)
+		.into_par_stream()
+		.pipe(&pool, Identity.map(|a: u32| a % 500).histogram().spill(32))
+		.await
+		.unwrap();
+	assert_eq!(histogram.len(), 500);
+	let mut histogram = histogram
+		.into_iter()
+		.collect::<Result<Vec<_>, _>>()
+		.unwrap();
+	histogram.sort_unstable();
+	assert_eq!(histogram, (0..500).map(|a| (a, 20)).collect::<Vec<_>>());
+
+	let histogram = Vec::<u32>::new()
+		.into_par_stream()
+		.pipe(&pool, Identity.histogram().spill(32))
+		.await
+		.unwrap();
+	assert!(histogram.is_empty());
+
+	let items = (0..10_000_u32)
+		.into_par_stream()
+		.pipe(&pool, Identity.collect::<Vec<u32>>().spill(100))
+		.await
+		.unwrap();
+	assert!(items.is_spilled());
+	assert_eq!(items.len(), 10_000);
+	let json = serde_json::to_string(&items).unwrap();
+	drop(items);
+	let items: SpillVec<u32> = serde_json::from_str(&json).unwrap();
+	assert!(items.is_spilled());
+	assert_eq!(items.len(), 10_000);
+	let mut items = items.into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+	items.sort_unstable();
+	assert_eq!(items, (0..10_000).collect::<Vec<_>>());
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn window() {
+	let pool = &ThreadPool::new(None, None).unwrap();
+
+	let tumbling = (0..1000_u64)
+		.into_par_stream()
+		.window(|a: &u64| *a, Tumbling(100))
+		.group_by_window(&pool, Identity.count())
+		.await;
+	assert_eq!(
+		tumbling
+			.iter()
+			.map(|(window, count)| (*window.start(), *window.end(), *count))
+			.collect::<Vec<_>>(),
+		(0..10)
+			.map(|i| (i * 100, i * 100 + 100, 100))
+			.collect::<Vec<_>>()
+	);
+
+	let sliding = (0..1000_u64)
+		.into_par_stream()
+		.window(|a: &u64| *a, Sliding(100, 50))
+		.group_by_window(&pool, Identity.count())
+		.await;
+	assert_eq!(sliding.len(), 20);
+	assert!(sliding
+		.iter()
+		.all(|(window, count)| *count == (window.end().min(&1000) - window.start()) as usize));
+
+	let times = vec![0_u64, 5, 12, 14, 40, 41, 100, 109, 118];
+	let sessions = times
+		.into_par_stream()
+		.window(|a: &u64| *a, Session(10))
+		.group_by_window(&pool, Identity.count())
+		.await;
+	assert_eq!(
+		sessions
+			.iter()
+			.map(|(window, count)| (*window.start(), *window.end(), *count))
+			.collect::<Vec<_>>(),
+		vec![(0, 24, 4), (40, 51, 2), (100, 128, 3)]
+	);
+
+	let sessions = (0..1000_u64)
+		.map(|a| a * 7 % 1000)
+		.collect::<Vec<_>>()
+		.into_par_stream()
+		.window(|a: &u64| *a, Session(2))
+		.group_by_window(&pool, Identity.count())
+		.await;
+	assert_eq!(
+		sessions
+			.iter()
+			.map(|(window, count)| (*window.start(), *window.end(), *count))
+			.collect::<Vec<_>>(),
+		vec![(0, 1001, 1000)]
+	);
+
+	let epoch = std::time::UNIX_EPOCH;
+	let secs = std::time::Duration::from_secs;
+	let before_epoch = (0..40_u64)
+		.map(|a| epoch - secs(25) + secs(a))
+		.collect::<Vec<_>>()
+		.into_par_stream()
+		.window(|time: &std::time::SystemTime| *time, Tumbling(secs(10)))
+		.group_by_window(&pool, Identity.count())
+		.await;
+	assert_eq!(
+		before_epoch
+			.iter()
+			.map(|(window, count)| (*window.start(), *count))
+			.collect::<Vec<_>>(),
+		vec![
+			(epoch - secs(30), 5),
+			(epoch - secs(20), 10),
+			(epoch - secs(10), 10),
+			(epoch, 10),
+			(epoch + secs(10), 5)
+		]
+	);
+
+	// Windows at the end of time are cut short rather than overflowing
+	let end = (u64::MAX - 10..=u64::MAX)
+		.collect::<Vec<_>>()
+		.into_par_stream()
+		.window(|a: &u64| *a, Tumbling(100))
+		.group_by_window(&pool, Identity.count())
+		.await;
+	assert_eq!(
+		end.iter()
+			.map(|(window, count)| (*window.start(), *window.end(), *count))
+			.collect::<Vec<_>>(),
+		vec![(u64::MAX - u64::MAX % 100, u64::MAX, 11)]
+	);
+	let end = (i64::MAX - 100..i64::MAX)
+		.collect::<Vec<_>>()
+		.into_par_stream()
+		.window(|a: &i64| *a, Session(10))
+		.group_by_window(&pool, Identity.count())
+		.await;
+	assert_eq!(
+		end.iter()
+			.map(|(window, count)| (*window.start(), *window.end(), *count))
+			.collect::<Vec<_>>(),
+		vec![(i64::MAX - 100, i64::MAX, 100)]
+	);
+
+	let utc = Timezone::UTC;
+	let hourly = (0..48_u8)
+		.into_par_stream()
+		.map(move |minute: u8| {
+			DateTime::new(2020, 1, 1, 10 + minute / 20, (minute % 20) * 3, 0, 0, utc).unwrap()
+		})
+		.pipe(
+			&pool,
+			Identity
+				.window(|time: &DateTime| *time, Tumbling(Duration::from_hours(1)))
+				.group_by_window(Identity.count()),
+		)
+		.await;
+	assert_eq!(
+		hourly
+			.iter()
+			.map(|(window, count)| (window.start().hour(), *count))
+			.collect::<Vec<_>>(),
+		vec![(10, 20), (11, 20), (12, 8)]
+	);
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn quantiles() {
+	let pool = &ThreadPool::new(None, None).unwrap();
+
+	let quantiles = (0..100_000_u32)
+		.into_par_stream()
+		.map(|a: u32| f64::from(a.wrapping_mul(2_654_435_761) % 100_000))
+		.quantiles(&pool, &[0.0, 0.5, 0.95, 0.99, 1.0])
+		.await
+		.unwrap();
+	let expected = [0.0, 50_000.0, 95_000.0, 99_000.0, 99_999.0];
+	for (estimate, exact) in quantiles.iter().zip(&expected) {
+		assert!((estimate - exact).abs() < 200.0, "{} {}", estimate, exact);
+	}
+
+	let none = Vec::<f64>::new()
+		.into_par_stream()
+		.quantiles(&pool, &[0.5])
+		.await;
+	assert_eq!(none, None);
+
+	let items = (0..1001_u32)
+		.map(|a| f64::from(a * 337 % 1001))
+		.collect::<Vec<_>>();
+	let median = items.clone().into_par_stream().median(&pool).await;
+	assert_eq!(median, Some(500.0));
+	let quantile = items.clone().into_par_stream().quantile(&pool, 0.9).await;
+	assert_eq!(quantile, Some(900.0));
+	let min_max = items.into_par_stream().min_max(&pool).await;
+	assert_eq!(min_max, Some((0.0, 1000.0)));
+	let median = vec![4.0, 1.0, 3.0, 2.0]
+		.into_par_stream()
+		.median(&pool)
+		.await;
+	assert_eq!(median, Some(2.5));
+	let min_max = Vec::<f64>::new().into_par_stream().min_max(&pool).await;
+	assert_eq!(min_max, None);
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+#[should_panic(expected = "window slide must be positive")]
+async fn window_zero() {
+	let pool = &ThreadPool::new(None, None).unwrap();
+
+	let _ = (0..10_u64)
+		.into_par_stream()
+		.window(|a: &u64| *a, Sliding(10, 0))
+		.group_by_window(&pool, Identity.count())
+		.await;
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+#[should_panic(expected = "q must be between 0 and 1")]
+async fn quantile_nan() {
+	let pool = &ThreadPool::new(None, None).unwrap();
+
+	let _ = vec![1.0].into_par_stream().quantile(&pool, f64::NAN).await;
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn describe() {
+	let pool = &ThreadPool::new(None, None).unwrap();
+
+	let items = (0..10_000_u32)
+		.map(|a| f64::from(a * 7919 % 10_007).sqrt() + 1e6)
+		.collect::<Vec<_>>();
+	let n = items.len() as f64;
+	let mean = items.iter().sum::<f64>() / n;
+	let moment = |k| items.iter().map(|a| (a - mean).powi(k)).sum::<f64>() / n;
+	let (m2, m3, m4) = (moment(2), moment(3), moment(4));
+
+	let description = items.clone().into_par_stream().describe(&pool).await;
+	assert_eq!(description.count, 10_000);
+	assert!((description.mean - mean).abs() < 1e-6);
+	assert!((description.variance - m2).abs() / m2 < 1e-6);
+	assert!((description.stddev - m2.sqrt()).abs() / m2.sqrt() < 1e-6);
+	assert!((description.skewness - m3 / m2.powf(1.5)).abs() < 1e-6);
+	assert!((description.kurtosis - (m4 / (m2 * m2) - 3.0)).abs() < 1e-6);
+	assert_eq!(description.min, 1e6);
+	assert_eq!(
+		description.max,
+		items.iter().copied().fold(f64::NEG_INFINITY, f64::max)
+	);
+
+	let empty = Vec::<f64>::new().into_par_stream().describe(&pool).await;
+	assert_eq!(empty.count, 0);
+	assert!(empty.mean.is_nan());
+
+	let pairs = items
+		.iter()
+		.map(|&a| (a, 2.0 * a + 1.0))
+		.collect::<Vec<_>>();
+	let covariance = pairs.clone().into_par_stream().covariance(&pool).await;
+	assert!((covariance - 2.0 * m2).abs() / m2 < 1e-6);
+	let correlation = pairs.into_par_stream().correlation(&pool).await;
+	assert!((correlation - 1.0).abs() < 1e-9);
+	let correlation = items
+		.into_iter()
+		.map(|a| (a, -a))
+		.collect::<Vec<_>>()
+		.into_par_stream()
+		.correlation(&pool)
+		.await;
+	assert!((correlation + 1.0).abs() < 1e-9);
+}
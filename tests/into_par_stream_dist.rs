@@ -1,9 +1,11 @@
 #[cfg(feature = "constellation")]
 use constellation::*;
 use either::Either;
-use std::time::{Duration, SystemTime};
+use std::{
+	collections::BTreeMap, time::{Duration, SystemTime}
+};
 
-use amadeus::dist::prelude::*;
+use amadeus::{dist::prelude::*, par_sink::SpillVec};
 
 fn main() {
 	if cfg!(miri) {
@@ -65,5 +67,205 @@ async fn run<P: amadeus_core::pool::ProcessPool>(pool: &P) -> Duration {
 	let sum: usize = slice.iter().cloned().dist().sum(&pool).await;
 	assert_eq!(sum, slice.iter().sum::<usize>());
 
+	let repartition = (0..1000_u32)
+		.into_dist_stream()
+		.repartition_by_key(&pool, 7, FnMut!(|a: &u32| a % 10))
+		.await
+		.unwrap();
+	let partitions = repartition
+		.partitions()
+		.collect::<Result<Vec<_>, _>>()
+		.unwrap();
+	assert_eq!(partitions.len(), 7);
+	for key in 0..10 {
+		let holding = partitions
+			.iter()
+			.filter(|partition| partition.iter().any(|a| a % 10 == key))
+			.count();
+		assert_eq!(holding, 1);
+	}
+	let items: Result<Vec<u32>, _> = repartition.collect(&pool).await;
+	assert_eq!(items.unwrap().iter().sum::<u32>(), (0..1000).sum::<u32>());
+
+	let left = (0..100_u32).map(|a| (a % 10, a)).collect::<Vec<_>>();
+	let right = (5..15_u32).map(|a| (a, a * 2)).collect::<Vec<_>>();
+	for &strategy in &[JoinStrategy::Partitioned(3), JoinStrategy::Broadcast] {
+		let mut inner: Vec<(u32, u32, u32)> = left
+			.clone()
+			.into_dist_stream()
+			.inner_join_stream(&pool, right.clone(), strategy)
+			.await
+			.unwrap()
+			.collect::<_, Result<_, _>>(&pool)
+			.await
+			.unwrap();
+		inner.sort_unstable();
+		assert_eq!(inner.len(), 50);
+		assert_eq!(inner[0], (5, 5, 10));
+		let outer: Vec<(u32, Option<u32>, Option<u32>)> = left
+			.clone()
+			.into_dist_stream()
+			.outer_join_stream(&pool, right.clone(), 3)
+			.await
+			.unwrap()
+			.collect::<_, Result<_, _>>(&pool)
+			.await
+			.unwrap();
+		assert_eq!(outer.len(), 100 + 5);
+	}
+
+	let distinct = (0..1000_u32)
+		.into_dist_stream()
+		.map(FnMut!(|a: u32| a % 100))
+		.distinct(&pool, 7)
+		.await
+		.unwrap();
+	let mut items: Vec<u32> = distinct.collect::<_, Result<_, _>>(&pool).await.unwrap();
+	items.sort_unstable();
+	assert_eq!(items, (0..100).collect::<Vec<_>>());
+	let count = (0..10_000_u32)
+		.into_dist_stream()
+		.map(FnMut!(|a: u32| a % 1000))
+		.count_distinct(&pool, 0.01)
+		.await;
+	assert!((count.len() - 1000.0).abs() < 50.0, "{}", count.len());
+	let bloom = (0..1000_u32)
+		.into_dist_stream()
+		.map(FnMut!(|a: u32| a * 3))
+		.bloom_filter(&pool, 1000, 0.01)
+		.await;
+	let kept = (0..3000_u32)
+		.into_dist_stream()
+		.filter_in(bloom, FnMut!(|a: &u32| *a))
+		.count(&pool)
+		.await;
+	assert!((1000..1060).contains(&kept), "{}", kept);
+	let mut cuckoo = (0..1000_u32)
+		.into_dist_stream()
+		.map(FnMut!(|a: u32| a * 3))
+		.cuckoo_filter(&pool, 1000, 0.01)
+		.await;
+	let kept = (0..3000_u32)
+		.into_dist_stream()
+		.pipe(
+			&pool,
+			Identity
+				.map(FnMut!(|a: u32| (a, a.to_string())))
+				.filter_in(cuckoo.clone(), FnMut!(|(a, _): &(u32, String)| *a))
+				.count(),
+		)
+		.await;
+	assert!((1000..1060).contains(&kept), "{}", kept);
+	assert!((0..1000_u32).all(|a| cuckoo.remove(&(a * 3))));
+	assert!(cuckoo.is_empty());
+
+	let items = (0..1000_u32)
+		.map(|a| a.wrapping_mul(2_654_435_761) % 500)
+		.collect::<Vec<_>>();
+	let mut expected = items.clone();
+	expected.sort_unstable();
+	let ranges: BTreeMap<usize, SpillVec<u32>> = items
+		.into_dist_stream()
+		.sort_by_key(&pool, 4, Fn!(|a: &u32| *a))
+		.await
+		.unwrap()
+		.spill(10)
+		.ranges()
+		.collect::<_, Result<_, _>>(&pool)
+		.await
+		.unwrap();
+	assert_eq!(ranges.keys().copied().collect::<Vec<_>>(), [0, 1, 2, 3]);
+	let sorted = ranges
+		.into_values()
+		.flatten()
+		.collect::<Result<Vec<_>, _>>()
+		.unwrap();
+	assert_eq!(sorted, expected);
+
+	let groups = (0..10_000_u32)
+		.into_dist_stream()
+		.pipe(
+			&pool,
+			Identity
+				.map(FnMut!(|a: u32| (a % 1000, ())))
+				.group_by(Identity.count())
+				.spill(64),
+		)
+		.await
+		.unwrap();
+	assert_eq!(groups.len(), 1000);
+	assert!(groups.into_iter().all(|group| group.unwrap().1 == 10));
+	let histogram = (0..10_000_u32)
+		.into_dist_stream()
+		.pipe(
+			&pool,
+			Identity.map(FnMut!(|a: u32| a % 500)).histogram().spill(32),
+		)
+		.await
+		.unwrap();
+	let mut histogram = histogram
+		.into_iter()
+		.collect::<Result<Vec<_>, _>>()
+		.unwrap();
+	histogram.sort_unstable();
+	assert_eq!(histogram, (0..500).map(|a| (a, 20)).collect::<Vec<_>>());
+	let items = (0..10_000_u32)
+		.into_dist_stream()
+		.pipe(&pool, Identity.collect::<Vec<u32>>().spill(100))
+		.await
+		.unwrap();
+	assert_eq!(items.len(), 10_000);
+	let mut items = items.into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+	items.sort_unstable();
+	assert_eq!(items, (0..10_000).collect::<Vec<_>>());
+
+	let sessions = vec![0_u64, 5, 12, 14, 40, 41, 100, 109, 118]
+		.into_dist_stream()
+		.window(FnMut!(|a: &u64| *a), Session(10))
+		.group_by_window(&pool, Identity.count())
+		.await;
+	assert_eq!(
+		sessions
+			.iter()
+			.map(|(window, count)| (*window.start(), *window.end(), *count))
+			.collect::<Vec<_>>(),
+		vec![(0, 24, 4), (40, 51, 2), (100, 128, 3)]
+	);
+
+	let quantiles = (0..100_000_u32)
+		.into_dist_stream()
+		.map(FnMut!(|a: u32| f64::from(a)))
+		.quantiles(&pool, &[0.5, 0.99])
+		.await
+		.unwrap();
+	assert!((quantiles[0] - 50_000.0).abs() < 200.0, "{}", quantiles[0]);
+	assert!((quantiles[1] - 99_000.0).abs() < 200.0, "{}", quantiles[1]);
+	let median = (0..1001_u32)
+		.into_dist_stream()
+		.map(FnMut!(|a: u32| f64::from(a)))
+		.median(&pool)
+		.await;
+	assert_eq!(median, Some(500.0));
+	let min_max = (0..1001_u32)
+		.into_dist_stream()
+		.map(FnMut!(|a: u32| f64::from(a)))
+		.min_max(&pool)
+		.await;
+	assert_eq!(min_max, Some((0.0, 1000.0)));
+	let description = (0..1001_u32)
+		.into_dist_stream()
+		.map(FnMut!(|a: u32| f64::from(a)))
+		.describe(&pool)
+		.await;
+	assert_eq!(description.count, 1001);
+	assert!((description.mean - 500.0).abs() < 1e-9);
+	assert!(description.skewness.abs() < 1e-9);
+	let correlation = (0..1001_u32)
+		.into_dist_stream()
+		.map(FnMut!(|a: u32| (f64::from(a), f64::from(a) * 3.0)))
+		.correlation(&pool)
+		.await;
+	assert!((correlation - 1.0).abs() < 1e-9);
+
 	start.elapsed().unwrap()
 }
@@ -2,7 +2,7 @@
 
 use std::{path::PathBuf, time::SystemTime};
 
-use amadeus::prelude::*;
+use amadeus::{data::PartitionError, prelude::*, source::json::JsonError};
 
 #[tokio::test(threaded_scheduler)]
 #[cfg_attr(miri, ignore)]
@@ -86,3 +86,202 @@ async fn json() {
 
 	println!("in {:?}", start.elapsed().unwrap());
 }
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn json_write() {
+	use std::io::Read;
+
+	// One task per thread, so that each write produces few files
+	let pool = &ThreadPool::new(None, Some(1)).unwrap();
+
+	#[derive(Data, Clone, PartialEq, PartialOrd, Debug)]
+	struct Row {
+		a: u32,
+		b: Option<String>,
+	}
+
+	let rows = (0..10_000u32)
+		.map(|a| Row {
+			a,
+			b: if a % 2 == 0 {
+				Some(a.to_string())
+			} else {
+				None
+			},
+		})
+		.collect::<Vec<_>>();
+	let files = |dir: &PathBuf| {
+		std::fs::read_dir(dir)
+			.unwrap()
+			.map(|entry| entry.unwrap().path())
+			.collect::<Vec<_>>()
+	};
+
+	let dir = std::env::temp_dir().join(format!("amadeus-json-write-{}", rand::random::<u64>()));
+	rows.clone()
+		.into_par_stream()
+		.pipe(
+			pool,
+			JsonDestination::new(dir.clone()).await.unwrap().par_sink(),
+		)
+		.await
+		.unwrap();
+	let mut read: Vec<Row> = Json::new(files(&dir))
+		.await
+		.unwrap()
+		.par_stream()
+		.map(|row: Result<_, _>| row.unwrap())
+		.collect(pool)
+		.await;
+	read.sort_by_key(|row| row.a);
+	assert_eq!(read, rows);
+	std::fs::remove_dir_all(&dir).unwrap();
+
+	let dir = std::env::temp_dir().join(format!("amadeus-json-write-{}", rand::random::<u64>()));
+	rows.clone()
+		.into_par_stream()
+		.pipe(
+			pool,
+			JsonDestination::builder(dir.clone())
+				.gzip(true)
+				.build()
+				.await
+				.unwrap()
+				.par_sink(),
+		)
+		.await
+		.unwrap();
+	let mut lines = 0;
+	for file in files(&dir) {
+		assert!(file.to_str().unwrap().ends_with(".json.gz"));
+		let mut json = String::new();
+		let _ = flate2::read::GzDecoder::new(std::fs::File::open(file).unwrap())
+			.read_to_string(&mut json)
+			.unwrap();
+		lines += json.lines().count();
+	}
+	assert_eq!(lines, rows.len());
+	std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn json_write_partitioned() {
+	let pool = &ThreadPool::new(None, Some(1)).unwrap();
+
+	#[derive(Data, Clone, PartialEq, PartialOrd, Debug)]
+	struct Row {
+		a: u32,
+		#[amadeus(partition)]
+		year: u16,
+		#[amadeus(partition)]
+		country: Option<String>,
+	}
+	#[derive(Data, Clone, PartialEq, PartialOrd, Debug)]
+	struct MissingColumn {
+		a: u32,
+		#[amadeus(partition)]
+		month: u8,
+	}
+
+	let rows = (0..1000u32)
+		.map(|a| Row {
+			a,
+			year: 2018 + (a % 2) as u16,
+			country: match a % 3 {
+				0 => Some(String::from("uk")),
+				1 => Some(String::from("a/b=c")),
+				_ => None,
+			},
+		})
+		.collect::<Vec<_>>();
+
+	let dir = std::env::temp_dir().join(format!(
+		"amadeus-json-write-partitioned-{}",
+		rand::random::<u64>()
+	));
+	let write = |mode, rows: Vec<Row>| {
+		let dir = dir.clone();
+		async move {
+			let destination = JsonDestination::builder(dir)
+				.mode(mode)
+				.partitioned(true)
+				.build()
+				.await?;
+			rows.into_par_stream()
+				.pipe(pool, destination.par_sink())
+				.await
+		}
+	};
+	let read = || async {
+		let mut read: Vec<Row> = Json::new(HiveDirectory::new(dir.clone()))
+			.await
+			.unwrap()
+			.par_stream()
+			.map(|row: Result<_, _>| row.unwrap())
+			.collect(pool)
+			.await;
+		read.sort_by_key(|row| row.a);
+		read
+	};
+
+	write(WriteMode::FailIfExists, rows.clone()).await.unwrap();
+	assert!(dir.join("year=2018/country=uk").is_dir());
+	assert!(dir.join("year=2019/country=a%2Fb%3Dc").is_dir());
+	assert!(dir
+		.join("year=2018/country=__HIVE_DEFAULT_PARTITION__")
+		.is_dir());
+	assert_eq!(read().await, rows);
+	// Files in the partition directories count as already written
+	assert!(write(WriteMode::FailIfExists, rows.clone()).await.is_err());
+	write(WriteMode::Create, rows[..10].to_vec()).await.unwrap();
+	assert_eq!(read().await, rows[..10]);
+
+	// Partition columns missing from the path are reported as such
+	let errors: Vec<_> = Json::<_, MissingColumn>::new(HiveDirectory::new(dir.clone()))
+		.await
+		.unwrap()
+		.par_stream()
+		.map(|row: Result<_, _>| row.unwrap_err())
+		.collect(pool)
+		.await;
+	assert_eq!(errors.len(), 10);
+	assert!(errors
+		.iter()
+		.all(|err| *err
+			== JsonError::PartitionValues(PartitionError::Missing(String::from("month")))));
+
+	std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn json_error() {
+	let pool = &ThreadPool::new(None, Some(1)).unwrap();
+
+	#[derive(Data, Clone, PartialEq, PartialOrd, Debug)]
+	struct Row {
+		a: u32,
+	}
+
+	let file =
+		std::env::temp_dir().join(format!("amadeus-json-error-{}.json", rand::random::<u64>()));
+	std::fs::write(&file, "{\"a\":1}\n{\"a\":\"not a number\"}\n").unwrap();
+
+	let errors: Vec<_> = Json::<_, Row>::new(file.clone())
+		.await
+		.unwrap()
+		.par_stream()
+		.filter(|row: &Result<_, _>| row.is_err())
+		.map(|row: Result<_, _>| row.unwrap_err())
+		.collect(pool)
+		.await;
+	assert_eq!(errors.len(), 1);
+	// Errors are sent as their message, so that they can be returned from other processes
+	let error = serde_json::from_str(&serde_json::to_string(&errors[0]).unwrap()).unwrap();
+	assert_eq!(errors[0], error);
+	assert_eq!(errors[0].to_string(), error.to_string());
+
+	std::fs::remove_file(&file).unwrap();
+}
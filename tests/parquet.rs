@@ -7,7 +7,9 @@
 
 use std::{collections::HashMap, path::PathBuf, time::SystemTime};
 
-use amadeus::prelude::*;
+use amadeus::{
+	data::PartitionError, prelude::*, source::parquet::{Compression, Filter, GroupPredicate, ParquetError, ValuePredicate}
+};
 
 #[tokio::test(threaded_scheduler)]
 #[cfg_attr(miri, ignore)]
@@ -1028,3 +1030,400 @@ async fn parquet() {
 
 	println!("in {:?}", start.elapsed().unwrap());
 }
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn parquet_projection() {
+	let pool = &ThreadPool::new(None, None).unwrap();
+
+	let rows = Parquet::<_, Group>::new_with_projection(
+		PathBuf::from("amadeus-testing/parquet/commits.parquet"),
+		GroupPredicate::new(vec![
+			(String::from("author_name"), None),
+			(String::from("nfiles"), None),
+		]),
+	)
+	.await
+	.unwrap();
+	assert_eq!(
+		rows.par_stream()
+			.map(|row: Result<Group, _>| -> Group {
+				let group = row.unwrap();
+				assert_eq!(
+					group.field_names().unwrap().keys().collect::<Vec<_>>(),
+					["author_name", "nfiles"]
+				);
+				group
+			})
+			.count(pool)
+			.await,
+		14_444
+	);
+
+	let rows = Parquet::<_, Value>::new_with_projection(
+		PathBuf::from("amadeus-testing/parquet/commits.parquet"),
+		ValuePredicate::Group(Some(GroupPredicate::new(vec![(
+			String::from("message"),
+			None,
+		)]))),
+	)
+	.await
+	.unwrap();
+	assert_eq!(
+		rows.par_stream()
+			.map(|row: Result<Value, _>| -> usize {
+				row.unwrap().into_group().unwrap().into_fields().len()
+			})
+			.sum::<_, usize>(pool)
+			.await,
+		14_444
+	);
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn parquet_write() {
+	// One task per thread, so that each write produces few files
+	let pool = &ThreadPool::new(None, Some(1)).unwrap();
+
+	#[derive(Data, Clone, PartialEq, Debug)]
+	struct Row {
+		a: u32,
+		b: Option<String>,
+		c: List<i64>,
+		d: Inner,
+	}
+	#[derive(Data, Clone, PartialEq, Debug)]
+	struct Inner {
+		e: f64,
+		f: Option<List<Option<String>>>,
+	}
+
+	let rows = (0..10_000u32)
+		.map(|a| Row {
+			a,
+			b: if a % 3 == 0 {
+				None
+			} else {
+				Some(format!("row {}", a % 10))
+			},
+			c: (0..i64::from(a % 4)).collect(),
+			d: Inner {
+				e: f64::from(a) / 2.0,
+				f: if a % 5 == 0 {
+					None
+				} else {
+					Some(
+						(0..a % 3)
+							.map(|i| if i == 1 { None } else { Some(i.to_string()) })
+							.collect(),
+					)
+				},
+			},
+		})
+		.collect::<Vec<_>>();
+
+	let files = |dir: &PathBuf| {
+		std::fs::read_dir(dir)
+			.unwrap()
+			.map(|entry| entry.unwrap().path())
+			.collect::<Vec<_>>()
+	};
+	let write = |compression, row_group_size| {
+		let rows = rows.clone();
+		async move {
+			let dir = std::env::temp_dir()
+				.join(format!("amadeus-parquet-write-{}", rand::random::<u64>()));
+			rows.into_par_stream()
+				.pipe(
+					pool,
+					ParquetDestination::builder(dir.clone())
+						.compression(compression)
+						.row_group_size(row_group_size)
+						.build()
+						.await
+						.unwrap()
+						.par_sink(),
+				)
+				.await
+				.unwrap();
+			dir
+		}
+	};
+	let read = |dir: PathBuf| async move {
+		let mut read: Vec<Row> = Parquet::new(files(&dir))
+			.await
+			.unwrap()
+			.par_stream()
+			.map(|row: Result<_, _>| row.unwrap())
+			.collect(pool)
+			.await;
+		read.sort_by_key(|row| row.a);
+		read
+	};
+	let size = |dir: &PathBuf| {
+		files(dir)
+			.iter()
+			.map(|file| std::fs::metadata(file).unwrap().len())
+			.sum::<u64>()
+	};
+
+	// Row groups smaller than each file, with a partial last row group
+	let uncompressed = write(Compression::Uncompressed, 999).await;
+	assert!(files(&uncompressed)
+		.iter()
+		.all(|file| file.to_str().unwrap().ends_with(".parquet")));
+	assert_eq!(read(uncompressed.clone()).await, rows);
+
+	for &compression in &[
+		Compression::Snappy,
+		Compression::Gzip,
+		Compression::Brotli,
+		Compression::Lz4,
+		Compression::Zstd,
+	] {
+		let dir = write(compression, 128 * 1024).await;
+		assert_eq!(read(dir.clone()).await, rows, "{:?}", compression);
+		assert!(size(&dir) < size(&uncompressed), "{:?}", compression);
+		std::fs::remove_dir_all(&dir).unwrap();
+	}
+
+	// A row group per row
+	let dir = write(Compression::Snappy, 1).await;
+	assert_eq!(read(dir.clone()).await, rows);
+	assert!(size(&dir) > size(&uncompressed));
+	std::fs::remove_dir_all(&dir).unwrap();
+
+	std::fs::remove_dir_all(&uncompressed).unwrap();
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn parquet_filter() {
+	// A single worker, so that the rows are written in order to one file
+	let pool = &ThreadPool::new(Some(1), Some(1)).unwrap();
+
+	#[derive(Data, Clone, PartialEq, Debug)]
+	struct Row {
+		a: u32,
+		b: String,
+	}
+
+	let rows = (0..10_000u32)
+		.map(|a| Row {
+			a,
+			b: a.to_string(),
+		})
+		.collect::<Vec<_>>();
+
+	let dir =
+		std::env::temp_dir().join(format!("amadeus-parquet-filter-{}", rand::random::<u64>()));
+	rows.clone()
+		.into_par_stream()
+		.pipe(
+			pool,
+			ParquetDestination::builder(dir.clone())
+				.row_group_size(1000)
+				.build()
+				.await
+				.unwrap()
+				.par_sink(),
+		)
+		.await
+		.unwrap();
+	let files = std::fs::read_dir(&dir)
+		.unwrap()
+		.map(|entry| entry.unwrap().path())
+		.collect::<Vec<_>>();
+	assert_eq!(files.len(), 1);
+
+	let read = |filter: Filter| {
+		let files = files.clone();
+		async move {
+			let mut read: Vec<Row> = Parquet::new(files)
+				.await
+				.unwrap()
+				.filter(filter)
+				.par_stream()
+				.map(|row: Result<_, _>| row.unwrap())
+				.collect(pool)
+				.await;
+			read.sort_by_key(|row| row.a);
+			read
+		}
+	};
+
+	// Only whole row groups are skipped, and the rows of those read aren't filtered
+	assert_eq!(read(Filter::ge("a", 5000u32)).await, rows[5000..]);
+	assert_eq!(read(Filter::ge("a", 5500u32)).await, rows[5000..]);
+	assert_eq!(read(Filter::lt("a", 1000u32)).await, rows[..1000]);
+	assert_eq!(read(Filter::eq("a", 4321u32)).await, rows[4000..5000]);
+	assert!(read(Filter::gt("a", 9999u32)).await.is_empty());
+
+	std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn parquet_large_footer() {
+	use std::{convert::TryInto, fs};
+
+	let pool = &ThreadPool::new(Some(1), Some(1)).unwrap();
+
+	#[derive(Data, Clone, PartialEq, Debug)]
+	struct Row {
+		a: u32,
+		b: String,
+	}
+
+	let rows = (0..4000u32)
+		.map(|a| Row {
+			a,
+			b: a.to_string(),
+		})
+		.collect::<Vec<_>>();
+
+	// Many small row groups, so the footer is larger than what's prefetched
+	let dir = std::env::temp_dir().join(format!(
+		"amadeus-parquet-large-footer-{}",
+		rand::random::<u64>()
+	));
+	rows.clone()
+		.into_par_stream()
+		.pipe(
+			pool,
+			ParquetDestination::builder(dir.clone())
+				.row_group_size(2)
+				.build()
+				.await
+				.unwrap()
+				.par_sink(),
+		)
+		.await
+		.unwrap();
+	let file = fs::read_dir(&dir).unwrap().next().unwrap().unwrap().path();
+	let bytes = fs::read(&file).unwrap();
+	let footer = bytes.len() - 8;
+	let metadata_len = i32::from_le_bytes(bytes[footer..footer + 4].try_into().unwrap());
+	assert!(metadata_len > 64 * 1024);
+
+	let read = |file: PathBuf| async move {
+		Parquet::<_, Row>::new(vec![file])
+			.await
+			.unwrap()
+			.par_stream()
+			.map(|row: Result<Row, _>| row.map_err(|e| e.to_string()))
+			.collect::<_, Vec<_>>(pool)
+			.await
+	};
+
+	let mut read_rows = read(file.clone())
+		.await
+		.into_iter()
+		.collect::<Result<Vec<_>, _>>()
+		.unwrap();
+	read_rows.sort_by_key(|row| row.a);
+	assert_eq!(read_rows, rows);
+
+	// Negative and out-of-range metadata lengths are reported rather than fetched
+	for &metadata_len in &[-1, i32::MAX, bytes.len() as i32] {
+		let mut bytes = bytes.clone();
+		bytes[footer..footer + 4].copy_from_slice(&metadata_len.to_le_bytes());
+		let corrupt = dir.join("corrupt.parquet");
+		fs::write(&corrupt, bytes).unwrap();
+		let read_rows = read(corrupt).await;
+		assert_eq!(read_rows.len(), 1);
+		assert!(read_rows[0].is_err());
+	}
+
+	fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn parquet_partitioned() {
+	let pool = &ThreadPool::new(None, Some(1)).unwrap();
+
+	#[derive(Data, Clone, PartialEq, Debug)]
+	struct Written {
+		a: u32,
+	}
+	#[derive(Data, Clone, PartialEq, Debug)]
+	struct Row {
+		a: u32,
+		#[amadeus(partition)]
+		year: u16,
+	}
+	#[derive(Data, Clone, PartialEq, Debug)]
+	struct MissingColumn {
+		a: u32,
+		#[amadeus(partition)]
+		month: u8,
+	}
+
+	let dir = std::env::temp_dir().join(format!(
+		"amadeus-parquet-partitioned-{}",
+		rand::random::<u64>()
+	));
+	let rows = (0..300u32)
+		.map(|a| Row {
+			a,
+			year: 2018 + (a / 100) as u16,
+		})
+		.collect::<Vec<_>>();
+	for year in 2018..2021 {
+		rows.iter()
+			.filter(|row| row.year == year)
+			.map(|row| Written { a: row.a })
+			.collect::<Vec<_>>()
+			.into_par_stream()
+			.pipe(
+				pool,
+				ParquetDestination::builder(dir.join(format!("year={}", year)))
+					.build()
+					.await
+					.unwrap()
+					.par_sink(),
+			)
+			.await
+			.unwrap();
+	}
+
+	let read = |directory: ParquetDirectory<PathBuf>| async move {
+		let mut rows: Vec<Row> = Parquet::new(directory)
+			.await
+			.unwrap()
+			.par_stream()
+			.map(|row: Result<_, _>| row.unwrap())
+			.collect(pool)
+			.await;
+		rows.sort_by_key(|row| row.a);
+		rows
+	};
+
+	assert_eq!(read(ParquetDirectory::new(dir.clone())).await, rows);
+	assert_eq!(
+		read(
+			ParquetDirectory::new(dir.clone())
+				.filter(|values| values.get("year").map_or(true, |year| year == Some("2019")))
+		)
+		.await,
+		rows[100..200]
+	);
+
+	// Partition columns missing from the path are reported as such
+	let errors: Vec<_> = Parquet::<_, MissingColumn>::new(ParquetDirectory::new(dir.clone()))
+		.await
+		.unwrap()
+		.par_stream()
+		.map(|row: Result<_, _>| row.unwrap_err())
+		.collect(pool)
+		.await;
+	assert_eq!(errors.len(), rows.len());
+	assert!(errors
+		.iter()
+		.all(|err| *err
+			== ParquetError::PartitionValues(PartitionError::Missing(String::from("month")))));
+
+	std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,271 @@
+//! Runs against a local S3-compatible server, such as MinIO started with
+//! `minio server <dir>` and a bucket named `amadeus-test`.
+
+use amadeus::prelude::*;
+use std::time::{Duration, SystemTime};
+
+fn directory(prefix: &str) -> S3Directory {
+	directory_with(
+		prefix,
+		AwsCredentials::AccessKey {
+			id: "minioadmin".to_owned(),
+			secret: "minioadmin".to_owned(),
+		},
+	)
+}
+fn directory_with(prefix: &str, credentials: AwsCredentials) -> S3Directory {
+	S3Directory::new_with(
+		AwsRegion::UsEast1,
+		"amadeus-test",
+		&format!("{}-{:016x}/", prefix, rand::random::<u64>()),
+		credentials,
+	)
+	.endpoint("http://localhost:9000")
+}
+
+#[derive(Data, Clone, PartialEq, PartialOrd, Debug)]
+struct Row {
+	a: u32,
+	b: String,
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn s3_write() {
+	let pool = &ThreadPool::new(None, Some(1)).unwrap();
+
+	let rows = (0..10_000_u32)
+		.map(|a| Row {
+			a,
+			b: a.to_string(),
+		})
+		.collect::<Vec<_>>();
+	let dir = directory("json-write");
+	rows.clone()
+		.into_par_stream()
+		.pipe(
+			pool,
+			JsonDestination::new(dir.clone()).await.unwrap().par_sink(),
+		)
+		.await
+		.unwrap();
+	let read = |dir: S3Directory| async move {
+		let mut read: Vec<Row> = Json::new(dir)
+			.await
+			.unwrap()
+			.par_stream()
+			.map(|row: Result<_, _>| row.unwrap())
+			.collect(pool)
+			.await;
+		read.sort_by_key(|row| row.a);
+		read
+	};
+	assert_eq!(read(dir.clone()).await, rows);
+
+	// Existing files are kept by default
+	assert!(JsonDestination::<_, Row>::new(dir.clone()).await.is_err());
+	rows[..10]
+		.to_vec()
+		.into_par_stream()
+		.pipe(
+			pool,
+			JsonDestination::builder(dir.clone())
+				.mode(WriteMode::Create)
+				.build()
+				.await
+				.unwrap()
+				.par_sink(),
+		)
+		.await
+		.unwrap();
+	assert_eq!(read(dir).await, rows[..10]);
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn s3_write_multipart() {
+	// One task, so that a single file is written in multiple parts
+	let pool = &ThreadPool::new(Some(1), Some(1)).unwrap();
+
+	let rows = (0..200_000_u32)
+		.map(|a| Row {
+			a,
+			b: format!("{:0>100}", a),
+		})
+		.collect::<Vec<_>>();
+	let dir = directory("json-write-multipart");
+	rows.clone()
+		.into_par_stream()
+		.pipe(
+			pool,
+			JsonDestination::new(dir.clone()).await.unwrap().par_sink(),
+		)
+		.await
+		.unwrap();
+	let mut read: Vec<Row> = Json::new(dir)
+		.await
+		.unwrap()
+		.par_stream()
+		.map(|row: Result<_, _>| row.unwrap())
+		.collect(pool)
+		.await;
+	read.sort_by_key(|row| row.a);
+	assert_eq!(read, rows);
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn s3_partitioned() {
+	let pool = &ThreadPool::new(None, Some(1)).unwrap();
+
+	#[derive(Data, Clone, PartialEq, PartialOrd, Debug)]
+	struct Row {
+		a: u32,
+		#[amadeus(partition)]
+		year: u16,
+		#[amadeus(partition)]
+		month: u8,
+	}
+
+	let rows = (0..1000_u32)
+		.map(|a| Row {
+			a,
+			year: 2018 + (a % 2) as u16,
+			month: 1 + (a % 12) as u8,
+		})
+		.collect::<Vec<_>>();
+	let dir = directory("json-partitioned");
+	let write = |rows: Vec<Row>, mode: WriteMode| {
+		let dir = dir.clone();
+		async move {
+			rows.into_par_stream()
+				.pipe(
+					pool,
+					JsonDestination::builder(dir)
+						.partitioned(true)
+						.mode(mode)
+						.build()
+						.await?
+						.par_sink(),
+				)
+				.await
+		}
+	};
+	write(rows.clone(), WriteMode::Create).await.unwrap();
+
+	// The mode applies to the files under each partition prefix too
+	assert!(write(rows.clone(), WriteMode::FailIfExists).await.is_err());
+	write(rows[..500].to_vec(), WriteMode::Create)
+		.await
+		.unwrap();
+	let mut read: Vec<Row> = Json::new(HiveDirectory::new(dir.clone()))
+		.await
+		.unwrap()
+		.par_stream()
+		.map(|row: Result<_, _>| row.unwrap())
+		.collect(pool)
+		.await;
+	read.sort_by_key(|row| row.a);
+	assert_eq!(read, rows[..500]);
+	write(rows.clone(), WriteMode::Create).await.unwrap();
+
+	// Directories the filter rejects are skipped without being listed
+	let mut read: Vec<Row> = Json::new(
+		HiveDirectory::new(dir)
+			.filter(|values| values.get("year").map_or(true, |year| year == Some("2019"))),
+	)
+	.await
+	.unwrap()
+	.par_stream()
+	.map(|row: Result<_, _>| row.unwrap())
+	.collect(pool)
+	.await;
+	read.sort_by_key(|row| row.a);
+	let expected = rows
+		.into_iter()
+		.filter(|row| row.year == 2019)
+		.collect::<Vec<_>>();
+	assert_eq!(read, expected);
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn s3_profile() {
+	let pool = &ThreadPool::new(None, Some(1)).unwrap();
+
+	let credentials = std::env::temp_dir().join(format!(
+		"amadeus-credentials-{:016x}",
+		rand::random::<u64>()
+	));
+	std::fs::write(
+		&credentials,
+		"[amadeus]\naws_access_key_id = minioadmin\naws_secret_access_key = minioadmin\n",
+	)
+	.unwrap();
+	std::env::set_var("AWS_SHARED_CREDENTIALS_FILE", &credentials);
+
+	let dir = directory_with(
+		"json-profile",
+		AwsCredentials::Profile {
+			name: "amadeus".to_owned(),
+		},
+	);
+	// Serialized as it would be to send to another process
+	let dir: S3Directory = serde_json::from_str(&serde_json::to_string(&dir).unwrap()).unwrap();
+	let rows = (0..100_u32)
+		.map(|a| Row {
+			a,
+			b: a.to_string(),
+		})
+		.collect::<Vec<_>>();
+	rows.clone()
+		.into_par_stream()
+		.pipe(
+			pool,
+			JsonDestination::new(dir.clone()).await.unwrap().par_sink(),
+		)
+		.await
+		.unwrap();
+	let mut read: Vec<Row> = Json::new(dir)
+		.await
+		.unwrap()
+		.par_stream()
+		.map(|row: Result<_, _>| row.unwrap())
+		.collect(pool)
+		.await;
+	read.sort_by_key(|row| row.a);
+	assert_eq!(read, rows);
+
+	std::fs::remove_file(credentials).unwrap();
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn s3_retry() {
+	let pool = &ThreadPool::new(None, Some(1)).unwrap();
+
+	// Nothing listens on port 1, so every attempt fails to connect
+	let policy = RetryPolicy::new()
+		.max_attempts(3)
+		.backoff(Duration::from_millis(100), Duration::from_millis(100));
+	let start = SystemTime::now();
+	let err = Json::<_, Value>::new(
+		directory("retry")
+			.endpoint("http://localhost:1")
+			.retry(policy),
+	)
+	.await
+	.unwrap_err();
+	assert!(format!("{:?}", err).contains("HttpDispatch"), "{:?}", err);
+	assert!(start.elapsed().unwrap() < Duration::from_secs(10));
+
+	// Missing files are errors rather than panics
+	let rows: Vec<Result<Value, _>> = Json::new(vec![directory("retry").file("missing.json")])
+		.await
+		.unwrap()
+		.par_stream()
+		.collect(pool)
+		.await;
+	assert_eq!(rows.len(), 1);
+	assert!(rows[0].is_err());
+}
@@ -0,0 +1,84 @@
+#[cfg(feature = "constellation")]
+use constellation::*;
+#[cfg(feature = "constellation")]
+use futures::future::join_all;
+#[cfg(feature = "constellation")]
+use std::time::{Duration, SystemTime};
+#[cfg(feature = "constellation")]
+use tokio::time::delay_for;
+
+#[cfg(feature = "constellation")]
+use amadeus::dist::prelude::*;
+
+fn main() {
+	#[cfg(feature = "constellation")]
+	{
+		if cfg!(miri) {
+			return;
+		}
+		init(Resources::default());
+
+		tokio::runtime::Builder::new()
+			.threaded_scheduler()
+			.enable_all()
+			.build()
+			.unwrap()
+			.block_on(async {
+				let start = SystemTime::now();
+				run().await;
+				println!("in {:?}", start.elapsed().unwrap());
+			})
+	}
+}
+
+#[cfg(feature = "constellation")]
+async fn run() {
+	let pool = ProcessPool::new(Some(2), None, None, Resources::default()).unwrap();
+
+	// Tasks dispatched concurrently are counted before they're sent, so are spread evenly across the processes, which each run them in turn
+	let tasks = (0..20)
+		.map(|_| {
+			tokio::spawn(pool.spawn(FnOnce!(|&_| async {
+				delay_for(Duration::from_secs(1)).await;
+			})))
+		})
+		.collect::<Vec<_>>();
+	while pool.queue_depths().iter().sum::<usize>() < 20 {
+		delay_for(Duration::from_millis(10)).await;
+	}
+	assert_eq!(pool.queue_depths(), [10, 10]);
+	for task in join_all(tasks).await {
+		task.unwrap().unwrap();
+	}
+	assert_eq!(pool.queue_depths(), [0, 0]);
+
+	// A slow task occupies one process...
+	let slow = tokio::spawn(pool.spawn(FnOnce!(|&_| async {
+		delay_for(Duration::from_secs(5)).await;
+		std::process::id()
+	})));
+	while pool.queue_depths().iter().sum::<usize>() < 1 {
+		delay_for(Duration::from_millis(10)).await;
+	}
+	let mut depths = pool.queue_depths();
+	depths.sort_unstable();
+	assert_eq!(depths, [0, 1]);
+
+	// ...so quick tasks go to the other one
+	let mut quick = Vec::new();
+	for _ in 0..10 {
+		quick.push(
+			pool.spawn(FnOnce!(|&_| async { std::process::id() }))
+				.await
+				.unwrap(),
+		);
+	}
+	let slow = slow.await.unwrap().unwrap();
+	assert!(
+		quick.iter().all(|&quick| quick != slow),
+		"{:?} {}",
+		quick,
+		slow
+	);
+	assert_eq!(pool.queue_depths(), [0, 0]);
+}